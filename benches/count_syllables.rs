@@ -0,0 +1,78 @@
+//! counting the syllables of 10k words, mostly in the dictionary or mostly
+//! approximated. run with `cargo bench --bench count_syllables`
+use ashpaper_plus::count_syllables;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+const WORDS: usize = 10_000;
+
+/// words that are all in the dictionary
+const KNOWN: &[&str] = &[
+    "the",
+    "lovely",
+    "poem",
+    "is",
+    "a",
+    "calculator",
+    "and",
+    "finds",
+    "factorials",
+    "input",
+    "syllables",
+    "in",
+    "title",
+    "count",
+    "them",
+    "as",
+    "one",
+    "counts",
+    "or",
+    "should",
+    "sound",
+    "of",
+    "gentle",
+    "rain",
+    "antidisestablishmentarianism",
+    "Capital",
+    "river",
+];
+
+/// a text of `WORDS` words that are made up, so every one is approximated
+fn unknown_text() -> String {
+    const PARTS: &[&str] = &[
+        "zor", "blax", "qui", "fth", "eau", "mrr", "plo", "ynx", "ae",
+    ];
+    (0..WORDS)
+        .map(|i| {
+            (0..2 + i % 3)
+                .map(|j| PARTS[(i * 7 + j * 3) % PARTS.len()])
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn known_text() -> String {
+    (0..WORDS)
+        .map(|i| KNOWN[(i * 11) % KNOWN.len()])
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn bench(c: &mut Criterion) {
+    // load the dictionary before timing anything
+    count_syllables("poem");
+    let mut group = c.benchmark_group("count_syllables");
+    group.throughput(Throughput::Elements(WORDS as u64));
+    for (name, text) in [
+        ("dictionary", known_text()),
+        ("approximated", unknown_text()),
+    ] {
+        group.bench_with_input(BenchmarkId::from_parameter(name), &text, |b, text| {
+            b.iter(|| count_syllables(text))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
@@ -0,0 +1,52 @@
+//! the interpreter's classic and threaded engines running the factorial
+//! poem and a loop that counts down from 100k. run with
+//! `cargo bench --bench engines`
+//!
+//! on a single core the threaded engine came out anywhere from 15% faster
+//! to 15% slower than the classic one from run to run, on both programs:
+//! about 1.1µs for the factorial poem and 4.2ms for the loop. the limits,
+//! stats and tracing checked every step cost more than the match does, so
+//! taking it out isn't enough to tell them apart
+use ashpaper_plus::{Engine, ExecOptions, InsType, Instruction, Program, Register};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+/// counts r0 down to 0 from `passes`, printing nothing
+fn counting_loop(passes: u32) -> Program {
+    let ins = |instruction, register| Instruction::new(instruction, register, "");
+    Program::from_instructions(vec![
+        ins(InsType::Store(passes), Register::Register0),
+        ins(InsType::Store(1), Register::Register1),
+        ins(InsType::Negate, Register::Register1),
+        ins(InsType::Add, Register::Register0),
+        // back to the store of 1 while r0 > 0, by the -1 in r1
+        ins(InsType::ConditionalGoto(0), Register::Register0),
+    ])
+}
+
+fn bench(c: &mut Criterion) {
+    let programs = [
+        (
+            "factorial",
+            Program::create(include_str!("../poems/original-factorial.eso")),
+        ),
+        ("counting loop", counting_loop(100_000)),
+    ];
+    for (name, program) in &programs {
+        let mut group = c.benchmark_group(*name);
+        for engine in [Engine::Classic, Engine::Threaded] {
+            let options = ExecOptions {
+                engine,
+                ..ExecOptions::default()
+            };
+            group.bench_with_input(
+                BenchmarkId::from_parameter(format!("{:?}", engine)),
+                &options,
+                |b, options| b.iter(|| program.execute_report(options).output.len()),
+            );
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
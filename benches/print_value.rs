@@ -0,0 +1,63 @@
+//! how long `print-value` takes to turn registers into text, against the
+//! `to_string` it used to use. run with `cargo bench --bench print_value`
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use ashpaper_plus::{InsType, Instruction, Interpreter, Program, Register};
+
+const ITERATIONS: i64 = 1_000_000;
+
+/// the values printed, spread over every length of number
+fn value(i: i64) -> i64 {
+    i.wrapping_mul(0x9e37_79b9_7f4a_7c15u64 as i64) >> (i % 64)
+}
+
+fn time(name: &str, run: impl FnOnce() -> usize) -> Duration {
+    let start = Instant::now();
+    let len = black_box(run());
+    let elapsed = start.elapsed();
+    println!(
+        "{: <24} {: >10.2?} ({:.1} ns/value, {} bytes)",
+        name,
+        elapsed,
+        elapsed.as_nanos() as f64 / ITERATIONS as f64,
+        len
+    );
+    elapsed
+}
+
+fn main() {
+    time("to_string", || {
+        let mut output = String::new();
+        for i in 0..ITERATIONS {
+            output.push_str(&value(i).to_string());
+        }
+        output.len()
+    });
+    time("itoa", || {
+        let mut output = String::new();
+        let mut buffer = itoa::Buffer::new();
+        for i in 0..ITERATIONS {
+            output.push_str(buffer.format(value(i)));
+        }
+        output.len()
+    });
+
+    let program = Program::from_instructions(vec![Instruction::new(
+        InsType::PrintValue,
+        Register::Register0,
+        "print.",
+    )]);
+    time("interpreter print-value", || {
+        let mut output = String::new();
+        for i in 0..ITERATIONS {
+            let mut interpreter = Interpreter::new(&program);
+            interpreter.set_register(Register::Register0, value(i));
+            let step = interpreter.step().unwrap().unwrap();
+            output.push_str(&step.output.unwrap());
+        }
+        output.len()
+    });
+}
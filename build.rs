@@ -0,0 +1,164 @@
+#[cfg(all(feature = "compact-dictionary", feature = "bundled-dictionary"))]
+#[path = "src/dictionary/compact.rs"]
+mod compact;
+
+fn main() {
+    poem_tests();
+    embedded_dictionary();
+    #[cfg(feature = "ffi")]
+    generate_header();
+    #[cfg(all(feature = "compact-dictionary", feature = "bundled-dictionary"))]
+    compact_dictionary();
+}
+
+/// compact the bundled dictionary into the out dir, so the library only has
+/// to include it
+#[cfg(all(feature = "compact-dictionary", feature = "bundled-dictionary"))]
+fn compact_dictionary() {
+    use std::{env, fs, path::Path};
+
+    println!("cargo:rerun-if-changed=src/dictionary/compact.rs");
+    let source = fs::read_to_string(dictionary_path()).expect("the bundled dictionary is readable");
+    let compact = compact::compact(&source);
+    let out = env::var("OUT_DIR").unwrap();
+    fs::write(Path::new(&out).join("cmudict.fst"), compact.words).unwrap();
+    fs::write(Path::new(&out).join("cmudict.entries"), compact.entries).unwrap();
+}
+
+/// the dictionary to compile into the library: the one `ASHPAPER_DICT`
+/// names, relative to the crate, or res/cmudict.dict
+fn dictionary_path() -> std::path::PathBuf {
+    use std::{env, path::Path};
+
+    let dir = Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).to_path_buf();
+    match env::var_os("ASHPAPER_DICT") {
+        Some(path) => dir.join(path),
+        None => dir.join("res/cmudict.dict"),
+    }
+}
+
+/// point the library at the dictionary to embed, along with its
+/// fingerprint. one from `ASHPAPER_DICT` is checked here, so a mistake in
+/// it fails the build rather than every run, and sets the
+/// `ashpaper_embedded_dict` cfg
+fn embedded_dictionary() {
+    use std::fs;
+
+    println!("cargo:rerun-if-env-changed=ASHPAPER_DICT");
+    let path = dictionary_path();
+    println!("cargo:rerun-if-changed={}", path.display());
+    println!("cargo:rustc-env=ASHPAPER_DICT_PATH={}", path.display());
+    let source = fs::read_to_string(&path)
+        .unwrap_or_else(|err| panic!("can't read dictionary {}: {}", path.display(), err));
+    println!(
+        "cargo:rustc-env=ASHPAPER_DICT_FINGERPRINT={}",
+        fingerprint(source.as_bytes())
+    );
+    if std::env::var_os("ASHPAPER_DICT").is_none() {
+        return;
+    }
+    for (i, line) in source.lines().enumerate() {
+        if let Err(err) = line.parse::<cmudict_fast::Cmudict>() {
+            panic!("{}:{}: {}\n{}", path.display(), i + 1, err, line);
+        }
+    }
+    println!("cargo:rustc-cfg=ashpaper_embedded_dict");
+}
+
+/// fnv-1a, the same as `dictionary::fingerprint_of`, so a cache made with a
+/// dictionary can tell when it's been swapped for another
+fn fingerprint(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// write the C header for the ffi module to the out dir. the copy checked in
+/// at include/ashpaper.h is kept the same by a test, since a build script
+/// mustn't write to the source
+#[cfg(feature = "ffi")]
+fn generate_header() {
+    use std::{env, path::Path};
+
+    println!("cargo:rerun-if-changed=src/ffi.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    let dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let config = cbindgen::Config::from_file(Path::new(&dir).join("cbindgen.toml"))
+        .expect("cbindgen.toml is valid");
+    cbindgen::Builder::new()
+        .with_crate(&dir)
+        .with_config(config)
+        .generate()
+        .expect("the ffi module can be translated to C")
+        .write_to_file(Path::new(&env::var("OUT_DIR").unwrap()).join("ashpaper.h"));
+}
+
+/// a test for every poem in poems/, included by tests/bundled_poems.rs. a
+/// poem without a .expected file gets a test that fails telling its author
+/// to add one, unless it's skipped in poems/manifest.toml
+fn poem_tests() {
+    use std::{env, fmt::Write, fs, path::Path};
+
+    println!("cargo:rerun-if-changed=poems");
+    let dir = Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).join("poems");
+    let mut poems: Vec<_> = fs::read_dir(&dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| Some(entry.ok()?.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "eso"))
+                .collect()
+        })
+        // the poems aren't there when the crate is built as a dependency
+        .unwrap_or_default();
+    poems.sort();
+    let skip = fs::read_to_string(dir.join("manifest.toml"))
+        .map(|manifest| manifest_skips(&manifest))
+        .unwrap_or_default();
+
+    let mut tests = String::new();
+    for poem in &poems {
+        let file_name = poem.file_name().unwrap().to_str().unwrap();
+        let test_name: String = poem
+            .file_stem()
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        let expected = poem.with_extension("expected");
+        let body = if expected.exists() {
+            format!(
+                "check_poem(include_bytes!({:?}), include_str!({:?}));",
+                poem, expected
+            )
+        } else {
+            format!("missing_expected({:?});", file_name)
+        };
+        if skip.iter().any(|skipped| skipped == file_name) {
+            writeln!(tests, "#[ignore = \"skipped in poems/manifest.toml\"]").unwrap();
+        }
+        writeln!(
+            tests,
+            "#[test]\nfn poem_{}() {{\n    {}\n}}\n",
+            test_name, body
+        )
+        .unwrap();
+    }
+    let out = env::var("OUT_DIR").unwrap();
+    fs::write(Path::new(&out).join("bundled_poems.rs"), tests).unwrap();
+}
+
+/// the poems in the `skip = [...]` line of a manifest. the build script
+/// can't depend on toml, so the list has to be on one line
+fn manifest_skips(manifest: &str) -> Vec<String> {
+    manifest
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("skip"))
+        .filter_map(|rest| rest.trim().strip_prefix('='))
+        .filter_map(|list| list.trim().strip_prefix('[')?.strip_suffix(']'))
+        .flat_map(|list| list.split(','))
+        .map(|poem| poem.trim().trim_matches('"').to_string())
+        .filter(|poem| !poem.is_empty())
+        .collect()
+}
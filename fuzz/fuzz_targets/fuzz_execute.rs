@@ -0,0 +1,20 @@
+#![no_main]
+use ashpaper_plus::{Error, ExecError, ExecOptions, Program};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let program = Program::create(&String::from_utf8_lossy(data));
+    let options = ExecOptions {
+        max_instructions: Some(10_000),
+        max_output: Some(64 * 1024),
+        ..ExecOptions::default()
+    };
+    // overflow is reported as an error, so anything but a panic is fine
+    match program.execute_with_options(&options) {
+        Ok(_)
+        | Err(Error::Exec(ExecError::FuelExhausted { .. }))
+        | Err(Error::Exec(ExecError::OutputLimit { .. }))
+        | Err(Error::Exec(ExecError::Overflow { .. })) => (),
+        Err(err) => panic!("unexpected error: {}", err),
+    }
+});
@@ -0,0 +1,7 @@
+#![no_main]
+use ashpaper_plus::Program;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    Program::create(&String::from_utf8_lossy(data));
+});
@@ -0,0 +1,18 @@
+#![no_main]
+use ashpaper_plus::{compose::compose, testing::ComposableProgram, InsType, Program};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: (ComposableProgram, u64)| {
+    let (ComposableProgram(instructions), seed) = input;
+    let poem = compose(&instructions, seed)
+        .unwrap_or_else(|err| panic!("{:?} can't be composed: {}", instructions, err));
+    let parsed = Program::create(&poem).ast;
+    assert_eq!(parsed.len(), instructions.len(), "{}", poem);
+    for (expected, parsed) in instructions.iter().zip(&parsed) {
+        assert_eq!(expected.instruction, parsed.instruction, "{}", poem);
+        // blank lines don't keep their indentation
+        if expected.instruction != InsType::Noop {
+            assert_eq!(expected.register, parsed.register, "{}", poem);
+        }
+    }
+});
@@ -0,0 +1,18 @@
+#![no_main]
+use ashpaper_plus::{count_syllables_with_options, ParseOptions, UnknownScriptSyllables};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|text: &str| {
+    for unknown_script_syllables in [
+        UnknownScriptSyllables::Zero,
+        UnknownScriptSyllables::PerCharacter,
+    ] {
+        count_syllables_with_options(
+            text,
+            &ParseOptions {
+                unknown_script_syllables,
+                ..ParseOptions::default()
+            },
+        );
+    }
+});
@@ -0,0 +1,98 @@
+/* generated by cbindgen from src/ffi.rs when building with the ffi feature, see build.rs. don't edit */
+
+#ifndef ASHPAPER_H
+#define ASHPAPER_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * the result of a call, written through the `status` pointer
+ */
+typedef enum AshpaperStatus {
+  ASHPAPER_STATUS_OK = 0,
+  ASHPAPER_STATUS_NULL_ARGUMENT = 1,
+  ASHPAPER_STATUS_INVALID_UTF8 = 2,
+  ASHPAPER_STATUS_FUEL_EXHAUSTED = 3,
+  ASHPAPER_STATUS_OUTPUT_LIMIT = 4,
+  ASHPAPER_STATUS_OVERFLOW = 5,
+  ASHPAPER_STATUS_PANIC = 6,
+  ASHPAPER_STATUS_CANCELLED = 7,
+  ASHPAPER_STATUS_STACK_UNDERFLOW = 8,
+  ASHPAPER_STATUS_DIVISION_BY_ZERO = 9,
+  ASHPAPER_STATUS_INPUT = 10,
+  ASHPAPER_STATUS_TAPE_LIMIT = 11,
+  ASHPAPER_STATUS_STACK_LIMIT = 12,
+} AshpaperStatus;
+
+/**
+ * limits for a single execution. 0 means no limit
+ */
+typedef struct AshpaperOptions {
+  uint64_t max_instructions;
+  size_t max_output;
+  /**
+   * fail with `StackUnderflow` on a pop from an empty stack
+   */
+  bool strict_pop;
+  /**
+   * fail with `StackLimit` on a push to a stack holding this many values
+   */
+  size_t max_stack;
+} AshpaperOptions;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * execute a poem with the default options, see
+ * [`ashpaper_execute_with_options`]
+ *
+ * # Safety
+ * `source` must be null or a valid nul-terminated string and `out_len`
+ * null or valid to write to
+ */
+char *ashpaper_execute(const char *source, size_t *out_len);
+
+/**
+ * execute a poem, returning its output or null on failure. the length of
+ * the output is written to `out_len` and the reason for a failure to
+ * `status`, either of which can be null. `options` can be null to use no
+ * limits
+ *
+ * # Safety
+ * `source` must be null or a valid nul-terminated string, `options` null or
+ * valid to read, and `out_len` and `status` null or valid to write to
+ */
+char *ashpaper_execute_with_options(const char *source,
+                                    const struct AshpaperOptions *options,
+                                    size_t *out_len,
+                                    enum AshpaperStatus *status);
+
+/**
+ * the number of syllables in a line, or `SIZE_MAX` if `source` is null or
+ * isn't utf-8
+ *
+ * # Safety
+ * `source` must be null or a valid nul-terminated string
+ */
+size_t ashpaper_count_syllables(const char *source);
+
+/**
+ * free a string returned by this library. null is ignored
+ *
+ * # Safety
+ * `output` must be null or a string returned by this library that hasn't
+ * been freed yet
+ */
+void ashpaper_free(char *output);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* ASHPAPER_H */
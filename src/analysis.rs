@@ -0,0 +1,602 @@
+//! everything an editor wants to know about a poem from a single call, e.g.
+//! for a language server
+//!
+//! positions are given two ways. a `span` is a half-open range of byte
+//! offsets into the source. a `range` is the same text as 0-based lines and
+//! characters counted in utf-16 code units, which is what the language
+//! server protocol expects. neither includes line endings, and both `\n`
+//! and `\r\n` end a line like they do for the parser
+use std::{collections::HashMap, ops};
+
+use super::{
+    lint::{self, Lint, Severity},
+    parser::{self, InsCategory, InsType, Register, SyllableSource, WordSyllables},
+    Program,
+};
+
+/// a place in the source, as a 0-based line and a utf-16 offset into it
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
+pub struct Position {
+    pub line: usize,
+    pub character: usize,
+}
+
+/// a half-open range between two positions
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct Range {
+    pub start: Position,
+    pub end: Position,
+}
+
+/// how a line was parsed
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LineAnalysis {
+    /// 0-based, like [`Position::line`]
+    pub line: usize,
+    pub instruction: InsType,
+    pub category: InsCategory,
+    pub register: Register,
+    pub syllables: usize,
+    /// how each word of the line was counted, adding up to `syllables`.
+    /// their spans are into the source, like the line's
+    pub words: Vec<WordSyllables>,
+    /// the line without its indentation or trailing whitespace, so blank
+    /// lines are empty
+    pub span: ops::Range<usize>,
+    pub range: Range,
+}
+
+/// a lint found in the poem
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Diagnostic {
+    pub lint: Lint,
+    pub severity: Severity,
+    pub message: String,
+    pub span: ops::Range<usize>,
+    pub range: Range,
+}
+
+/// a word whose syllables are approximated because it isn't in the
+/// dictionary. the span leaves out punctuation around the word
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct UnknownWord {
+    /// the word lowercased, as it was looked up
+    pub word: String,
+    pub syllables: usize,
+    pub span: ops::Range<usize>,
+    pub range: Range,
+}
+
+/// where a goto or conditional goto can jump, for showing inline. the
+/// target depends on a register at runtime, so it's described rather than
+/// resolved
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct GotoHint {
+    pub line: usize,
+    /// the register whose value, modulo the number of lines, is the 0-based
+    /// line jumped to
+    pub target_register: Register,
+    /// for a conditional goto, the jump is only taken when the line's own
+    /// register holds more than this
+    pub threshold: Option<usize>,
+    /// e.g. `if r1 > 3, jumps to line |r0| % 17 + 1`
+    pub label: String,
+    pub span: ops::Range<usize>,
+    pub range: Range,
+}
+
+/// the result of [`analyze`], each part ordered by position
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Analysis {
+    pub lines: Vec<LineAnalysis>,
+    pub diagnostics: Vec<Diagnostic>,
+    pub unknown_words: Vec<UnknownWord>,
+    pub goto_hints: Vec<GotoHint>,
+}
+
+/// a meter [`meter`] can make out in a line
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Meter {
+    /// an unstressed syllable then a stressed one, over and over
+    Iambic,
+    /// a stressed syllable then an unstressed one, over and over
+    Trochaic,
+    /// neither fits the line better than the other, or too little of it
+    Unknown,
+}
+
+impl Meter {
+    pub fn name(self) -> &'static str {
+        match self {
+            Meter::Iambic => "iambic",
+            Meter::Trochaic => "trochaic",
+            Meter::Unknown => "unknown",
+        }
+    }
+}
+
+/// how much of a line has to fit a meter for [`meter`] to call it that
+pub(crate) const METER_FIT: f64 = 0.8;
+
+/// the stresses of a line that isn't blank, see [`meter`]
+#[derive(Debug, PartialEq, Clone)]
+pub struct LineMeter {
+    /// 0-based, like [`Position::line`]
+    pub line: usize,
+    /// the stress of each syllable, word by word and separated by spaces: `1`
+    /// for primary or secondary stress, `0` for none and `?` for the
+    /// syllables of a word that isn't in the dictionary. words without
+    /// syllables are left out
+    pub pattern: String,
+    pub meter: Meter,
+    /// how much of the line fits `meter`, from 0 to 1. for
+    /// [`Meter::Unknown`] it's how much fits the closer meter, which can
+    /// be all of it when both fit equally well
+    pub confidence: f64,
+    /// the words that aren't in the dictionary, lowercased
+    pub unknown_words: Vec<String>,
+    pub span: ops::Range<usize>,
+    pub range: Range,
+}
+
+impl LineMeter {
+    /// how much of the line fits `meter`, from 0 to 1, or `None` if none of
+    /// its syllables are known or `meter` is [`Meter::Unknown`]. a word of
+    /// one syllable fits either beat, since it takes the stress its place in
+    /// the line gives it
+    pub fn fit(&self, meter: Meter) -> Option<f64> {
+        let stressed_first = match meter {
+            Meter::Iambic => false,
+            Meter::Trochaic => true,
+            Meter::Unknown => return None,
+        };
+        let (mut known, mut fits) = (0, 0);
+        let mut syllable = 0;
+        for word in self.pattern.split(' ') {
+            for stress in word.chars() {
+                let expected = if syllable % 2 == 0 {
+                    stressed_first
+                } else {
+                    !stressed_first
+                };
+                syllable += 1;
+                if stress == '?' {
+                    continue;
+                }
+                known += 1;
+                if word.len() == 1 || (stress == '1') == expected {
+                    fits += 1;
+                }
+            }
+        }
+        if known == 0 {
+            None
+        } else {
+            Some(fits as f64 / known as f64)
+        }
+    }
+}
+
+/// the letters [`rhyme_scheme`] gives rhymes, in order
+const SCHEME_LETTERS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// the lines of `source` like [`str::lines`], with the byte offset each one
+/// starts at
+pub(crate) fn lines(source: &str) -> impl Iterator<Item = (usize, &str)> {
+    source.split_inclusive('\n').scan(0, |next, line| {
+        let start = *next;
+        *next += line.len();
+        let line = match line.strip_suffix('\n') {
+            Some(line) => line.strip_suffix('\r').unwrap_or(line),
+            None => line,
+        };
+        Some((start, line))
+    })
+}
+
+/// the span of `line`, starting at `start`, without surrounding whitespace
+pub(crate) fn trimmed(start: usize, line: &str) -> ops::Range<usize> {
+    let start = start + line.len() - line.trim_start().len();
+    start..start + line.trim().len()
+}
+
+/// converts byte offsets to utf-16 positions
+struct LineIndex<'a> {
+    source: &'a str,
+    starts: Vec<usize>,
+}
+
+impl LineIndex<'_> {
+    fn new(source: &str) -> LineIndex<'_> {
+        LineIndex {
+            source,
+            starts: lines(source).map(|(start, _)| start).collect(),
+        }
+    }
+
+    fn position(&self, offset: usize) -> Position {
+        let line = self
+            .starts
+            .partition_point(|&start| start <= offset)
+            .saturating_sub(1);
+        let start = self.starts.get(line).copied().unwrap_or(0);
+        Position {
+            line,
+            character: self.source[start..offset].encode_utf16().count(),
+        }
+    }
+
+    fn range(&self, span: &ops::Range<usize>) -> Range {
+        Range {
+            start: self.position(span.start),
+            end: self.position(span.end),
+        }
+    }
+}
+
+fn register_name(register: Register) -> &'static str {
+    ["r0", "r1", "r2", "r3"][register.index()]
+}
+
+/// parse and lint a poem, locating everything that's found in the source
+pub fn analyze(source: &str) -> Analysis {
+    let program = Program::create(source);
+    let index = LineIndex::new(source);
+    let mut analysis = Analysis::default();
+
+    for (line, ((start, text), ins)) in lines(source).zip(&program.ast).enumerate() {
+        let span = trimmed(start, text);
+        let range = index.range(&span);
+        let jump = match ins.instruction {
+            InsType::Goto => Some((ins.register, None)),
+            InsType::ConditionalGoto(syllables) => {
+                Some((ins.register.paired(), Some(syllables as usize)))
+            }
+            _ => None,
+        };
+        if let Some((target_register, threshold)) = jump {
+            let jump = format!(
+                "jumps to line |{}| % {} + 1",
+                register_name(target_register),
+                program.ast.len()
+            );
+            analysis.goto_hints.push(GotoHint {
+                line,
+                target_register,
+                threshold,
+                label: match threshold {
+                    Some(threshold) => format!(
+                        "if {} > {}, {}",
+                        register_name(ins.register),
+                        threshold,
+                        jump
+                    ),
+                    None => jump,
+                },
+                span: span.clone(),
+                range,
+            });
+        }
+        analysis.lines.push(LineAnalysis {
+            line,
+            instruction: ins.instruction,
+            category: ins.instruction.category(),
+            register: ins.register,
+            syllables: parser::count_syllables(text),
+            words: parser::count_syllables_detailed(text)
+                .into_iter()
+                .map(|word| WordSyllables {
+                    span: start + word.span.start..start + word.span.end,
+                    ..word
+                })
+                .collect(),
+            span,
+            range,
+        });
+    }
+
+    analysis.diagnostics = lint::lint(source)
+        .into_iter()
+        .map(|diagnostic| Diagnostic {
+            lint: diagnostic.lint,
+            severity: diagnostic.lint.severity(),
+            range: index.range(&diagnostic.span),
+            message: diagnostic.message,
+            span: diagnostic.span,
+        })
+        .collect();
+
+    analysis.unknown_words = lint::unknown_words(source)
+        .into_iter()
+        .map(|(_, span, word)| UnknownWord {
+            syllables: parser::count_syllables(&word),
+            word,
+            range: index.range(&span),
+            span,
+        })
+        .collect();
+
+    analysis
+}
+
+/// the stresses of every line of a poem that isn't blank, from the
+/// pronunciations its syllables are counted by, with a guess at the meter
+/// of each. a line is iambic or trochaic when at least 80% of its known
+/// syllables fit that meter and fewer fit the other
+pub fn meter(source: &str) -> Vec<LineMeter> {
+    let index = LineIndex::new(source);
+    lines(source)
+        .enumerate()
+        .filter(|(_, (_, text))| !text.trim().is_empty())
+        .map(|(line, (start, text))| {
+            let mut unknown_words = vec![];
+            let pattern: Vec<String> = parser::count_syllables_detailed(text)
+                .into_iter()
+                .filter(|word| word.syllables > 0)
+                .map(|word| match word.source {
+                    SyllableSource::Dictionary { .. } => parser::stress_pattern(&word.word)
+                        .expect("words counted from the dictionary are in it"),
+                    SyllableSource::Approximated => {
+                        unknown_words.push(word.word);
+                        "?".repeat(word.syllables)
+                    }
+                })
+                .collect();
+            let span = trimmed(start, text);
+            let mut line_meter = LineMeter {
+                line,
+                pattern: pattern.join(" "),
+                meter: Meter::Unknown,
+                confidence: 0.0,
+                unknown_words,
+                range: index.range(&span),
+                span,
+            };
+            let iambic = line_meter.fit(Meter::Iambic).unwrap_or(0.0);
+            let trochaic = line_meter.fit(Meter::Trochaic).unwrap_or(0.0);
+            let (meter, confidence) = if iambic >= METER_FIT && iambic > trochaic {
+                (Meter::Iambic, iambic)
+            } else if trochaic >= METER_FIT && trochaic > iambic {
+                (Meter::Trochaic, trochaic)
+            } else {
+                (Meter::Unknown, iambic.max(trochaic))
+            };
+            line_meter.meter = meter;
+            line_meter.confidence = confidence;
+            line_meter
+        })
+        .collect()
+}
+
+/// the root of `line` in a union-find over lines
+fn rhyme_root(parents: &mut [usize], mut line: usize) -> usize {
+    while parents[line] != line {
+        parents[line] = parents[parents[line]];
+        line = parents[line];
+    }
+    line
+}
+
+/// the rhyme scheme of a poem, e.g. `AABBA` for a limerick, as a letter for
+/// each line that isn't blank. lines share a letter when their last words
+/// rhyme, or when both rhyme with the same other line, and the letters go
+/// in the order rhymes first appear. a line that rhymes with nothing, or
+/// whose last word isn't in the dictionary, is `-`. after `z` every rhyme
+/// is `+`
+///
+/// the last word is found the same way as for a conditional push, so
+/// punctuation after it usually keeps it from rhyming
+pub fn rhyme_scheme(source: &str) -> Vec<char> {
+    let ends: Vec<Option<String>> = lines(source)
+        .map(|(_, line)| line)
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| parser::end_word(&parser::lowercase(line)).map(str::to_string))
+        .collect();
+
+    let mut parents: Vec<usize> = (0..ends.len()).collect();
+    for (one, one_end) in ends.iter().enumerate() {
+        for (two, two_end) in ends.iter().enumerate().skip(one + 1) {
+            if let (Some(one_end), Some(two_end)) = (one_end, two_end) {
+                if parser::words_rhyme(one_end, two_end) == Some(true) {
+                    let one = rhyme_root(&mut parents, one);
+                    let two = rhyme_root(&mut parents, two);
+                    parents[two] = one;
+                }
+            }
+        }
+    }
+
+    let mut sizes = vec![0; ends.len()];
+    for line in 0..ends.len() {
+        sizes[rhyme_root(&mut parents, line)] += 1;
+    }
+    let mut letters: HashMap<usize, char> = HashMap::new();
+    (0..ends.len())
+        .map(|line| {
+            let root = rhyme_root(&mut parents, line);
+            if sizes[root] < 2 {
+                return '-';
+            }
+            let next = letters.len();
+            *letters.entry(root).or_insert_with(|| {
+                SCHEME_LETTERS
+                    .get(next)
+                    .map_or('+', |&letter| letter as char)
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[cfg(feature = "bundled-dictionary")]
+    fn range(line: usize, start: usize, end: usize) -> Range {
+        Range {
+            start: Position {
+                line,
+                character: start,
+            },
+            end: Position {
+                line,
+                character: end,
+            },
+        }
+    }
+
+    #[test]
+    fn line_offsets() {
+        assert_eq!(
+            lines("a\r\n\nbc\n  d").collect::<Vec<_>>(),
+            vec![(0, "a"), (3, ""), (4, "bc"), (7, "  d")]
+        );
+        assert_eq!(lines("").count(), 0);
+        assert_eq!(
+            lines("a\n").map(|(_, line)| line).collect::<Vec<_>>(),
+            "a\n".lines().collect::<Vec<_>>()
+        );
+    }
+
+    #[cfg(feature = "bundled-dictionary")]
+    #[test]
+    fn multi_byte_ranges() {
+        let source = "café olé\n\n  naïve 🐟 zorblax\nsells sea shells\r\n";
+        let analysis = analyze(source);
+
+        let spans: Vec<_> = analysis
+            .lines
+            .iter()
+            .map(|line| (line.span.clone(), line.range))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                (0..10, range(0, 0, 8)),
+                (11..11, range(1, 0, 0)),
+                (14..33, range(2, 2, 18)),
+                (34..50, range(3, 0, 16)),
+            ]
+        );
+        assert_eq!(analysis.lines[3].instruction, InsType::Goto);
+        assert_eq!(analysis.lines[2].register, Register::Register1);
+        let words: Vec<_> = analysis.lines[2]
+            .words
+            .iter()
+            .map(|word| (word.word.as_str(), word.span.clone(), word.syllables))
+            .collect();
+        assert_eq!(
+            words,
+            vec![
+                ("naïve", 14..20, 2),
+                ("🐟", 21..25, 0),
+                ("zorblax", 26..33, 2)
+            ]
+        );
+        assert_eq!(analysis.lines[2].syllables, 4);
+
+        let words: Vec<_> = analysis
+            .unknown_words
+            .iter()
+            .map(|word| (word.word.as_str(), word.span.clone(), word.range))
+            .collect();
+        assert_eq!(
+            words,
+            vec![
+                ("café", 0..5, range(0, 0, 4)),
+                ("olé", 6..10, range(0, 5, 8)),
+                ("naïve", 14..20, range(2, 2, 7)),
+                ("zorblax", 26..33, range(2, 11, 18)),
+            ]
+        );
+        assert_eq!(&source[26..33], "zorblax");
+
+        assert_eq!(analysis.diagnostics.len(), 4);
+        let zorblax = &analysis.diagnostics[3];
+        assert_eq!(zorblax.lint, Lint::UnknownWord);
+        assert_eq!(zorblax.severity, Severity::Warning);
+        assert_eq!(zorblax.span, 26..33);
+        assert_eq!(zorblax.range, range(2, 11, 18));
+
+        assert_eq!(
+            analysis.goto_hints,
+            vec![GotoHint {
+                line: 3,
+                target_register: Register::Register0,
+                threshold: None,
+                label: "jumps to line |r0| % 4 + 1".to_string(),
+                span: 34..50,
+                range: range(3, 0, 16),
+            }]
+        );
+    }
+
+    #[cfg(feature = "bundled-dictionary")]
+    #[test]
+    fn rhyme_schemes() {
+        let scheme = |source| rhyme_scheme(source).into_iter().collect::<String>();
+        let limerick = "There was an Old Man with a beard
+Who said It is just as I feared
+
+Two Owls and a Hen
+Four Larks and a Wren
+Have all built their nests in my beard
+";
+        assert_eq!(scheme(limerick), "AABBA");
+        // the punctuation after feared keeps it out of the dictionary
+        assert_eq!(scheme("with a beard\nas I feared!\na Hen\na Wren"), "--AA");
+        assert_eq!(
+            scheme(include_str!("../poems/math-test.eso")),
+            "AB-C-ADB-C-AD--C-"
+        );
+        assert_eq!(
+            scheme(include_str!("../poems/lovely-poem.eso")),
+            "--------A-----A-"
+        );
+        assert_eq!(scheme(""), "");
+    }
+
+    #[cfg(feature = "bundled-dictionary")]
+    #[test]
+    fn meters() {
+        let couplet =
+            "Shall I compare thee to a summer's day\n\n  Thou art more lovely and more temperate";
+        let meters = meter(couplet);
+        assert_eq!(meters.len(), 2);
+        assert_eq!(meters[0].pattern, "1 1 01 1 1 0 10 1");
+        assert_eq!(meters[0].meter, Meter::Iambic);
+        assert_eq!(meters[0].confidence, 1.0);
+        assert_eq!(meters[1].line, 2);
+        assert_eq!(meters[1].pattern, "1 1 1 10 0 1 100");
+        assert_eq!(meters[1].meter, Meter::Iambic);
+        assert_eq!(meters[1].confidence, 0.9);
+        assert_eq!(meters[1].fit(Meter::Trochaic), Some(0.6));
+        assert_eq!(meters[1].span, 42..81);
+        assert_eq!(meters[1].range, range(2, 2, 41));
+
+        let irregular = &meter("Happily wandering, merrily zorblax")[0];
+        assert_eq!(irregular.pattern, "100 ??? 100 ??");
+        assert_eq!(irregular.meter, Meter::Unknown);
+        assert_eq!(irregular.unknown_words, vec!["wandering,", "zorblax"]);
+        assert_eq!(
+            meter("Tyger Tyger burning bright")[0].meter,
+            Meter::Trochaic
+        );
+        assert_eq!(meter("the sea")[0].fit(Meter::Unknown), None);
+    }
+
+    #[cfg(feature = "bundled-dictionary")]
+    #[test]
+    fn hijacked_title() {
+        let analysis = analyze("\n  sells sea shells\n  re/cur\n");
+        let diagnostic = &analysis.diagnostics[0];
+        assert_eq!(diagnostic.lint, Lint::HijackedStore);
+        assert_eq!(diagnostic.span, 3..19);
+        assert_eq!(diagnostic.range, range(1, 2, 18));
+        assert_eq!(
+            analysis.goto_hints[1].label,
+            "if r1 > 2, jumps to line |r0| % 3 + 1"
+        );
+    }
+}
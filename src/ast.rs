@@ -0,0 +1,277 @@
+//! the versioned json form of a program, printed by `--ast=json` and read by
+//! `generate`, behind the `serde` feature
+//!
+//! # schema v1
+//!
+//! an object with the version of the schema as `"major.minor"` and an object
+//! per line of the poem, e.g.
+//!
+//! ```json
+//! {
+//!   "schema_version": "1.0",
+//!   "instructions": [
+//!     { "instruction": "store", "syllables": 3, "register": 0, "line": "lovely poem" },
+//!     { "instruction": "cond-push", "prev_syllables": 3, "cur_syllables": 2, "register": 1 },
+//!     { "instruction": "print-value", "register": 0 }
+//!   ]
+//! }
+//! ```
+//!
+//! `register` defaults to 0 and `line` to nothing when reading, since only
+//! the instructions matter. the other fields of each instruction are those of
+//! [`InsType`](crate::InsType) in kebab-case
+//!
+//! # compatibility
+//!
+//! a new minor version only adds fields that can be ignored, so documents of
+//! a newer minor are read as if they were the current one. an older document
+//! is upgraded with [`migrate`], one version at a time. a newer major version
+//! can't be read. a bare array of instructions, as printed before the schema
+//! was versioned, is read as v1.0
+use std::{fmt, str::FromStr};
+
+use serde_json::{json, Value};
+
+pub use super::errors::ast::{AstError, AstResult};
+use super::{Instruction, Program, Result};
+
+/// the version of a schema, compared by major and then minor
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+}
+
+/// the version [`to_json`] writes
+pub const SCHEMA_VERSION: Version = Version { major: 1, minor: 0 };
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+impl FromStr for Version {
+    type Err = AstError;
+
+    fn from_str(s: &str) -> AstResult<Version> {
+        let invalid = || AstError::InvalidVersion(s.to_string());
+        let (major, minor) = s.split_once('.').ok_or_else(invalid)?;
+        Ok(Version {
+            major: major.parse().map_err(|_| invalid())?,
+            minor: minor.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// a document in any schema that can be read, before it's migrated
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum VersionedAst {
+    /// a bare array of instructions, from before the schema was versioned
+    Unversioned(Vec<Instruction>),
+    V1 {
+        minor: u32,
+        instructions: Vec<Instruction>,
+    },
+}
+
+/// the instructions of a document, naming the index of the one that's
+/// malformed on failure
+fn instructions(value: Value) -> AstResult<Vec<Instruction>> {
+    let values = match value {
+        Value::Array(values) => values,
+        _ => return Err(AstError::UnexpectedShape),
+    };
+    values
+        .into_iter()
+        .enumerate()
+        .map(|(index, value)| {
+            serde_json::from_value(value).map_err(|err| AstError::Instruction {
+                index,
+                message: err.to_string(),
+            })
+        })
+        .collect()
+}
+
+impl VersionedAst {
+    pub fn from_json(json: &str) -> Result<VersionedAst> {
+        let value = serde_json::from_str(json).map_err(|err| AstError::Json(err.to_string()))?;
+        VersionedAst::from_value(value)
+    }
+
+    pub fn from_value(value: Value) -> Result<VersionedAst> {
+        let mut document = match value {
+            Value::Array(_) => return Ok(VersionedAst::Unversioned(instructions(value)?)),
+            Value::Object(document) => document,
+            _ => return Err(AstError::UnexpectedShape.into()),
+        };
+        let version: Version = match document.get("schema_version") {
+            Some(Value::String(version)) => version.parse()?,
+            Some(version) => return Err(AstError::InvalidVersion(version.to_string()).into()),
+            None => return Err(AstError::UnexpectedShape.into()),
+        };
+        let instructions = instructions(
+            document
+                .remove("instructions")
+                .ok_or(AstError::UnexpectedShape)?,
+        )?;
+        match version.major {
+            1 => Ok(VersionedAst::V1 {
+                minor: version.minor,
+                instructions,
+            }),
+            _ => Err(AstError::UnsupportedVersion {
+                found: version.to_string(),
+                supported: SCHEMA_VERSION.to_string(),
+            }
+            .into()),
+        }
+    }
+
+    /// the version of the schema the document was written in, or `None` if
+    /// it's unversioned
+    pub fn version(&self) -> Option<Version> {
+        match self {
+            VersionedAst::Unversioned(_) => None,
+            VersionedAst::V1 { minor, .. } => Some(Version {
+                major: 1,
+                minor: *minor,
+            }),
+        }
+    }
+}
+
+/// an unversioned document is the same as v1.0
+fn unversioned_to_v1(instructions: Vec<Instruction>) -> VersionedAst {
+    VersionedAst::V1 {
+        minor: 0,
+        instructions,
+    }
+}
+
+/// upgrade a document to the current schema, returning its instructions.
+/// each step converts to the next version, so a new version only needs a
+/// conversion from the one before it
+pub fn migrate(mut ast: VersionedAst) -> Vec<Instruction> {
+    loop {
+        ast = match ast {
+            VersionedAst::Unversioned(instructions) => unversioned_to_v1(instructions),
+            // v1.0 is the current version, and newer minors only add fields
+            // that were already ignored when reading them
+            VersionedAst::V1 { instructions, .. } => return instructions,
+        };
+    }
+}
+
+/// the current schema's json for `program`
+pub fn to_json(program: &Program) -> String {
+    let document = json!({
+        "schema_version": SCHEMA_VERSION.to_string(),
+        "instructions": program.ast,
+    });
+    serde_json::to_string_pretty(&document).expect("instructions are always valid json")
+}
+
+/// read a program in any schema that can be read, see [`migrate`]
+pub fn from_json(json: &str) -> Result<Program> {
+    Ok(Program::from_instructions(migrate(
+        VersionedAst::from_json(json)?,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+    use pretty_assertions::assert_eq;
+
+    /// the error reading `json` failed with
+    fn ast_error(json: &str) -> AstError {
+        match from_json(json) {
+            Err(Error::Ast(err)) => err,
+            other => panic!("expected an AstError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let program = Program::create(include_str!("../poems/lovely-poem.eso"));
+        let json = to_json(&program);
+        assert_eq!(
+            VersionedAst::from_json(&json).unwrap().version(),
+            Some(SCHEMA_VERSION)
+        );
+        assert_eq!(from_json(&json).unwrap(), program);
+
+        // from before the schema was versioned
+        let bare = serde_json::to_string(&program.ast).unwrap();
+        let ast = VersionedAst::from_json(&bare).unwrap();
+        assert_eq!(ast.version(), None);
+        assert_eq!(migrate(ast), program.ast);
+
+        // a newer minor only adds fields
+        let newer = r#"{"schema_version": "1.7", "instructions": [{"instruction": "negate", "register": 1, "color": "red"}], "author": "shea"}"#;
+        assert_eq!(
+            from_json(newer).unwrap().ast[0].instruction,
+            crate::InsType::Negate
+        );
+    }
+
+    #[test]
+    fn newer_major() {
+        let v2 = r#"{"schema_version": "2.0", "instructions": [], "labels": {}}"#;
+        let err = ast_error(v2);
+        assert_eq!(
+            err,
+            AstError::UnsupportedVersion {
+                found: "2.0".to_string(),
+                supported: "1.0".to_string()
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "schema version 2.0 is newer than the newest this version of ashpaper-plus can read (1.0), try upgrading it"
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let err = from_json(r#"[{"instruction": "add", "register": 0}, {"instruction": "jump"}]"#)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.starts_with("instruction 1: unknown variant `jump`"),
+            "{}",
+            err
+        );
+        let err = from_json(
+            r#"{"schema_version": "1.0", "instructions": [{"instruction": "store", "register": 0}]}"#,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(
+            err.starts_with("instruction 0: missing field `syllables`"),
+            "{}",
+            err
+        );
+        let err = from_json(r#"[{"instruction": "add", "register": 4}]"#).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "instruction 0: there is no register 4, only 0 to 3"
+        );
+        assert_eq!(
+            ast_error(r#"{"schema_version": 1, "instructions": []}"#),
+            AstError::InvalidVersion("1".to_string())
+        );
+        assert_eq!(
+            ast_error(r#"{"schema_version": "one", "instructions": []}"#),
+            AstError::InvalidVersion("one".to_string())
+        );
+        assert_eq!(
+            ast_error(r#"{"instructions": []}"#),
+            AstError::UnexpectedShape
+        );
+        assert!(matches!(ast_error("["), AstError::Json(_)));
+    }
+}
@@ -0,0 +1,190 @@
+use crate::{io::Encoding, ExecOptions, Executor, InterpreterExecutor, Program};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process,
+    time::{Duration, Instant},
+};
+
+use super::{conditional_jit_arg, jit_executor, parse_arg};
+
+/// instruction limit applied to every run of `bench` unless overridden
+const DEFAULT_BENCH_LIMIT: &str = "1000000";
+
+/// timings of repeated runs of a single poem
+struct RunStats {
+    mean: Duration,
+    min: Duration,
+    output_len: usize,
+}
+
+impl RunStats {
+    /// `None` if a run doesn't complete, e.g. by hitting the limit in
+    /// `options`
+    fn measure(
+        executor: &dyn Executor,
+        program: &Program,
+        options: &ExecOptions,
+        iterations: u32,
+    ) -> Option<RunStats> {
+        let run = || {
+            let report = executor.run(program, options).ok()?;
+            report.completed().then_some(report.output)
+        };
+        let mut total = Duration::default();
+        let mut min = Duration::MAX;
+        let mut output_len = 0;
+        for _ in 0..iterations {
+            let start = Instant::now();
+            let output = run()?;
+            let elapsed = start.elapsed();
+            total += elapsed;
+            min = min.min(elapsed);
+            output_len = output.len();
+        }
+        Some(RunStats {
+            mean: total / iterations,
+            min,
+            output_len,
+        })
+    }
+}
+
+pub(crate) fn discover_poems(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut poems = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "eso") {
+            poems.push(path);
+        }
+    }
+    poems.sort();
+    Ok(poems)
+}
+
+pub(crate) fn print_table(headers: &[&str], rows: &[Vec<String>]) {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.len()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let format_row = |cells: Vec<String>| {
+        cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, &width)| format!("{: <width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join(" | ")
+            .trim_end()
+            .to_string()
+    };
+    println!(
+        "{}",
+        format_row(headers.iter().map(|h| h.to_string()).collect())
+    );
+    println!(
+        "{}",
+        format_row(widths.iter().map(|&w| "-".repeat(w)).collect())
+    );
+    for row in rows {
+        println!("{}", format_row(row.clone()));
+    }
+}
+
+pub fn run(matches: &ArgMatches) {
+    let dir = Path::new(matches.value_of("DIR").unwrap());
+    let iterations: u32 = parse_arg(matches, "iterations");
+    if iterations == 0 {
+        eprintln!("--iterations must be at least 1");
+        process::exit(1);
+    }
+    let options = ExecOptions::with_max_instructions(parse_arg(matches, "limit"));
+    let jit = jit_executor(matches);
+
+    let poems = match discover_poems(dir) {
+        Ok(poems) => poems,
+        Err(err) => {
+            eprintln!("error reading {}: {}", dir.display(), err);
+            process::exit(1);
+        }
+    };
+
+    let mut headers = vec!["poem", "parse", "exec mean", "exec min", "output bytes"];
+    if jit.is_some() {
+        headers.extend(&["jit mean", "jit min"]);
+    }
+
+    // load the dictionary up front so it isn't counted in the first parse time
+    crate::count_syllables("warm up");
+
+    let mut rows = Vec::new();
+    for path in poems {
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        let contents = match crate::io::read_to_string(&path, Encoding::Lossy) {
+            Ok(contents) => contents,
+            Err(err) => {
+                eprintln!("skipping {}: {}", name, err);
+                continue;
+            }
+        };
+
+        let start = Instant::now();
+        let program = Program::create(&contents);
+        let parse_time = start.elapsed();
+
+        let mut row = vec![name, format!("{:?}", parse_time)];
+        let stats = RunStats::measure(&InterpreterExecutor, &program, &options, iterations);
+        match &stats {
+            Some(stats) => row.extend(vec![
+                format!("{:?}", stats.mean),
+                format!("{:?}", stats.min),
+                stats.output_len.to_string(),
+            ]),
+            None => row.extend(vec![
+                "timed out".to_string(),
+                "-".to_string(),
+                "-".to_string(),
+            ]),
+        }
+        if let Some(jit) = &jit {
+            // the jit has no instruction limit, so only run poems that are
+            // known to halt
+            let unlimited = ExecOptions::default();
+            match stats.and_then(|_| RunStats::measure(&**jit, &program, &unlimited, iterations)) {
+                Some(jit_stats) => row.extend(vec![
+                    format!("{:?}", jit_stats.mean),
+                    format!("{:?}", jit_stats.min),
+                ]),
+                None => row.extend(vec!["-".to_string(), "-".to_string()]),
+            }
+        }
+        rows.push(row);
+    }
+
+    print_table(&headers, &rows);
+}
+
+pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+    let bench = SubCommand::with_name("bench")
+        .about("Time parsing and execution of every .eso poem in a directory")
+        .args(&[
+            Arg::with_name("DIR")
+                .help("directory containing .eso poems")
+                .required(true)
+                .index(1),
+            Arg::with_name("iterations")
+                .short("n")
+                .long("iterations")
+                .value_name("N")
+                .help("Number of times to execute each poem")
+                .default_value("10"),
+            Arg::with_name("limit")
+                .long("limit")
+                .value_name("INSTRUCTIONS")
+                .help("Instructions to execute before a poem is considered timed out")
+                .default_value(DEFAULT_BENCH_LIMIT),
+        ]);
+    conditional_jit_arg(bench)
+}
@@ -0,0 +1,259 @@
+use crate::{
+    io::{self, Encoding},
+    ExecOptions, Executor, Program,
+};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    process,
+};
+
+use super::{bench::discover_poems, conditional_jit_arg, jit_executor, parse_arg};
+
+/// instruction limit applied to every poem unless overridden
+const DEFAULT_TEST_LIMIT: &str = "1000000";
+
+const MANIFEST: &str = "manifest.toml";
+
+/// expected outputs listed in a corpus' `manifest.toml`, e.g.
+///
+/// ```toml
+/// # poems that never halt can't be tested
+/// skip = ["forever.eso"]
+///
+/// [expected]
+/// "lovely-poem.eso" = "24\n"
+/// ```
+#[derive(Default)]
+struct Manifest {
+    expected: HashMap<String, String>,
+    skip: HashSet<String>,
+}
+
+impl Manifest {
+    fn load(dir: &Path) -> Result<Manifest, String> {
+        let path = dir.join(MANIFEST);
+        if !path.exists() {
+            return Ok(Manifest::default());
+        }
+        let contents = fs::read_to_string(&path).map_err(|err| err.to_string())?;
+        let table: toml::Table = contents.parse().map_err(|err| format!("{}", err))?;
+
+        let mut manifest = Manifest::default();
+        if let Some(expected) = table.get("expected") {
+            let expected = expected
+                .as_table()
+                .ok_or("'expected' must be a table of poem names to outputs")?;
+            for (poem, output) in expected {
+                let output = output
+                    .as_str()
+                    .ok_or_else(|| format!("expected output of {} must be a string", poem))?;
+                manifest.expected.insert(poem.clone(), output.to_string());
+            }
+        }
+        if let Some(skip) = table.get("skip") {
+            let skip = skip
+                .as_array()
+                .ok_or("'skip' must be an array of poem names")?;
+            for poem in skip {
+                let poem = poem
+                    .as_str()
+                    .ok_or("'skip' must be an array of poem names")?;
+                manifest.skip.insert(poem.to_string());
+            }
+        }
+        Ok(manifest)
+    }
+}
+
+enum Outcome {
+    Passed,
+    Failed { expected: String, actual: String },
+    Missing,
+    Error(String),
+    Skipped,
+    Blessed,
+}
+
+/// read a poem as utf-8, falling back to latin-1 since that's what older
+/// poems in a corpus are most likely to be saved as
+pub(crate) fn read_poem(path: &Path) -> crate::Result<String> {
+    let bytes = fs::read(path).map_err(io::DecodeError::Io)?;
+    io::decode(&bytes, Encoding::Utf8).or_else(|_| io::decode(&bytes, Encoding::Latin1))
+}
+
+fn run_poem(
+    path: &Path,
+    options: &ExecOptions,
+    jit: Option<&dyn Executor>,
+) -> Result<String, String> {
+    let contents = read_poem(path).map_err(|err| err.to_string())?;
+    let program = Program::create(&contents);
+    let output = program
+        .execute_with_options(options)
+        .map_err(|err| err.to_string())?;
+    // the jit has no instruction limit, so it only runs poems the
+    // interpreter has shown to halt
+    match jit {
+        Some(jit) => jit
+            .run(&program, &ExecOptions::default())
+            .map_err(|err| err.to_string())?
+            .into_result()
+            .map_err(|err| err.to_string()),
+        None => Ok(output),
+    }
+}
+
+fn expected_path(path: &Path) -> PathBuf {
+    path.with_extension("expected")
+}
+
+fn check(
+    path: &Path,
+    name: &str,
+    manifest: &Manifest,
+    options: &ExecOptions,
+    jit: Option<&dyn Executor>,
+    bless: bool,
+) -> Outcome {
+    if manifest.skip.contains(name) {
+        return Outcome::Skipped;
+    }
+    let actual = match run_poem(path, options, jit) {
+        Ok(actual) => actual,
+        Err(err) => return Outcome::Error(err),
+    };
+    if bless {
+        return match fs::write(expected_path(path), &actual) {
+            Ok(()) => Outcome::Blessed,
+            Err(err) => Outcome::Error(format!("couldn't write expected output: {}", err)),
+        };
+    }
+    // a sibling .expected file takes precedence over the manifest
+    let expected = match fs::read_to_string(expected_path(path)) {
+        Ok(expected) => expected,
+        Err(_) => match manifest.expected.get(name) {
+            Some(expected) => expected.clone(),
+            None => return Outcome::Missing,
+        },
+    };
+    if expected == actual {
+        Outcome::Passed
+    } else {
+        Outcome::Failed { expected, actual }
+    }
+}
+
+/// a minimal line diff: the lines between the common prefix and suffix of
+/// the two outputs, marked `-` for expected and `+` for actual
+fn diff(expected: &str, actual: &str) -> String {
+    let expected_lines: Vec<&str> = expected.split('\n').collect();
+    let actual_lines: Vec<&str> = actual.split('\n').collect();
+    let prefix = expected_lines
+        .iter()
+        .zip(&actual_lines)
+        .take_while(|(e, a)| e == a)
+        .count();
+    let suffix = expected_lines[prefix..]
+        .iter()
+        .rev()
+        .zip(actual_lines[prefix..].iter().rev())
+        .take_while(|(e, a)| e == a)
+        .count();
+
+    let mut diff = String::new();
+    for line in &expected_lines[prefix..expected_lines.len() - suffix] {
+        diff.push_str(&format!("-{:?}\n", line));
+    }
+    for line in &actual_lines[prefix..actual_lines.len() - suffix] {
+        diff.push_str(&format!("+{:?}\n", line));
+    }
+    diff
+}
+
+pub fn run(matches: &ArgMatches) {
+    let dir = Path::new(matches.value_of("DIR").unwrap());
+    let options = ExecOptions::with_max_instructions(parse_arg(matches, "limit"));
+    let jit = jit_executor(matches);
+    let bless = matches.is_present("bless");
+
+    let poems = discover_poems(dir).unwrap_or_else(|err| {
+        eprintln!("error reading {}: {}", dir.display(), err);
+        process::exit(1);
+    });
+    let manifest = Manifest::load(dir).unwrap_or_else(|err| {
+        eprintln!("error reading {}: {}", dir.join(MANIFEST).display(), err);
+        process::exit(1);
+    });
+
+    let (mut passed, mut skipped, mut blessed) = (0, 0, 0);
+    let mut failures = Vec::new();
+    for path in poems {
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        let outcome = check(&path, &name, &manifest, &options, jit.as_deref(), bless);
+        let status = match &outcome {
+            Outcome::Passed => "ok",
+            Outcome::Skipped => "skipped",
+            Outcome::Blessed => "blessed",
+            Outcome::Failed { .. } | Outcome::Missing | Outcome::Error(_) => "FAILED",
+        };
+        println!("test {} ... {}", name, status);
+        match outcome {
+            Outcome::Passed => passed += 1,
+            Outcome::Skipped => skipped += 1,
+            Outcome::Blessed => blessed += 1,
+            Outcome::Failed { expected, actual } => failures.push((name, diff(&expected, &actual))),
+            Outcome::Missing => failures.push((
+                name,
+                "no expected output, run with --bless to record it\n".to_string(),
+            )),
+            Outcome::Error(err) => failures.push((name, format!("{}\n", err))),
+        }
+    }
+
+    if !failures.is_empty() {
+        println!("\nfailures:");
+        for (name, details) in &failures {
+            print!("\n---- {} ----\n{}", name, details);
+        }
+    }
+
+    let result = if failures.is_empty() { "ok" } else { "FAILED" };
+    let mut summary = format!(
+        "\ntest result: {}. {} passed; {} failed; {} skipped",
+        result,
+        passed,
+        failures.len(),
+        skipped
+    );
+    if bless {
+        summary.push_str(&format!("; {} blessed", blessed));
+    }
+    println!("{}", summary);
+
+    if !failures.is_empty() {
+        process::exit(1);
+    }
+}
+
+pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+    let test = SubCommand::with_name("test")
+        .about("Run every .eso poem in a directory and compare it to its .expected output")
+        .args(&[
+            Arg::with_name("DIR")
+                .help("directory containing .eso poems with .expected files or a manifest.toml")
+                .required(true)
+                .index(1),
+            Arg::with_name("bless")
+                .long("bless")
+                .help("Write each poem's output to its .expected file instead of comparing"),
+            Arg::with_name("limit")
+                .long("limit")
+                .value_name("INSTRUCTIONS")
+                .help("Instructions to execute before a poem is considered timed out")
+                .default_value(DEFAULT_TEST_LIMIT),
+        ]);
+    conditional_jit_arg(test)
+}
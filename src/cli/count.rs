@@ -0,0 +1,272 @@
+//! the `count` subcommand, a syllable report for poems and the text they're
+//! written from
+use crate::{count_syllables_detailed, dictionary, trace::csv_field, SyllableSource};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use serde_json::json;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process,
+};
+
+use super::{bench::print_table, corpus::read_poem};
+
+/// a line of a file and how its words were counted
+struct LineCount {
+    text: String,
+    syllables: usize,
+    words: usize,
+    /// words that weren't in the dictionary, so were approximated
+    approximated: usize,
+}
+
+struct FileCount {
+    path: PathBuf,
+    lines: Vec<LineCount>,
+}
+
+impl FileCount {
+    fn new(path: PathBuf, contents: &str) -> FileCount {
+        let lines = contents
+            .lines()
+            .map(|line| {
+                let words = count_syllables_detailed(line);
+                LineCount {
+                    text: line.trim_end().to_string(),
+                    syllables: words.iter().map(|word| word.syllables).sum(),
+                    words: words.len(),
+                    approximated: words
+                        .iter()
+                        .filter(|word| word.source == SyllableSource::Approximated)
+                        .count(),
+                }
+            })
+            .collect();
+        FileCount { path, lines }
+    }
+
+    /// how many lines, words, syllables and approximated words it has
+    fn totals(&self) -> [usize; 4] {
+        self.lines
+            .iter()
+            .fold([self.lines.len(), 0, 0, 0], |totals, line| {
+                [
+                    totals[0],
+                    totals[1] + line.words,
+                    totals[2] + line.syllables,
+                    totals[3] + line.approximated,
+                ]
+            })
+    }
+}
+
+const COLUMNS: [&str; 5] = ["file", "lines", "words", "syllables", "approximated"];
+const LINE_COLUMNS: [&str; 5] = ["file", "line", "syllables", "approximated", "text"];
+
+/// the `.eso` and `.txt` files under `path`, or `path` itself if it's a
+/// file, in order
+fn discover(path: &Path) -> io::Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let mut files = vec![];
+    let mut dirs = vec![path.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path
+                .extension()
+                .is_some_and(|ext| ext == "eso" || ext == "txt")
+            {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// the totals of every file, and of all of them together
+fn summary(files: &[FileCount]) -> Vec<Vec<String>> {
+    let mut all = [0; 4];
+    let mut rows: Vec<Vec<String>> = files
+        .iter()
+        .map(|file| {
+            let totals = file.totals();
+            for (all, total) in all.iter_mut().zip(totals) {
+                *all += total;
+            }
+            let mut row = vec![file.path.display().to_string()];
+            row.extend(totals.iter().map(usize::to_string));
+            row
+        })
+        .collect();
+    let mut total = vec!["total".to_string()];
+    total.extend(all.iter().map(usize::to_string));
+    rows.push(total);
+    rows
+}
+
+fn print_text(files: &[FileCount], per_line: bool) {
+    if per_line {
+        for file in files {
+            let [_, _, syllables, approximated] = file.totals();
+            println!(
+                "{}: {} syllables, {} approximated",
+                file.path.display(),
+                syllables,
+                approximated
+            );
+            let rows: Vec<Vec<String>> = file
+                .lines
+                .iter()
+                .enumerate()
+                .map(|(index, line)| {
+                    vec![
+                        (index + 1).to_string(),
+                        line.syllables.to_string(),
+                        line.approximated.to_string(),
+                        line.text.clone(),
+                    ]
+                })
+                .collect();
+            print_table(&LINE_COLUMNS[1..], &rows);
+            println!();
+        }
+    }
+    print_table(&COLUMNS, &summary(files));
+}
+
+fn print_csv(files: &[FileCount], per_line: bool) {
+    if per_line {
+        println!("{}", LINE_COLUMNS.join(","));
+        for file in files {
+            let path = csv_field(&file.path.display().to_string());
+            for (index, line) in file.lines.iter().enumerate() {
+                println!(
+                    "{},{},{},{},{}",
+                    path,
+                    index + 1,
+                    line.syllables,
+                    line.approximated,
+                    csv_field(&line.text)
+                );
+            }
+        }
+        return;
+    }
+    println!("{}", COLUMNS.join(","));
+    for mut row in summary(files) {
+        row[0] = csv_field(&row[0]);
+        println!("{}", row.join(","));
+    }
+}
+
+fn print_json(files: &[FileCount], per_line: bool) {
+    let totals = |[lines, words, syllables, approximated]: [usize; 4]| {
+        json!({
+            "lines": lines,
+            "words": words,
+            "syllables": syllables,
+            "approximated": approximated,
+        })
+    };
+    let mut all = [0; 4];
+    let files: Vec<_> = files
+        .iter()
+        .map(|file| {
+            let file_totals = file.totals();
+            for (all, total) in all.iter_mut().zip(file_totals) {
+                *all += total;
+            }
+            let mut count = totals(file_totals);
+            count["file"] = json!(file.path.display().to_string());
+            if per_line {
+                let lines: Vec<_> = file
+                    .lines
+                    .iter()
+                    .enumerate()
+                    .map(|(index, line)| {
+                        json!({
+                            "line": index + 1,
+                            "text": line.text,
+                            "syllables": line.syllables,
+                            "approximated": line.approximated,
+                        })
+                    })
+                    .collect();
+                count["per_line"] = json!(lines);
+            }
+            count
+        })
+        .collect();
+    let report = json!({ "files": files, "total": totals(all) });
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&report).expect("the report is always valid json")
+    );
+}
+
+pub fn run(matches: &ArgMatches) {
+    let mut paths = vec![];
+    for path in matches.values_of("PATHS").unwrap() {
+        match discover(Path::new(path)) {
+            Ok(found) => paths.extend(found),
+            Err(err) => {
+                eprintln!("error reading {}: {}", path, err);
+                process::exit(1);
+            }
+        }
+    }
+    // up front, rather than on the first word of the first file
+    if !dictionary::is_initialized() {
+        if let Err(err) = dictionary::init_default() {
+            eprintln!("error: {}", err);
+            process::exit(1);
+        }
+    }
+    let mut failed = false;
+    let files: Vec<FileCount> = paths
+        .into_iter()
+        .filter_map(|path| match read_poem(&path) {
+            Ok(contents) => Some(FileCount::new(path, &contents)),
+            Err(err) => {
+                eprintln!("error reading {}: {}", path.display(), err);
+                failed = true;
+                None
+            }
+        })
+        .collect();
+
+    let per_line = matches.is_present("per-line");
+    match matches.value_of("format") {
+        Some("csv") => print_csv(&files, per_line),
+        Some("json") => print_json(&files, per_line),
+        _ => print_text(&files, per_line),
+    }
+    if failed {
+        process::exit(1);
+    }
+}
+
+pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("count")
+        .about("Count the syllables of every .eso and .txt file in directories, or of single files")
+        .args(&[
+            Arg::with_name("PATHS")
+                .help("directories to search, or files to count")
+                .required(true)
+                .multiple(true),
+            Arg::with_name("per-line")
+                .long("per-line")
+                .help("Also count each line, with its text"),
+            Arg::with_name("format")
+                .long("format")
+                .value_name("FORMAT")
+                .help("Print the report as a table, csv or json")
+                .possible_values(&["text", "csv", "json"])
+                .default_value("text"),
+        ])
+}
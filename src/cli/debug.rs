@@ -0,0 +1,214 @@
+use crate::{Interpreter, Program, Register};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use std::{
+    collections::BTreeSet,
+    io::{self, BufRead, Write},
+};
+
+use super::{encoding_arg, read_source};
+
+const PROMPT: &str = "(ashpaper) ";
+
+const HELP: &str = "commands:
+  step, s           execute the next instruction
+  back, p           undo the last instruction
+  continue, c       run until a breakpoint or the end of the poem
+  break, b <line>   toggle a breakpoint on a line
+  regs              show the registers
+  stack             show the stack
+  set <r0|r1> <n>   store n in a register
+  list, l           show the poem with an arrow at the next instruction
+  quit, q           leave the debugger";
+
+/// an interactive session over a single program
+struct Debugger<'a> {
+    interpreter: Interpreter<'a>,
+    breakpoints: BTreeSet<usize>,
+}
+
+impl<'a> Debugger<'a> {
+    fn new(program: &'a Program) -> Debugger<'a> {
+        Debugger {
+            interpreter: Interpreter::new(program),
+            breakpoints: BTreeSet::new(),
+        }
+    }
+
+    fn lines(&self) -> usize {
+        self.interpreter.program().ast.len()
+    }
+
+    /// execute one instruction, reporting output and whether it halted
+    fn step<W: Write>(&mut self, out: &mut W) -> io::Result<bool> {
+        match self.interpreter.step() {
+            Ok(Some(step)) => {
+                if let Some(output) = step.output {
+                    writeln!(out, "[output] {:?}", output)?;
+                }
+                if self.interpreter.is_halted() {
+                    self.report_halt(out)?;
+                    return Ok(false);
+                }
+                Ok(true)
+            }
+            Ok(None) => {
+                self.report_halt(out)?;
+                Ok(false)
+            }
+            Err(err) => {
+                writeln!(out, "{}", err)?;
+                Ok(false)
+            }
+        }
+    }
+
+    fn report_halt<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(
+            out,
+            "program halted after {} instructions",
+            self.interpreter.instructions_executed()
+        )
+    }
+
+    fn show_position<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let ip = self.interpreter.instruction_pointer();
+        if let Some(ins) = self.interpreter.program().ast.get(ip) {
+            writeln!(
+                out,
+                "{:>3}: {} ({})",
+                ip + 1,
+                ins.line(),
+                ins.instruction.mnemonic()
+            )?;
+        }
+        Ok(())
+    }
+
+    fn list<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let ip = self.interpreter.instruction_pointer();
+        for (index, ins) in self.interpreter.program().ast.iter().enumerate() {
+            let arrow = if index == ip { "->" } else { "  " };
+            let breakpoint = if self.breakpoints.contains(&index) {
+                '*'
+            } else {
+                ' '
+            };
+            writeln!(
+                out,
+                "{}{}{:>3} | {}",
+                arrow,
+                breakpoint,
+                index + 1,
+                ins.line()
+            )?;
+        }
+        Ok(())
+    }
+
+    fn run_command<W: Write>(&mut self, command: &str, out: &mut W) -> io::Result<bool> {
+        let words: Vec<&str> = command.split_whitespace().collect();
+        match words.as_slice() {
+            [] => (),
+            ["step"] | ["s"] => {
+                if self.step(out)? {
+                    self.show_position(out)?;
+                }
+            }
+            ["back"] | ["p"] => match self.interpreter.step_back() {
+                Ok(()) => self.show_position(out)?,
+                Err(err) => writeln!(out, "can't step back: {}", err)?,
+            },
+            ["continue"] | ["c"] => {
+                // always move past the current line so a breakpoint on it
+                // doesn't stop us straight away
+                let mut running = self.step(out)?;
+                while running {
+                    if self
+                        .breakpoints
+                        .contains(&self.interpreter.instruction_pointer())
+                    {
+                        writeln!(out, "breakpoint hit")?;
+                        self.show_position(out)?;
+                        break;
+                    }
+                    running = self.step(out)?;
+                }
+            }
+            ["break", line] | ["b", line] => match line.parse::<usize>() {
+                Ok(line) if line >= 1 && line <= self.lines() => {
+                    let index = line - 1;
+                    if self.breakpoints.remove(&index) {
+                        writeln!(out, "removed breakpoint on line {}", line)?;
+                    } else {
+                        self.breakpoints.insert(index);
+                        writeln!(out, "breakpoint set on line {}", line)?;
+                    }
+                }
+                _ => writeln!(out, "no line {} in the poem", line)?,
+            },
+            ["regs"] => writeln!(
+                out,
+                "r0 = {}\nr1 = {}",
+                self.interpreter.register(Register::Register0),
+                self.interpreter.register(Register::Register1)
+            )?,
+            ["stack"] => writeln!(out, "{:?}", self.interpreter.stack())?,
+            ["set", register, value] => {
+                let register = match *register {
+                    "r0" => Some(Register::Register0),
+                    "r1" => Some(Register::Register1),
+                    _ => None,
+                };
+                match (register, value.parse::<i64>()) {
+                    (Some(register), Ok(value)) => {
+                        self.interpreter.set_register(register, value);
+                        writeln!(out, "{} = {}", words[1], value)?;
+                    }
+                    _ => writeln!(out, "usage: set <r0|r1> <n>")?,
+                }
+            }
+            ["list"] | ["l"] => self.list(out)?,
+            ["quit"] | ["q"] => return Ok(false),
+            ["help"] | ["h"] => writeln!(out, "{}", HELP)?,
+            _ => writeln!(out, "unknown command '{}', try 'help'", command.trim())?,
+        }
+        Ok(true)
+    }
+
+    fn session<R: BufRead, W: Write>(&mut self, input: R, out: &mut W) -> io::Result<()> {
+        self.show_position(out)?;
+        write!(out, "{}", PROMPT)?;
+        out.flush()?;
+        for command in input.lines() {
+            if !self.run_command(&command?, out)? {
+                return Ok(());
+            }
+            write!(out, "{}", PROMPT)?;
+            out.flush()?;
+        }
+        writeln!(out)
+    }
+}
+
+pub fn run(matches: &ArgMatches) {
+    let contents = read_source(matches.value_of("POEM").unwrap(), matches);
+    let program = Program::create(&contents);
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let mut debugger = Debugger::new(&program);
+    if let Err(err) = debugger.session(stdin.lock(), &mut stdout.lock()) {
+        eprintln!("debugger error: {}", err);
+    }
+}
+
+pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("debug")
+        .about("Step through a poem interactively (type 'help' at the prompt)")
+        .arg(
+            Arg::with_name("POEM")
+                .help(".eso file to debug")
+                .required(true)
+                .index(1),
+        )
+        .arg(encoding_arg())
+}
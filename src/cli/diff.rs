@@ -0,0 +1,169 @@
+//! the `diff` subcommand, which prints what an edit to a poem changed about
+//! the program it runs
+use crate::{
+    diff::{Change, LineDiff, SemanticDiff},
+    io::Encoding,
+    semantic_diff,
+};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use serde_json::json;
+use std::{
+    env,
+    io::{self, IsTerminal},
+    path::Path,
+    process,
+};
+
+use super::encoding_arg;
+
+const RESET: &str = "\x1b[0m";
+const ADDED: &str = "\x1b[32m";
+const REMOVED: &str = "\x1b[31m";
+const CHANGED: &str = "\x1b[33m";
+
+/// the name a change has in the json
+fn kind(change: &Change) -> &'static str {
+    match change {
+        Change::Added(_) => "added",
+        Change::Removed(_) => "removed",
+        Change::Instruction { .. } => "instruction",
+        Change::Syllables { .. } => "syllables",
+        Change::Register { .. } => "register",
+        Change::TextOnly => "text",
+    }
+}
+
+fn to_json(diff: &SemanticDiff) -> serde_json::Value {
+    let number = |line: Option<usize>| line.map(|line| line + 1);
+    let lines: Vec<_> = diff
+        .lines
+        .iter()
+        .map(|line| {
+            let changes: Vec<_> = line
+                .changes
+                .iter()
+                .map(|change| {
+                    json!({
+                        "kind": kind(change),
+                        "semantic": change.is_semantic(),
+                        "description": change.to_string(),
+                    })
+                })
+                .collect();
+            json!({
+                "old_line": number(line.old_line),
+                "new_line": number(line.new_line),
+                "changes": changes,
+            })
+        })
+        .collect();
+    let shifted: Vec<_> = diff
+        .shifted_goto_targets
+        .iter()
+        .map(|&(old, new)| json!({ "old_line": old + 1, "new_line": new + 1 }))
+        .collect();
+    json!({
+        "semantically_equal": diff.is_semantically_equal(),
+        "lines": lines,
+        "shifted_goto_targets": shifted,
+    })
+}
+
+/// the color of a line, by whether it was added, removed or runs
+/// differently. lines that only changed their words aren't colored
+fn line_color(line: &LineDiff) -> Option<&'static str> {
+    match (line.old_line, line.new_line) {
+        (_, None) => Some(REMOVED),
+        (None, _) => Some(ADDED),
+        _ if line.changes.iter().any(Change::is_semantic) => Some(CHANGED),
+        _ => None,
+    }
+}
+
+/// the diff as [`SemanticDiff`]'s display prints it, colored line by line
+fn render(diff: &SemanticDiff, color: bool) -> String {
+    let report = diff.to_string();
+    if !color {
+        return report;
+    }
+    let paint = |text: &str, style: Option<&str>| match style {
+        Some(style) => format!("{}{}{}\n", style, text, RESET),
+        None => format!("{}\n", text),
+    };
+    let mut rows = report.lines();
+    let mut rendered = String::new();
+    if diff.is_semantically_equal() {
+        rendered.extend(rows.next().map(|heading| paint(heading, None)));
+    }
+    for (line, row) in diff.lines.iter().zip(&mut rows) {
+        rendered.push_str(&paint(row, line_color(line)));
+    }
+    // the gotos that moved
+    for row in rows {
+        rendered.push_str(&paint(row, Some(CHANGED)));
+    }
+    rendered
+}
+
+fn use_color(matches: &ArgMatches) -> bool {
+    match matches.value_of("color") {
+        Some("always") => true,
+        Some("never") => false,
+        _ => {
+            let no_color = env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+            !no_color && io::stdout().is_terminal()
+        }
+    }
+}
+
+/// read a poem, exiting with code 2 if it can't be
+fn read(fname: &str, matches: &ArgMatches) -> String {
+    // clap restricts the value to one of the possible encodings
+    let encoding: Encoding = matches.value_of("encoding").unwrap().parse().unwrap();
+    crate::program::read_file(Path::new(fname), encoding).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        process::exit(2);
+    })
+}
+
+pub fn run(matches: &ArgMatches) {
+    let old = read(matches.value_of("OLD").unwrap(), matches);
+    let new = read(matches.value_of("NEW").unwrap(), matches);
+    let diff = semantic_diff(&old, &new);
+    if matches.is_present("json") {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&to_json(&diff)).expect("the diff is always valid json")
+        );
+    } else {
+        print!("{}", render(&diff, use_color(matches)));
+    }
+    if !diff.is_semantically_equal() {
+        process::exit(1);
+    }
+}
+
+pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("diff")
+        .about("Compare what two poems do line by line, exiting with code 1 if they run differently and 2 if either can't be read")
+        .args(&[
+            Arg::with_name("OLD")
+                .help("the .eso file before the edit")
+                .required(true)
+                .index(1),
+            Arg::with_name("NEW")
+                .help("the .eso file after the edit")
+                .required(true)
+                .index(2),
+            Arg::with_name("json")
+                .long("json")
+                .help("Print the diff as json"),
+            Arg::with_name("color")
+                .long("color")
+                .value_name("WHEN")
+                .help("Color added, removed and changed lines (auto respects NO_COLOR and whether stdout is a terminal)")
+                .possible_values(&["auto", "always", "never"])
+                .default_value("auto"),
+        ])
+        .arg(encoding_arg())
+}
@@ -0,0 +1,76 @@
+use crate::format;
+use clap::{App, Arg, ArgMatches, SubCommand};
+use std::{fs, io, path::Path, process};
+
+/// replace `path` with `contents` by writing a temporary file next to it and
+/// renaming it over the original, so a poem is never left half written
+fn write_atomically(path: &Path, contents: &str) -> io::Result<()> {
+    let mut temp_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::other("not a file"))?
+        .to_os_string();
+    temp_name.push(".fmt-tmp");
+    let temp = path.with_file_name(temp_name);
+    fs::write(&temp, contents)?;
+    fs::rename(&temp, path).inspect_err(|_| {
+        let _ = fs::remove_file(&temp);
+    })
+}
+
+pub fn run(matches: &ArgMatches) {
+    let check = matches.is_present("check");
+    let mut failed = false;
+    let mut unformatted = false;
+    for fname in matches.values_of("FILES").unwrap() {
+        let path = Path::new(fname);
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) => {
+                eprintln!("error reading {}: {}", fname, err);
+                failed = true;
+                continue;
+            }
+        };
+        let formatted = match format::format_checked(&contents) {
+            Ok(formatted) => formatted,
+            Err(err) => {
+                eprintln!(
+                    "error: refusing to format {}: {}. this is a bug in the formatter, \
+                     the file has been left untouched",
+                    fname, err
+                );
+                failed = true;
+                continue;
+            }
+        };
+        if formatted == contents {
+            continue;
+        }
+        if check {
+            println!("would reformat {}", fname);
+            unformatted = true;
+        } else if let Err(err) = write_atomically(path, &formatted) {
+            eprintln!("error writing {}: {}", fname, err);
+            failed = true;
+        } else {
+            println!("formatted {}", fname);
+        }
+    }
+    if failed || unformatted {
+        process::exit(1);
+    }
+}
+
+pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("fmt")
+        .about("Tidy up the whitespace of poems in place without changing what they do")
+        .args(&[
+            Arg::with_name("FILES")
+                .help(".eso files to format")
+                .required(true)
+                .multiple(true),
+            Arg::with_name("check")
+                .long("check")
+                .help("Don't write anything, exit with code 1 if any file would be reformatted"),
+        ])
+}
@@ -0,0 +1,55 @@
+use crate::{ast, compose};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use std::{fs, process};
+
+use super::parse_arg;
+
+pub fn run(matches: &ArgMatches) {
+    let fname = matches.value_of("PROGRAM").unwrap();
+    let seed: u64 = parse_arg(matches, "seed");
+
+    let json = fs::read_to_string(fname).unwrap_or_else(|err| {
+        eprintln!("error reading {}: {}", fname, err);
+        process::exit(1);
+    });
+    let program = ast::from_json(&json).unwrap_or_else(|err| {
+        eprintln!("error: {} isn't a valid program: {}", fname, err);
+        process::exit(1);
+    });
+    // compose re-parses the poem to check it round-trips
+    let poem = compose::compose(&program.ast, seed).unwrap_or_else(|err| {
+        eprintln!("error: {}", err);
+        process::exit(1);
+    });
+
+    match matches.value_of("output") {
+        Some(path) => {
+            if let Err(err) = fs::write(path, &poem) {
+                eprintln!("error writing {}: {}", path, err);
+                process::exit(1);
+            }
+        }
+        None => print!("{}", poem),
+    }
+}
+
+pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("generate")
+        .about("Write a poem that compiles to the instructions in a json file (as printed by --ast=json)")
+        .args(&[
+            Arg::with_name("PROGRAM")
+                .help(".json file with the instructions of the program")
+                .required(true)
+                .index(1),
+            Arg::with_name("output")
+                .short("o")
+                .long("output")
+                .value_name("FILE")
+                .help("Write the poem to a file instead of stdout"),
+            Arg::with_name("seed")
+                .long("seed")
+                .value_name("N")
+                .help("Seed for picking words, the same seed always writes the same poem")
+                .default_value("0"),
+        ])
+}
@@ -0,0 +1,101 @@
+use clap::{Arg, ArgMatches};
+use serde_json::json;
+use std::{collections::HashSet, io::Write, process};
+
+use super::parse_values;
+use crate::lint::{self, Diagnostic, Lint};
+
+fn to_json(fname: &str, diagnostics: &[(Diagnostic, bool)]) -> String {
+    let diagnostics: Vec<_> = diagnostics
+        .iter()
+        .map(|(diagnostic, denied)| {
+            json!({
+                "file": fname,
+                "line": diagnostic.line,
+                "code": diagnostic.lint.code(),
+                "name": diagnostic.lint.name(),
+                "severity": if *denied { "error" } else { diagnostic.lint.severity().name() },
+                "message": diagnostic.message,
+            })
+        })
+        .collect();
+    serde_json::to_string_pretty(&diagnostics).expect("diagnostics are always valid json")
+}
+
+/// lint `contents`, print the diagnostics and exit if any denied lint was
+/// hit. returns whether the poem should be executed afterwards
+pub fn run(fname: &str, contents: &str, matches: &ArgMatches) -> bool {
+    let allowed: HashSet<Lint> = parse_values(matches, "allow");
+    let denied: HashSet<Lint> = parse_values(matches, "deny");
+    let execute = matches.is_present("run");
+    // denying a lint that's off by default turns it on too
+    let mut enabled: Vec<Lint> = parse_values(matches, "enable");
+    enabled.extend(denied.iter().filter(|lint| !lint.is_default()));
+
+    let diagnostics: Vec<(Diagnostic, bool)> = lint::lint_with(contents, &enabled)
+        .into_iter()
+        .filter(|diagnostic| !allowed.contains(&diagnostic.lint))
+        .map(|diagnostic| {
+            let is_denied = denied.contains(&diagnostic.lint);
+            (diagnostic, is_denied)
+        })
+        .collect();
+
+    let rendered = match matches.value_of("lint") {
+        Some("json") => format!("{}\n", to_json(fname, &diagnostics)),
+        _ => diagnostics
+            .iter()
+            .map(|(diagnostic, _)| format!("{}:{}\n", fname, diagnostic))
+            .collect(),
+    };
+    // keep the poem's own output clean when it's going to be executed too
+    if execute {
+        eprint!("{}", rendered);
+    } else {
+        print!("{}", rendered);
+        let _ = std::io::stdout().flush();
+    }
+
+    if diagnostics.iter().any(|(_, denied)| *denied) {
+        process::exit(1);
+    }
+    execute
+}
+
+pub fn args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name("lint")
+            .long("lint")
+            .value_name("FORMAT")
+            .help("Check the poem for likely mistakes instead of executing it")
+            .possible_values(&["text", "json"])
+            .takes_value(true)
+            .min_values(0)
+            .require_equals(true),
+        Arg::with_name("run")
+            .long("run")
+            .help("Execute the poem after linting it")
+            .requires("lint"),
+        Arg::with_name("allow")
+            .long("allow")
+            .value_name("LINT")
+            .help("Don't report a lint, by code or name (e.g. W001 or unknown-word)")
+            .multiple(true)
+            .number_of_values(1)
+            .requires("lint"),
+        Arg::with_name("enable")
+            .long("enable")
+            .value_name("LINT")
+            .help("Report a lint that's off by default, e.g. I001 or broken-meter for lines that break the poem's meter")
+            .multiple(true)
+            .number_of_values(1)
+            .requires("lint"),
+        Arg::with_name("deny")
+            .long("deny")
+            .value_name("LINT")
+            .help("Treat a lint as an error, exiting with code 1 if it's found")
+            .multiple(true)
+            .number_of_values(1)
+            .requires("lint"),
+    ]
+}
@@ -0,0 +1,896 @@
+//! the command line interface of the `ashpaper-plus` binary. it lives in the
+//! library so every binary shares the exact same flags and behavior
+mod bench;
+mod corpus;
+mod count;
+mod debug;
+mod diff;
+mod fmt;
+mod generate;
+mod lint;
+#[cfg(feature = "serve")]
+mod serve;
+#[cfg(feature = "tui")]
+mod tui;
+
+use crate::{
+    ast, io::Encoding, profile::Profile, program, title, trace::Trace, Dialect, ExecError,
+    ExecOptions, ExecutionReport, Executor, InputSource, ParseOptions, Program,
+};
+use clap::{App, AppSettings, Arg, ArgMatches};
+use serde_json::json;
+use std::{
+    env, fs,
+    io::{self, IsTerminal, Write},
+    process,
+};
+
+/// exit code used when a poem's output is cut off by --limit-output
+const EXIT_OUTPUT_LIMIT: i32 = 3;
+
+#[cfg(feature = "jit")]
+pub(crate) fn conditional_jit_arg<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+    app.arg(
+        Arg::with_name("jit")
+            .short("j")
+            .long("jit")
+            .help("Enable high performace jit compilation with cranelift (disables debugging)"),
+    )
+}
+
+#[cfg(not(feature = "jit"))]
+pub(crate) fn conditional_jit_arg<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+    app
+}
+
+#[cfg(feature = "tui")]
+fn conditional_tui_subcommand<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+    app.subcommand(tui::subcommand())
+}
+
+#[cfg(not(feature = "tui"))]
+fn conditional_tui_subcommand<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+    app
+}
+
+#[cfg(feature = "serve")]
+fn conditional_serve_subcommand<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+    app.subcommand(serve::subcommand())
+}
+
+#[cfg(not(feature = "serve"))]
+fn conditional_serve_subcommand<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+    app
+}
+
+#[cfg(feature = "jit")]
+pub(crate) fn jit_requested(matches: &ArgMatches) -> bool {
+    matches.is_present("jit")
+}
+
+#[cfg(not(feature = "jit"))]
+pub(crate) fn jit_requested(_matches: &ArgMatches) -> bool {
+    false
+}
+
+/// the jit if `--jit` was given
+#[cfg(feature = "jit")]
+pub(crate) fn jit_executor(matches: &ArgMatches) -> Option<Box<dyn Executor>> {
+    jit_requested(matches).then(|| Box::new(crate::JitExecutor) as Box<dyn Executor>)
+}
+
+#[cfg(not(feature = "jit"))]
+pub(crate) fn jit_executor(_matches: &ArgMatches) -> Option<Box<dyn Executor>> {
+    None
+}
+
+/// whether the trace table written to stderr should be colored
+fn use_color(matches: &ArgMatches) -> bool {
+    match matches.value_of("color") {
+        Some("always") => true,
+        Some("never") => false,
+        _ => {
+            let no_color = env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+            !no_color && io::stderr().is_terminal()
+        }
+    }
+}
+
+/// parse a byte count with an optional binary suffix, e.g. `512`, `64k` or `1m`
+fn parse_size(size: &str) -> Option<usize> {
+    let size = size.trim().to_lowercase();
+    let (digits, multiplier) = match size.chars().last()? {
+        'k' => (&size[..size.len() - 1], 1024),
+        'm' => (&size[..size.len() - 1], 1024 * 1024),
+        'g' => (&size[..size.len() - 1], 1024 * 1024 * 1024),
+        _ => (&size[..], 1),
+    };
+    digits.parse::<usize>().ok()?.checked_mul(multiplier)
+}
+
+fn exec_options(matches: &ArgMatches) -> ExecOptions {
+    let max_instructions = matches
+        .is_present("limit-fuel")
+        .then(|| parse_arg(matches, "limit-fuel"));
+    let max_output = matches.value_of("limit-output").map(|limit| {
+        parse_size(limit).unwrap_or_else(|| {
+            eprintln!("invalid value for --limit-output: '{}'", limit);
+            process::exit(1);
+        })
+    });
+    let args: Vec<String> = matches
+        .values_of("ARGS")
+        .map(|args| args.map(str::to_string).collect())
+        .unwrap_or_default();
+    let seeded = if matches.is_present("int-args") {
+        ExecOptions::stack_seed_from_integers(&args).unwrap_or_else(|err| {
+            eprintln!("error: {}", err);
+            process::exit(1);
+        })
+    } else if args.is_empty() {
+        ExecOptions::default()
+    } else {
+        ExecOptions::stack_seed_from(&args)
+    };
+    ExecOptions {
+        max_instructions,
+        max_output,
+        input: Some(InputSource::new(io::stdin())),
+        ..seeded
+    }
+}
+
+/// write the trace to stderr as a table, or to the file given with
+/// `--trace=FILE` in a format picked by its extension: json lines for
+/// `.jsonl` and `.ndjson`, csv for `.csv` and an uncolored table otherwise
+fn write_trace(matches: &ArgMatches, trace: &Trace) {
+    let path = match matches.value_of("trace") {
+        Some(path) => path,
+        None => {
+            eprint!("{}", trace.render_table(use_color(matches)));
+            return;
+        }
+    };
+    let extension = std::path::Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_lowercase);
+    let result = fs::File::create(path).and_then(|file| {
+        let mut file = io::BufWriter::new(file);
+        match extension.as_deref() {
+            Some("jsonl") | Some("ndjson") => trace.write_jsonl(file),
+            Some("csv") => trace.write_csv(file),
+            _ => {
+                file.write_all(trace.render_table(false).as_bytes())?;
+                file.flush()
+            }
+        }
+    });
+    if let Err(err) = result {
+        eprintln!("error writing trace to {}: {}", path, err);
+        process::exit(1);
+    }
+}
+
+/// write the profile to the file given with `--profile=FILE`, or stderr
+fn write_profile(matches: &ArgMatches, program: &Program, profile: &Profile) {
+    let rendered = profile.render(program, matches.is_present("profile-percentages"));
+    match matches.value_of("profile") {
+        Some(path) => {
+            if let Err(err) = fs::write(path, rendered) {
+                eprintln!("error writing profile to {}: {}", path, err);
+                process::exit(1);
+            }
+        }
+        None => eprint!("{}", rendered),
+    }
+}
+
+/// write the statistics `--stats` asks for to stderr
+fn write_stats(report: &ExecutionReport) {
+    eprintln!("instructions executed: {}", report.instructions_executed);
+    eprintln!("max stack depth: {}", report.max_stack_depth);
+    let width = report
+        .per_opcode_counts
+        .keys()
+        .map(|mnemonic| mnemonic.len())
+        .max()
+        .unwrap_or(0);
+    for (mnemonic, count) in &report.per_opcode_counts {
+        eprintln!("  {: <width$}  {}", mnemonic, count, width = width);
+    }
+}
+
+/// with `--jit`, what compiling the poem took for `--stats` and `--time`.
+/// it's compiled again to measure it, apart from the run
+#[cfg(feature = "jit")]
+fn write_compile_stats(matches: &ArgMatches, program: &Program) {
+    let stats = match program.compile() {
+        Ok(stats) => stats,
+        Err(err) => return eprintln!("{}", err),
+    };
+    if matches.is_present("stats") {
+        eprintln!("blocks: {}", stats.blocks);
+        eprintln!("instructions lowered: {}", stats.instructions_lowered);
+        eprintln!("jump table entries: {}", stats.jump_table_entries);
+        eprintln!("code bytes: {}", stats.clif_bytes);
+    }
+    if matches.is_present("time") {
+        eprintln!("compile time: {:?}", stats.compile_time);
+    }
+}
+
+#[cfg(not(feature = "jit"))]
+fn write_compile_stats(_matches: &ArgMatches, _program: &Program) {}
+
+/// write the control-flow graph to the file given with `--dot=FILE`, or
+/// stdout. with `--dot-open` it's also rendered to svg and opened if
+/// graphviz is installed
+fn write_dot(matches: &ArgMatches, program: &Program) {
+    let dot = program.to_dot();
+    let path = match matches.value_of("dot") {
+        Some(path) => {
+            if let Err(err) = fs::write(path, &dot) {
+                eprintln!("error writing graph to {}: {}", path, err);
+                process::exit(1);
+            }
+            path.into()
+        }
+        None => {
+            print!("{}", dot);
+            env::temp_dir().join(format!("ashpaper-{}.dot", process::id()))
+        }
+    };
+    if matches.is_present("dot-open") {
+        if let Err(err) = open_svg(&dot, &path.with_extension("svg")) {
+            eprintln!("warning: couldn't render the graph: {}", err);
+        }
+    }
+}
+
+/// best-effort rendering of a graph with graphviz's `dot` and opening the
+/// result with the platform's default viewer
+fn open_svg(dot: &str, svg: &std::path::Path) -> io::Result<()> {
+    use std::process::{Command, Stdio};
+
+    let mut child = Command::new("dot")
+        .arg("-Tsvg")
+        .arg("-o")
+        .arg(svg)
+        .stdin(Stdio::piped())
+        .spawn()?;
+    child.stdin.take().unwrap().write_all(dot.as_bytes())?;
+    if !child.wait()?.success() {
+        return Err(io::Error::other("dot failed"));
+    }
+    eprintln!("graph rendered to {}", svg.display());
+    let opener = if cfg!(target_os = "macos") {
+        "open"
+    } else if cfg!(windows) {
+        "explorer"
+    } else {
+        "xdg-open"
+    };
+    Command::new(opener).arg(svg).spawn().map(|_| ())
+}
+
+/// turn a register into a process exit code. values above 255 are clamped
+/// to 255 and so are negative values, so they still count as a failure
+/// log at the level picked by how many times -v was given, or off without
+/// it. RUST_LOG still overrides it for anyone used to env_logger
+fn init_logger(matches: &ArgMatches) {
+    let level = match matches.occurrences_of("verbose") {
+        0 => log::LevelFilter::Off,
+        1 => log::LevelFilter::Info,
+        _ => log::LevelFilter::Trace,
+    };
+    let mut builder = env_logger::Builder::new();
+    builder.filter_level(level);
+    if let Ok(filters) = env::var("RUST_LOG") {
+        builder.parse_filters(&filters);
+    }
+    builder.init();
+}
+
+fn exit_code(register: i64) -> i32 {
+    if register < 0 {
+        255
+    } else {
+        register.min(255) as i32
+    }
+}
+
+/// the run as the one json object `--json` prints, with `output` being
+/// everything the poem printed
+fn report_json(report: &ExecutionReport, output: &str) -> serde_json::Value {
+    let mut warnings = vec![];
+    let error = match &report.error {
+        Some(ExecError::OutputLimit { limit, .. }) => {
+            warnings.push(format!("output truncated at the {} byte limit", limit));
+            None
+        }
+        error => error.as_ref().map(ToString::to_string),
+    };
+    if report.ignored_pops > 0 {
+        let plural = if report.ignored_pops == 1 { "" } else { "s" };
+        warnings.push(format!(
+            "ignored {} pop{} from an empty stack",
+            report.ignored_pops, plural
+        ));
+    }
+    json!({
+        "output": output,
+        "exit_reason": report.halted_reason.name(),
+        "instructions_executed": report.instructions_executed,
+        "registers": {
+            "r0": report.final_registers.r0,
+            "r1": report.final_registers.r1,
+        },
+        "stack": report.final_stack,
+        "duration_ms": report.elapsed.as_secs_f64() * 1000.0,
+        "warnings": warnings,
+        "error": error,
+    })
+}
+
+/// print `--json`'s object for a run that failed before the poem could run
+fn fail_json(message: &str) -> ! {
+    println!("{}", json!({ "error": message }));
+    process::exit(1);
+}
+
+fn interpret(matches: &ArgMatches, program: &Program) {
+    let json = matches.is_present("json");
+    if !json {
+        println!("executing");
+    }
+    let options = exec_options(matches);
+
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    // with --json the output goes in the object printed after the run
+    let mut captured = String::new();
+    let emit = |s: &str| {
+        if json {
+            captured.push_str(s);
+        } else {
+            // a closed stdout shouldn't abort the poem
+            let _ = stdout.write_all(s.as_bytes());
+        }
+    };
+
+    let mut trace = matches.is_present("trace").then(Trace::default);
+    let mut profile = matches.is_present("profile").then(|| Profile::new(program));
+    let report = program.execute_report_with_hook(&options, emit, |interpreter, step| {
+        if let Some(trace) = trace.as_mut() {
+            trace.push(interpreter.trace_row(step));
+        }
+        if let Some(profile) = profile.as_mut() {
+            profile.record(step);
+        }
+    });
+    let _ = stdout.flush();
+
+    if let Some(trace) = trace {
+        write_trace(matches, &trace);
+    }
+    if let Some(profile) = profile {
+        write_profile(matches, program, &profile);
+    }
+    if matches.is_present("stats") {
+        write_stats(&report);
+    }
+    if matches.is_present("time") {
+        eprintln!("time: {:?}", report.elapsed);
+    }
+
+    if json {
+        let _ = writeln!(stdout, "{}", report_json(&report, &captured));
+        let _ = stdout.flush();
+        process::exit(match report.error {
+            None if matches.is_present("exit-register") => exit_code(report.final_registers.r0),
+            None => 0,
+            Some(ExecError::OutputLimit { .. }) => EXIT_OUTPUT_LIMIT,
+            Some(_) => 1,
+        });
+    }
+
+    match report.error {
+        None => {
+            if matches.is_present("exit-register") {
+                process::exit(exit_code(report.final_registers.r0));
+            }
+        }
+        Some(ExecError::OutputLimit { limit, .. }) => {
+            eprintln!("warning: output truncated at the {} byte limit", limit);
+            process::exit(EXIT_OUTPUT_LIMIT);
+        }
+        Some(err) => {
+            // the error already says it's an error and where
+            eprintln!("{}", program.render_error(&err));
+            process::exit(1);
+        }
+    }
+}
+
+fn execute_program(matches: &ArgMatches, program: &Program) {
+    let jit = match jit_executor(matches) {
+        Some(jit) => jit,
+        None => return interpret(matches, program),
+    };
+    if matches.is_present("trace") {
+        eprintln!("warning: --trace is not supported with --jit");
+    }
+    if matches.is_present("profile") {
+        eprintln!("warning: --profile is not supported with --jit");
+    }
+    if matches.is_present("stats") || matches.is_present("time") {
+        write_compile_stats(matches, program);
+    }
+    if matches.is_present("exit-register") {
+        eprintln!("warning: --exit-register is not supported with --jit");
+    }
+    if matches.is_present("json") {
+        match jit.run(program, &ExecOptions::default()) {
+            Ok(report) => println!("{}", report_json(&report, &report.output)),
+            Err(err) => fail_json(&err.to_string()),
+        }
+        return;
+    }
+    println!("jit executing");
+    match jit.run(program, &ExecOptions::default()) {
+        Ok(report) => print!("{}", report.output),
+        Err(err) => eprintln!("{}", err),
+    }
+}
+
+pub(crate) fn parse_arg<T: std::str::FromStr>(matches: &ArgMatches, name: &str) -> T {
+    let value = matches.value_of(name).unwrap();
+    match value.parse() {
+        Ok(parsed) => parsed,
+        Err(_) => {
+            eprintln!("invalid value for --{}: '{}'", name, value);
+            process::exit(1);
+        }
+    }
+}
+
+/// parse every occurrence of a flag that can be given multiple times
+pub(crate) fn parse_values<T, C>(matches: &ArgMatches, name: &str) -> C
+where
+    T: std::str::FromStr,
+    C: std::iter::FromIterator<T>,
+{
+    matches
+        .values_of(name)
+        .into_iter()
+        .flatten()
+        .map(|value| match value.parse() {
+            Ok(parsed) => parsed,
+            Err(_) => {
+                eprintln!("invalid value for --{}: '{}'", name, value);
+                process::exit(1);
+            }
+        })
+        .collect()
+}
+
+pub(crate) fn encoding_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("encoding")
+        .long("encoding")
+        .value_name("ENCODING")
+        .help("Encoding of the input file")
+        .possible_values(&["utf8", "latin1", "lossy"])
+        .default_value("utf8")
+}
+
+/// read a poem using the encoding selected by `encoding_arg`, exiting on
+/// failure
+pub(crate) fn read_source(fname: &str, matches: &ArgMatches) -> String {
+    // clap restricts the value to one of the possible encodings
+    let encoding: Encoding = matches.value_of("encoding").unwrap().parse().unwrap();
+    crate::program::read_file(std::path::Path::new(fname), encoding).unwrap_or_else(|err| {
+        if matches.is_present("json") {
+            fail_json(&err.to_string());
+        }
+        eprintln!("{}", err);
+        process::exit(1);
+    })
+}
+
+/// the clap app for the `ashpaper-plus` binary with every flag and
+/// subcommand
+pub fn app() -> App<'static, 'static> {
+    let app = App::new(clap::crate_name!())
+        .version(clap::crate_version!())
+        .author(env!("CARGO_PKG_AUTHORS"))
+        .about(clap::crate_description!())
+        .setting(AppSettings::SubcommandsNegateReqs)
+        // without this clap rejects poems whose path merely looks like a
+        // subcommand, e.g. tests/poem.eso as a misspelling of test
+        .setting(AppSettings::InferSubcommands)
+        .subcommand(bench::subcommand())
+        .subcommand(debug::subcommand())
+        .subcommand(corpus::subcommand())
+        .subcommand(count::subcommand())
+        .subcommand(diff::subcommand())
+        .subcommand(fmt::subcommand())
+        .subcommand(generate::subcommand())
+        .args(&[
+            Arg::with_name("INPUT")
+                .help(".eso file to compile")
+                .required_unless_one(&["syllables", "eval"])
+                .index(1),
+            Arg::with_name("eval")
+                .short("e")
+                .long("eval")
+                .value_name("STRING")
+                .help("Run the poem given on the command line instead of a file, each -e adding a line")
+                .multiple(true)
+                .number_of_values(1)
+                .conflicts_with("INPUT"),
+            Arg::with_name("ARGS")
+                .help("Arguments pushed onto the stack before the poem runs, given after --, the first byte of the first one on top and a 0 below the last")
+                .multiple(true)
+                .last(true)
+                .index(2),
+            Arg::with_name("int-args")
+                .long("int-args")
+                .help("Push the arguments as integers, the first one on top, instead of as bytes")
+                .requires("ARGS"),
+            Arg::with_name("syllables")
+                .short("s")
+                .long("syllables")
+                .value_name("STRING")
+                .help("Count number of syllables in a string and exit")
+                .takes_value(true),
+            encoding_arg(),
+            Arg::with_name("extended")
+                .long("extended")
+                .help("Parse with the extended dialect, where ; subtracts, : divides and ! reads a character from stdin"),
+            Arg::with_name("no-dict")
+                .long("no-dict")
+                .help("Approximate the syllables of every word rather than looking them up, and find no rhymes, as if there were no dictionary"),
+            Arg::with_name("min-indent")
+                .long("min-indent")
+                .value_name("N")
+                .help("Only use r1 for lines indented by at least N columns (default 1)")
+                .takes_value(true),
+            Arg::with_name("tab-width")
+                .long("tab-width")
+                .value_name("N")
+                .help("Count a tab as N columns of indent (default 1, or 4 with --extended)")
+                .takes_value(true),
+            Arg::with_name("dict")
+                .long("dict")
+                .value_name("FILE")
+                .help("Look words up in FILE, in the cmudict format, instead of the dictionary compiled in")
+                .takes_value(true),
+            Arg::with_name("title")
+                .long("title")
+                .value_name("TITLE")
+                .help("Replace the poem's first line, whose syllables are conventionally its input")
+                .takes_value(true),
+            Arg::with_name("gen-title")
+                .long("gen-title")
+                .value_name("N")
+                .help("Replace the poem's first line with a generated title of N syllables")
+                .takes_value(true)
+                .conflicts_with("title"),
+            Arg::with_name("trace")
+                .short("t")
+                .long("trace")
+                .value_name("FILE")
+                .help("Print a table of the machine state after every instruction to stderr, or write it to FILE with --trace=FILE (as json lines or csv for .jsonl and .csv files)")
+                .takes_value(true)
+                .min_values(0)
+                .require_equals(true),
+            Arg::with_name("verbose")
+                .short("v")
+                .multiple(true)
+                .help("Log the interpreter's state after every instruction (-vv for everything), RUST_LOG takes precedence"),
+            Arg::with_name("color")
+                .long("color")
+                .value_name("WHEN")
+                .help("Color the trace table (auto respects NO_COLOR and whether stderr is a terminal)")
+                .possible_values(&["auto", "always", "never"])
+                .default_value("auto"),
+            Arg::with_name("limit-output")
+                .long("limit-output")
+                .value_name("BYTES")
+                .help("Stop after printing BYTES of output (accepts k and m suffixes) and exit with code 3")
+                .takes_value(true),
+            Arg::with_name("limit-fuel")
+                .long("limit-fuel")
+                .value_name("N")
+                .help("Stop a poem with an error after N instructions")
+                .takes_value(true),
+            Arg::with_name("json")
+                .long("json")
+                .help("Print one json object describing the run instead of the poem's output: its output, exit_reason, instructions_executed, registers, stack, duration_ms, warnings and error"),
+            Arg::with_name("profile")
+                .long("profile")
+                .value_name("FILE")
+                .help("Print how many times each line ran to stderr, or to FILE with --profile=FILE")
+                .takes_value(true)
+                .min_values(0)
+                .require_equals(true),
+            Arg::with_name("stats")
+                .long("stats")
+                .help("Print how many instructions ran, of each kind, and the deepest the stack got to stderr (with --jit, what the poem compiled to)"),
+            Arg::with_name("time")
+                .long("time")
+                .help("Print how long the poem took to run to stderr (with --jit, how long it took to compile)"),
+            Arg::with_name("exit-register")
+                .long("exit-register")
+                .help("Exit with the final value of r0 (clamped to 0-255, negative values exit with 255)"),
+            Arg::with_name("profile-percentages")
+                .long("profile-percentages")
+                .help("Include each line's share of executed instructions in the profile")
+                .requires("profile"),
+            Arg::with_name("dot")
+                .long("dot")
+                .value_name("FILE")
+                .help("Print the poem's control-flow graph in graphviz DOT format (or write it to FILE with --dot=FILE) instead of executing it")
+                .takes_value(true)
+                .min_values(0)
+                .require_equals(true)
+                .conflicts_with_all(&[
+                    "trace",
+                    "profile",
+                    "stats",
+                    "time",
+                    "exit-register",
+                    "limit-output",
+                    "lint",
+                ]),
+            Arg::with_name("dot-open")
+                .long("dot-open")
+                .help("Also render the graph to svg with graphviz and open it, if graphviz is installed")
+                .requires("dot"),
+            Arg::with_name("ast")
+                .long("ast")
+                .value_name("FORMAT")
+                .help("Print the instructions the poem parses to instead of executing it")
+                .possible_values(&["json"])
+                .takes_value(true)
+                .min_values(0)
+                .require_equals(true)
+                .conflicts_with_all(&[
+                    "trace",
+                    "profile",
+                    "stats",
+                    "time",
+                    "exit-register",
+                    "limit-output",
+                    "lint",
+                    "dot",
+                ]),
+            Arg::with_name("explain")
+                .long("explain")
+                .help("Print where each goto can land instead of executing the poem")
+                .conflicts_with_all(&[
+                    "trace",
+                    "profile",
+                    "stats",
+                    "time",
+                    "exit-register",
+                    "limit-output",
+                    "lint",
+                    "dot",
+                    "ast",
+                ]),
+        ])
+        .args(&lint::args());
+
+    conditional_jit_arg(conditional_serve_subcommand(conditional_tui_subcommand(
+        app,
+    )))
+}
+
+/// run whatever the parsed command line asks for, exiting the process on
+/// failure
+pub fn run(matches: &ArgMatches) {
+    if let Some(bench_matches) = matches.subcommand_matches("bench") {
+        bench::run(bench_matches);
+        return;
+    }
+
+    if let Some(test_matches) = matches.subcommand_matches("test") {
+        corpus::run(test_matches);
+        return;
+    }
+
+    if let Some(debug_matches) = matches.subcommand_matches("debug") {
+        debug::run(debug_matches);
+        return;
+    }
+
+    #[cfg(feature = "tui")]
+    if let Some(tui_matches) = matches.subcommand_matches("tui") {
+        tui::run(tui_matches);
+        return;
+    }
+
+    #[cfg(feature = "serve")]
+    if let Some(serve_matches) = matches.subcommand_matches("serve") {
+        serve::run_server(serve_matches);
+        return;
+    }
+
+    if let Some(count_matches) = matches.subcommand_matches("count") {
+        count::run(count_matches);
+        return;
+    }
+
+    if let Some(diff_matches) = matches.subcommand_matches("diff") {
+        diff::run(diff_matches);
+        return;
+    }
+
+    if let Some(fmt_matches) = matches.subcommand_matches("fmt") {
+        fmt::run(fmt_matches);
+        return;
+    }
+
+    if let Some(generate_matches) = matches.subcommand_matches("generate") {
+        generate::run(generate_matches);
+        return;
+    }
+
+    if let Some(path) = matches.value_of("dict") {
+        if let Err(err) = crate::dictionary::init_from_path(path) {
+            eprintln!("error: can't use --dict: {}", err);
+            process::exit(1);
+        }
+    }
+
+    let parse_options = ParseOptions {
+        dialect: if matches.is_present("extended") {
+            Dialect::Extended
+        } else {
+            Dialect::Standard
+        },
+        force_heuristics: matches.is_present("no-dict"),
+        register1_min_indent: if matches.is_present("min-indent") {
+            parse_arg(matches, "min-indent")
+        } else {
+            1
+        },
+        indent_tab_width: matches
+            .is_present("tab-width")
+            .then(|| parse_arg(matches, "tab-width")),
+        ..ParseOptions::default()
+    };
+
+    if let Some(syl_str) = matches.value_of("syllables") {
+        println!(
+            "{}",
+            crate::count_syllables_with_options(syl_str, &parse_options)
+        );
+        return;
+    }
+
+    init_logger(matches);
+
+    let (fname, contents) = match matches.values_of("eval") {
+        Some(lines) => ("<eval>", lines.collect::<Vec<_>>().join("\n")),
+        None => {
+            let fname = matches.value_of("INPUT").unwrap();
+            (fname, read_source(fname, matches))
+        }
+    };
+
+    if matches.is_present("lint") && !lint::run(fname, &contents, matches) {
+        return;
+    }
+
+    let title = if matches.is_present("gen-title") {
+        let syllables: usize = parse_arg(matches, "gen-title");
+        let title = title::generate(syllables, &contents).unwrap_or_else(|err| {
+            eprintln!("error: can't use --gen-title: {}", err);
+            process::exit(1);
+        });
+        eprintln!("title: {}", title);
+        Some(title)
+    } else {
+        matches.value_of("title").map(str::to_string)
+    };
+    let contents = match title {
+        Some(title) => program::retitle(&contents, &title).unwrap_or_else(|err| {
+            eprintln!("error: can't use --title: {}", err);
+            process::exit(1);
+        }),
+        None => contents,
+    };
+    let program = Program::create_with_options(&contents, &parse_options);
+    if matches.is_present("ast") {
+        if jit_requested(matches) {
+            eprintln!("error: --ast doesn't execute the poem, so it can't be used with --jit");
+            process::exit(1);
+        }
+        println!("{}", ast::to_json(&program));
+        return;
+    }
+    if matches.is_present("dot") {
+        if jit_requested(matches) {
+            eprintln!("error: --dot doesn't execute the poem, so it can't be used with --jit");
+            process::exit(1);
+        }
+        write_dot(matches, &program);
+        return;
+    }
+    if matches.is_present("explain") {
+        if jit_requested(matches) {
+            eprintln!("error: --explain doesn't execute the poem, so it can't be used with --jit");
+            process::exit(1);
+        }
+        println!(
+            "r1 from an indent of {}, a tab counting {}",
+            parse_options.register1_min_indent,
+            parse_options.tab_width()
+        );
+        print!("{}", program.jump_targets());
+        return;
+    }
+    execute_program(matches, &program);
+}
+
+#[cfg(not(tarpaulin_include))]
+pub fn main() {
+    run(&app().get_matches());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags() {
+        let matches = app()
+            .get_matches_from_safe([
+                "ashpaper-plus",
+                "--trace",
+                "--color=never",
+                "--limit-output",
+                "1k",
+                "--profile=out.txt",
+                "--profile-percentages",
+                "--exit-register",
+                "--title",
+                "a lovely poem",
+                "poem.eso",
+            ])
+            .unwrap();
+        assert_eq!(matches.value_of("INPUT"), Some("poem.eso"));
+        assert_eq!(matches.value_of("profile"), Some("out.txt"));
+        assert_eq!(matches.value_of("limit-output"), Some("1k"));
+        assert!(matches.is_present("exit-register"));
+
+        // a path that looks like a subcommand is still a poem
+        let matches = app()
+            .get_matches_from_safe(["ashpaper-plus", "tests/poem.eso"])
+            .unwrap();
+        assert_eq!(matches.subcommand_name(), None);
+        let matches = app()
+            .get_matches_from_safe(["ashpaper-plus", "test", "poems"])
+            .unwrap();
+        assert_eq!(matches.subcommand_name(), Some("test"));
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(parse_size("512"), Some(512));
+        assert_eq!(parse_size("64k"), Some(64 * 1024));
+        assert_eq!(parse_size("1M"), Some(1024 * 1024));
+        assert_eq!(parse_size("lots"), None);
+    }
+
+    #[test]
+    fn exit_codes() {
+        assert_eq!(exit_code(0), 0);
+        assert_eq!(exit_code(7), 7);
+        assert_eq!(exit_code(300), 255);
+        assert_eq!(exit_code(-3), 255);
+    }
+}
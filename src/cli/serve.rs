@@ -0,0 +1,202 @@
+//! the `serve` subcommand, a small http server for running poems from a
+//! playground page. every run is limited, so a poem that loops forever or
+//! pushes until memory runs out only costs the request that sent it
+use crate::{ast, count_syllables, dictionary, ExecOptions, Program};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use serde_json::{json, Value};
+use std::{
+    io::{Cursor, Read},
+    process,
+    sync::Arc,
+    thread,
+};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use super::{parse_arg, parse_size};
+
+type JsonResponse = Response<Cursor<Vec<u8>>>;
+
+/// what every request is held to
+#[derive(Debug, Clone, Copy)]
+struct Limits {
+    fuel: u64,
+    output: usize,
+    stack: usize,
+    body: usize,
+}
+
+fn respond_json(status: u16, body: &str) -> JsonResponse {
+    let content_type =
+        Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).expect("a valid header");
+    Response::from_string(body)
+        .with_status_code(status)
+        .with_header(content_type)
+}
+
+fn respond(status: u16, body: Value) -> JsonResponse {
+    respond_json(status, &body.to_string())
+}
+
+fn error(status: u16, message: &str) -> JsonResponse {
+    respond(status, json!({ "error": message }))
+}
+
+/// the body of `request`, or the response refusing it
+fn read_body(request: &mut Request, limit: usize) -> Result<String, JsonResponse> {
+    let too_large = || error(413, &format!("the poem is over {} bytes", limit));
+    if request.body_length().is_some_and(|length| length > limit) {
+        return Err(too_large());
+    }
+    // a chunked body doesn't say how long it is up front
+    let mut body = vec![];
+    request
+        .as_reader()
+        .take(limit as u64 + 1)
+        .read_to_end(&mut body)
+        .map_err(|err| error(400, &format!("can't read the body: {}", err)))?;
+    if body.len() > limit {
+        return Err(too_large());
+    }
+    String::from_utf8(body).map_err(|_| error(400, "the body isn't valid utf-8"))
+}
+
+fn run(poem: &str, limits: &Limits) -> JsonResponse {
+    let options = ExecOptions {
+        max_instructions: Some(limits.fuel),
+        max_output: Some(limits.output),
+        max_stack: Some(limits.stack),
+        ..ExecOptions::default()
+    };
+    let report = Program::create(poem).execute_report(&options);
+    respond(
+        200,
+        json!({
+            "output": report.output,
+            "instructions_executed": report.instructions_executed,
+            "halted_reason": report.halted_reason.name(),
+            "error": report.error.map(|err| err.to_string()),
+        }),
+    )
+}
+
+fn handle(request: &mut Request, limits: &Limits) -> JsonResponse {
+    let path = request
+        .url()
+        .split('?')
+        .next()
+        .unwrap_or_default()
+        .to_string();
+    match (request.method(), path.as_str()) {
+        (Method::Post, "/run" | "/syllables" | "/ast") => {}
+        (_, "/run" | "/syllables" | "/ast") => return error(405, "only POST is supported"),
+        _ => return error(404, "no such endpoint, try /run, /syllables or /ast"),
+    }
+    let body = match read_body(request, limits.body) {
+        Ok(body) => body,
+        Err(response) => return response,
+    };
+    match path.as_str() {
+        "/run" => run(&body, limits),
+        "/syllables" => respond(200, json!({ "syllables": count_syllables(&body) })),
+        _ => respond_json(200, &ast::to_json(&Program::create(&body))),
+    }
+}
+
+/// answer requests until the server goes away
+fn work(server: &Server, limits: &Limits) {
+    while let Ok(mut request) = server.recv() {
+        let response = handle(&mut request, limits);
+        if let Err(err) = request.respond(response) {
+            log::warn!("couldn't send a response: {}", err);
+        }
+    }
+}
+
+pub fn run_server(matches: &ArgMatches) {
+    let port: u16 = parse_arg(matches, "port");
+    let workers: usize = parse_arg(matches, "workers");
+    let size = |name: &str| {
+        let value = matches.value_of(name).unwrap();
+        parse_size(value).unwrap_or_else(|| {
+            eprintln!("invalid value for --{}: '{}'", name, value);
+            process::exit(1);
+        })
+    };
+    let limits = Limits {
+        fuel: parse_arg(matches, "limit-fuel"),
+        output: size("limit-output"),
+        stack: parse_arg(matches, "limit-stack"),
+        body: size("limit-body"),
+    };
+
+    // every request shares the dictionary, so load it before the first one
+    // rather than during it
+    if let Err(err) = dictionary::init_default() {
+        eprintln!("error loading the dictionary: {}", err);
+        process::exit(1);
+    }
+
+    let address = format!("{}:{}", matches.value_of("host").unwrap(), port);
+    let server = Server::http(&address).unwrap_or_else(|err| {
+        eprintln!("error: can't listen on {}: {}", address, err);
+        process::exit(1);
+    });
+    // the port the os picked when asked for port 0
+    match server.server_addr().to_ip() {
+        Some(address) => eprintln!("listening on http://{}", address),
+        None => eprintln!("listening on {}", address),
+    }
+
+    let server = Arc::new(server);
+    let handles: Vec<_> = (0..workers.max(1))
+        .map(|_| {
+            let server = Arc::clone(&server);
+            thread::spawn(move || work(&server, &limits))
+        })
+        .collect();
+    for handle in handles {
+        let _ = handle.join();
+    }
+}
+
+pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("serve")
+        .about("Run poems sent over http: POST a poem to /run, /syllables or /ast")
+        .args(&[
+            Arg::with_name("port")
+                .long("port")
+                .value_name("PORT")
+                .help("Port to listen on, 0 picks a free one")
+                .default_value("8080"),
+            Arg::with_name("host")
+                .long("host")
+                .value_name("ADDRESS")
+                .help("Address to listen on")
+                .default_value("127.0.0.1"),
+            Arg::with_name("workers")
+                .long("workers")
+                .value_name("N")
+                .help("How many requests are handled at once")
+                .default_value("4"),
+            Arg::with_name("limit-fuel")
+                .long("limit-fuel")
+                .value_name("N")
+                .help("Stop a poem after N instructions")
+                .default_value("1000000"),
+            Arg::with_name("limit-output")
+                .long("limit-output")
+                .value_name("BYTES")
+                .help("Stop a poem after printing BYTES of output (accepts k and m suffixes)")
+                .default_value("64k"),
+            Arg::with_name("limit-stack")
+                .long("limit-stack")
+                .value_name("N")
+                .help("Stop a poem that pushes more than N values")
+                .default_value("10000"),
+            Arg::with_name("limit-body")
+                .long("limit-body")
+                .value_name("BYTES")
+                .help("Refuse poems over BYTES (accepts k and m suffixes)")
+                .default_value("64k"),
+        ])
+}
@@ -0,0 +1,377 @@
+use crate::{Interpreter, Program, Register};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Modifier, Style},
+    text::Line,
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap},
+    DefaultTerminal, Frame,
+};
+use std::{collections::BTreeSet, io, process};
+
+use super::{encoding_arg, read_source};
+
+/// how many instructions `continue` runs before handing control back, so a
+/// poem that loops forever doesn't freeze the screen
+const CONTINUE_BUDGET: u64 = 1_000_000;
+
+/// the smallest screen the three panes fit on
+const MIN_WIDTH: u16 = 40;
+const MIN_HEIGHT: u16 = 10;
+
+const HELP: &str = "s step  p back  c continue  b breakpoint  ↑↓ move  r restart  q quit";
+
+/// everything on screen, driven by key presses
+struct Debugger<'a> {
+    interpreter: Interpreter<'a>,
+    breakpoints: BTreeSet<usize>,
+    /// the line breakpoints are toggled on, following the instruction
+    /// pointer as it moves
+    cursor: usize,
+    output: String,
+    /// why the poem stopped, or anything else worth telling
+    status: String,
+    halted: bool,
+}
+
+impl<'a> Debugger<'a> {
+    fn new(program: &'a Program) -> Debugger<'a> {
+        Debugger {
+            interpreter: Interpreter::new(program),
+            breakpoints: BTreeSet::new(),
+            cursor: 0,
+            output: String::new(),
+            status: String::new(),
+            halted: program.ast.is_empty(),
+        }
+    }
+
+    fn restart(&mut self) {
+        let program = self.interpreter.program();
+        *self = Debugger {
+            breakpoints: std::mem::take(&mut self.breakpoints),
+            ..Debugger::new(program)
+        };
+        self.status = "restarted".to_string();
+    }
+
+    /// execute one instruction, returning false once the poem has stopped
+    fn step(&mut self) -> bool {
+        if self.halted {
+            return false;
+        }
+        match self.interpreter.step() {
+            Ok(Some(step)) => {
+                if let Some(output) = step.output {
+                    self.output.push_str(&output);
+                }
+            }
+            Ok(None) => {}
+            Err(err) => {
+                self.halted = true;
+                self.status = err.to_string();
+                return false;
+            }
+        }
+        self.cursor = self.interpreter.instruction_pointer();
+        if self.interpreter.is_halted() {
+            self.halted = true;
+            self.status = format!(
+                "halted after {} instructions",
+                self.interpreter.instructions_executed()
+            );
+        }
+        !self.halted
+    }
+
+    /// undo the last step, taking back what it printed
+    fn step_back(&mut self) {
+        match self.interpreter.step_back() {
+            Ok(()) => {
+                self.output.truncate(self.interpreter.output_len());
+                self.cursor = self.interpreter.instruction_pointer();
+                self.halted = false;
+            }
+            Err(err) => self.status = format!("can't step back: {}", err),
+        }
+    }
+
+    fn resume(&mut self) {
+        // always move past the current line so a breakpoint on it doesn't
+        // stop us straight away
+        let mut running = self.step();
+        let mut budget = CONTINUE_BUDGET;
+        while running {
+            let ip = self.interpreter.instruction_pointer();
+            if self.breakpoints.contains(&ip) {
+                self.status = format!("breakpoint on line {}", ip + 1);
+                return;
+            }
+            budget -= 1;
+            if budget == 0 {
+                self.status = format!(
+                    "still running after {} instructions, c to keep going",
+                    CONTINUE_BUDGET
+                );
+                return;
+            }
+            running = self.step();
+        }
+    }
+
+    /// handle a key, returning false to quit
+    fn key(&mut self, code: KeyCode) -> bool {
+        let lines = self.interpreter.program().ast.len();
+        self.status.clear();
+        match code {
+            KeyCode::Char('s') | KeyCode::Char('n') => {
+                self.step();
+            }
+            KeyCode::Char('p') => self.step_back(),
+            KeyCode::Char('c') => self.resume(),
+            KeyCode::Char('b') if lines > 0 && !self.breakpoints.remove(&self.cursor) => {
+                self.breakpoints.insert(self.cursor);
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.cursor = (self.cursor + 1).min(lines.saturating_sub(1))
+            }
+            KeyCode::Char('r') => self.restart(),
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            _ => {}
+        }
+        true
+    }
+
+    fn poem(&self) -> List<'_> {
+        let ip = self.interpreter.instruction_pointer();
+        let items: Vec<ListItem> = self
+            .interpreter
+            .program()
+            .ast
+            .iter()
+            .enumerate()
+            .map(|(index, ins)| {
+                let breakpoint = if self.breakpoints.contains(&index) {
+                    '●'
+                } else {
+                    ' '
+                };
+                let arrow = if index == ip && !self.halted {
+                    '▶'
+                } else {
+                    ' '
+                };
+                let line = format!("{}{}{:>3} │ {}", breakpoint, arrow, index + 1, ins.line());
+                let style = if index == ip && !self.halted {
+                    Style::default().add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                };
+                ListItem::new(line).style(style)
+            })
+            .collect();
+        List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(" poem "))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+    }
+
+    fn state(&self) -> Paragraph<'_> {
+        let mut lines: Vec<Line> = [Register::Register0, Register::Register1]
+            .iter()
+            .map(|&register| {
+                Line::from(format!(
+                    "r{} = {}",
+                    register.index(),
+                    self.interpreter.register(register)
+                ))
+            })
+            .collect();
+        lines.push(Line::from("stack"));
+        // the top of the stack first
+        let stack = self.interpreter.stack();
+        if stack.is_empty() {
+            lines.push(Line::from("  (empty)"));
+        }
+        lines.extend(
+            stack
+                .iter()
+                .rev()
+                .map(|value| Line::from(format!("  {}", value))),
+        );
+        Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(" state "))
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let area = frame.area();
+        if area.width < MIN_WIDTH || area.height < MIN_HEIGHT {
+            let message = format!(
+                "the terminal is too small, it needs to be at least {}x{}. q quits",
+                MIN_WIDTH, MIN_HEIGHT
+            );
+            frame.render_widget(Paragraph::new(message).wrap(Wrap { trim: true }), area);
+            return;
+        }
+
+        let [main, footer] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(area);
+        let [poem, side] =
+            Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)])
+                .areas(main);
+        let [state, output] =
+            Layout::vertical([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(side);
+
+        let mut list_state = ListState::default();
+        if !self.interpreter.program().ast.is_empty() {
+            list_state.select(Some(self.cursor));
+        }
+        frame.render_stateful_widget(self.poem(), poem, &mut list_state);
+        frame.render_widget(self.state(), state);
+        frame.render_widget(
+            Paragraph::new(self.output.as_str())
+                .wrap(Wrap { trim: false })
+                .block(Block::default().borders(Borders::ALL).title(" output ")),
+            output,
+        );
+        let footer_text = if self.status.is_empty() {
+            HELP
+        } else {
+            &self.status
+        };
+        frame.render_widget(Paragraph::new(footer_text), footer);
+    }
+}
+
+fn session(terminal: &mut DefaultTerminal, debugger: &mut Debugger) -> io::Result<()> {
+    loop {
+        terminal.draw(|frame| debugger.draw(frame))?;
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press && !debugger.key(key.code) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+pub fn run(matches: &ArgMatches) {
+    let contents = read_source(matches.value_of("POEM").unwrap(), matches);
+    let program = Program::create(&contents);
+    let mut debugger = Debugger::new(&program);
+    // also restores the terminal if we panic
+    let mut terminal = ratatui::try_init().unwrap_or_else(|err| {
+        eprintln!("error: can't set up the terminal: {}", err);
+        process::exit(1);
+    });
+    let result = session(&mut terminal, &mut debugger);
+    ratatui::restore();
+    if let Err(err) = result {
+        eprintln!("debugger error: {}", err);
+        process::exit(1);
+    }
+}
+
+pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("tui")
+        .about("Step through a poem in a full screen debugger")
+        .arg(
+            Arg::with_name("POEM")
+                .help(".eso file to debug")
+                .required(true)
+                .index(1),
+        )
+        .arg(encoding_arg())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use ratatui::{backend::TestBackend, Terminal};
+
+    fn render(debugger: &Debugger, width: u16, height: u16) -> Vec<String> {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|frame| debugger.draw(frame)).unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..height)
+            .map(|y| {
+                (0..width)
+                    .map(|x| buffer[(x, y)].symbol())
+                    .collect::<String>()
+                    .trim_end()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn mid_execution() {
+        let program = Program::create("lovely poem\npush-ed\n  a fish\n  print.\nsea shells?");
+        let mut debugger = Debugger::new(&program);
+        debugger.key(KeyCode::Down);
+        debugger.key(KeyCode::Down);
+        debugger.key(KeyCode::Down);
+        debugger.key(KeyCode::Char('b'));
+        debugger.key(KeyCode::Char('c'));
+        assert_eq!(debugger.status, "breakpoint on line 4");
+        assert_eq!(
+            render(&debugger, 60, 12),
+            [
+                "┌ poem ────────────────────────────┐┌ state ───────────────┐",
+                "│    1 │ lovely poem               ││r0 = 4                │",
+                "│    2 │ push-ed                   ││r1 = 2                │",
+                "│    3 │   a fish                  ││stack                 │",
+                "│●▶  4 │   print.                  ││  4                   │",
+                "│    5 │ sea shells?               │└──────────────────────┘",
+                "│                                  │┌ output ──────────────┐",
+                "│                                  ││                      │",
+                "│                                  ││                      │",
+                "│                                  ││                      │",
+                "└──────────────────────────────────┘└──────────────────────┘",
+                "breakpoint on line 4",
+            ]
+        );
+
+        debugger.key(KeyCode::Char('s'));
+        assert_eq!(debugger.output, "2");
+        debugger.key(KeyCode::Char('c'));
+        assert!(debugger.halted);
+        assert_eq!(debugger.status, "halted after 5 instructions");
+
+        // the print on line 4 is taken back
+        debugger.key(KeyCode::Char('p'));
+        debugger.key(KeyCode::Char('p'));
+        assert!(!debugger.halted);
+        assert_eq!(debugger.output, "");
+        assert_eq!(debugger.cursor, 3);
+
+        debugger.key(KeyCode::Char('r'));
+        assert!(!debugger.halted);
+        assert_eq!(debugger.output, "");
+        assert!(debugger.breakpoints.contains(&3));
+        debugger.key(KeyCode::Char('p'));
+        assert_eq!(
+            debugger.status,
+            "can't step back: nothing has been executed yet"
+        );
+        assert!(!debugger.key(KeyCode::Char('q')));
+    }
+
+    #[test]
+    fn tiny_terminal() {
+        let program = Program::create("lovely poem\nprint.");
+        let debugger = Debugger::new(&program);
+        let screen = render(&debugger, 20, 5);
+        assert_eq!(screen[0], "the terminal is too");
+    }
+
+    #[test]
+    fn loops_forever() {
+        // jumps back to its first line forever
+        let program = Program::create("  sea\nsoft sand");
+        let mut debugger = Debugger::new(&program);
+        debugger.key(KeyCode::Char('c'));
+        assert!(!debugger.halted);
+        assert!(debugger.status.starts_with("still running"));
+    }
+}
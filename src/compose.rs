@@ -0,0 +1,359 @@
+//! the inverse of the parser: writing a poem that parses to a given list of
+//! instructions
+//!
+//! lines are built from the title word bank and then decorated with whatever
+//! makes the parser pick the right instruction. a line can still turn into
+//! something else by rhyming with the line before it, so each one is checked
+//! by parsing it after its predecessor, trying other words until it comes out
+//! right
+pub use super::errors::compose::{ComposeError, ComposeResult};
+use super::{
+    format,
+    parser::{self, InsType, Instruction, Register},
+    title, Program, Result,
+};
+
+/// how many different lines to try for an instruction before giving up
+const ATTEMPTS: usize = 64;
+
+/// the most syllables a single line will be written with
+pub const MAX_SYLLABLES: usize = 1000;
+
+/// a line that isn't blank but has no syllables
+const SILENCE: &str = "…";
+
+fn words(syllables: usize, seed: usize) -> Option<Vec<String>> {
+    if syllables == 0 {
+        return Some(Vec::new());
+    }
+    title::build(syllables, seed).map(|line| line.split(' ').map(str::to_string).collect())
+}
+
+/// a pair of adjacent words starting with the same letter, and their
+/// syllables
+fn alliteration(seed: usize) -> (String, usize) {
+    let pairs: Vec<_> = title::ADJECTIVES
+        .iter()
+        .flat_map(|adjective| title::NOUNS.iter().map(move |noun| (adjective, noun)))
+        .filter(|((adjective, _), (noun, _))| adjective[..1] == noun[..1])
+        .collect();
+    let ((adjective, a), (noun, n)) = pairs[seed % pairs.len()];
+    (format!("{} {}", adjective, noun), a + n)
+}
+
+fn check_syllables(index: usize, syllables: usize) -> ComposeResult<()> {
+    if syllables > MAX_SYLLABLES {
+        Err(ComposeError::TooManySyllables { index, syllables })
+    } else {
+        Ok(())
+    }
+}
+
+/// the text of a line for `instruction`, without its indentation. `target`
+/// is how many syllables the line should have if the instruction doesn't
+/// decide that itself, and `rhymed` whether the next line has to rhyme with
+/// it. `None` means these words didn't work out but others might
+fn realize(
+    index: usize,
+    instruction: InsType,
+    previous: Option<&str>,
+    target: usize,
+    rhymed: bool,
+    seed: usize,
+) -> ComposeResult<Option<String>> {
+    let line = match instruction {
+        InsType::Noop => Some(String::new()),
+        InsType::Subtract
+        | InsType::Divide
+        | InsType::ReadChar
+        | InsType::TapeStore
+        | InsType::TapeLoad => {
+            return Err(ComposeError::Extended {
+                index,
+                instruction: instruction.mnemonic(),
+            })
+        }
+        InsType::Store(0) => Some(SILENCE.to_string()),
+        InsType::Store(syllables) => {
+            let syllables = syllables as usize;
+            check_syllables(index, syllables)?;
+            title::build(syllables, seed)
+        }
+        InsType::ConditionalGoto(syllables) => {
+            let syllables = syllables as usize;
+            check_syllables(index, syllables)?;
+            words(syllables, seed).map(|mut words| {
+                words.insert(words.len().min(1), "/".to_string());
+                words.join(" ")
+            })
+        }
+        InsType::ConditionalPush {
+            prev_syllables,
+            cur_syllables,
+        } => {
+            let (prev_syllables, cur_syllables) = (prev_syllables as usize, cur_syllables as usize);
+            check_syllables(index, cur_syllables)?;
+            let previous = previous.unwrap_or("");
+            let actual = parser::count_syllables(previous);
+            if actual != prev_syllables {
+                return Err(ComposeError::PreviousSyllables {
+                    index,
+                    expected: prev_syllables,
+                    actual,
+                });
+            }
+            // the same word always rhymes with itself
+            let last = previous
+                .split(' ')
+                .rev()
+                .find(|word| !word.is_empty())
+                .map(str::to_lowercase)
+                .filter(|word| parser::in_dictionary(word))
+                .ok_or(ComposeError::NothingToRhyme { index })?;
+            let last_syllables = parser::count_syllables(&last);
+            cur_syllables
+                .checked_sub(last_syllables)
+                .and_then(|syllables| words(syllables, seed))
+                .map(|mut words| {
+                    words.push(last);
+                    words.join(" ")
+                })
+        }
+        InsType::Goto => {
+            let (pair, syllables) = alliteration(seed);
+            match target.checked_sub(syllables) {
+                Some(0) => Some(pair),
+                Some(rest) => {
+                    words(rest, seed).map(|words| format!("{} {}", words.join(" "), pair))
+                }
+                None => None,
+            }
+        }
+        InsType::Negate => words(target, seed).map(|mut words| {
+            // a capital inside a word doesn't change how it's looked up
+            let last = words.last_mut().unwrap();
+            *last = last
+                .char_indices()
+                .map(|(i, c)| if i == 1 { c.to_ascii_uppercase() } else { c })
+                .collect();
+            words.join(" ")
+        }),
+        InsType::Multiply => words(target, seed).map(|mut words| {
+            let first = words.first_mut().unwrap();
+            *first = first[..1].to_uppercase() + &first[1..];
+            words.join(" ")
+        }),
+        InsType::Add => target
+            .checked_sub(1)
+            .filter(|&syllables| syllables > 0)
+            .and_then(|syllables| words(syllables, seed))
+            .map(|mut words| {
+                words.insert(words.len() - 1, "like".to_string());
+                words.join(" ")
+            }),
+        InsType::Push => words(target, seed).map(|mut words| {
+            words.insert(words.len().min(1), "-".to_string());
+            words.join(" ")
+        }),
+        InsType::PrintChar | InsType::PrintValue | InsType::Pop => {
+            let mark = match instruction {
+                InsType::PrintChar => '?',
+                InsType::PrintValue => '.',
+                _ => ',',
+            };
+            words(target, seed).map(|mut words| {
+                // keep the last word intact if the next line rhymes with it
+                let at = if rhymed { 0 } else { words.len() - 1 };
+                words[at].push(mark);
+                words.join(" ")
+            })
+        }
+    };
+    Ok(line)
+}
+
+fn parses_as(previous: Option<&str>, line: &str, expected: &Instruction) -> bool {
+    let source = match previous {
+        Some(previous) => format!("{}\n{}", previous, line),
+        None => line.to_string(),
+    };
+    Program::create(&source).ast.last().is_some_and(|parsed| {
+        parsed.instruction == expected.instruction && parsed.register == expected.register
+    })
+}
+
+/// write a poem that parses to `instructions`, ignoring the text of their
+/// lines. `seed` picks between the many poems that would do
+pub fn compose(instructions: &[Instruction], seed: u64) -> Result<String> {
+    // the word banks are small, so larger seeds wouldn't add anything
+    let seed = (seed % 65536) as usize;
+    let mut lines: Vec<String> = Vec::with_capacity(instructions.len());
+    for (index, ins) in instructions.iter().enumerate() {
+        if ins.instruction == InsType::Noop {
+            lines.push(String::new());
+            continue;
+        }
+        let previous = lines.last().map(String::as_str);
+        // a cond-push counts the syllables of the line before it
+        let rhymed_syllables = match instructions.get(index + 1).map(|next| next.instruction) {
+            Some(InsType::ConditionalPush { prev_syllables, .. }) => Some(prev_syllables as usize),
+            _ => None,
+        };
+        let indent = match ins.register {
+            Register::Register0 => "",
+            Register::Register1 => "  ",
+            register => {
+                return Err(ComposeError::ExtendedRegister {
+                    index,
+                    register: register.index(),
+                }
+                .into())
+            }
+        };
+
+        let mut fallback = None;
+        let mut found = None;
+        for attempt in 0..ATTEMPTS {
+            let seed = seed + index * 7 + attempt;
+            let target = rhymed_syllables.unwrap_or(1 + seed % 3);
+            check_syllables(index, target)?;
+            let body = match realize(
+                index,
+                ins.instruction,
+                previous,
+                target,
+                rhymed_syllables.is_some(),
+                seed,
+            )? {
+                Some(body) => body,
+                None => continue,
+            };
+            let line = format!("{}{}", indent, body);
+            if !parses_as(previous, &line, ins) {
+                continue;
+            }
+            let rhymable = rhymed_syllables.is_none_or(|syllables| {
+                parser::count_syllables(&line) == syllables
+                    && line
+                        .rsplit(' ')
+                        .next()
+                        .is_some_and(|word| parser::in_dictionary(&word.to_lowercase()))
+            });
+            if rhymable {
+                found = Some(line);
+                break;
+            }
+            // the next instruction will report why it can't rhyme with this
+            fallback = fallback.or(Some(line));
+        }
+        let line = found.or(fallback).ok_or(ComposeError::Unrealizable {
+            index,
+            instruction: ins.instruction.mnemonic(),
+        })?;
+        lines.push(line);
+    }
+
+    let poem: String = lines.iter().map(|line| format!("{}\n", line)).collect();
+    let expected = Program::from_instructions(instructions.to_vec());
+    match format::first_difference(&expected, &Program::create(&poem)) {
+        Some(index) => Err(ComposeError::RoundTrip { index }.into()),
+        None => Ok(poem),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+    use pretty_assertions::assert_eq;
+
+    fn instruction(instruction: InsType, register: Register) -> Instruction {
+        Instruction::new(instruction, register, "")
+    }
+
+    #[test]
+    fn round_trips_every_instruction() {
+        let source = "lovely poem\n\n  it is a calculator, like a\n      poem, is a poem, and finds\n        factori-\n          als\n  The input is the syllAbles\nin the title, count them, as one counts\n  (q) what other poem, programs can be writ\n  (a) anyhow, no thing.\nshe sells sea shells\n  re/cur\n";
+        let program = Program::create(source);
+        for seed in 0..8 {
+            let poem = compose(&program.ast, seed).unwrap();
+            let composed = Program::create(&poem);
+            assert_eq!(
+                format::first_difference(&program, &composed),
+                None,
+                "{}",
+                poem
+            );
+        }
+    }
+
+    #[test]
+    fn same_output() {
+        let program = Program::create(include_str!("../poems/lovely-poem.eso"));
+        let poem = compose(&program.ast, 3).unwrap();
+        assert_eq!(Program::create(&poem).execute(), program.execute());
+    }
+
+    #[cfg(feature = "bundled-dictionary")]
+    #[test]
+    fn conditional_push() {
+        let instructions = [
+            instruction(InsType::Store(2), Register::Register0),
+            instruction(
+                InsType::ConditionalPush {
+                    prev_syllables: 2,
+                    cur_syllables: 4,
+                },
+                Register::Register1,
+            ),
+        ];
+        let poem = compose(&instructions, 0).unwrap();
+        assert_eq!(
+            Program::create(&poem).ast[1].instruction,
+            instructions[1].instruction
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let push = |prev_syllables| {
+            instruction(
+                InsType::ConditionalPush {
+                    prev_syllables,
+                    cur_syllables: 3,
+                },
+                Register::Register0,
+            )
+        };
+        let error = |instructions: &[Instruction]| match compose(instructions, 0) {
+            Err(Error::Compose(err)) => err,
+            other => panic!("expected a ComposeError, got {:?}", other),
+        };
+        assert_eq!(error(&[push(0)]), ComposeError::NothingToRhyme { index: 0 });
+        assert_eq!(
+            error(&[instruction(InsType::Store(2), Register::Register0), push(3)]),
+            ComposeError::PreviousSyllables {
+                index: 1,
+                expected: 3,
+                actual: 2
+            }
+        );
+        assert_eq!(
+            error(&[
+                instruction(InsType::Noop, Register::Register0),
+                instruction(InsType::Store(5000), Register::Register0)
+            ]),
+            ComposeError::TooManySyllables {
+                index: 1,
+                syllables: 5000
+            }
+        );
+        assert_eq!(
+            error(&[instruction(InsType::Divide, Register::Register1)]),
+            ComposeError::Extended {
+                index: 0,
+                instruction: "divide"
+            }
+        );
+    }
+}
@@ -0,0 +1,242 @@
+//! words' syllables kept in a file, so a corpus parsed again and again
+//! doesn't look the same words up every time, see
+//! [`ParseOptions::syllable_cache_path`](crate::ParseOptions::syllable_cache_path)
+//!
+//! the file starts with a header naming the crate version and the
+//! fingerprint of the dictionary it was made with, followed by a line per
+//! word: the word, its syllables, and `d` if they came from the dictionary
+//! or `a` if they were approximated, separated by tabs. words counted
+//! since the file was read are appended to it when it's flushed. a file
+//! from another version or dictionary, or one that can't be read, is
+//! started over as an empty cache rather than trusted
+use std::{
+    collections::HashMap,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use super::fingerprint;
+
+const MAGIC: &str = "ashpaper-syllable-cache";
+
+/// a word's syllables, and whether they came from the dictionary
+type Count = (usize, bool);
+
+struct SyllableCache {
+    path: PathBuf,
+    header: String,
+    words: HashMap<String, Count>,
+    /// words counted since the file was last written, in order
+    pending: Vec<String>,
+    /// whether the file has to be written from scratch rather than
+    /// appended to
+    rewrite: bool,
+}
+
+/// the words in a cache file, `None` unless it was made with `header` and
+/// every line is whole
+fn read(contents: &str, header: &str) -> Option<HashMap<String, Count>> {
+    let mut lines = contents.split_terminator('\n');
+    if lines.next()? != header || !contents.ends_with('\n') {
+        return None;
+    }
+    lines
+        .map(|line| {
+            let mut fields = line.split('\t');
+            let word = fields.next()?;
+            let syllables = fields.next()?.parse().ok()?;
+            let from_dictionary = match fields.next()? {
+                "d" => true,
+                "a" => false,
+                _ => return None,
+            };
+            match fields.next() {
+                Some(_) => None,
+                None => Some((word.to_string(), (syllables, from_dictionary))),
+            }
+        })
+        .collect()
+}
+
+fn line(word: &str, (syllables, from_dictionary): Count) -> String {
+    let source = if from_dictionary { "d" } else { "a" };
+    format!("{}\t{}\t{}\n", word, syllables, source)
+}
+
+impl SyllableCache {
+    fn open(path: &Path, fingerprint: u64) -> SyllableCache {
+        let header = format!(
+            "{}\t{}\t{:016x}",
+            MAGIC,
+            env!("CARGO_PKG_VERSION"),
+            fingerprint
+        );
+        let words = fs::read_to_string(path)
+            .ok()
+            .and_then(|contents| read(&contents, &header));
+        SyllableCache {
+            path: path.to_path_buf(),
+            header,
+            rewrite: words.is_none(),
+            words: words.unwrap_or_default(),
+            pending: vec![],
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let mut text = String::new();
+        let mut file = if self.rewrite {
+            text.push_str(&self.header);
+            text.push('\n');
+            text.extend(self.words.iter().map(|(word, &count)| line(word, count)));
+            fs::File::create(&self.path)?
+        } else if self.pending.is_empty() {
+            return Ok(());
+        } else {
+            text.extend(self.pending.iter().map(|word| line(word, self.words[word])));
+            OpenOptions::new().append(true).open(&self.path)?
+        };
+        file.write_all(text.as_bytes())?;
+        self.rewrite = false;
+        self.pending.clear();
+        Ok(())
+    }
+}
+
+impl Drop for SyllableCache {
+    fn drop(&mut self) {
+        // nothing can be done about a cache that can't be written here, and
+        // it only costs the words being counted again
+        let _ = self.flush();
+    }
+}
+
+/// every cache that's been opened, by its path
+static CACHES: Mutex<Vec<SyllableCache>> = Mutex::new(Vec::new());
+
+fn caches() -> std::sync::MutexGuard<'static, Vec<SyllableCache>> {
+    // a cache is only ever left with words that weren't written yet
+    CACHES
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// the syllables of `word` from the cache at `path`, counted with `count`
+/// if it doesn't have them. a cache that can't be used, because the
+/// dictionary can't be loaded or the word can't be written to it, leaves it
+/// all to `count`
+pub(crate) fn syllables(path: &Path, word: &str, count: impl FnOnce() -> Count) -> usize {
+    if word.contains(['\t', '\n', '\r']) {
+        return count().0;
+    }
+    let fingerprint = match fingerprint() {
+        Ok(fingerprint) => fingerprint,
+        Err(_) => return count().0,
+    };
+    let mut caches = caches();
+    let index = match caches.iter().position(|cache| cache.path == path) {
+        Some(index) => index,
+        None => {
+            caches.push(SyllableCache::open(path, fingerprint));
+            caches.len() - 1
+        }
+    };
+    let cache = &mut caches[index];
+    if let Some(&(syllables, _)) = cache.words.get(word) {
+        return syllables;
+    }
+    let counted = count();
+    cache.words.insert(word.to_string(), counted);
+    cache.pending.push(word.to_string());
+    counted.0
+}
+
+/// write what's been counted to the cache at `path`, if it's open
+pub(crate) fn flush(path: &Path) -> io::Result<()> {
+    match caches().iter_mut().find(|cache| cache.path == path) {
+        Some(cache) => cache.flush(),
+        None => Ok(()),
+    }
+}
+
+/// write what's been counted to every open syllable cache. they're written
+/// after each poem parsed with one, so this is only needed for words
+/// counted some other way, like
+/// [`count_syllables_with_options`](crate::count_syllables_with_options)
+pub fn flush_syllable_caches() -> io::Result<()> {
+    caches().iter_mut().try_for_each(SyllableCache::flush)
+}
+
+/// write the cache at `path` and forget it, as if the process had ended
+#[cfg(test)]
+pub(crate) fn close(path: &Path) {
+    caches().retain(|cache| cache.path != path);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{self, ParseOptions, SYLLABLE_LOOKUPS};
+    use pretty_assertions::assert_eq;
+    use std::env;
+
+    const POEM: &str = include_str!("../../poems/lovely-poem.eso");
+
+    fn cache_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("ashpaper-cache-{}-{}", name, std::process::id()))
+    }
+
+    /// the poem parsed with the cache at `path`, and how many words had to
+    /// be looked up
+    fn parse_with_cache(path: &Path) -> (Vec<parser::Instruction>, usize) {
+        let options = ParseOptions {
+            syllable_cache_path: Some(path.to_path_buf()),
+            ..ParseOptions::default()
+        };
+        SYLLABLE_LOOKUPS.with(|lookups| lookups.set(0));
+        let ast = parser::parse_with_options(POEM, &options);
+        (ast, SYLLABLE_LOOKUPS.with(|lookups| lookups.get()))
+    }
+
+    #[test]
+    fn warm() {
+        let path = cache_path("warm");
+        let _ = fs::remove_file(&path);
+        let (cold, lookups) = parse_with_cache(&path);
+        assert!(lookups > 0);
+        assert_eq!(cold, parser::parse(POEM));
+
+        close(&path);
+        let (warm, lookups) = parse_with_cache(&path);
+        assert_eq!(lookups, 0);
+        assert_eq!(warm, cold);
+        close(&path);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn corrupted() {
+        let path = cache_path("corrupted");
+        let header = format!("{}\t{}\t{:016x}", MAGIC, env!("CARGO_PKG_VERSION"), 0);
+        let files = [
+            "\u{0}\u{1}not a cache".to_string(),
+            // cut off part way through a line
+            format!("{}\nlovely\t2\td\npoem\t2", header),
+            // made with another dictionary
+            format!("{}\nlovely\t9\td\n", header),
+        ];
+        for contents in &files {
+            fs::write(&path, contents).unwrap();
+            let (ast, lookups) = parse_with_cache(&path);
+            assert!(lookups > 0);
+            assert_eq!(ast, parser::parse(POEM));
+            close(&path);
+            let written = fs::read_to_string(&path).unwrap();
+            assert!(written.starts_with(MAGIC));
+            assert!(!written.contains("lovely\t9"));
+        }
+        fs::remove_file(&path).unwrap();
+    }
+}
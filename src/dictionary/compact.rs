@@ -0,0 +1,83 @@
+//! turns a dictionary in the cmudict format into the compact form the
+//! `compact-dictionary` feature looks words up in. this is also compiled
+//! into the build script, which compacts the bundled dictionary ahead of
+//! time, so it only uses std and fst
+//!
+//! the compact form is an fst mapping each word to the offset of its entry,
+//! and the entries as little-endian u32s. an entry starts with how many
+//! pronunciations the word has shifted left 8 bits plus how many rhyme keys
+//! it has. the rhyme keys follow, then two for each pronunciation in the
+//! order they're listed: its syllables, and a bit for each of its first 32
+//! syllables that's set when the syllable is stressed. a rhyme key stands for the phonemes from
+//! the last vowel of a pronunciation onward, so two words rhyme when they
+//! share one
+use std::collections::{BTreeMap, HashMap};
+
+pub struct Compact {
+    pub words: Vec<u8>,
+    pub entries: Vec<u8>,
+}
+
+/// the word a line is for, without the `(2)` of an alternate pronunciation
+fn label(line: &str) -> &str {
+    let label = line.split(' ').next().unwrap_or("");
+    match label.rsplit_once('(') {
+        Some((label, _)) => label,
+        None => label,
+    }
+}
+
+/// compact `source`, which should already be known to be valid. lines are
+/// read the way cmudict-fast reads them: `;;` starts a comment line and `#`
+/// the comment at the end of a line
+pub fn compact(source: &str) -> Compact {
+    let mut words: BTreeMap<&str, (Vec<u32>, Vec<u32>)> = BTreeMap::new();
+    let mut keys: HashMap<Vec<&str>, u32> = HashMap::new();
+    for line in source.lines().filter(|line| !line.starts_with(";;")) {
+        let rule = line.split('#').next().unwrap_or("");
+        // only vowels have a stress marker, and they're the syllables
+        let phonemes: Vec<&str> = rule.split_whitespace().skip(1).collect();
+        let is_vowel = |phoneme: &str| phoneme.ends_with(|c: char| c.is_ascii_digit());
+        let (pronunciations, word_keys) = words.entry(label(line)).or_default();
+        let vowels: Vec<&str> = phonemes.iter().copied().filter(|p| is_vowel(p)).collect();
+        // 1 is primary stress and 2 secondary
+        let stresses = vowels
+            .iter()
+            .take(32)
+            .enumerate()
+            .filter(|(_, vowel)| !vowel.ends_with('0'))
+            .fold(0u32, |stresses, (i, _)| stresses | 1 << i);
+        pronunciations.extend([vowels.len() as u32, stresses]);
+        if let Some(last) = phonemes.iter().rposition(|p| is_vowel(p)) {
+            let next = keys.len() as u32;
+            let key = *keys.entry(phonemes[last..].to_vec()).or_insert(next);
+            if !word_keys.contains(&key) {
+                word_keys.push(key);
+            }
+        }
+    }
+
+    let mut entries: Vec<u32> = Vec::new();
+    let mut offsets: HashMap<Vec<u32>, u64> = HashMap::new();
+    let mut builder = fst::MapBuilder::memory();
+    for (word, (pronunciations, word_keys)) in words {
+        let mut entry = vec![(pronunciations.len() as u32 / 2) << 8 | word_keys.len() as u32];
+        entry.extend(word_keys);
+        entry.extend(pronunciations);
+        let offset = *offsets.entry(entry).or_insert_with_key(|entry| {
+            let offset = entries.len() as u64;
+            entries.extend(entry);
+            offset
+        });
+        builder
+            .insert(word, offset)
+            .expect("words are inserted once each, in order");
+    }
+    Compact {
+        words: builder.into_inner().expect("building in memory can't fail"),
+        entries: entries
+            .iter()
+            .flat_map(|entry| entry.to_le_bytes())
+            .collect(),
+    }
+}
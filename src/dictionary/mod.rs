@@ -0,0 +1,487 @@
+//! the pronunciation dictionary behind syllable counting and rhymes
+//!
+//! there's one dictionary for the whole process. it's set up by the first
+//! lookup, with the bundled dictionary, unless one of the `init_` functions
+//! was called first to load it at a time of the caller's choosing or to use
+//! a different one. it can only be set up once
+//!
+//! by default it's cmudict-fast's map of every pronunciation. the
+//! `compact-dictionary` feature swaps it for a compact form built by the
+//! `compact` module, which keeps only what the parser needs: the syllables
+//! of each of a word's pronunciations and which of them are stressed, and
+//! which words rhyme
+//!
+//! the bundled dictionary is res/cmudict.dict, unless the `ASHPAPER_DICT`
+//! environment variable names another file in the cmudict format when the
+//! crate is built. the build script checks that file, failing on the first
+//! line cmudict-fast can't parse, and compiles it in instead
+mod cache;
+#[cfg(feature = "compact-dictionary")]
+mod compact;
+
+use std::{
+    path::Path,
+    sync::{Mutex, OnceLock},
+};
+
+pub use super::errors::dictionary::{DictionaryError, DictionaryResult};
+use super::{io::Encoding, program, Result};
+pub use cache::flush_syllable_caches;
+pub(crate) use cache::{flush as flush_syllable_cache, syllables as cached_syllables};
+
+#[cfg(not(feature = "compact-dictionary"))]
+pub(crate) use full::Dictionary;
+
+#[cfg(feature = "compact-dictionary")]
+pub(crate) use packed::Dictionary;
+
+/// one way of saying a word, as far as counting syllables and scanning
+/// meter goes
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) struct Pronunciation {
+    pub(crate) syllables: usize,
+    /// bit `i` is set when syllable `i` has primary or secondary stress.
+    /// syllables after the 32nd are read as unstressed
+    pub(crate) stresses: u32,
+}
+
+impl Pronunciation {
+    /// the stress of each syllable, `1` for stressed and `0` for unstressed
+    pub(crate) fn stress_pattern(&self) -> String {
+        (0..self.syllables)
+            .map(|i| {
+                if i < 32 && self.stresses >> i & 1 == 1 {
+                    '1'
+                } else {
+                    '0'
+                }
+            })
+            .collect()
+    }
+}
+
+/// a dictionary that was set up, and its fingerprint
+struct Loaded {
+    dictionary: Dictionary,
+    fingerprint: u64,
+}
+
+static DICTIONARY: OnceLock<Loaded> = OnceLock::new();
+/// held while the bundled dictionary is loaded, so threads that race to the
+/// first lookup wait for one load instead of each doing their own
+static LOADING: Mutex<()> = Mutex::new(());
+
+/// the dictionary that was set up, setting up the bundled one if nothing
+/// was
+pub(crate) fn load() -> DictionaryResult<&'static Dictionary> {
+    loaded().map(|loaded| &loaded.dictionary)
+}
+
+/// tells the dictionary that was set up apart from any other, setting up
+/// the bundled one if nothing was
+pub(crate) fn fingerprint() -> DictionaryResult<u64> {
+    loaded().map(|loaded| loaded.fingerprint)
+}
+
+fn loaded() -> DictionaryResult<&'static Loaded> {
+    if let Some(loaded) = DICTIONARY.get() {
+        return Ok(loaded);
+    }
+    // a thread that panicked while loading didn't leave anything behind
+    let _loading = LOADING
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(loaded) = DICTIONARY.get() {
+        return Ok(loaded);
+    }
+    let loaded = bundled_loaded()?;
+    Ok(DICTIONARY.get_or_init(|| loaded))
+}
+
+fn set(loaded: Loaded) -> DictionaryResult<()> {
+    DICTIONARY
+        .set(loaded)
+        .map_err(|_| DictionaryError::AlreadyInitialized)
+}
+
+/// fnv-1a of a dictionary's source, which unlike the std hashers is the
+/// same in every build. the build script fingerprints the bundled one the
+/// same way
+pub(crate) fn fingerprint_of(source: &[u8]) -> u64 {
+    source.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn bundled_loaded() -> DictionaryResult<Loaded> {
+    #[cfg(feature = "bundled-dictionary")]
+    let fingerprint = env!("ASHPAPER_DICT_FINGERPRINT")
+        .parse()
+        .expect("the build script writes a number");
+    #[cfg(not(feature = "bundled-dictionary"))]
+    let fingerprint = fingerprint_of(b"");
+    Ok(Loaded {
+        dictionary: bundled()?,
+        fingerprint,
+    })
+}
+
+/// whether the dictionary has been set up, by an `init_` function or a
+/// lookup
+pub fn is_initialized() -> bool {
+    DICTIONARY.get().is_some()
+}
+
+/// load the bundled dictionary now rather than on the first lookup. without
+/// the `bundled-dictionary` feature that's an empty dictionary, so every
+/// word's syllables are approximated and nothing rhymes
+pub fn init_default() -> Result<()> {
+    let _loading = LOADING
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if is_initialized() {
+        return Err(DictionaryError::AlreadyInitialized.into());
+    }
+    Ok(set(bundled_loaded()?)?)
+}
+
+/// use a dictionary in the cmudict format instead of the bundled one, e.g.
+/// one fetched by a web page after it loads. an empty string leaves only
+/// the approximation
+pub fn init_from_str(source: &str) -> Result<()> {
+    Ok(set(Loaded {
+        dictionary: Dictionary::from_source(source)?,
+        fingerprint: fingerprint_of(source.as_bytes()),
+    })?)
+}
+
+/// [`init_from_str`] with the utf-8 dictionary at `path`
+pub fn init_from_path(path: impl AsRef<Path>) -> Result<()> {
+    let source = program::read_file(path.as_ref(), Encoding::Utf8)?;
+    init_from_str(&source)
+}
+
+/// the dictionary compiled into the library. without the
+/// `bundled-dictionary` feature that's an empty dictionary
+#[cfg(not(feature = "bundled-dictionary"))]
+fn bundled() -> DictionaryResult<Dictionary> {
+    Dictionary::from_source("")
+}
+
+/// parse `source` like cmudict-fast does, failing with the same errors
+fn validate(source: &str) -> DictionaryResult<cmudict_fast::Cmudict> {
+    source
+        .parse()
+        .map_err(|err: cmudict_fast::Error| DictionaryError::Invalid(err.to_string()))
+}
+
+#[cfg(not(feature = "compact-dictionary"))]
+mod full {
+    use cmudict_fast::{self as cmudict, Cmudict, Rule};
+
+    use super::{validate, DictionaryResult, Pronunciation};
+
+    fn syllables(rule: &Rule) -> usize {
+        rule.pronunciation()
+            .iter()
+            .filter(|po| po.is_syllable())
+            .count()
+    }
+
+    fn pronunciation(rule: &Rule) -> Pronunciation {
+        let stresses = rule
+            .pronunciation()
+            .iter()
+            .filter(|po| po.is_syllable())
+            .take(32)
+            .enumerate()
+            .filter(|(_, po)| po.is_primary() || po.is_secondary())
+            .fold(0, |stresses, (i, _)| stresses | 1 << i);
+        Pronunciation {
+            syllables: syllables(rule),
+            stresses,
+        }
+    }
+
+    pub(crate) struct Dictionary(Cmudict);
+
+    impl Dictionary {
+        pub(crate) fn from_source(source: &str) -> DictionaryResult<Dictionary> {
+            validate(source).map(Dictionary)
+        }
+
+        pub(crate) fn contains(&self, word: &str) -> bool {
+            self.0.get(word).is_some()
+        }
+
+        /// the most syllables of any of the word's pronunciations
+        pub(crate) fn syllables(&self, word: &str) -> Option<usize> {
+            self.0
+                .get(word)
+                .map(|rules| rules.iter().map(syllables).max().unwrap())
+        }
+
+        /// each of the word's pronunciations, in the order the dictionary
+        /// lists them
+        pub(crate) fn pronunciations(&self, word: &str) -> Option<Vec<Pronunciation>> {
+            self.0
+                .get(word)
+                .map(|rules| rules.iter().map(pronunciation).collect())
+        }
+
+        /// `None` unless both words are in the dictionary
+        pub(crate) fn rhymes(&self, one: &str, two: &str) -> Option<bool> {
+            Some(cmudict::rhymes(self.0.get(one)?, self.0.get(two)?))
+        }
+    }
+
+    #[cfg(feature = "bundled-dictionary")]
+    pub(crate) fn bundled() -> DictionaryResult<Dictionary> {
+        Dictionary::from_source(include_str!(env!("ASHPAPER_DICT_PATH")))
+    }
+}
+
+#[cfg(all(not(feature = "compact-dictionary"), feature = "bundled-dictionary"))]
+use full::bundled;
+
+#[cfg(feature = "compact-dictionary")]
+mod packed {
+    use std::{borrow::Cow, convert::TryInto};
+
+    use fst::Map;
+
+    use super::{compact, validate, DictionaryResult, Pronunciation};
+
+    pub(crate) struct Dictionary {
+        words: Map<Cow<'static, [u8]>>,
+        entries: Cow<'static, [u8]>,
+    }
+
+    impl Dictionary {
+        pub(crate) fn from_source(source: &str) -> DictionaryResult<Dictionary> {
+            validate(source)?;
+            let compact = compact::compact(source);
+            Ok(Dictionary::new(
+                Cow::Owned(compact.words),
+                Cow::Owned(compact.entries),
+            ))
+        }
+
+        fn new(words: Cow<'static, [u8]>, entries: Cow<'static, [u8]>) -> Dictionary {
+            Dictionary {
+                words: Map::new(words).expect("compacted dictionaries are valid fsts"),
+                entries,
+            }
+        }
+
+        fn entry(&self, offset: usize) -> u32 {
+            let bytes = &self.entries[offset * 4..offset * 4 + 4];
+            u32::from_le_bytes(bytes.try_into().expect("entries are four bytes"))
+        }
+
+        /// the word's pronunciations and rhyme keys
+        fn lookup(
+            &self,
+            word: &str,
+        ) -> Option<(
+            impl Iterator<Item = Pronunciation> + '_,
+            impl Iterator<Item = u32> + Clone + '_,
+        )> {
+            let offset = self.words.get(word)? as usize;
+            let header = self.entry(offset);
+            let key_count = (header & 0xff) as usize;
+            let keys = (0..key_count).map(move |i| self.entry(offset + 1 + i));
+            let variants = (0..(header >> 8) as usize).map(move |i| {
+                let at = offset + 1 + key_count + i * 2;
+                Pronunciation {
+                    syllables: self.entry(at) as usize,
+                    stresses: self.entry(at + 1),
+                }
+            });
+            Some((variants, keys))
+        }
+
+        pub(crate) fn contains(&self, word: &str) -> bool {
+            self.words.contains_key(word)
+        }
+
+        pub(crate) fn syllables(&self, word: &str) -> Option<usize> {
+            self.lookup(word)
+                .map(|(variants, _)| variants.map(|variant| variant.syllables).max().unwrap_or(0))
+        }
+
+        pub(crate) fn pronunciations(&self, word: &str) -> Option<Vec<Pronunciation>> {
+            self.lookup(word).map(|(variants, _)| variants.collect())
+        }
+
+        /// `None` unless both words are in the dictionary
+        pub(crate) fn rhymes(&self, one: &str, two: &str) -> Option<bool> {
+            let (_, mut ones) = self.lookup(one)?;
+            let (_, twos) = self.lookup(two)?;
+            Some(ones.any(|key| twos.clone().any(|other| other == key)))
+        }
+    }
+
+    /// compacted by the build script
+    #[cfg(feature = "bundled-dictionary")]
+    pub(crate) fn bundled() -> DictionaryResult<Dictionary> {
+        Ok(Dictionary::new(
+            Cow::Borrowed(include_bytes!(concat!(env!("OUT_DIR"), "/cmudict.fst"))),
+            Cow::Borrowed(include_bytes!(concat!(env!("OUT_DIR"), "/cmudict.entries"))),
+        ))
+    }
+}
+
+#[cfg(all(feature = "compact-dictionary", feature = "bundled-dictionary"))]
+use packed::bundled;
+
+/// the compact dictionary has to agree with cmudict-fast on everything the
+/// parser asks it
+#[cfg(all(test, feature = "compact-dictionary", feature = "bundled-dictionary"))]
+mod tests {
+    use super::*;
+    use cmudict_fast as cmudict;
+    use pretty_assertions::assert_eq;
+
+    const SOURCE: &str = include_str!(env!("ASHPAPER_DICT_PATH"));
+
+    fn full_pronunciations(full: &cmudict::Cmudict, word: &str) -> Option<Vec<Pronunciation>> {
+        full.get(word).map(|rules| {
+            rules
+                .iter()
+                .map(|r| {
+                    let syllables: Vec<_> = r
+                        .pronunciation()
+                        .iter()
+                        .filter(|po| po.is_syllable())
+                        .collect();
+                    Pronunciation {
+                        syllables: syllables.len(),
+                        stresses: syllables
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, po)| !po.is_unstressed())
+                            .map(|(i, _)| 1 << i)
+                            .sum(),
+                    }
+                })
+                .collect()
+        })
+    }
+
+    /// every word of the bundled poems, split and lowercased like the parser
+    /// does
+    fn poem_words() -> Vec<String> {
+        let poems = [
+            include_str!("../../poems/cond-goto-test.eso").to_string(),
+            include_str!("../../poems/goto-test.eso").to_string(),
+            crate::io::latin1_to_string(include_bytes!("../../poems/latin1-test.eso")),
+            include_str!("../../poems/lovely-poem.eso").to_string(),
+            include_str!("../../poems/math-test.eso").to_string(),
+            include_str!("../../poems/original-factorial.eso").to_string(),
+            include_str!("../../poems/stack-overflow-test.eso").to_string(),
+            include_str!("../../poems/stack-test.eso").to_string(),
+        ];
+        let mut words: Vec<String> = poems
+            .iter()
+            .flat_map(|poem| poem.lines().flat_map(|line| line.split(' ')))
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase)
+            .collect();
+        words.sort();
+        words.dedup();
+        words
+    }
+
+    #[test]
+    fn same_as_full() {
+        let full = validate(SOURCE).unwrap();
+        let compact = bundled().unwrap();
+
+        let labels = SOURCE
+            .lines()
+            .filter(|line| !line.starts_with(";;"))
+            .map(|line| line.split(' ').next().unwrap());
+        for word in labels.chain(["", "ashpaper", "poem."]) {
+            assert_eq!(compact.contains(word), full.get(word).is_some(), "{}", word);
+            let pronunciations = full_pronunciations(&full, word);
+            assert_eq!(compact.pronunciations(word), pronunciations, "{}", word);
+            assert_eq!(
+                compact.syllables(word),
+                pronunciations.and_then(|variants| {
+                    variants.into_iter().map(|variant| variant.syllables).max()
+                }),
+                "{}",
+                word
+            );
+        }
+
+        let words = poem_words();
+        let known = words.iter().filter(|word| compact.contains(word)).count();
+        assert!(known > 50, "only {} poem words are known", known);
+        for one in &words {
+            for two in &words {
+                let expected = match (full.get(one), full.get(two)) {
+                    (Some(ones), Some(twos)) => Some(cmudict::rhymes(ones, twos)),
+                    _ => None,
+                };
+                assert_eq!(compact.rhymes(one, two), expected, "{} / {}", one, two);
+            }
+        }
+    }
+
+    /// the blob the build script wrote, against compacting the source when
+    /// it's loaded like a dictionary given at runtime is
+    #[test]
+    fn build_time_matches_runtime() {
+        let built = bundled().unwrap();
+        let runtime = Dictionary::from_source(SOURCE).unwrap();
+
+        let labels: Vec<&str> = SOURCE
+            .lines()
+            .filter(|line| !line.starts_with(";;"))
+            .map(|line| line.split(' ').next().unwrap())
+            // alternate pronunciations are folded into the word
+            .filter(|label| !label.ends_with(')'))
+            .collect();
+        let sample: Vec<&str> = labels
+            .iter()
+            .step_by(labels.len() / 1000)
+            .take(1000)
+            .copied()
+            .collect();
+        assert_eq!(sample.len(), 1000);
+        for (i, word) in sample.iter().enumerate() {
+            assert_eq!(
+                built.pronunciations(word),
+                runtime.pronunciations(word),
+                "{}",
+                word
+            );
+            let other = sample[(i * 7 + 1) % sample.len()];
+            assert_eq!(
+                built.rhymes(word, other),
+                runtime.rhymes(word, other),
+                "{} / {}",
+                word,
+                other
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_source() {
+        assert_eq!(
+            Dictionary::from_source("poem  P OW9 AH0 M").err(),
+            validate("poem  P OW9 AH0 M").err()
+        );
+        let small = Dictionary::from_source(
+            "poem  P OW1 AH0 M\nnone  N AH1 N\nmoan  M OW1 N\nscone  S K OW1 N\n",
+        )
+        .unwrap();
+        assert_eq!(small.syllables("poem"), Some(2));
+        assert_eq!(small.rhymes("moan", "scone"), Some(true));
+        assert_eq!(small.rhymes("moan", "none"), Some(false));
+        assert_eq!(small.rhymes("moan", "groan"), None);
+    }
+}
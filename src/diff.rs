@@ -0,0 +1,377 @@
+//! whether an edit to a poem changed the program it runs
+//!
+//! the lines of the two poems are lined up by their instructions, so a line
+//! that was added or removed doesn't make every line after it look changed.
+//! lines between two that line up are paired off in order, which is how a
+//! line that parses to something else shows up as changed rather than as
+//! removed and added again
+use std::fmt;
+
+use super::{InsType, Instruction, Program, Register};
+
+/// what changed about a line
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Change {
+    /// only in the second poem
+    Added(InsType),
+    /// only in the first poem
+    Removed(InsType),
+    /// parsed to a different instruction, e.g. add to store
+    Instruction {
+        from: InsType,
+        to: InsType,
+    },
+    /// the same instruction with different syllables in it, e.g. a store of
+    /// 3 to a store of 4
+    Syllables {
+        from: InsType,
+        to: InsType,
+    },
+    Register {
+        from: Register,
+        to: Register,
+    },
+    /// different words that run the same
+    TextOnly,
+}
+
+impl Change {
+    /// whether the program runs differently because of it
+    pub fn is_semantic(&self) -> bool {
+        *self != Change::TextOnly
+    }
+}
+
+/// a line that differs between the poems
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LineDiff {
+    /// 0-based line in the first poem, `None` if the line was added
+    pub old_line: Option<usize>,
+    /// 0-based line in the second poem, `None` if the line was removed
+    pub new_line: Option<usize>,
+    /// never empty. a line that was added or removed has just that change
+    pub changes: Vec<Change>,
+}
+
+/// the result of [`semantic_diff`]
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct SemanticDiff {
+    /// in the order of the lines, lines that are the same in both poems
+    /// left out
+    pub lines: Vec<LineDiff>,
+    /// pairs of 0-based lines in the first and second poem that are the same
+    /// line, but moved. a goto jumps to a line by its number, so these land
+    /// somewhere else than they used to. only filled in if either poem has a
+    /// goto or a cond-goto
+    pub shifted_goto_targets: Vec<(usize, usize)>,
+}
+
+impl SemanticDiff {
+    /// whether both poems run the same, even if the words differ
+    pub fn is_semantically_equal(&self) -> bool {
+        self.shifted_goto_targets.is_empty()
+            && self
+                .lines
+                .iter()
+                .all(|line| line.changes.iter().all(|change| !change.is_semantic()))
+    }
+}
+
+/// the mnemonic of an instruction, with the syllables it holds
+fn describe(instruction: InsType) -> String {
+    match instruction {
+        InsType::Store(syllables) | InsType::ConditionalGoto(syllables) => {
+            format!("{} {}", instruction.mnemonic(), syllables)
+        }
+        InsType::ConditionalPush {
+            prev_syllables,
+            cur_syllables,
+        } => format!(
+            "{} {}/{}",
+            instruction.mnemonic(),
+            prev_syllables,
+            cur_syllables
+        ),
+        _ => instruction.mnemonic().to_string(),
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Change::Added(instruction) => write!(f, "added {}", describe(instruction)),
+            Change::Removed(instruction) => write!(f, "removed {}", describe(instruction)),
+            Change::Instruction { from, to } | Change::Syllables { from, to } => {
+                write!(f, "{} became {}", describe(from), describe(to))
+            }
+            Change::Register { from, to } => {
+                write!(f, "r{} became r{}", from.index(), to.index())
+            }
+            Change::TextOnly => write!(f, "text only"),
+        }
+    }
+}
+
+impl fmt::Display for SemanticDiff {
+    /// a line per changed line, numbered from 1 like the rest of the crate
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let number =
+            |line: Option<usize>| line.map_or("-".to_string(), |line| (line + 1).to_string());
+        if self.is_semantically_equal() {
+            writeln!(f, "no semantic changes")?;
+        }
+        for line in &self.lines {
+            let changes: Vec<String> = line.changes.iter().map(Change::to_string).collect();
+            writeln!(
+                f,
+                "{} -> {}: {}",
+                number(line.old_line),
+                number(line.new_line),
+                changes.join(", ")
+            )?;
+        }
+        if !self.shifted_goto_targets.is_empty() {
+            let moves: Vec<String> = self
+                .shifted_goto_targets
+                .iter()
+                .map(|&(old, new)| format!("{} -> {}", old + 1, new + 1))
+                .collect();
+            writeln!(f, "goto targets moved: {}", moves.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// pairs of indices into `a` and `b` of the longest common subsequence of
+/// their mnemonics
+fn align(a: &[Instruction], b: &[Instruction]) -> Vec<(usize, usize)> {
+    let same = |i: usize, j: usize| a[i].instruction.mnemonic() == b[j].instruction.mnemonic();
+    // lengths[i][j] is the length of the subsequence of a[i..] and b[j..]
+    let mut lengths = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lengths[i][j] = if same(i, j) {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+    let mut pairs = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if same(i, j) {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    pairs
+}
+
+/// what changed between two lines that line up
+fn compare(old: &Instruction, new: &Instruction) -> Vec<Change> {
+    let (from, to) = (old.instruction, new.instruction);
+    let mut changes = vec![];
+    if from.mnemonic() != to.mnemonic() {
+        changes.push(Change::Instruction { from, to });
+    } else if from != to {
+        changes.push(Change::Syllables { from, to });
+    }
+    if old.register != new.register {
+        changes.push(Change::Register {
+            from: old.register,
+            to: new.register,
+        });
+    }
+    if changes.is_empty() && old.line().trim_end() != new.line().trim_end() {
+        changes.push(Change::TextOnly);
+    }
+    changes
+}
+
+/// compare what two poems do line by line, see [`SemanticDiff`]
+pub fn semantic_diff(a: &str, b: &str) -> SemanticDiff {
+    let (old, new) = (Program::create(a).ast, Program::create(b).ast);
+    let mut diff = SemanticDiff::default();
+    let mut pairs = vec![];
+
+    let mut push = |old_line, new_line, changes: Vec<Change>| {
+        if !changes.is_empty() {
+            diff.lines.push(LineDiff {
+                old_line,
+                new_line,
+                changes,
+            });
+        }
+    };
+    let (mut i, mut j) = (0, 0);
+    // a sentinel past the end of both, so the lines after the last match are
+    // paired off too
+    for (next_i, next_j) in align(&old, &new)
+        .into_iter()
+        .chain(Some((old.len(), new.len())))
+    {
+        while i < next_i && j < next_j {
+            push(Some(i), Some(j), compare(&old[i], &new[j]));
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        }
+        for (i, ins) in old.iter().enumerate().take(next_i).skip(i) {
+            push(Some(i), None, vec![Change::Removed(ins.instruction)]);
+        }
+        for (j, ins) in new.iter().enumerate().take(next_j).skip(j) {
+            push(None, Some(j), vec![Change::Added(ins.instruction)]);
+        }
+        if next_i < old.len() {
+            push(
+                Some(next_i),
+                Some(next_j),
+                compare(&old[next_i], &new[next_j]),
+            );
+            pairs.push((next_i, next_j));
+        }
+        i = next_i + 1;
+        j = next_j + 1;
+    }
+
+    let jumps = |ast: &[Instruction]| {
+        ast.iter()
+            .any(|ins| matches!(ins.instruction, InsType::Goto | InsType::ConditionalGoto(_)))
+    };
+    if jumps(&old) || jumps(&new) {
+        diff.shifted_goto_targets = pairs.into_iter().filter(|(i, j)| i != j).collect();
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn whitespace() {
+        let diff = semantic_diff(
+            "lovely poem\n  fish swim\nprint.",
+            "lovely  poem \n  fish   swim\nprint.\t",
+        );
+        assert!(diff.is_semantically_equal());
+        assert_eq!(
+            diff.lines
+                .iter()
+                .map(|line| line.old_line)
+                .collect::<Vec<_>>(),
+            vec![Some(0), Some(1)]
+        );
+        assert_eq!(
+            diff.to_string(),
+            "no semantic changes\n1 -> 1: text only\n2 -> 2: text only\n"
+        );
+        assert_eq!(
+            semantic_diff("a poem\nprint.", "a poem\nprint.\n"),
+            SemanticDiff::default()
+        );
+    }
+
+    #[cfg(feature = "bundled-dictionary")]
+    #[test]
+    fn like_to_per() {
+        let diff = semantic_diff(
+            "lovely poem\n  fish\nswim like whales\nprint.",
+            "lovely poem\n  fish\nswim per whales\nprint.",
+        );
+        assert_eq!(
+            diff.lines,
+            vec![LineDiff {
+                old_line: Some(2),
+                new_line: Some(2),
+                changes: vec![Change::Instruction {
+                    from: InsType::Add,
+                    to: InsType::Store(3),
+                }],
+            }]
+        );
+        assert!(!diff.is_semantically_equal());
+        assert_eq!(diff.to_string(), "3 -> 3: add became store 3\n");
+    }
+
+    #[test]
+    fn syllables_and_registers() {
+        let diff = semantic_diff("lovely poem\nprint.", "  a lovely poem\nprint.");
+        assert_eq!(
+            diff.lines[0].changes,
+            vec![
+                Change::Syllables {
+                    from: InsType::Store(4),
+                    to: InsType::Store(5),
+                },
+                Change::Register {
+                    from: Register::Register0,
+                    to: Register::Register1,
+                },
+            ]
+        );
+        assert_eq!(
+            diff.to_string(),
+            "1 -> 1: store 4 became store 5, r0 became r1\n"
+        );
+    }
+
+    #[test]
+    fn inserted_blank_line() {
+        let old = "lovely poem\nbig bad\nprint.\n  a fish\n  print.";
+        let new = "lovely poem\n\nbig bad\nprint.\n  a fish\n  print.";
+        let diff = semantic_diff(old, new);
+        assert_eq!(
+            diff.lines,
+            vec![LineDiff {
+                old_line: None,
+                new_line: Some(1),
+                changes: vec![Change::Added(InsType::Noop)],
+            }]
+        );
+        assert_eq!(
+            diff.shifted_goto_targets,
+            vec![(1, 2), (2, 3), (3, 4), (4, 5)]
+        );
+        assert!(!diff.is_semantically_equal());
+        assert_eq!(
+            diff.to_string(),
+            "- -> 2: added noop\ngoto targets moved: 2 -> 3, 3 -> 4, 4 -> 5, 5 -> 6\n"
+        );
+
+        // without a goto, moving lines doesn't matter
+        let diff = semantic_diff("lovely poem\nprint.", "lovely poem\n\nprint.");
+        assert!(diff.shifted_goto_targets.is_empty());
+        assert_eq!(diff.lines.len(), 1);
+    }
+
+    #[test]
+    fn removed_lines() {
+        let diff = semantic_diff(
+            "lovely poem\npush-ed\nprint.\n  fish",
+            "lovely poem\nprint.",
+        );
+        assert_eq!(
+            diff.lines,
+            vec![
+                LineDiff {
+                    old_line: Some(1),
+                    new_line: None,
+                    changes: vec![Change::Removed(InsType::Push)],
+                },
+                LineDiff {
+                    old_line: Some(3),
+                    new_line: None,
+                    changes: vec![Change::Removed(InsType::Store(1))],
+                },
+            ]
+        );
+    }
+}
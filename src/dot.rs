@@ -0,0 +1,96 @@
+//! graphviz export of a program's control flow
+//!
+//! every line is a node. gotos jump to wherever the value in a register
+//! points, which can't be known without running the poem, so they all lead
+//! to a single "computed jump" node instead of a particular line
+use super::{parser::InsType, Program};
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+impl Program {
+    /// the control-flow graph of the program in graphviz DOT format
+    pub fn to_dot(&self) -> String {
+        let mut dot =
+            String::from("digraph poem {\n    node [shape=box, fontname=\"monospace\"];\n");
+        dot.push_str("    start [shape=circle];\n    halt [shape=doublecircle];\n");
+        let has_jumps = self
+            .ast
+            .iter()
+            .any(|ins| matches!(ins.instruction, InsType::Goto | InsType::ConditionalGoto(_)));
+        if has_jumps {
+            dot.push_str("    jump [shape=diamond, label=\"computed jump\"];\n");
+        }
+
+        for (index, ins) in self.ast.iter().enumerate() {
+            dot.push_str(&format!(
+                "    l{} [label=\"{}: {}\\n{}\"];\n",
+                index,
+                index + 1,
+                ins.instruction.mnemonic(),
+                escape(ins.line().trim())
+            ));
+        }
+
+        let node = |index: usize| {
+            if index < self.ast.len() {
+                format!("l{}", index)
+            } else {
+                "halt".to_string()
+            }
+        };
+        dot.push_str(&format!("    start -> {};\n", node(0)));
+        for (index, ins) in self.ast.iter().enumerate() {
+            match ins.instruction {
+                InsType::Goto => {
+                    dot.push_str(&format!("    l{} -> jump [style=dashed];\n", index));
+                }
+                InsType::ConditionalGoto(_) => {
+                    dot.push_str(&format!(
+                        "    l{} -> jump [style=dashed, label=\"taken\"];\n",
+                        index
+                    ));
+                    dot.push_str(&format!("    l{} -> {};\n", index, node(index + 1)));
+                }
+                _ => dot.push_str(&format!("    l{} -> {};\n", index, node(index + 1))),
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Program;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn small_poem() {
+        let program = Program::create("fish\nsells sea shells\n  \"quoted\" re/cur\nprint.");
+        let expected = r#"digraph poem {
+    node [shape=box, fontname="monospace"];
+    start [shape=circle];
+    halt [shape=doublecircle];
+    jump [shape=diamond, label="computed jump"];
+    l0 [label="1: store\nfish"];
+    l1 [label="2: goto\nsells sea shells"];
+    l2 [label="3: cond-goto\n\"quoted\" re/cur"];
+    l3 [label="4: print-value\nprint."];
+    start -> l0;
+    l0 -> l1;
+    l1 -> jump [style=dashed];
+    l2 -> jump [style=dashed, label="taken"];
+    l2 -> l3;
+    l3 -> halt;
+}
+"#;
+        assert_eq!(program.to_dot(), expected);
+    }
+
+    #[test]
+    fn empty_poem() {
+        assert!(Program::create("").to_dot().contains("start -> halt;"));
+    }
+}
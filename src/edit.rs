@@ -0,0 +1,379 @@
+//! changing a program's instructions in place, for tools like minifiers and
+//! optimizers
+//!
+//! edits are made on a copy and only land on the program when they're
+//! committed. inserting or removing a line moves the lines after it, so an
+//! edit that would move the line a goto always jumps to is refused. a goto
+//! always jumps to the same line when the line right before it stores
+//! syllables into the register it jumps by, the way
+//! [`generate`](crate::generate) writes them. gotos that jump by a computed
+//! value can't be checked
+use super::{
+    parser::{self, InsType, Instruction},
+    Dialect, ParseOptions, Program, Result,
+};
+
+pub use super::errors::edit::{EditError, EditResult};
+
+/// edits to a [`Program`], from [`Program::edit`]. nothing changes until
+/// [`AstEditor::commit`], and dropping the editor throws the edits away.
+/// indices are always into the instructions as they are after the edits
+/// made so far
+pub struct AstEditor<'a> {
+    program: &'a mut Program,
+    ast: Vec<Instruction>,
+    /// the index each instruction had in the program, or `None` for ones
+    /// that were inserted or replaced
+    origins: Vec<Option<usize>>,
+}
+
+impl Program {
+    /// start editing the program's instructions
+    pub fn edit(&mut self) -> AstEditor<'_> {
+        AstEditor {
+            ast: self.ast.clone(),
+            origins: (0..self.ast.len()).map(Some).collect(),
+            program: self,
+        }
+    }
+}
+
+/// the gotos in `ast` that always jump to the same line, by their index and
+/// the syllables stored right before them
+pub(crate) fn constant_jumps(
+    ast: &[Instruction],
+    dialect: Dialect,
+) -> impl Iterator<Item = (usize, u64)> + '_ {
+    ast.windows(2).enumerate().filter_map(move |(index, pair)| {
+        let (store, jump) = (&pair[0], &pair[1]);
+        let syllables = match store.instruction {
+            InsType::Store(syllables) => syllables,
+            _ => return None,
+        };
+        let jumps_by_store = match jump.instruction {
+            InsType::Goto => jump.register == store.register,
+            // by the inactive register, which in the extended dialect is the
+            // one the line before used
+            InsType::ConditionalGoto(_) => {
+                dialect == Dialect::Extended || jump.register.paired() == store.register
+            }
+            _ => false,
+        };
+        jumps_by_store.then_some((index + 1, u64::from(syllables)))
+    })
+}
+
+/// `ast[index]` parsed again after the line before it
+fn reparse(ast: &[Instruction], index: usize, options: &ParseOptions) -> Instruction {
+    let lines = match index.checked_sub(1) {
+        Some(previous) => format!("{}\n{}", ast[previous].line(), ast[index].line()),
+        None => ast[index].line().to_string(),
+    };
+    parser::parse_with_options(&lines, options)
+        .pop()
+        .expect("a line always parses to an instruction")
+}
+
+impl AstEditor<'_> {
+    /// how many instructions there are with the edits so far
+    pub fn len(&self) -> usize {
+        self.ast.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ast.is_empty()
+    }
+
+    /// the instructions with the edits so far
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.ast
+    }
+
+    fn check_index(&self, index: usize, len: usize) -> EditResult<()> {
+        if index < len {
+            Ok(())
+        } else {
+            Err(EditError::OutOfRange {
+                index,
+                len: self.ast.len(),
+            })
+        }
+    }
+
+    /// refuse to insert (or remove) a line at `index` if it would change
+    /// where a goto always jumps
+    fn check_jumps(&self, index: usize, inserted: bool) -> EditResult<()> {
+        let len = self.ast.len();
+        let new_len = if inserted { len + 1 } else { len - 1 };
+        for (goto, syllables) in constant_jumps(&self.ast, self.program.dialect) {
+            // the goto, or the store it jumps by, is removed along with it
+            if !inserted && (index == goto || index + 1 == goto) {
+                continue;
+            }
+            let target = (syllables % len as u64) as usize;
+            let moved = if target < index {
+                Some(target)
+            } else if inserted {
+                Some(target + 1)
+            } else if target == index {
+                None
+            } else {
+                Some(target - 1)
+            };
+            // a line between the store and the goto could change the register
+            let separated = inserted && index == goto;
+            if separated || moved != Some((syllables % new_len as u64) as usize) {
+                return Err(EditError::MovesGotoTarget { goto, target });
+            }
+        }
+        Ok(())
+    }
+
+    /// insert `instruction` before the one at `index`, or at the end if
+    /// `index` is the number of instructions
+    pub fn insert(&mut self, index: usize, instruction: Instruction) -> Result<()> {
+        self.check_index(index, self.ast.len() + 1)?;
+        self.check_jumps(index, true)?;
+        self.ast.insert(index, instruction);
+        self.origins.insert(index, None);
+        Ok(())
+    }
+
+    /// remove the instruction at `index`, returning it
+    pub fn remove(&mut self, index: usize) -> Result<Instruction> {
+        self.check_index(index, self.ast.len())?;
+        self.check_jumps(index, false)?;
+        self.origins.remove(index);
+        Ok(self.ast.remove(index))
+    }
+
+    /// replace the instruction at `index` with `instruction`, returning the
+    /// one it replaced
+    pub fn replace(&mut self, index: usize, instruction: Instruction) -> Result<Instruction> {
+        self.check_index(index, self.ast.len())?;
+        self.origins[index] = None;
+        Ok(std::mem::replace(&mut self.ast[index], instruction))
+    }
+
+    /// apply the edits to the program, returning the indices of the
+    /// instructions that are different from the ones at the same index
+    /// before. a line of the program that now comes after a different line
+    /// is parsed again, with the options the program was parsed with, if it
+    /// rhymes with the new one or did with the old one, since that's what a
+    /// conditional push is. instructions that were
+    /// inserted or replaced are kept as they were given. the program's
+    /// source becomes the text of its lines
+    pub fn commit(self) -> Vec<usize> {
+        let AstEditor {
+            program,
+            mut ast,
+            origins,
+        } = self;
+        let options = ParseOptions {
+            dialect: program.dialect,
+            ..program.parse_options.clone()
+        };
+        let cond_push =
+            |ins: &Instruction| matches!(ins.instruction, InsType::ConditionalPush { .. });
+        for (index, origin) in origins.iter().enumerate() {
+            let origin = match origin {
+                Some(origin) => *origin,
+                None => continue,
+            };
+            let same_previous = match (index.checked_sub(1), origin.checked_sub(1)) {
+                (None, None) => true,
+                (Some(previous), Some(previous_origin)) => {
+                    origins[previous] == Some(previous_origin)
+                }
+                _ => false,
+            };
+            if same_previous || ast[index].line().trim().is_empty() {
+                continue;
+            }
+            let reparsed = reparse(&ast, index, &options);
+            if cond_push(&ast[index]) || cond_push(&reparsed) {
+                ast[index].instruction = reparsed.instruction;
+            }
+        }
+
+        let changed = (0..ast.len())
+            .filter(|&index| program.ast.get(index) != Some(&ast[index]))
+            .collect();
+        if let Some(source) = &program.source {
+            let mut lines: Vec<&str> = ast.iter().map(Instruction::line).collect();
+            if source.ends_with('\n') {
+                lines.push("");
+            }
+            program.source = Some(lines.join("\n").into());
+        }
+        program.ast = ast;
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "bundled-dictionary")]
+    use crate::ExecOptions;
+    use crate::{parser::Register, Error};
+    use pretty_assertions::assert_eq;
+
+    /// the error an edit failed with
+    fn edit_error<T: std::fmt::Debug>(result: Result<T>) -> EditError {
+        match result {
+            Err(Error::Edit(err)) => err,
+            other => panic!("expected an EditError, got {:?}", other),
+        }
+    }
+
+    /// what `text` parses to on a line of its own
+    fn line(text: &str) -> Instruction {
+        parser::parse(text)
+            .pop()
+            .unwrap_or_else(|| Instruction::new(InsType::Noop, Register::Register0, ""))
+    }
+
+    /// the program parsed again from its edited source. moving lines can
+    /// make the factorial loop forever, so neither runs for long
+    #[cfg(feature = "bundled-dictionary")]
+    fn assert_reparses(program: &Program) {
+        let fresh = Program::create(program.source());
+        assert_eq!(program.ast, fresh.ast);
+        let options = ExecOptions::with_max_instructions(10_000);
+        let run = |program: &Program| {
+            program
+                .execute_with_options(&options)
+                .map_err(|err| err.to_string())
+        };
+        assert_eq!(run(program), run(&fresh));
+    }
+
+    #[cfg(feature = "bundled-dictionary")]
+    #[test]
+    fn factorial() {
+        let mut program = Program::create(include_str!("../poems/lovely-poem.eso"));
+
+        // a title of 5 syllables
+        let mut editor = program.edit();
+        editor.replace(1, line("a lovely poem")).unwrap();
+        assert_eq!(editor.commit(), vec![1]);
+        assert_reparses(&program);
+        assert_eq!(program.execute(), "120\n");
+
+        // "would do" now rhymes with the line before it
+        assert_eq!(program.ast[12].instruction, InsType::Store(2));
+        let mut editor = program.edit();
+        editor.replace(11, line("    push-it to")).unwrap();
+        assert_eq!(editor.commit(), vec![11, 12]);
+        assert!(matches!(
+            program.ast[12].instruction,
+            InsType::ConditionalPush { .. }
+        ));
+        assert_reparses(&program);
+
+        // and no longer does once it's moved away from it
+        let mut editor = program.edit();
+        editor.insert(12, line("")).unwrap();
+        assert_eq!(editor.commit(), (12..19).collect::<Vec<_>>());
+        assert_eq!(program.ast[13].instruction, InsType::Store(2));
+        assert_reparses(&program);
+
+        // which makes it the number of the line the cond-goto after it
+        // jumps to, so that line can't move
+        let mut editor = program.edit();
+        assert_eq!(
+            edit_error(editor.remove(0)),
+            EditError::MovesGotoTarget {
+                goto: 14,
+                target: 2
+            }
+        );
+        assert_eq!(editor.remove(12).unwrap().instruction, InsType::Noop);
+        assert_eq!(editor.len(), 18);
+        assert_eq!(editor.commit(), (12..18).collect::<Vec<_>>());
+        assert!(matches!(
+            program.ast[12].instruction,
+            InsType::ConditionalPush { .. }
+        ));
+        assert_reparses(&program);
+
+        // putting the line back makes it the factorial again
+        let mut editor = program.edit();
+        editor
+            .replace(11, line("    machine-machine-machine"))
+            .unwrap();
+        assert_eq!(editor.commit(), vec![11, 12]);
+        assert_eq!(program.ast[12].instruction, InsType::Store(2));
+        assert_reparses(&program);
+        assert_eq!(program.execute(), "120\n");
+    }
+
+    #[cfg(feature = "bundled-dictionary")]
+    #[test]
+    fn parse_options() {
+        // a syllable for every letter
+        let options = ParseOptions {
+            syllable_counter: Some(parser::CustomCounter::new(|word: &str| word.len())),
+            ..ParseOptions::default()
+        };
+        let mut program = Program::create_with_options("the cat\nsat\n  on the mat", &options);
+        let mut editor = program.edit();
+        editor.remove(1).unwrap();
+        assert_eq!(editor.commit(), vec![1]);
+        assert_eq!(
+            program.ast[1].instruction,
+            InsType::ConditionalPush {
+                prev_syllables: 6,
+                cur_syllables: 8
+            }
+        );
+        assert_eq!(
+            program.ast,
+            Program::create_with_options(program.source(), &options).ast
+        );
+    }
+
+    #[test]
+    fn discarded() {
+        let mut program = Program::create("lovely poem\nprint.");
+        let mut editor = program.edit();
+        editor.remove(0).unwrap();
+        assert_eq!(
+            edit_error(editor.remove(1)),
+            EditError::OutOfRange { index: 1, len: 1 }
+        );
+        drop(editor);
+        assert_eq!(program.ast.len(), 2);
+        assert_eq!(program.edit().commit(), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn goto_targets() {
+        let ins = |instruction, register| Instruction::new(instruction, register, "");
+        let mut program = Program::from_instructions(vec![
+            ins(InsType::Store(3), Register::Register1),
+            ins(InsType::Goto, Register::Register1),
+            ins(InsType::PrintValue, Register::Register1),
+            ins(InsType::Store(4), Register::Register0),
+            ins(InsType::PrintValue, Register::Register0),
+        ]);
+        assert_eq!(program.execute(), "4");
+
+        let mut editor = program.edit();
+        let moved = EditError::MovesGotoTarget { goto: 1, target: 3 };
+        assert_eq!(edit_error(editor.remove(2)), moved);
+        assert_eq!(edit_error(editor.insert(3, line(""))), moved);
+        // between the store and the goto
+        assert_eq!(edit_error(editor.insert(1, line(""))), moved);
+
+        editor.insert(4, line("")).unwrap();
+        editor.replace(2, line("print.")).unwrap();
+        // the goto goes with its store
+        editor.remove(0).unwrap();
+        editor.remove(0).unwrap();
+        assert_eq!(editor.commit(), vec![0, 1, 2, 3]);
+        assert_eq!(program.execute(), "04");
+        assert_eq!(program.source(), "");
+    }
+}
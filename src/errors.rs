@@ -1,12 +1,441 @@
+//! error types for every part of the library. each module has its own
+//! error, and [`Error`] wraps all of them. the public functions all return
+//! [`Result`], so a caller only has to handle the one
+use std::path::PathBuf;
+use thiserror::Error;
+
+/// any error the library can produce, convertible from each module's error
+#[derive(Debug, Error)]
+pub enum Error {
+    /// a poem file couldn't be read or decoded
+    #[error("error reading {}: {source}", path.display())]
+    Input {
+        path: PathBuf,
+        source: io::DecodeError,
+    },
+    #[error(transparent)]
+    Decode(#[from] io::DecodeError),
+    /// reading or writing something other than a poem file failed
+    #[error("i/o error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Exec(#[from] program::ExecError),
+    #[error(transparent)]
+    Title(#[from] program::TitleError),
+    #[error(transparent)]
+    Seed(#[from] program::SeedError),
+    #[error(transparent)]
+    Rewind(#[from] program::RewindError),
+    #[error(transparent)]
+    Range(#[from] program::RangeError),
+    #[error(transparent)]
+    Format(#[from] format::FormatError),
+    #[error(transparent)]
+    Compose(#[from] compose::ComposeError),
+    #[error(transparent)]
+    Edit(#[from] edit::EditError),
+    #[error(transparent)]
+    Minify(#[from] minify::MinifyError),
+    #[error(transparent)]
+    Divergence(#[from] trace::Divergence),
+    #[error(transparent)]
+    Lint(#[from] lint::LintError),
+    #[error(transparent)]
+    Dictionary(#[from] dictionary::DictionaryError),
+    #[cfg(feature = "serde")]
+    #[error(transparent)]
+    Ast(#[from] ast::AstError),
+    /// boxed, since cranelift's errors are much larger than the rest
+    #[cfg(feature = "jit")]
+    #[error(transparent)]
+    Jit(#[from] Box<jit::JitError>),
+}
+
+#[cfg(feature = "jit")]
+impl From<jit::JitError> for Error {
+    fn from(err: jit::JitError) -> Self {
+        Error::Jit(Box::new(err))
+    }
+}
+
+pub type Result<T> = ::std::result::Result<T, Error>;
+
+pub mod io {
+    use thiserror::Error;
+
+    #[derive(Debug, Error)]
+    pub enum DecodeError {
+        #[error("invalid utf-8 sequence at byte offset {offset}")]
+        InvalidUtf8 { offset: usize },
+        #[error("unknown encoding '{0}' (expected one of utf8, latin1, lossy)")]
+        UnknownEncoding(String),
+        #[error("failed to read input: {0}")]
+        Io(#[from] std::io::Error),
+    }
+
+    pub type DecodeResult<T> = ::std::result::Result<T, DecodeError>;
+}
+
+pub mod program {
+    use std::fmt;
+    use thiserror::Error;
+
+    /// where in the poem an error happened
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct SourceLocation {
+        /// 1-based line number
+        pub line_number: usize,
+        /// the line without its indentation
+        pub line_text: String,
+    }
+
+    impl ExecError {
+        /// the line the error happened on
+        pub fn location(&self) -> &SourceLocation {
+            match self {
+                ExecError::FuelExhausted { location, .. }
+                | ExecError::OutputLimit { location, .. }
+                | ExecError::Overflow { location }
+                | ExecError::Cancelled { location }
+                | ExecError::StackUnderflow { location }
+                | ExecError::DivisionByZero { location }
+                | ExecError::Input { location, .. }
+                | ExecError::TapeLimit { location, .. }
+                | ExecError::StackLimit { location, .. } => location,
+            }
+        }
+    }
+
+    impl fmt::Display for SourceLocation {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "line {} ({:?})", self.line_number, self.line_text)
+        }
+    }
+
+    #[derive(Debug, Error, PartialEq, Eq, Clone)]
+    pub enum ExecError {
+        /// `location` is the line that would have been executed next
+        #[error(
+            "error at {location}: instruction limit of {limit} reached before the program halted"
+        )]
+        FuelExhausted {
+            limit: u64,
+            location: SourceLocation,
+        },
+        #[error("error at {location}: output limit of {limit} bytes reached")]
+        OutputLimit {
+            limit: usize,
+            location: SourceLocation,
+        },
+        #[error("error at {location}: arithmetic overflow")]
+        Overflow { location: SourceLocation },
+        /// `location` is the line that would have been executed next
+        #[error("error at {location}: cancelled before the program halted")]
+        Cancelled { location: SourceLocation },
+        /// a pop with nothing on the stack, only with
+        /// [`ExecOptions::strict_pop`](crate::ExecOptions::strict_pop)
+        #[error("error at {location}: pop from an empty stack")]
+        StackUnderflow { location: SourceLocation },
+        /// a division by zero, only with
+        /// [`ExecOptions::strict_division`](crate::ExecOptions::strict_division)
+        #[error("error at {location}: division by zero")]
+        DivisionByZero { location: SourceLocation },
+        /// the input of a `read-char` couldn't be read
+        #[error("error at {location}: couldn't read input: {message}")]
+        Input {
+            message: String,
+            location: SourceLocation,
+        },
+        /// a `tape-store` to a new cell with
+        /// [`ExecOptions::max_tape_cells`](crate::ExecOptions::max_tape_cells)
+        /// cells already written
+        #[error("error at {location}: tape limit of {limit} cells reached")]
+        TapeLimit {
+            limit: usize,
+            location: SourceLocation,
+        },
+        /// a push with
+        /// [`ExecOptions::max_stack`](crate::ExecOptions::max_stack) values
+        /// already on the stack
+        #[error("error at {location}: stack limit of {limit} values reached")]
+        StackLimit {
+            limit: usize,
+            location: SourceLocation,
+        },
+    }
+
+    pub type ExecResult<T> = ::std::result::Result<T, ExecError>;
+
+    #[derive(Debug, Error, PartialEq, Eq)]
+    pub enum TitleError {
+        #[error("the poem is empty, so it has no title to replace")]
+        EmptyPoem,
+        #[error("a title must be a single line")]
+        MultilineTitle,
+        #[error("a title needs at least one syllable")]
+        NoSyllables,
+        #[error("couldn't generate a title with {syllables} syllables for this poem")]
+        GenerationFailed { syllables: usize },
+    }
+
+    pub type TitleResult<T> = ::std::result::Result<T, TitleError>;
+
+    /// an argument to
+    /// [`ExecOptions::stack_seed_from_integers`](crate::ExecOptions::stack_seed_from_integers)
+    /// that isn't an integer
+    #[derive(Debug, Error, PartialEq, Eq, Clone)]
+    #[error("argument {position} ({argument:?}) isn't an integer")]
+    pub struct SeedError {
+        /// 1-based, like line numbers
+        pub position: usize,
+        pub argument: String,
+    }
+
+    pub type SeedResult<T> = ::std::result::Result<T, SeedError>;
+
+    /// [`Interpreter::step_back`](crate::Interpreter::step_back) with no
+    /// step left to undo
+    #[derive(Debug, Error, PartialEq, Eq, Clone)]
+    pub enum RewindError {
+        #[error("nothing has been executed yet")]
+        AtStart,
+        #[error("only the last {limit} steps can be undone")]
+        HistoryExhausted { limit: usize },
+    }
+
+    pub type RewindResult<T> = ::std::result::Result<T, RewindError>;
+
+    /// [`Program::execute_range`](crate::Program::execute_range) given
+    /// instructions the program doesn't have
+    #[derive(Debug, Error, PartialEq, Eq, Clone)]
+    #[error("instructions {start}..{end} aren't in a program of {len}")]
+    pub struct RangeError {
+        pub start: usize,
+        pub end: usize,
+        pub len: usize,
+    }
+}
+
+pub mod format {
+    use thiserror::Error;
+
+    #[derive(Debug, Error, PartialEq, Eq)]
+    pub enum FormatError {
+        #[error("formatting would change the instruction on line {line}")]
+        ChangedProgram { line: usize },
+    }
+
+    pub type FormatResult<T> = ::std::result::Result<T, FormatError>;
+}
+
+pub mod compose {
+    use thiserror::Error;
+
+    #[derive(Debug, Error, PartialEq, Eq)]
+    pub enum ComposeError {
+        #[error("instruction {index} needs {syllables} syllables on one line, more than will be written")]
+        TooManySyllables { index: usize, syllables: usize },
+        #[error("instruction {index} is a cond-push, but the line before it doesn't end in a dictionary word to rhyme with")]
+        NothingToRhyme { index: usize },
+        #[error("instruction {index} is a cond-push of {expected} syllables from the line before it, but that line has {actual}")]
+        PreviousSyllables {
+            index: usize,
+            expected: usize,
+            actual: usize,
+        },
+        #[error("instruction {index} ({instruction}) only exists in the extended dialect, and poems are written in the standard one")]
+        Extended {
+            index: usize,
+            instruction: &'static str,
+        },
+        #[error("instruction {index} uses r{register}, which only exists in the extended dialect, and poems are written in the standard one")]
+        ExtendedRegister { index: usize, register: usize },
+        #[error("couldn't write a line for instruction {index} ({instruction})")]
+        Unrealizable {
+            index: usize,
+            instruction: &'static str,
+        },
+        #[error("the composed poem parses to a different instruction {index}")]
+        RoundTrip { index: usize },
+    }
+
+    pub type ComposeResult<T> = ::std::result::Result<T, ComposeError>;
+}
+
+pub mod edit {
+    use thiserror::Error;
+
+    #[derive(Debug, Error, PartialEq, Eq)]
+    pub enum EditError {
+        #[error("instruction {index} is out of range, the program has {len}")]
+        OutOfRange { index: usize, len: usize },
+        #[error("the goto at instruction {goto} always jumps to instruction {target}, which the edit would move it away from")]
+        MovesGotoTarget { goto: usize, target: usize },
+    }
+
+    pub type EditResult<T> = ::std::result::Result<T, EditError>;
+}
+
+pub mod minify {
+    use thiserror::Error;
+
+    /// a minified poem that doesn't do what it did before it was minified,
+    /// which would be a bug in the minifier
+    #[derive(Debug, Error, PartialEq, Eq, Clone)]
+    pub enum MinifyError {
+        /// `line` of the minified poem parses to a different instruction
+        #[error("line {line} of the minified poem parses differently")]
+        Reparsed { line: usize },
+        #[error("the minified poem prints something else with input {input}")]
+        Diverged { input: usize },
+    }
+
+    pub type MinifyResult<T> = ::std::result::Result<T, MinifyError>;
+}
+
+pub mod trace {
+    use crate::trace::TraceRow;
+    use thiserror::Error;
+
+    /// the first step where a run didn't match a
+    /// [`RecordedTrace`](crate::trace::RecordedTrace), with the state after
+    /// it in each. a missing state is a run that had already stopped
+    #[derive(Debug, Error, PartialEq, Clone)]
+    #[error("the run diverged from the recording at step {step}")]
+    pub struct Divergence {
+        pub step: usize,
+        pub recorded: Option<Box<TraceRow>>,
+        pub replayed: Option<Box<TraceRow>>,
+    }
+}
+
+pub mod lint {
+    use thiserror::Error;
+
+    #[derive(Debug, Error, PartialEq, Eq)]
+    pub enum LintError {
+        #[error("unknown lint '{0}'")]
+        UnknownLint(String),
+    }
+
+    pub type LintResult<T> = ::std::result::Result<T, LintError>;
+}
+
+pub mod dictionary {
+    use thiserror::Error;
+
+    #[derive(Debug, Error, PartialEq, Eq)]
+    pub enum DictionaryError {
+        #[error("invalid dictionary: {0}")]
+        Invalid(String),
+        #[error("the dictionary was already initialized")]
+        AlreadyInitialized,
+    }
+
+    pub type DictionaryResult<T> = ::std::result::Result<T, DictionaryError>;
+}
+
+#[cfg(feature = "serde")]
+pub mod ast {
+    use thiserror::Error;
+
+    #[derive(Debug, Error, PartialEq, Eq)]
+    pub enum AstError {
+        #[error("invalid json: {0}")]
+        Json(String),
+        #[error("expected an object with a schema_version and instructions, or an array of instructions")]
+        UnexpectedShape,
+        #[error("invalid schema_version {0:?}, expected a version like \"1.0\"")]
+        InvalidVersion(String),
+        #[error("schema version {found} is newer than the newest this version of ashpaper-plus can read ({supported}), try upgrading it")]
+        UnsupportedVersion { found: String, supported: String },
+        #[error("instruction {index}: {message}")]
+        Instruction { index: usize, message: String },
+    }
+
+    pub type AstResult<T> = ::std::result::Result<T, AstError>;
+}
+
 #[cfg(feature = "jit")]
 pub mod jit {
     use thiserror::Error;
 
     #[derive(Debug, Error)]
     pub enum JitError {
+        /// boxed, since it's much larger than the other variants
         #[error("cranelift_module error: {0}")]
-        CraneliftModuleError(#[from] cranelift_module::ModuleError),
+        CraneliftModuleError(Box<cranelift_module::ModuleError>),
+        /// the program uses an instruction only the interpreter runs
+        #[error("instruction {index} ({instruction}) can't be jit compiled, run it with the interpreter")]
+        Unsupported {
+            index: usize,
+            instruction: &'static str,
+        },
+        /// an [`ExecOptions`](crate::ExecOptions) field the jit can't keep
+        /// was set
+        #[error("the jit can't keep {0}, run it with the interpreter")]
+        UnsupportedOption(&'static str),
+        /// cranelift can't compile for the machine this is running on
+        #[error("the jit doesn't support this machine ({0}), run the poem with the interpreter")]
+        UnsupportedHost(String),
+        /// a push to the jit's stack when it already held `limit` values,
+        /// which stopped the program
+        #[error("the jit's stack overflowed its {limit} values")]
+        StackOverflow { limit: usize },
+    }
+
+    impl From<cranelift_module::ModuleError> for JitError {
+        fn from(err: cranelift_module::ModuleError) -> Self {
+            JitError::CraneliftModuleError(Box::new(err))
+        }
     }
 
     pub type JitResult<T> = ::std::result::Result<T, JitError>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::error::Error as _;
+
+    #[test]
+    fn display() {
+        let err: Error = program::ExecError::Overflow {
+            location: program::SourceLocation {
+                line_number: 14,
+                line_text: "machine-machine-machine".to_string(),
+            },
+        }
+        .into();
+        assert_eq!(
+            err.to_string(),
+            "error at line 14 (\"machine-machine-machine\"): arithmetic overflow"
+        );
+        let err: Error = lint::LintError::UnknownLint("W999".to_string()).into();
+        assert_eq!(err.to_string(), "unknown lint 'W999'");
+    }
+
+    #[test]
+    fn source_chain() {
+        let err: Error = std::io::Error::new(std::io::ErrorKind::BrokenPipe, "closed").into();
+        assert!(matches!(err, Error::Io(_)));
+        assert_eq!(err.to_string(), "i/o error: closed");
+        assert_eq!(err.source().unwrap().to_string(), "closed");
+
+        let err: Error =
+            io::DecodeError::from(std::io::Error::new(std::io::ErrorKind::NotFound, "no poem"))
+                .into();
+        assert!(matches!(err, Error::Decode(io::DecodeError::Io(_))));
+        assert_eq!(err.to_string(), "failed to read input: no poem");
+        assert_eq!(err.source().unwrap().to_string(), "no poem");
+    }
+
+    #[cfg(feature = "jit")]
+    #[test]
+    fn jit_is_boxed() {
+        let err: Error = jit::JitError::StackOverflow { limit: 128 }.into();
+        assert!(matches!(err, Error::Jit(_)));
+        assert_eq!(err.to_string(), "the jit's stack overflowed its 128 values");
+    }
+}
@@ -0,0 +1,166 @@
+//! running a program without caring which backend runs it
+//!
+//! code that just wants a poem run with some options takes a
+//! `&dyn Executor`, and only the code that picks the backend has to know
+//! whether the jit is compiled in
+use super::{ExecOptions, ExecutionReport, Program, Result};
+
+/// a way of running a program
+pub trait Executor {
+    /// a short name for the backend, e.g. for a column of a table
+    fn name(&self) -> &'static str;
+
+    /// run `program` to completion. a run that fails is reported in the
+    /// [`ExecutionReport`], the error is for a program the backend can't
+    /// run at all
+    fn run(&self, program: &Program, options: &ExecOptions) -> Result<ExecutionReport>;
+}
+
+impl dyn Executor {
+    /// the fastest backend compiled in, the jit with the `jit` feature and
+    /// the interpreter otherwise. call it as `<dyn Executor>::best_available()`
+    pub fn best_available() -> Box<dyn Executor> {
+        #[cfg(feature = "jit")]
+        return Box::new(JitExecutor);
+        #[cfg(not(feature = "jit"))]
+        return Box::new(InterpreterExecutor);
+    }
+}
+
+/// runs a program one instruction at a time, keeping every option and
+/// filling in the whole report
+#[derive(Debug, Clone, Copy, Default)]
+pub struct InterpreterExecutor;
+
+impl Executor for InterpreterExecutor {
+    fn name(&self) -> &'static str {
+        "interpreter"
+    }
+
+    fn run(&self, program: &Program, options: &ExecOptions) -> Result<ExecutionReport> {
+        Ok(program.execute_report(options))
+    }
+}
+
+/// compiles a program to machine code with cranelift before running it.
+/// the jit can't keep limits or read input, so any option but the trace
+/// format is a [`JitError::UnsupportedOption`](crate::JitError), and it
+/// only reports the output and how long the run took. the registers, stack
+/// and counts are left at zero
+#[cfg(feature = "jit")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JitExecutor;
+
+#[cfg(feature = "jit")]
+impl JitExecutor {
+    /// the first option set in `options` that the jit can't keep
+    fn unsupported(options: &ExecOptions) -> Option<&'static str> {
+        [
+            ("max_instructions", options.max_instructions.is_some()),
+            ("max_output", options.max_output.is_some()),
+            ("cancel", options.cancel.is_some()),
+            ("trace_sink", options.trace_sink.is_some()),
+            ("strict_pop", options.strict_pop),
+            ("strict_division", options.strict_division),
+            ("input", options.input.is_some()),
+            ("max_tape_cells", options.max_tape_cells.is_some()),
+            ("max_stack", options.max_stack.is_some()),
+            ("initial_stack", !options.initial_stack.is_empty()),
+        ]
+        .iter()
+        .find(|(_, set)| *set)
+        .map(|(option, _)| *option)
+    }
+}
+
+#[cfg(feature = "jit")]
+impl Executor for JitExecutor {
+    fn name(&self) -> &'static str {
+        "jit"
+    }
+
+    fn run(&self, program: &Program, options: &ExecOptions) -> Result<ExecutionReport> {
+        use super::{HaltReason, JitError, Registers};
+
+        if let Some(option) = JitExecutor::unsupported(options) {
+            return Err(JitError::UnsupportedOption(option).into());
+        }
+        let start = std::time::Instant::now();
+        let events = program.jit_execute_io(Vec::new())?;
+        Ok(ExecutionReport {
+            output: events.iter().map(ToString::to_string).collect(),
+            events,
+            final_registers: Registers::default(),
+            final_stack: vec![],
+            instructions_executed: 0,
+            per_opcode_counts: Default::default(),
+            max_stack_depth: 0,
+            ignored_pops: 0,
+            elapsed: start.elapsed(),
+            halted_reason: HaltReason::Completed,
+            error: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    const FACTORIAL: &str = include_str!("../poems/original-factorial.eso");
+
+    #[test]
+    fn interpreter() {
+        let program = Program::create(FACTORIAL);
+        let executor: Box<dyn Executor> = Box::new(InterpreterExecutor);
+        let report = executor.run(&program, &ExecOptions::default()).unwrap();
+        let expected = program.execute_report(&ExecOptions::default());
+        assert_eq!(
+            ExecutionReport {
+                elapsed: expected.elapsed,
+                ..report.clone()
+            },
+            expected
+        );
+        assert_eq!(report.output, "24\n");
+        assert_eq!(executor.name(), "interpreter");
+    }
+
+    #[test]
+    fn best_available() {
+        let program = Program::create(FACTORIAL);
+        let best = <dyn Executor>::best_available();
+        let report = best.run(&program, &ExecOptions::default()).unwrap();
+        assert_eq!(report.output, "24\n");
+        #[cfg(feature = "jit")]
+        assert_eq!(best.name(), "jit");
+        #[cfg(not(feature = "jit"))]
+        assert_eq!(best.name(), "interpreter");
+    }
+
+    #[cfg(feature = "jit")]
+    #[test]
+    fn same_reports() {
+        let executors: [&dyn Executor; 2] = [&InterpreterExecutor, &JitExecutor];
+        for poem in [FACTORIAL, include_str!("../poems/stack-test.eso")] {
+            let program = Program::create(poem);
+            let [interpreted, jitted] =
+                executors.map(|executor| executor.run(&program, &ExecOptions::default()).unwrap());
+            assert_eq!(interpreted.output, jitted.output);
+            assert_eq!(interpreted.halted_reason, jitted.halted_reason);
+            assert!(jitted.completed());
+        }
+
+        let err = JitExecutor
+            .run(
+                &Program::create(FACTORIAL),
+                &ExecOptions::with_max_instructions(100),
+            )
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "the jit can't keep max_instructions, run it with the interpreter"
+        );
+    }
+}
@@ -0,0 +1,314 @@
+//! a C interface for embedding the interpreter in other languages
+//!
+//! strings passed in must be nul-terminated utf-8. output is returned as a
+//! nul-terminated string that must be freed with [`ashpaper_free`], along
+//! with its length since poems can print nul characters. a panic never
+//! crosses the boundary, it's reported as [`AshpaperStatus::Panic`] instead.
+//! the header is generated at `include/ashpaper.h` when building with the
+//! `ffi` feature
+use std::{
+    ffi::CStr,
+    mem,
+    os::raw::c_char,
+    panic::{self, AssertUnwindSafe},
+    ptr,
+};
+
+use super::{count_syllables, ExecError, ExecOptions, Program};
+
+/// the result of a call, written through the `status` pointer
+#[repr(C)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum AshpaperStatus {
+    Ok = 0,
+    NullArgument = 1,
+    InvalidUtf8 = 2,
+    FuelExhausted = 3,
+    OutputLimit = 4,
+    Overflow = 5,
+    Panic = 6,
+    Cancelled = 7,
+    StackUnderflow = 8,
+    DivisionByZero = 9,
+    Input = 10,
+    TapeLimit = 11,
+    StackLimit = 12,
+}
+
+/// limits for a single execution. 0 means no limit
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AshpaperOptions {
+    pub max_instructions: u64,
+    pub max_output: usize,
+    /// fail with `StackUnderflow` on a pop from an empty stack
+    pub strict_pop: bool,
+    /// fail with `StackLimit` on a push to a stack holding this many values
+    pub max_stack: usize,
+}
+
+impl From<&AshpaperOptions> for ExecOptions {
+    fn from(options: &AshpaperOptions) -> Self {
+        ExecOptions {
+            max_instructions: Some(options.max_instructions).filter(|&limit| limit > 0),
+            max_output: Some(options.max_output).filter(|&limit| limit > 0),
+            strict_pop: options.strict_pop,
+            max_stack: Some(options.max_stack).filter(|&limit| limit > 0),
+            ..ExecOptions::default()
+        }
+    }
+}
+
+/// bytes before each returned string that remember how long it is, so it
+/// can be freed even if it contains nul characters
+const HEADER: usize = mem::size_of::<usize>();
+
+fn into_raw(output: &str) -> *mut c_char {
+    let mut buffer = Vec::with_capacity(HEADER + output.len() + 1);
+    buffer.extend_from_slice(&output.len().to_ne_bytes());
+    buffer.extend_from_slice(output.as_bytes());
+    buffer.push(0);
+    let buffer = Box::into_raw(buffer.into_boxed_slice()) as *mut u8;
+    // safety: the buffer is at least HEADER bytes long
+    unsafe { buffer.add(HEADER) as *mut c_char }
+}
+
+/// # Safety
+/// `source` must be null or a valid nul-terminated string
+unsafe fn read_str<'a>(source: *const c_char) -> Result<&'a str, AshpaperStatus> {
+    if source.is_null() {
+        return Err(AshpaperStatus::NullArgument);
+    }
+    CStr::from_ptr(source)
+        .to_str()
+        .map_err(|_| AshpaperStatus::InvalidUtf8)
+}
+
+unsafe fn set<T>(out: *mut T, value: T) {
+    if !out.is_null() {
+        *out = value;
+    }
+}
+
+/// execute a poem with the default options, see
+/// [`ashpaper_execute_with_options`]
+///
+/// # Safety
+/// `source` must be null or a valid nul-terminated string and `out_len`
+/// null or valid to write to
+#[no_mangle]
+pub unsafe extern "C" fn ashpaper_execute(
+    source: *const c_char,
+    out_len: *mut usize,
+) -> *mut c_char {
+    ashpaper_execute_with_options(source, ptr::null(), out_len, ptr::null_mut())
+}
+
+/// execute a poem, returning its output or null on failure. the length of
+/// the output is written to `out_len` and the reason for a failure to
+/// `status`, either of which can be null. `options` can be null to use no
+/// limits
+///
+/// # Safety
+/// `source` must be null or a valid nul-terminated string, `options` null or
+/// valid to read, and `out_len` and `status` null or valid to write to
+#[no_mangle]
+pub unsafe extern "C" fn ashpaper_execute_with_options(
+    source: *const c_char,
+    options: *const AshpaperOptions,
+    out_len: *mut usize,
+    status: *mut AshpaperStatus,
+) -> *mut c_char {
+    set(out_len, 0);
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let source = read_str(source)?;
+        let options = options.as_ref().map(ExecOptions::from).unwrap_or_default();
+        // the report's error is always an `ExecError`
+        let report = Program::create(source).execute_report(&options);
+        match report.error {
+            None => Ok(report.output),
+            Some(err) => Err(match err {
+                ExecError::FuelExhausted { .. } => AshpaperStatus::FuelExhausted,
+                ExecError::OutputLimit { .. } => AshpaperStatus::OutputLimit,
+                ExecError::Overflow { .. } => AshpaperStatus::Overflow,
+                ExecError::Cancelled { .. } => AshpaperStatus::Cancelled,
+                ExecError::StackUnderflow { .. } => AshpaperStatus::StackUnderflow,
+                ExecError::DivisionByZero { .. } => AshpaperStatus::DivisionByZero,
+                ExecError::Input { .. } => AshpaperStatus::Input,
+                ExecError::TapeLimit { .. } => AshpaperStatus::TapeLimit,
+                ExecError::StackLimit { .. } => AshpaperStatus::StackLimit,
+            }),
+        }
+    }))
+    .unwrap_or(Err(AshpaperStatus::Panic));
+
+    match result {
+        Ok(output) => {
+            set(out_len, output.len());
+            set(status, AshpaperStatus::Ok);
+            into_raw(&output)
+        }
+        Err(err) => {
+            set(status, err);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// the number of syllables in a line, or `SIZE_MAX` if `source` is null or
+/// isn't utf-8
+///
+/// # Safety
+/// `source` must be null or a valid nul-terminated string
+#[no_mangle]
+pub unsafe extern "C" fn ashpaper_count_syllables(source: *const c_char) -> usize {
+    panic::catch_unwind(|| read_str(source).map(count_syllables))
+        .ok()
+        .and_then(Result::ok)
+        .unwrap_or(usize::MAX)
+}
+
+/// free a string returned by this library. null is ignored
+///
+/// # Safety
+/// `output` must be null or a string returned by this library that hasn't
+/// been freed yet
+#[no_mangle]
+pub unsafe extern "C" fn ashpaper_free(output: *mut c_char) {
+    if output.is_null() {
+        return;
+    }
+    let buffer = (output as *mut u8).sub(HEADER);
+    let mut len = [0; HEADER];
+    ptr::copy_nonoverlapping(buffer, len.as_mut_ptr(), HEADER);
+    let len = usize::from_ne_bytes(len);
+    drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+        buffer,
+        HEADER + len + 1,
+    )));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::{ffi::CString, slice};
+
+    unsafe fn take(output: *mut c_char, len: usize) -> String {
+        assert!(!output.is_null());
+        let text = String::from_utf8(slice::from_raw_parts(output as *const u8, len).to_vec());
+        assert_eq!(*output.add(len), 0);
+        ashpaper_free(output);
+        text.unwrap()
+    }
+
+    #[test]
+    fn execute() {
+        let source = CString::new(include_str!("../poems/lovely-poem.eso")).unwrap();
+        let mut len = 0;
+        unsafe {
+            let output = ashpaper_execute(source.as_ptr(), &mut len);
+            assert_eq!(take(output, len), "24\n");
+        }
+    }
+
+    #[test]
+    fn nul_in_output() {
+        // prints the character with value 0
+        let source = CString::new("\n?").unwrap();
+        let mut len = 0;
+        unsafe {
+            let output = ashpaper_execute(source.as_ptr(), &mut len);
+            assert_eq!(take(output, len), "\0");
+        }
+    }
+
+    #[test]
+    fn options() {
+        let source = CString::new("sells sea shells").unwrap();
+        let options = AshpaperOptions {
+            max_instructions: 100,
+            ..AshpaperOptions::default()
+        };
+        let mut status = AshpaperStatus::Ok;
+        let mut len = 7;
+        unsafe {
+            let output =
+                ashpaper_execute_with_options(source.as_ptr(), &options, &mut len, &mut status);
+            assert!(output.is_null());
+        }
+        assert_eq!(status, AshpaperStatus::FuelExhausted);
+        assert_eq!(len, 0);
+
+        let source = CString::new("lovely poem\npop,").unwrap();
+        let options = AshpaperOptions {
+            strict_pop: true,
+            ..AshpaperOptions::default()
+        };
+        unsafe {
+            let output = ashpaper_execute_with_options(
+                source.as_ptr(),
+                &options,
+                ptr::null_mut(),
+                &mut status,
+            );
+            assert!(output.is_null());
+        }
+        assert_eq!(status, AshpaperStatus::StackUnderflow);
+
+        let source = CString::new("lovely poem\npush-ed\npush-ed").unwrap();
+        let options = AshpaperOptions {
+            max_stack: 1,
+            ..AshpaperOptions::default()
+        };
+        unsafe {
+            let output = ashpaper_execute_with_options(
+                source.as_ptr(),
+                &options,
+                ptr::null_mut(),
+                &mut status,
+            );
+            assert!(output.is_null());
+        }
+        assert_eq!(status, AshpaperStatus::StackLimit);
+    }
+
+    #[test]
+    fn invalid_input() {
+        let invalid = CString::new(vec![b'a', 0xff, b'b']).unwrap();
+        let mut status = AshpaperStatus::Ok;
+        unsafe {
+            let output = ashpaper_execute_with_options(
+                invalid.as_ptr(),
+                ptr::null(),
+                ptr::null_mut(),
+                &mut status,
+            );
+            assert!(output.is_null());
+            assert_eq!(status, AshpaperStatus::InvalidUtf8);
+
+            ashpaper_execute_with_options(ptr::null(), ptr::null(), ptr::null_mut(), &mut status);
+            assert_eq!(status, AshpaperStatus::NullArgument);
+
+            assert_eq!(ashpaper_count_syllables(invalid.as_ptr()), usize::MAX);
+            assert_eq!(ashpaper_count_syllables(ptr::null()), usize::MAX);
+            ashpaper_free(ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn header_up_to_date() {
+        let generated = include_str!(concat!(env!("OUT_DIR"), "/ashpaper.h"));
+        assert!(
+            generated == include_str!("../include/ashpaper.h"),
+            "include/ashpaper.h is out of date, copy {}/ashpaper.h over it",
+            env!("OUT_DIR")
+        );
+    }
+
+    #[test]
+    fn syllables() {
+        let line = CString::new("a lovely poem").unwrap();
+        assert_eq!(unsafe { ashpaper_count_syllables(line.as_ptr()) }, 5);
+    }
+}
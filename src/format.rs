@@ -0,0 +1,99 @@
+//! a formatter that tidies up the whitespace of a poem without changing
+//! what it does
+//!
+//! only whitespace that the parser ignores is touched: trailing spaces, runs
+//! of spaces between words, whitespace-only lines and line endings. the
+//! parser only splits words on spaces, so tabs are part of the words around
+//! them and are left alone, as is indentation since it picks the register
+pub use super::errors::format::{FormatError, FormatResult};
+use super::{parser::InsType, Program, Result};
+
+fn format_line(line: &str) -> String {
+    if line.trim().is_empty() {
+        return String::new();
+    }
+    let body = line.trim_start_matches(' ');
+    let indent = &line[..line.len() - body.len()];
+    let words: Vec<&str> = body.split(' ').filter(|word| !word.is_empty()).collect();
+    format!("{}{}", indent, words.join(" "))
+}
+
+/// format a poem without checking that it still parses to the same program
+pub fn format(source: &str) -> String {
+    let mut formatted: String = source
+        .lines()
+        .map(|line| format!("{}\n", format_line(line)))
+        .collect();
+    if formatted.is_empty() {
+        formatted.push('\n');
+    }
+    formatted
+}
+
+/// the index of the first instruction that differs between two programs,
+/// ignoring the text of the lines
+pub(crate) fn first_difference(a: &Program, b: &Program) -> Option<usize> {
+    let differs = a.ast.iter().zip(&b.ast).position(|(a, b)| {
+        // a noop's register doesn't matter, and blank lines lose theirs
+        a.instruction != b.instruction
+            || (a.instruction != InsType::Noop && a.register != b.register)
+    });
+    differs.or_else(|| {
+        if a.ast.len() != b.ast.len() {
+            Some(a.ast.len().min(b.ast.len()))
+        } else {
+            None
+        }
+    })
+}
+
+/// format a poem, refusing to if the formatted poem would parse to a
+/// different program. that should never happen, so it's a formatter bug
+pub fn format_checked(source: &str) -> Result<String> {
+    let formatted = format(source);
+    match first_difference(&Program::create(source), &Program::create(&formatted)) {
+        Some(index) => Err(FormatError::ChangedProgram { line: index + 1 }.into()),
+        None => Ok(formatted),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn whitespace() {
+        let source = "lovely   poem  \r\n\t \r\n  it is a  calculator\n\tprint. \n";
+        assert_eq!(
+            format_checked(source).unwrap(),
+            "lovely poem\n\n  it is a calculator\n\tprint.\n"
+        );
+    }
+
+    #[test]
+    fn already_formatted() {
+        let source = include_str!("../poems/lovely-poem.eso");
+        assert_eq!(format_checked(source).unwrap(), source);
+    }
+
+    #[test]
+    fn tabs_are_kept() {
+        // the parser doesn't split words on tabs, so this is a store, and
+        // replacing the tab with a space would make it alliterate
+        let source = "fish\nsea\tsells\n";
+        assert_eq!(format_checked(source).unwrap(), source);
+    }
+
+    #[test]
+    fn changed_program() {
+        let original = Program::create("fish\nsea\tsells");
+        let changed = Program::create("fish\nsea sells");
+        assert_eq!(first_difference(&original, &original), None);
+        assert_eq!(first_difference(&original, &changed), Some(1));
+        assert_eq!(
+            first_difference(&original, &Program::create("fish")),
+            Some(1)
+        );
+    }
+}
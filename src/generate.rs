@@ -0,0 +1,293 @@
+//! random poems, for fuzz corpora, benchmarks and exercises
+//!
+//! a list of instructions is picked at random and then written out with
+//! [`compose`](crate::compose::compose), so the poems read like the ones it
+//! writes. every goto and cond-goto is put right after a store of a later
+//! line's number into the register it jumps by, so jumps only ever go
+//! forward and every poem halts
+use std::collections::BTreeMap;
+
+use super::{
+    compose,
+    parser::{InsType, Instruction, Register},
+};
+
+/// how many different instruction lists to try before settling for stores
+const ATTEMPTS: usize = 64;
+
+/// the mnemonics [`random_poem`] can pick from
+pub const MNEMONICS: &[&str] = &[
+    "cond-push",
+    "cond-goto",
+    "negate",
+    "multiply",
+    "add",
+    "print-char",
+    "print-value",
+    "pop",
+    "push",
+    "goto",
+    "store",
+    "noop",
+];
+
+/// what kind of poem [`random_poem`] writes
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct GenConfig {
+    /// how many lines the poem has
+    pub lines: usize,
+    /// how likely each instruction is to be picked, relative to the others,
+    /// by its mnemonic. instructions that aren't in [`MNEMONICS`] or have no
+    /// weight are never picked, and neither is anything if none have weight.
+    /// a goto or cond-goto comes with the store in front of it, which isn't
+    /// counted as a pick of its own
+    pub weights: BTreeMap<&'static str, u32>,
+    /// the most syllables a store, or a line a cond-push counts, is picked
+    /// with
+    pub max_syllables: u32,
+}
+
+impl Default for GenConfig {
+    /// 16 lines, every instruction as likely as the others
+    fn default() -> Self {
+        GenConfig {
+            lines: 16,
+            weights: MNEMONICS.iter().map(|&mnemonic| (mnemonic, 1)).collect(),
+            max_syllables: 8,
+        }
+    }
+}
+
+/// splitmix64, so the poems don't depend on a random number crate
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// in `0..n`
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+
+    fn register(&mut self) -> Register {
+        if self.below(2) == 0 {
+            Register::Register0
+        } else {
+            Register::Register1
+        }
+    }
+}
+
+/// how many syllables the line for `instruction` has, if it decides that
+/// itself
+fn syllables(instruction: InsType) -> Option<u32> {
+    match instruction {
+        InsType::Store(syllables) | InsType::ConditionalGoto(syllables) => Some(syllables),
+        InsType::ConditionalPush { cur_syllables, .. } => Some(cur_syllables),
+        _ => None,
+    }
+}
+
+fn pick(config: &GenConfig, rng: &mut Rng) -> Option<&'static str> {
+    let weights: Vec<(&str, u64)> = MNEMONICS
+        .iter()
+        .map(|&mnemonic| {
+            let weight = config.weights.get(mnemonic).copied().unwrap_or(0);
+            (mnemonic, u64::from(weight))
+        })
+        .collect();
+    let total: u64 = weights.iter().map(|(_, weight)| weight).sum();
+    if total == 0 {
+        return None;
+    }
+    let mut at = rng.below(total);
+    weights.into_iter().find_map(|(mnemonic, weight)| {
+        if at < weight {
+            Some(mnemonic)
+        } else {
+            at -= weight;
+            None
+        }
+    })
+}
+
+/// a random list of `config.lines` instructions that halts
+fn plan(config: &GenConfig, rng: &mut Rng) -> Vec<Instruction> {
+    let lines = config.lines;
+    let max_syllables = u64::from(config.max_syllables.max(1));
+    let mut ast: Vec<Instruction> = Vec::with_capacity(lines);
+    while ast.len() < lines {
+        let index = ast.len();
+        let register = rng.register();
+        let stored = |rng: &mut Rng| InsType::Store(1 + rng.below(max_syllables) as u32);
+        let instruction = match pick(config, rng) {
+            None | Some("noop") => InsType::Noop,
+            Some("store") => stored(rng),
+            Some("negate") => InsType::Negate,
+            Some("multiply") => InsType::Multiply,
+            Some("add") => InsType::Add,
+            Some("print-char") => InsType::PrintChar,
+            Some("print-value") => InsType::PrintValue,
+            Some("pop") => InsType::Pop,
+            Some("push") => InsType::Push,
+            // the line before has to end in a word to rhyme with
+            Some("cond-push") => match ast.last() {
+                Some(previous) if previous.instruction != InsType::Noop => {
+                    let prev_syllables = syllables(previous.instruction)
+                        .unwrap_or(1 + rng.below(max_syllables) as u32);
+                    InsType::ConditionalPush {
+                        prev_syllables,
+                        cur_syllables: prev_syllables + 1 + rng.below(max_syllables) as u32,
+                    }
+                }
+                _ => stored(rng),
+            },
+            // a jump needs a store before it, and a line after it to land on
+            Some(jump) if index + 2 < lines => {
+                let target = (index + 2 + rng.below((lines - index - 2) as u64) as usize) as u32;
+                let (jump, target_register) = if jump == "goto" {
+                    (InsType::Goto, register)
+                } else {
+                    let syllables = rng.below(max_syllables) as u32;
+                    (InsType::ConditionalGoto(syllables), register.paired())
+                };
+                ast.push(Instruction::new(
+                    InsType::Store(target),
+                    target_register,
+                    "",
+                ));
+                jump
+            }
+            Some(_) => stored(rng),
+        };
+        ast.push(Instruction::new(instruction, register, ""));
+    }
+    // landing on a later jump would skip the store it jumps by, so land on
+    // the store instead
+    let jumps =
+        |ins: &Instruction| matches!(ins.instruction, InsType::Goto | InsType::ConditionalGoto(_));
+    for index in 1..ast.len() {
+        if let InsType::Store(target) = ast[index - 1].instruction {
+            if jumps(&ast[index]) && jumps(&ast[target as usize]) {
+                ast[index - 1].instruction = InsType::Store(target - 1);
+            }
+        }
+    }
+    ast
+}
+
+/// a poem of `config.lines` lines, picking instructions by
+/// `config.weights`, that always halts. the same `config` and `seed` always
+/// give the same poem
+pub fn random_poem(config: &GenConfig, seed: u64) -> String {
+    let mut rng = Rng(seed);
+    for _ in 0..ATTEMPTS {
+        let ast = plan(config, &mut rng);
+        if let Ok(poem) = compose::compose(&ast, rng.next()) {
+            return poem;
+        }
+    }
+    // stores of a few syllables can always be written
+    let ast: Vec<_> = (0..config.lines)
+        .map(|line| Instruction::new(InsType::Store(1 + line as u32 % 3), Register::Register0, ""))
+        .collect();
+    compose::compose(&ast, seed).expect("short stores can always be composed")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ExecOptions, Program};
+    use pretty_assertions::assert_eq;
+
+    fn config(lines: usize, weights: &[(&'static str, u32)]) -> GenConfig {
+        GenConfig {
+            lines,
+            weights: weights.iter().copied().collect(),
+            ..GenConfig::default()
+        }
+    }
+
+    fn halts(poem: &str) -> bool {
+        Program::create(poem)
+            .execute_with_options(&ExecOptions::with_max_instructions(1_000_000))
+            .is_ok()
+    }
+
+    #[test]
+    fn deterministic() {
+        let config = GenConfig::default();
+        for seed in 0..8 {
+            let poem = random_poem(&config, seed);
+            assert_eq!(poem, random_poem(&config, seed));
+            assert_eq!(Program::create(&poem).ast.len(), config.lines);
+        }
+        assert_ne!(random_poem(&config, 0), random_poem(&config, 1));
+    }
+
+    #[test]
+    fn instruction_mix() {
+        let weights = [
+            ("store", 1),
+            ("push", 1),
+            ("pop", 1),
+            ("add", 1),
+            ("print-value", 1),
+        ];
+        let poem = random_poem(&config(250, &weights), 7);
+        let program = Program::create(&poem);
+        assert_eq!(program.ast.len(), 250);
+        for (mnemonic, _) in weights {
+            let count = program
+                .ast
+                .iter()
+                .filter(|ins| ins.instruction.mnemonic() == mnemonic)
+                .count();
+            // 50 each, give or take
+            assert!((35..=65).contains(&count), "{} {}", mnemonic, count);
+        }
+    }
+
+    #[test]
+    fn jumps_halt() {
+        let config = config(
+            24,
+            &[
+                ("goto", 2),
+                ("cond-goto", 2),
+                ("store", 1),
+                ("negate", 1),
+                ("push", 1),
+                ("pop", 1),
+                ("print-value", 1),
+            ],
+        );
+        for seed in 0..32 {
+            let poem = random_poem(&config, seed);
+            let program = Program::create(&poem);
+            assert_eq!(program.ast.len(), 24);
+            assert!(program
+                .ast
+                .iter()
+                .any(|ins| matches!(ins.instruction, InsType::Goto | InsType::ConditionalGoto(_))));
+            assert!(halts(&poem), "{}", poem);
+        }
+    }
+
+    #[test]
+    fn every_instruction_halts() {
+        for seed in 0..32 {
+            assert!(halts(&random_poem(&GenConfig::default(), seed)));
+        }
+        assert_eq!(
+            random_poem(&config(3, &[]), 0),
+            random_poem(&config(3, &[("noop", 1)]), 0)
+        );
+    }
+}
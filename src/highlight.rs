@@ -0,0 +1,124 @@
+//! classifying a poem's lines by what they do, for syntax highlighting
+//!
+//! spans are byte offsets into the source, like the spans of
+//! [`analysis`](crate::analysis)
+use std::ops;
+
+use super::{
+    analysis,
+    parser::{self, InsCategory},
+    Program,
+};
+
+/// the kind of token a whole line is, following [`InsCategory`]. blank lines
+/// do nothing, so they're highlighted like comments
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum TokenKind {
+    ControlFlow,
+    Arithmetic,
+    Io,
+    Stack,
+    Store,
+    Comment,
+}
+
+impl From<InsCategory> for TokenKind {
+    fn from(category: InsCategory) -> Self {
+        match category {
+            InsCategory::ControlFlow => TokenKind::ControlFlow,
+            InsCategory::Arithmetic => TokenKind::Arithmetic,
+            InsCategory::Io => TokenKind::Io,
+            InsCategory::Stack => TokenKind::Stack,
+            InsCategory::Store => TokenKind::Store,
+            InsCategory::Noop => TokenKind::Comment,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LineHighlight {
+    /// 0-based
+    pub line: usize,
+    pub kind: TokenKind,
+    /// the line without its indentation or trailing whitespace
+    pub span: ops::Range<usize>,
+    /// what made the parser pick the line's instruction, e.g. the `/` of a
+    /// conditional goto or the final word of a line that rhymes. stores and
+    /// blank lines have none
+    pub triggers: Vec<ops::Range<usize>>,
+}
+
+/// a highlight for every line of `source`
+pub fn highlight(source: &str) -> Vec<LineHighlight> {
+    let program = Program::create(source);
+    analysis::lines(source)
+        .zip(&program.ast)
+        .enumerate()
+        .map(|(line, ((start, text), ins))| LineHighlight {
+            line,
+            kind: ins.instruction.category().into(),
+            span: analysis::trimmed(start, text),
+            triggers: parser::triggers(ins.instruction, text, &program.parse_options)
+                .into_iter()
+                .map(|span| start + span.start..start + span.end)
+                .collect(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn factorial() {
+        let source = include_str!("../poems/lovely-poem.eso");
+        let highlights = highlight(source);
+        let golden: Vec<_> = highlights
+            .iter()
+            .map(|highlight| {
+                let triggers: Vec<_> = highlight
+                    .triggers
+                    .iter()
+                    .map(|span| &source[span.clone()])
+                    .collect();
+                (highlight.line, highlight.kind, triggers)
+            })
+            .collect();
+        let expected = vec![
+            (0, TokenKind::Comment, vec![]),
+            (1, TokenKind::Store, vec![]),
+            (2, TokenKind::Comment, vec![]),
+            (3, TokenKind::Arithmetic, vec!["like"]),
+            (4, TokenKind::Stack, vec![","]),
+            (5, TokenKind::Stack, vec!["-"]),
+            (6, TokenKind::Store, vec![]),
+            (7, TokenKind::Arithmetic, vec!["syllAbles"]),
+            (8, TokenKind::Arithmetic, vec!["as"]),
+            (9, TokenKind::Stack, vec![","]),
+            (10, TokenKind::Arithmetic, vec!["Turing"]),
+            (11, TokenKind::Stack, vec!["-"]),
+            (12, TokenKind::Store, vec![]),
+            (13, TokenKind::ControlFlow, vec!["/"]),
+            (14, TokenKind::Stack, vec![","]),
+            (15, TokenKind::Io, vec!["."]),
+            (16, TokenKind::Store, vec![]),
+            (17, TokenKind::Io, vec!["?"]),
+        ];
+        assert_eq!(golden, expected);
+    }
+
+    #[cfg(feature = "bundled-dictionary")]
+    #[test]
+    fn rhymes_and_alliteration() {
+        let source = "he thrust every elf\n  far back on the shelf\nsells sea shells\n";
+        let highlights = highlight(source);
+        assert_eq!(highlights[1].kind, TokenKind::Stack);
+        assert_eq!(highlights[1].span, 22..43);
+        assert_eq!(highlights[1].triggers, vec![38..43]);
+        assert_eq!(&source[38..43], "shelf");
+        assert_eq!(highlights[2].kind, TokenKind::ControlFlow);
+        assert_eq!(highlights[2].triggers, vec![44..49, 50..53]);
+    }
+}
@@ -0,0 +1,220 @@
+//! the side effects of running a poem, for hosts that embed it in a game or a
+//! sandbox and want to decide where its output goes
+//!
+//! printing is a poem's only side effect, besides reading input in the
+//! extended dialect. both the interpreter
+//! ([`Program::execute_io`](crate::Program::execute_io)) and the jit hand
+//! what they print to a [`PoemIo`] as values and character codes, rather
+//! than as text
+use std::fmt;
+
+use super::parser::InsType;
+
+/// where everything a poem prints goes
+pub trait PoemIo {
+    /// a `print-value` of `value`
+    fn put_value(&mut self, value: i64);
+    /// a `print-char` of the character with the code `c`, the absolute value
+    /// of the register modulo 255
+    fn put_char(&mut self, c: u8);
+    /// a `read-char` by the jit, the next byte of input or `None` at the end
+    /// of it. the interpreter reads
+    /// [`ExecOptions::input`](crate::ExecOptions::input) instead. there's no
+    /// input unless this is implemented
+    fn get_char(&mut self) -> Option<u8> {
+        None
+    }
+}
+
+impl<T: PoemIo + ?Sized> PoemIo for &mut T {
+    fn put_value(&mut self, value: i64) {
+        (**self).put_value(value)
+    }
+
+    fn put_char(&mut self, c: u8) {
+        (**self).put_char(c)
+    }
+
+    fn get_char(&mut self) -> Option<u8> {
+        (**self).get_char()
+    }
+}
+
+impl<T: PoemIo + ?Sized> PoemIo for Box<T> {
+    fn put_value(&mut self, value: i64) {
+        (**self).put_value(value)
+    }
+
+    fn put_char(&mut self, c: u8) {
+        (**self).put_char(c)
+    }
+
+    fn get_char(&mut self) -> Option<u8> {
+        (**self).get_char()
+    }
+}
+
+/// collects the output into a string exactly like
+/// [`Program::execute`](crate::Program::execute) does. character codes are
+/// read as latin-1
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct StringIo {
+    pub output: String,
+}
+
+impl StringIo {
+    pub fn new() -> StringIo {
+        StringIo::default()
+    }
+
+    pub fn into_string(self) -> String {
+        self.output
+    }
+}
+
+impl PoemIo for StringIo {
+    fn put_value(&mut self, value: i64) {
+        self.output.push_str(itoa::Buffer::new().format(value));
+    }
+
+    fn put_char(&mut self, c: u8) {
+        self.output.push(c as char);
+    }
+}
+
+/// something a poem printed, telling a `print-value` of 7 apart from a
+/// `print-char` of the character `'7'`, see
+/// [`ExecutionReport::events`](crate::ExecutionReport::events). displays as
+/// the text it adds to the output
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum OutputEvent {
+    /// a `print-value`
+    Value(i64),
+    /// a `print-char` of the character with this code
+    Char(u8),
+}
+
+impl OutputEvent {
+    /// what `instruction` printed when its register held `value`
+    pub(crate) fn of(instruction: InsType, value: i64) -> OutputEvent {
+        match instruction {
+            InsType::PrintChar => OutputEvent::Char(char_code(value)),
+            _ => OutputEvent::Value(value),
+        }
+    }
+}
+
+impl fmt::Display for OutputEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputEvent::Value(value) => write!(f, "{}", value),
+            OutputEvent::Char(c) => write!(f, "{}", *c as char),
+        }
+    }
+}
+
+/// records everything printed, in order
+impl PoemIo for Vec<OutputEvent> {
+    fn put_value(&mut self, value: i64) {
+        self.push(OutputEvent::Value(value));
+    }
+
+    fn put_char(&mut self, c: u8) {
+        self.push(OutputEvent::Char(c));
+    }
+}
+
+/// the character code a `print-char` of `value` prints
+pub(crate) fn char_code(value: i64) -> u8 {
+    (value.unsigned_abs() % u8::MAX as u64) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ExecOptions, Program};
+    use pretty_assertions::assert_eq;
+
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    enum Call {
+        Value(i64),
+        Char(u8),
+    }
+
+    #[derive(Default)]
+    struct Recorder(Vec<Call>);
+
+    impl PoemIo for Recorder {
+        fn put_value(&mut self, value: i64) {
+            self.0.push(Call::Value(value));
+        }
+
+        fn put_char(&mut self, c: u8) {
+            self.0.push(Call::Char(c));
+        }
+    }
+
+    const POEM: &str = "lovely poem\n  a bird\nprint.\n  birds?\nnEgative\nprint.\n";
+
+    #[test]
+    fn interpreter() {
+        let program = Program::create(POEM);
+        let mut recorder = Recorder::default();
+        program.execute_io(&mut recorder).unwrap();
+        assert_eq!(
+            recorder.0,
+            vec![Call::Value(4), Call::Char(2), Call::Value(-4)]
+        );
+
+        let mut io = StringIo::new();
+        program.execute_io(&mut io).unwrap();
+        assert_eq!(io.into_string(), program.execute());
+    }
+
+    #[test]
+    fn events() {
+        let program = Program::create(POEM);
+        let report = program.execute_report(&ExecOptions::default());
+        let events = vec![
+            OutputEvent::Value(4),
+            OutputEvent::Char(2),
+            OutputEvent::Value(-4),
+        ];
+        assert_eq!(report.events, events);
+        let joined: String = report.events.iter().map(ToString::to_string).collect();
+        assert_eq!(joined, report.output);
+
+        let mut recorded = Vec::new();
+        program.execute_io(&mut recorded).unwrap();
+        assert_eq!(recorded, events);
+        // streamed output isn't kept, and neither are its events
+        let streamed = program.execute_report_with_hook(&ExecOptions::default(), |_| (), |_, _| ());
+        assert!(streamed.events.is_empty());
+    }
+
+    #[cfg(feature = "bundled-dictionary")]
+    #[test]
+    fn string_io() {
+        let program = Program::create(include_str!("../poems/lovely-poem.eso"));
+        let mut io = StringIo::new();
+        program.execute_io(&mut io).unwrap();
+        assert_eq!(io.output, "24\n");
+        assert_eq!(char_code(-265), 10);
+        assert_eq!(char_code(i64::MIN), (i64::MIN.unsigned_abs() % 255) as u8);
+    }
+
+    #[cfg(feature = "jit")]
+    #[test]
+    fn jit() {
+        let program = Program::create(POEM);
+        let recorder = program.jit_execute_io(Recorder::default()).unwrap();
+        assert_eq!(
+            recorder.0,
+            vec![Call::Value(4), Call::Char(2), Call::Value(-4)]
+        );
+        assert_eq!(
+            program.jit_execute_io(StringIo::new()).unwrap().output,
+            program.execute()
+        );
+    }
+}
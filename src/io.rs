@@ -0,0 +1,108 @@
+//! helpers for reading poems that aren't necessarily valid utf-8
+//!
+//! plenty of older poem files are stored as latin-1 (ISO-8859-1), which
+//! `fs::read_to_string` refuses to read. these helpers let you pick how the
+//! raw bytes should be interpreted before they're handed to the parser.
+use std::{fs, path::Path, str::FromStr};
+
+pub use super::errors::io::{DecodeError, DecodeResult};
+use super::Result;
+
+/// how the raw bytes of a poem should be turned into text
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum Encoding {
+    /// strict utf-8, failing on the first invalid sequence
+    #[default]
+    Utf8,
+    /// ISO-8859-1, where every byte maps directly to the same code point
+    Latin1,
+    /// utf-8, replacing invalid sequences with U+FFFD
+    Lossy,
+}
+
+impl FromStr for Encoding {
+    type Err = DecodeError;
+
+    fn from_str(s: &str) -> DecodeResult<Self> {
+        match s.to_lowercase().as_str() {
+            "utf8" | "utf-8" => Ok(Encoding::Utf8),
+            "latin1" | "latin-1" | "iso-8859-1" => Ok(Encoding::Latin1),
+            "lossy" => Ok(Encoding::Lossy),
+            _ => Err(DecodeError::UnknownEncoding(s.to_string())),
+        }
+    }
+}
+
+/// transcode ISO-8859-1 bytes to a utf-8 string. this can't fail since every
+/// latin-1 byte is the code point of the same value
+pub fn latin1_to_string(bytes: &[u8]) -> String {
+    bytes.iter().map(|&b| b as char).collect()
+}
+
+/// decode the bytes of a poem using the given encoding
+pub fn decode(bytes: &[u8], encoding: Encoding) -> Result<String> {
+    match encoding {
+        Encoding::Utf8 => std::str::from_utf8(bytes).map(str::to_string).map_err(|e| {
+            DecodeError::InvalidUtf8 {
+                offset: e.valid_up_to(),
+            }
+            .into()
+        }),
+        Encoding::Latin1 => Ok(latin1_to_string(bytes)),
+        Encoding::Lossy => Ok(String::from_utf8_lossy(bytes).into_owned()),
+    }
+}
+
+/// read a poem from disk, decoding it with the given encoding
+pub fn read_to_string(path: impl AsRef<Path>, encoding: Encoding) -> Result<String> {
+    let bytes = fs::read(path).map_err(DecodeError::Io)?;
+    decode(&bytes, encoding)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{count_syllables, Error, Program};
+    use pretty_assertions::assert_eq;
+
+    const LATIN1_POEM: &[u8] = include_bytes!("../poems/latin1-test.eso");
+
+    #[test]
+    fn latin1() {
+        let decoded = decode(LATIN1_POEM, Encoding::Latin1).unwrap();
+        assert_eq!(
+            decoded,
+            "café au lait\nprint.\n  résumés\n  print.\n".to_string()
+        );
+        assert_eq!(count_syllables("café au lait"), 3);
+        assert_eq!(count_syllables("résumés"), 1);
+        assert_eq!(
+            count_syllables(decoded.lines().next().unwrap()),
+            count_syllables("café au lait")
+        );
+        assert_eq!(Program::create(&decoded).execute(), "31");
+    }
+
+    #[test]
+    fn strict_utf8_offset() {
+        match decode(LATIN1_POEM, Encoding::Utf8) {
+            Err(Error::Decode(DecodeError::InvalidUtf8 { offset })) => assert_eq!(offset, 3),
+            other => panic!("expected invalid utf-8 error, got {:?}", other),
+        }
+        assert_eq!(decode(b"fine", Encoding::Utf8).unwrap(), "fine".to_string());
+    }
+
+    #[test]
+    fn lossy() {
+        let decoded = decode(LATIN1_POEM, Encoding::Lossy).unwrap();
+        assert!(decoded.starts_with("caf\u{FFFD} au lait"));
+    }
+
+    #[test]
+    fn parse_encoding() {
+        assert_eq!("utf8".parse::<Encoding>().unwrap(), Encoding::Utf8);
+        assert_eq!("Latin1".parse::<Encoding>().unwrap(), Encoding::Latin1);
+        assert_eq!("lossy".parse::<Encoding>().unwrap(), Encoding::Lossy);
+        assert!("ebcdic".parse::<Encoding>().is_err());
+    }
+}
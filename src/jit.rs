@@ -1,7 +1,21 @@
-use std::mem;
+//! compiles a poem to machine code with cranelift
+//!
+//! the hosts it runs on are the ones cranelift has a native backend for
+//! here, each built with the flags it needs:
+//!
+//! | arch    | os                    | flags                                     |
+//! |---------|-----------------------|-------------------------------------------|
+//! | x86_64  | linux, macos, windows | pic, no colocated libcalls                |
+//! | aarch64 | linux, macos          | pic, no colocated libcalls, no probestack |
+//!
+//! on any other host [`JIT::new`] fails with [`JitError::UnsupportedHost`]
+use std::{
+    mem,
+    time::{Duration, Instant},
+};
 
 use cranelift::{
-    codegen::ir::{FuncRef, JumpTable, StackSlot},
+    codegen::ir::{FuncRef, StackSlot},
     prelude::*,
 };
 use cranelift_jit::{JITBuilder, JITModule};
@@ -9,9 +23,9 @@ use cranelift_module::{FuncId, Linkage, Module};
 use itertools::{EitherOrBoth, Itertools};
 
 use super::{
-    errors::jit::JitResult,
-    parser::{InsType, Instruction, Register},
-    rt::{put_char, put_value},
+    errors::jit::{JitError, JitResult},
+    parser::{Dialect, InsType, Instruction, Register},
+    rt::{get_char, put_char, put_value, stack_overflow},
 };
 
 #[derive(Debug)]
@@ -23,38 +37,126 @@ struct Stack {
     overflow_trap: Block,
 }
 
-const STACK_SIZE: u32 = 128;
+/// how many values the stack holds
+pub(crate) const STACK_SIZE: u32 = 128;
+
+/// the variables holding the registers, and in [`Dialect::Extended`] the
+/// index of the register the last instruction used, which is the inactive one
+#[derive(Debug)]
+struct RegisterVars {
+    registers: [Variable; 4],
+    previous: Option<Variable>,
+}
 
 pub struct JIT {
     builder_context: FunctionBuilderContext,
     ctx: codegen::Context,
     module: JITModule,
+    collapse_noops: bool,
+}
+
+/// what compiling a program took, from [`JIT::compile`]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub struct CompileStats {
+    /// blocks the lines were compiled to. a noop shares the block of the
+    /// line after it when noops are collapsed, otherwise every line has
+    /// its own
+    pub blocks: usize,
+    /// cranelift instructions the lines were lowered to
+    pub instructions_lowered: usize,
+    /// entries in the jump tables of the gotos, a line each for every goto
+    pub jump_table_entries: usize,
+    /// bytes of machine code the function was compiled to
+    pub clif_bytes: usize,
+    pub compile_time: Duration,
+}
+
+/// the codegen flags for the host. the runtime functions are somewhere in
+/// the process rather than next to the compiled code, so calls to them
+/// can't use the short relocations colocated calls get, which on aarch64
+/// only reach 128MB away. aarch64 has no stack probe libcall, and the
+/// stack slot is far smaller than a page, so probing is left off there
+fn host_flags() -> settings::Flags {
+    let mut flags = settings::builder();
+    flags.set("use_colocated_libcalls", "false").unwrap();
+    flags.set("is_pic", "true").unwrap();
+    if cfg!(target_arch = "aarch64") {
+        flags.set("enable_probestack", "false").unwrap();
+    }
+    settings::Flags::new(flags)
 }
 
-impl Default for JIT {
-    fn default() -> Self {
-        let mut builder = JITBuilder::new(cranelift_module::default_libcall_names());
+impl JIT {
+    /// a jit for the host, failing on a host it doesn't support (see the
+    /// module docs)
+    pub fn new() -> JitResult<Self> {
+        if !cfg!(any(target_arch = "x86_64", target_arch = "aarch64")) {
+            return Err(JitError::UnsupportedHost(
+                std::env::consts::ARCH.to_string(),
+            ));
+        }
+        let isa = cranelift_native::builder()
+            .map_err(|msg| JitError::UnsupportedHost(msg.to_string()))?
+            .finish(host_flags());
+        let mut builder = JITBuilder::with_isa(isa, cranelift_module::default_libcall_names());
         // import runtime functions into jit
         let put_val_addr: *const u8 = unsafe { mem::transmute(put_value as fn(_)) };
         builder.symbol("put_value", put_val_addr);
         let put_char_addr: *const u8 = unsafe { mem::transmute(put_char as fn(_)) };
         builder.symbol("put_char", put_char_addr);
+        let get_char_addr: *const u8 = unsafe { mem::transmute(get_char as fn() -> _) };
+        builder.symbol("get_char", get_char_addr);
+        let stack_overflow_addr = stack_overflow as fn() as *const u8;
+        builder.symbol("stack_overflow", stack_overflow_addr);
         let module = JITModule::new(builder);
-        Self {
+        Ok(Self {
             builder_context: FunctionBuilderContext::new(),
             ctx: module.make_context(),
             module,
-        }
+            collapse_noops: true,
+        })
     }
-}
 
-impl JIT {
-    pub fn compile(&mut self, ast: &[Instruction]) -> JitResult<fn()> {
+    /// whether noops are compiled into the block of the line after them
+    /// rather than a block of their own that jumps there, which they always
+    /// are outside of tests
+    #[cfg(test)]
+    fn set_collapse_noops(&mut self, collapse_noops: bool) {
+        self.collapse_noops = collapse_noops;
+    }
+
+    pub fn compile(
+        &mut self,
+        ast: &[Instruction],
+        dialect: Dialect,
+    ) -> JitResult<(fn(), CompileStats)> {
+        if let Some((index, ins)) = ast
+            .iter()
+            .enumerate()
+            .find(|(_, ins)| matches!(ins.instruction, InsType::TapeStore | InsType::TapeLoad))
+        {
+            return Err(JitError::Unsupported {
+                index,
+                instruction: ins.instruction.mnemonic(),
+            });
+        }
+
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!(
+            "jit_compile",
+            blocks = tracing::field::Empty,
+            duration_us = tracing::field::Empty
+        )
+        .entered();
+        let start = Instant::now();
+
         let int = self.module.target_config().pointer_type();
 
         // create imported funcs before builder
         let put_val_id = self.make_put_value()?;
         let put_char_id = self.make_put_char()?;
+        let get_char_id = self.make_get_char()?;
+        let stack_overflow_id = self.make_stack_overflow()?;
 
         let mut builder = FunctionBuilder::new(&mut self.ctx.func, &mut self.builder_context);
 
@@ -65,6 +167,12 @@ impl JIT {
         let put_char_func = self
             .module
             .declare_func_in_func(put_char_id, &mut builder.func);
+        let get_char_func = self
+            .module
+            .declare_func_in_func(get_char_id, &mut builder.func);
+        let stack_overflow_func = self
+            .module
+            .declare_func_in_func(stack_overflow_id, &mut builder.func);
 
         // build stack
         let stack_byte_size = STACK_SIZE * int.bytes();
@@ -107,35 +215,58 @@ impl JIT {
             overflow_trap: stack_overflow_trap,
         };
 
-        let r0 = Variable::new(3);
-        let r1 = Variable::new(4);
-
-        builder.declare_var(r0, int);
-        builder.declare_var(r1, int);
-
-        let zero1 = builder.ins().iconst(int, 0);
-        builder.def_var(r0, zero1);
-        let zero2 = builder.ins().iconst(int, 0);
-        builder.def_var(r1, zero2);
-
-        let mut jump_table_data = JumpTableData::new();
+        let registers = [3, 4, 5, 6].map(Variable::new);
+        for &register in &registers {
+            builder.declare_var(register, int);
+            let zero = builder.ins().iconst(int, 0);
+            builder.def_var(register, zero);
+        }
+        let previous = match dialect {
+            Dialect::Standard => None,
+            Dialect::Extended => {
+                let previous = Variable::new(7);
+                builder.declare_var(previous, int);
+                // like the interpreter, the first line in r0 sees r1
+                let r1 = builder.ins().iconst(int, 1);
+                builder.def_var(previous, r1);
+                Some(previous)
+            }
+        };
+        let registers = RegisterVars {
+            registers,
+            previous,
+        };
 
-        let mut blocks = Vec::new();
-        // create blocks and add to jump table
-        for _ in ast {
-            let block = builder.create_block();
-            jump_table_data.push_entry(block);
+        // one block per line, jumped to in order or from a goto. collapsed
+        // noops have the block of the line after them, and ones at the end
+        // a block that returns
+        let mut blocks: Vec<Block> = Vec::with_capacity(ast.len());
+        let mut end_block = None;
+        for ins in ast.iter().rev() {
+            let block = if self.collapse_noops && ins.instruction == InsType::Noop {
+                match blocks.last() {
+                    Some(&next) => next,
+                    None => *end_block.get_or_insert_with(|| builder.create_block()),
+                }
+            } else {
+                builder.create_block()
+            };
             blocks.push(block);
         }
-
-        let jump_table = builder.create_jump_table(jump_table_data);
+        blocks.reverse();
+        let mut stats = CompileStats {
+            blocks: ast.len(),
+            ..CompileStats::default()
+        };
 
         // connect entry block to first block
         Self::connect_end(&mut builder, blocks.first().copied());
 
-        // build stack overflow trap block
+        // a push to a full stack tells the runtime and stops the program,
+        // which the caller reports as an error
         builder.switch_to_block(stack_overflow_trap);
-        builder.ins().trap(TrapCode::StackOverflow);
+        builder.ins().call(stack_overflow_func, &[]);
+        builder.ins().return_(&[]);
 
         // build unreachable trap block
         let unreach_trap_block = builder.create_block();
@@ -152,6 +283,16 @@ impl JIT {
                     EitherOrBoth::Both(l, r) => (*l, Some(*r)),
                     EitherOrBoth::Right(_) => unreachable!(),
                 };
+                if self.collapse_noops && node.instruction == InsType::Noop {
+                    stats.blocks -= 1;
+                    continue;
+                }
+                if matches!(
+                    node.instruction,
+                    InsType::Goto | InsType::ConditionalGoto(_)
+                ) {
+                    stats.jump_table_entries += blocks.len();
+                }
                 // get block ready for instructions
                 builder.switch_to_block(block);
 
@@ -160,35 +301,52 @@ impl JIT {
                     node,
                     int,
                     &stack,
-                    jump_table,
+                    &blocks,
                     unreach_trap_block,
                     blocks.len() as i64,
                     next,
                     &mut builder,
                     put_val_func,
                     put_char_func,
-                    r0,
-                    r1,
+                    get_char_func,
+                    &registers,
                 );
             }
         }
 
+        if let Some(end_block) = end_block {
+            builder.switch_to_block(end_block);
+            builder.ins().return_(&[]);
+            stats.blocks += 1;
+        }
+
         builder.seal_all_blocks();
+        stats.instructions_lowered = self.ctx.func.dfg.num_insts();
 
         let id = self
             .module
             .declare_function("main", Linkage::Export, &self.ctx.func.signature)?;
 
-        self.module
-            .define_function(id, &mut self.ctx, &mut codegen::binemit::NullTrapSink {})?;
+        let compiled = self.module.define_function(
+            id,
+            &mut self.ctx,
+            &mut codegen::binemit::NullTrapSink {},
+        )?;
+        stats.clif_bytes = compiled.size as usize;
 
         self.module.clear_context(&mut self.ctx);
 
         self.module.finalize_definitions();
 
         let ptr = self.module.get_finalized_function(id);
+        stats.compile_time = start.elapsed();
+        #[cfg(feature = "tracing")]
+        {
+            span.record("blocks", stats.blocks as u64);
+            span.record("duration_us", stats.compile_time.as_micros() as u64);
+        }
 
-        Ok(unsafe { std::mem::transmute::<_, fn()>(ptr) })
+        Ok((unsafe { std::mem::transmute::<_, fn()>(ptr) }, stats))
     }
 
     pub fn make_put_value(&mut self) -> JitResult<FuncId> {
@@ -214,33 +372,52 @@ impl JIT {
         Ok(put_char)
     }
 
+    pub fn make_stack_overflow(&mut self) -> JitResult<FuncId> {
+        let stack_overflow = self.module.declare_function(
+            "stack_overflow",
+            Linkage::Import,
+            &self.ctx.func.signature,
+        )?;
+        self.module.clear_context(&mut self.ctx);
+        Ok(stack_overflow)
+    }
+
+    pub fn make_get_char(&mut self) -> JitResult<FuncId> {
+        let int = self.module.target_config().pointer_type();
+        self.ctx.func.signature.returns.push(AbiParam::new(int));
+
+        let get_char =
+            self.module
+                .declare_function("get_char", Linkage::Import, &self.ctx.func.signature)?;
+        self.module.clear_context(&mut self.ctx);
+        Ok(get_char)
+    }
+
     fn translate_instruction(
         ins: &Instruction,
         int: Type,
         stack: &Stack,
-        jump_table: JumpTable,
+        blocks: &[Block],
         unreach_trap: Block,
         max_lines: i64,
         next_block: Option<Block>,
         builder: &mut FunctionBuilder,
         put_val_func: FuncRef,
         put_char_func: FuncRef,
-        r0: Variable,
-        r1: Variable,
+        get_char_func: FuncRef,
+        registers: &RegisterVars,
     ) {
         let Instruction {
             instruction: kind,
             register: reg,
-            line: _line,
+            ..
         } = ins;
-        let active_reg = match reg {
-            Register::Register0 => r0,
-            Register::Register1 => r1,
-        };
-        let inactive_reg = match reg {
-            Register::Register0 => r1,
-            Register::Register1 => r0,
-        };
+        let active_reg = registers.registers[reg.index()];
+        let inactive_val = Self::translate_inactive(*reg, registers, builder);
+        if let Some(previous) = registers.previous {
+            let index_val = builder.ins().iconst(int, reg.index() as i64);
+            builder.def_var(previous, index_val);
+        }
         match kind {
             InsType::Store(syl) => {
                 let store_val = builder.ins().iconst(int, *syl as i64);
@@ -255,27 +432,31 @@ impl JIT {
             }
             InsType::Multiply => {
                 let active_val = builder.use_var(active_reg);
-                let inactive_val = builder.use_var(inactive_reg);
                 let mult = builder.ins().imul(active_val, inactive_val);
                 builder.def_var(active_reg, mult);
                 Self::connect_end(builder, next_block);
             }
             InsType::Add => {
                 let active_val = builder.use_var(active_reg);
-                let inactive_val = builder.use_var(inactive_reg);
                 let add = builder.ins().iadd(active_val, inactive_val);
                 builder.def_var(active_reg, add);
                 Self::connect_end(builder, next_block);
             }
+            InsType::Subtract => {
+                let active_val = builder.use_var(active_reg);
+                let sub = builder.ins().isub(active_val, inactive_val);
+                builder.def_var(active_reg, sub);
+                Self::connect_end(builder, next_block);
+            }
+            InsType::Divide => {
+                let active_val = builder.use_var(active_reg);
+                let div = Self::translate_divide(int, active_val, inactive_val, builder);
+                builder.def_var(active_reg, div);
+                Self::connect_end(builder, next_block);
+            }
             InsType::Goto => {
-                Self::translate_goto(
-                    int,
-                    active_reg,
-                    builder,
-                    unreach_trap,
-                    jump_table,
-                    max_lines,
-                );
+                let reg_val = builder.use_var(active_reg);
+                Self::translate_goto(int, reg_val, builder, unreach_trap, blocks, max_lines);
             }
             InsType::ConditionalGoto(syl) => {
                 let syl_val = builder.ins().iconst(int, *syl as i64);
@@ -289,14 +470,7 @@ impl JIT {
                 builder.ins().jump(merge_block, &[]);
 
                 builder.switch_to_block(then_block);
-                Self::translate_goto(
-                    int,
-                    inactive_reg,
-                    builder,
-                    unreach_trap,
-                    jump_table,
-                    max_lines,
-                );
+                Self::translate_goto(int, inactive_val, builder, unreach_trap, blocks, max_lines);
 
                 builder.switch_to_block(merge_block);
                 Self::connect_end(builder, next_block);
@@ -313,11 +487,10 @@ impl JIT {
                 prev_syllables,
                 cur_syllables,
             } => {
-                let active_val = builder.use_var(active_reg);
-                let inactive_val = builder.use_var(inactive_reg);
-                let cond_val = builder
-                    .ins()
-                    .icmp(IntCC::SignedLessThan, active_val, inactive_val);
+                // r0 and r1 whichever register the line uses
+                let r0_val = builder.use_var(registers.registers[0]);
+                let r1_val = builder.use_var(registers.registers[1]);
+                let cond_val = builder.ins().icmp(IntCC::SignedLessThan, r0_val, r1_val);
                 let then_block = builder.create_block();
                 let else_block = builder.create_block();
                 let merge_block = builder.create_block();
@@ -333,6 +506,8 @@ impl JIT {
                 let prev_val = builder.ins().iconst(int, *prev_syllables as i64);
                 Self::translate_push_val(int, prev_val, builder, stack);
                 builder.ins().jump(merge_block, &[]);
+
+                builder.switch_to_block(merge_block);
                 Self::connect_end(builder, next_block);
             }
             InsType::PrintValue => {
@@ -340,24 +515,84 @@ impl JIT {
                 builder.ins().call(put_val_func, &[reg_val]);
                 Self::connect_end(builder, next_block);
             }
+            InsType::ReadChar => {
+                let call = builder.ins().call(get_char_func, &[]);
+                let read_val = builder.inst_results(call)[0];
+                builder.def_var(active_reg, read_val);
+                Self::connect_end(builder, next_block);
+            }
             InsType::PrintChar => {
                 let reg_val = builder.use_var(active_reg);
                 builder.ins().call(put_char_func, &[reg_val]);
                 Self::connect_end(builder, next_block);
             }
+            InsType::TapeStore | InsType::TapeLoad => {
+                unreachable!("the tape is rejected before compiling")
+            }
             InsType::Noop => Self::connect_end(builder, next_block),
         }
     }
 
+    /// `active / inactive`, rounding towards zero. sdiv traps when dividing
+    /// by zero and when `i64::MIN / -1` overflows, so those never reach it:
+    /// dividing by zero gives zero, and dividing by -1 is a wrapping negation
+    /// like the other arithmetic
+    fn translate_divide(
+        int: Type,
+        active_val: Value,
+        inactive_val: Value,
+        builder: &mut FunctionBuilder,
+    ) -> Value {
+        let is_zero = builder.ins().icmp_imm(IntCC::Equal, inactive_val, 0);
+        let is_minus_one = builder.ins().icmp_imm(IntCC::Equal, inactive_val, -1);
+        let one = builder.ins().iconst(int, 1);
+        let divisor = builder.ins().select(is_zero, one, inactive_val);
+        let divisor = builder.ins().select(is_minus_one, one, divisor);
+        let quotient = builder.ins().sdiv(active_val, divisor);
+        let negated = builder.ins().ineg(active_val);
+        let quotient = builder.ins().select(is_minus_one, negated, quotient);
+        let zero = builder.ins().iconst(int, 0);
+        builder.ins().select(is_zero, zero, quotient)
+    }
+
+    /// the value of the inactive register, which in the extended dialect is
+    /// only known at runtime
+    fn translate_inactive(
+        reg: Register,
+        registers: &RegisterVars,
+        builder: &mut FunctionBuilder,
+    ) -> Value {
+        let previous = match registers.previous {
+            Some(previous) => previous,
+            None => return builder.use_var(registers.registers[reg.paired().index()]),
+        };
+        let previous_val = builder.use_var(previous);
+        let mut inactive_val = builder.use_var(registers.registers[3]);
+        for index in (0..3).rev() {
+            let is_previous = builder
+                .ins()
+                .icmp_imm(IntCC::Equal, previous_val, index as i64);
+            let reg_val = builder.use_var(registers.registers[index]);
+            inactive_val = builder.ins().select(is_previous, reg_val, inactive_val);
+        }
+        inactive_val
+    }
+
     fn translate_goto(
         int: Type,
-        reg: Variable,
+        index_val: Value,
         builder: &mut FunctionBuilder,
         unreach_trap: Block,
-        jump_table: JumpTable,
+        blocks: &[Block],
         max_lines: i64,
     ) {
-        let index_val = builder.use_var(reg);
+        // every goto gets its own table, since the ssa builder only sees a
+        // shared one as a predecessor of each block once
+        let mut jump_table_data = JumpTableData::new();
+        for &block in blocks {
+            jump_table_data.push_entry(block);
+        }
+        let jump_table = builder.create_jump_table(jump_table_data);
         let abs_block = builder.create_block();
         builder.append_block_param(abs_block, int);
         let merge_block = builder.create_block();
@@ -406,11 +641,12 @@ impl JIT {
     fn translate_push_val(int: Type, value: Value, builder: &mut FunctionBuilder, stack: &Stack) {
         let merge_block = builder.create_block();
 
+        // checked before the store, since a full stack's pointer is already
+        // past the end of the slot
         let ptr_val = builder.use_var(stack.ptr);
-        builder.ins().store(MemFlags::new(), value, ptr_val, 0);
         let end_val = builder.use_var(stack.end);
         builder.ins().br_icmp(
-            IntCC::SignedGreaterThan,
+            IntCC::UnsignedGreaterThanOrEqual,
             ptr_val,
             end_val,
             stack.overflow_trap,
@@ -419,6 +655,7 @@ impl JIT {
         builder.ins().jump(merge_block, &[]);
 
         builder.switch_to_block(merge_block);
+        builder.ins().store(MemFlags::new(), value, ptr_val, 0);
         let size = builder.ins().iconst(int, int.bytes() as i64);
         let inc = builder.ins().iadd(ptr_val, size);
         builder.def_var(stack.ptr, inc);
@@ -447,46 +684,207 @@ mod tests {
     fn basic_goto() {
         let source = include_str!("../poems/goto-test.eso");
         let tokens = parser::parse(source);
-        let mut jit = JIT::default();
-        jit.compile(&tokens).unwrap()();
+        let mut jit = JIT::new().unwrap();
+        jit.compile(&tokens, Dialect::Standard).unwrap().0();
     }
 
     #[test]
     fn factorial() {
         let source = include_str!("../poems/original-factorial.eso");
         let tokens = parser::parse(source);
-        let mut jit = JIT::default();
-        jit.compile(&tokens).unwrap()();
+        let mut jit = JIT::new().unwrap();
+        jit.compile(&tokens, Dialect::Standard).unwrap().0();
     }
 
     #[test]
     fn stack() {
         let source = include_str!("../poems/stack-test.eso");
         let tokens = parser::parse(source);
-        let mut jit = JIT::default();
-        jit.compile(&tokens).unwrap()();
+        let mut jit = JIT::new().unwrap();
+        jit.compile(&tokens, Dialect::Standard).unwrap().0();
     }
 
     #[test]
     fn cond_goto() {
         let source = include_str!("../poems/cond-goto-test.eso");
         let tokens = parser::parse(source);
-        let mut jit = JIT::default();
-        jit.compile(&tokens).unwrap()();
+        let mut jit = JIT::new().unwrap();
+        jit.compile(&tokens, Dialect::Standard).unwrap().0();
     }
 
     #[test]
     fn math() {
         let source = include_str!("../poems/math-test.eso");
         let tokens = parser::parse(source);
-        let mut jit = JIT::default();
-        jit.compile(&tokens).unwrap()();
+        let mut jit = JIT::new().unwrap();
+        jit.compile(&tokens, Dialect::Standard).unwrap().0();
     }
 
     #[test]
     fn empty() {
         let tokens = parser::parse("");
-        let mut jit = JIT::default();
-        jit.compile(&tokens).unwrap()();
+        let mut jit = JIT::new().unwrap();
+        jit.compile(&tokens, Dialect::Standard).unwrap().0();
+    }
+
+    /// the flags apple silicon needs, which the other tests only cover when
+    /// they're run there
+    #[cfg(target_arch = "aarch64")]
+    #[test]
+    fn aarch64_smoke() {
+        let flags = host_flags();
+        assert!(flags.is_pic());
+        assert!(!flags.use_colocated_libcalls());
+        assert!(!flags.enable_probestack());
+        let tokens = parser::parse(include_str!("../poems/lovely-poem.eso"));
+        let mut jit = JIT::new().unwrap();
+        jit.compile(&tokens, Dialect::Standard).unwrap().0();
+    }
+
+    #[test]
+    fn compile_stats() {
+        let tokens = parser::parse(include_str!("../poems/original-factorial.eso"));
+        let mut jit = JIT::new().unwrap();
+        let (_, stats) = jit.compile(&tokens, Dialect::Standard).unwrap();
+        assert_eq!(stats.blocks, tokens.len());
+        assert_eq!(stats.jump_table_entries, tokens.len());
+        assert!(stats.instructions_lowered > tokens.len());
+        assert!(stats.clif_bytes > 0);
+
+        // the same poem with blank lines between its stanzas, one of which a
+        // goto lands on
+        let spaced = parser::parse("\n\nfox.\n\nlovely\n\nbig bad\n\n");
+        let blocks = |collapse_noops| {
+            let mut jit = JIT::new().unwrap();
+            jit.set_collapse_noops(collapse_noops);
+            jit.compile(&spaced, Dialect::Standard).unwrap().1.blocks
+        };
+        assert_eq!(blocks(false), spaced.len());
+        // the lines that aren't blank, and one to return from at the end
+        assert_eq!(blocks(true), 4);
+    }
+
+    #[test]
+    fn collapsed_noops_agree() {
+        // the goto lands on the noop before the print
+        let program = crate::Program::create("lovely\nbig bad\n\nfox.\n\n");
+        assert_eq!(program.jit_execute_captured().unwrap(), program.execute());
+    }
+
+    #[test]
+    fn stack_overflow() {
+        let pushes = |count| {
+            let mut instructions =
+                vec![Instruction::new(InsType::Store(1), Register::Register0, "")];
+            instructions.extend(
+                (0..count).map(|_| Instruction::new(InsType::Push, Register::Register0, "")),
+            );
+            instructions.push(Instruction::new(
+                InsType::PrintValue,
+                Register::Register0,
+                "",
+            ));
+            crate::Program::from_instructions(instructions)
+        };
+        assert!(matches!(
+            pushes(200).jit_execute_captured(),
+            Err(crate::Error::Jit(err)) if matches!(*err, JitError::StackOverflow { limit: 128 })
+        ));
+        // a full stack is fine until something else is pushed
+        assert_eq!(pushes(128).jit_execute_captured().unwrap(), "1");
+        // and running again starts over
+        assert_eq!(pushes(3).jit_execute_captured().unwrap(), "1");
+    }
+
+    #[test]
+    fn full_stack_pops_back() {
+        let mut instructions = vec![];
+        for syllables in 0..128 {
+            instructions.push((InsType::Store(syllables), Register::Register0));
+            instructions.push((InsType::Push, Register::Register0));
+        }
+        for _ in 0..128 {
+            instructions.push((InsType::Pop, Register::Register1));
+            instructions.push((InsType::PrintValue, Register::Register1));
+        }
+        agrees(&instructions);
+    }
+
+    fn agrees(instructions: &[(InsType, Register)]) {
+        let program = crate::Program::from_instructions(
+            instructions
+                .iter()
+                .map(|&(instruction, register)| Instruction::new(instruction, register, ""))
+                .collect(),
+        );
+        assert_eq!(
+            program.jit_execute_captured().unwrap(),
+            program.execute(),
+            "{:?}",
+            instructions
+        );
+    }
+
+    #[test]
+    fn cond_push_then_more() {
+        let push = InsType::ConditionalPush {
+            prev_syllables: 1,
+            cur_syllables: 2,
+        };
+        agrees(&[
+            (push, Register::Register0),
+            (InsType::Pop, Register::Register1),
+            (InsType::PrintValue, Register::Register1),
+        ]);
+    }
+
+    #[test]
+    fn noop_first_without_gotos() {
+        agrees(&[
+            (InsType::Noop, Register::Register0),
+            (InsType::Negate, Register::Register0),
+        ]);
+    }
+
+    #[test]
+    fn subtract_and_divide() {
+        agrees(&[
+            (InsType::Store(7), Register::Register0),
+            (InsType::Store(2), Register::Register1),
+            (InsType::Divide, Register::Register0),
+            (InsType::PrintValue, Register::Register0),
+            (InsType::Subtract, Register::Register1),
+            (InsType::PrintValue, Register::Register1),
+            (InsType::Divide, Register::Register0),
+            (InsType::PrintValue, Register::Register0),
+            // by zero
+            (InsType::Store(0), Register::Register1),
+            (InsType::Divide, Register::Register0),
+            (InsType::PrintValue, Register::Register0),
+            // by -1
+            (InsType::Store(5), Register::Register0),
+            (InsType::Store(1), Register::Register1),
+            (InsType::Negate, Register::Register1),
+            (InsType::Divide, Register::Register0),
+            (InsType::PrintValue, Register::Register0),
+        ]);
+    }
+
+    #[test]
+    fn gotos_to_the_same_line() {
+        // both gotos can reach the cond-push, with different values in r1
+        agrees(&[
+            (InsType::ConditionalGoto(0), Register::Register0),
+            (InsType::Store(3), Register::Register1),
+            (InsType::Goto, Register::Register1),
+            (
+                InsType::ConditionalPush {
+                    prev_syllables: 0,
+                    cur_syllables: 0,
+                },
+                Register::Register0,
+            ),
+            (InsType::PrintValue, Register::Register1),
+        ]);
     }
 }
@@ -0,0 +1,309 @@
+//! where a poem's gotos can land
+//!
+//! a goto jumps by the value in a register, modulo the length of the poem.
+//! the values the registers can hold are followed through the poem from
+//! its start: a store sets its register to a known value, and anything
+//! else that writes one (arithmetic, a pop, a read) makes it unknown. where
+//! paths through the poem meet, the values they bring are gathered up, so a
+//! goto reached with the register holding one of a few known values jumps
+//! to one of a few known lines. one that can't be pinned down is dynamic,
+//! and could land on any line, so it leaves every register unknown after
+//! it too
+use std::{collections::BTreeSet, fmt};
+
+use super::{Dialect, InsType, Instruction, Program, Register};
+
+/// beyond this many values a register is as good as unknown
+const MAX_VALUES: usize = 16;
+
+/// where a goto can land
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum JumpTargets {
+    /// one of these lines, by their indices. empty if the goto can't be
+    /// reached at all
+    Lines(Vec<usize>),
+    /// wherever a value the poem computed points
+    Dynamic,
+}
+
+/// a goto or conditional goto, and where it can land
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Jump {
+    /// the index of the goto
+    pub line: usize,
+    pub conditional: bool,
+    pub targets: JumpTargets,
+}
+
+/// every goto in a poem and where it lands, from [`Program::jump_targets`]
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct JumpTargetReport {
+    pub jumps: Vec<Jump>,
+    /// for each line, the indices of the gotos known to land on it. dynamic
+    /// gotos aren't listed, since they could land on any line
+    pub landings: Vec<Vec<usize>>,
+}
+
+impl JumpTargetReport {
+    /// the gotos that could land on any line
+    pub fn dynamic(&self) -> impl Iterator<Item = &Jump> {
+        self.jumps
+            .iter()
+            .filter(|jump| jump.targets == JumpTargets::Dynamic)
+    }
+}
+
+impl fmt::Display for JumpTargetReport {
+    /// a line per goto and then per line jumped to, numbered from 1 like the
+    /// rest of the crate
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let numbers = |lines: &[usize]| {
+            let numbers: Vec<String> = lines.iter().map(|line| (line + 1).to_string()).collect();
+            numbers.join(", ")
+        };
+        if self.jumps.is_empty() {
+            return writeln!(f, "no gotos");
+        }
+        for jump in &self.jumps {
+            let kind = if jump.conditional {
+                "cond-goto"
+            } else {
+                "goto"
+            };
+            let targets = match &jump.targets {
+                JumpTargets::Lines(lines) if lines.is_empty() => "never reached".to_string(),
+                JumpTargets::Lines(lines) => numbers(lines),
+                JumpTargets::Dynamic => "dynamic".to_string(),
+            };
+            writeln!(f, "{} ({}) -> {}", jump.line + 1, kind, targets)?;
+        }
+        for (line, gotos) in self.landings.iter().enumerate() {
+            if !gotos.is_empty() {
+                writeln!(f, "{} <- {}", line + 1, numbers(gotos))?;
+            }
+        }
+        let dynamic: Vec<usize> = self.dynamic().map(|jump| jump.line).collect();
+        if !dynamic.is_empty() {
+            writeln!(f, "any line <- {}", numbers(&dynamic))?;
+        }
+        Ok(())
+    }
+}
+
+/// the values a register can hold, or `None` if they aren't known
+type Values = Option<BTreeSet<i64>>;
+
+/// what's known about the machine before a line runs
+#[derive(Clone, PartialEq)]
+struct State {
+    registers: [Values; 4],
+    /// the registers the line before could have used, as a mask of their
+    /// indices, since it's the inactive one in [`Dialect::Extended`]
+    previous: u8,
+}
+
+impl State {
+    fn start() -> State {
+        let zero = Some(BTreeSet::from([0]));
+        State {
+            registers: [zero.clone(), zero.clone(), zero.clone(), zero],
+            previous: 1 << Register::Register1.index(),
+        }
+    }
+
+    /// everything `self` or `other` could be. `true` if that's more than
+    /// `self` was
+    fn join(&mut self, other: &State) -> bool {
+        let before = self.clone();
+        for (values, other) in self.registers.iter_mut().zip(&other.registers) {
+            *values = match (values.take(), other) {
+                (Some(mut values), Some(other)) => {
+                    values.extend(other);
+                    Some(values).filter(|values| values.len() <= MAX_VALUES)
+                }
+                _ => None,
+            };
+        }
+        self.previous |= other.previous;
+        *self != before
+    }
+
+    /// the values the register a goto jumps by can hold
+    fn jump_values(&self, ins: &Instruction, dialect: Dialect) -> Values {
+        let registers = match ins.instruction {
+            InsType::Goto => 1 << ins.register.index(),
+            _ if dialect == Dialect::Extended => self.previous,
+            _ => 1 << ins.register.paired().index(),
+        };
+        let mut all = BTreeSet::new();
+        for (index, values) in self.registers.iter().enumerate() {
+            if registers & (1 << index) != 0 {
+                all.extend(values.as_ref()?);
+            }
+        }
+        Some(all)
+    }
+
+    /// the state after `ins` runs
+    fn after(&self, ins: &Instruction) -> State {
+        let mut state = self.clone();
+        let register = &mut state.registers[ins.register.index()];
+        match ins.instruction {
+            InsType::Store(syllables) => *register = Some(BTreeSet::from([i64::from(syllables)])),
+            InsType::Negate
+            | InsType::Multiply
+            | InsType::Add
+            | InsType::Subtract
+            | InsType::Divide
+            | InsType::Pop
+            | InsType::ReadChar
+            | InsType::TapeLoad => *register = None,
+            _ => (),
+        }
+        state.previous = 1 << ins.register.index();
+        state
+    }
+}
+
+/// `state` arriving at line `to`, which is looked at again if that's more
+/// than it was known to be
+fn flow(states: &mut [Option<State>], pending: &mut Vec<usize>, to: usize, state: State) {
+    let changed = match &mut states[to] {
+        Some(existing) => existing.join(&state),
+        slot @ None => {
+            *slot = Some(state);
+            true
+        }
+    };
+    if changed && !pending.contains(&to) {
+        pending.push(to);
+    }
+}
+
+impl Program {
+    /// where each of the program's gotos can land, see the
+    /// [module docs](crate::jumps)
+    pub fn jump_targets(&self) -> JumpTargetReport {
+        let len = self.ast.len();
+        let mut states: Vec<Option<State>> = vec![None; len];
+        let mut pending = vec![];
+        if len > 0 {
+            states[0] = Some(State::start());
+            pending.push(0);
+        }
+        while let Some(index) = pending.pop() {
+            let ins = &self.ast[index];
+            let state = states[index]
+                .clone()
+                .expect("only reached lines are pending");
+            let after = state.after(ins);
+            if matches!(ins.instruction, InsType::Goto | InsType::ConditionalGoto(_)) {
+                let to: Vec<usize> = match state.jump_values(ins, self.dialect) {
+                    Some(values) => values
+                        .iter()
+                        .map(|value| (value.unsigned_abs() % len as u64) as usize)
+                        .collect(),
+                    None => (0..len).collect(),
+                };
+                for to in to {
+                    flow(&mut states, &mut pending, to, after.clone());
+                }
+            }
+            if ins.instruction != InsType::Goto && index + 1 < len {
+                flow(&mut states, &mut pending, index + 1, after);
+            }
+        }
+
+        let mut report = JumpTargetReport {
+            landings: vec![vec![]; len],
+            ..JumpTargetReport::default()
+        };
+        for (index, (ins, state)) in self.ast.iter().zip(&states).enumerate() {
+            let conditional = match ins.instruction {
+                InsType::Goto => false,
+                InsType::ConditionalGoto(_) => true,
+                _ => continue,
+            };
+            let values = match state {
+                Some(state) => state.jump_values(ins, self.dialect),
+                None => Some(BTreeSet::new()),
+            };
+            let targets = match values {
+                Some(values) => {
+                    let lines: BTreeSet<usize> = values
+                        .iter()
+                        .map(|value| (value.unsigned_abs() % len as u64) as usize)
+                        .collect();
+                    for &line in &lines {
+                        report.landings[line].push(index);
+                    }
+                    JumpTargets::Lines(lines.into_iter().collect())
+                }
+                None => JumpTargets::Dynamic,
+            };
+            report.jumps.push(Jump {
+                line: index,
+                conditional,
+                targets,
+            });
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn factorial() {
+        let program = Program::create(include_str!("../poems/original-factorial.eso"));
+        let report = program.jump_targets();
+        // the cond-goto loops back to the pop after the store of 2 before it
+        assert_eq!(
+            report.jumps,
+            vec![Jump {
+                line: 11,
+                conditional: true,
+                targets: JumpTargets::Lines(vec![2]),
+            }]
+        );
+        assert_eq!(report.landings[2], vec![11]);
+        assert_eq!(report.to_string(), "12 (cond-goto) -> 3\n3 <- 12\n");
+    }
+
+    #[test]
+    fn dynamic() {
+        // the goto jumps by whatever the add left in r0
+        let program = Program::create("fox\n  sea\nlike a bird\nbig bad\nprint.");
+        let report = program.jump_targets();
+        assert_eq!(report.jumps[0].targets, JumpTargets::Dynamic);
+        assert_eq!(report.landings, vec![Vec::<usize>::new(); 5]);
+        assert_eq!(report.to_string(), "4 (goto) -> dynamic\nany line <- 4\n");
+    }
+
+    #[test]
+    fn paths_meet() {
+        // the goto on line 5 is reached by the cond-goto with 1 in r0, or
+        // from the store of 4 before it
+        let program = Program::create("fox\n  lovely poem\nwe/go\nlovely poem\nbig bad");
+        let report = program.jump_targets();
+        assert_eq!(
+            report.jumps,
+            vec![
+                Jump {
+                    line: 2,
+                    conditional: true,
+                    targets: JumpTargets::Lines(vec![4]),
+                },
+                Jump {
+                    line: 4,
+                    conditional: false,
+                    targets: JumpTargets::Lines(vec![1, 4]),
+                },
+            ]
+        );
+        assert_eq!(report.landings[4], vec![2, 4]);
+    }
+}
@@ -75,11 +75,70 @@
 //!
 //! ## Some caveats about compliance with the informal spec
 //! - It's entirely possible at this point that some of the implementation deviates from the spec in unintended ways. If you spot anything like that, please raise an issue
+#[cfg(all(target_os = "wasi", feature = "jit"))]
+compile_error!("the jit can't run under wasi, build with the `wasi` feature and without `jit`");
+
+pub mod analysis;
+#[cfg(feature = "serde")]
+pub mod ast;
+#[cfg(feature = "cli")]
+pub mod cli;
+pub mod compose;
+pub mod dictionary;
+pub mod diff;
+mod dot;
+pub mod edit;
 mod errors;
+pub mod executor;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod format;
+pub mod generate;
+pub mod highlight;
+pub mod host;
+pub mod io;
 #[cfg(feature = "jit")]
 mod jit;
+pub mod jumps;
+pub mod lint;
+pub mod minify;
+#[cfg(feature = "async")]
+mod nonblocking;
 mod parser;
+pub mod profile;
 mod program;
+#[cfg(feature = "python")]
+mod python;
+mod report;
+#[cfg(feature = "jit")]
 mod rt;
-pub use parser::count_syllables;
-pub use program::Program;
+pub mod stats;
+pub mod testing;
+pub mod title;
+pub mod trace;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub use analysis::{analyze, Analysis};
+pub use diff::{semantic_diff, SemanticDiff};
+pub use errors::{Error, Result};
+#[cfg(feature = "jit")]
+pub use executor::JitExecutor;
+pub use executor::{Executor, InterpreterExecutor};
+pub use highlight::{highlight, LineHighlight};
+pub use host::{OutputEvent, PoemIo, StringIo};
+pub use parser::{
+    count_syllables, count_syllables_detailed, count_syllables_with_options,
+    init_dictionary_from_str, AllitOptions, CmuCounter, CustomCounter, CustomInsFactory,
+    CustomRule, Dialect, DictionaryError, DictionaryResult, InsCategory, InsType, Instruction,
+    LineFeatures, ParseOptions, Register, SyllableCounter, SyllableSource, UnknownScriptSyllables,
+    WordSyllables,
+};
+pub use program::{
+    run_file, run_many, run_str, CancelToken, Engine, ExecError, ExecOptions, ExecResult,
+    InputSource, Interpreter, MachineState, Memory, Program, RangeError, Registers, RewindError,
+    RewindResult, SeedError, SeedResult, SourceLocation, Step, TitleError, TitleResult,
+    TraceFormat, TraceSink,
+};
+#[cfg(feature = "jit")]
+pub use program::{CompileStats, JitError, JitResult};
+pub use report::{ExecutionReport, HaltReason};
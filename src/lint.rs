@@ -0,0 +1,305 @@
+//! static checks for mistakes that are easy to make while writing a poem
+//!
+//! every lint has a stable code (e.g. `W001`) and a name (e.g.
+//! `unknown-word`), either of which can be used to refer to it
+use std::{fmt, ops, str::FromStr};
+
+pub use super::errors::lint::{LintError, LintResult};
+use super::{
+    analysis::{self, Meter},
+    parser::{self, InsType},
+    title, Program,
+};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
+pub enum Lint {
+    /// a word isn't in the dictionary, so its syllables are a guess
+    UnknownWord,
+    /// the title, conventionally the poem's input, isn't parsed as a store
+    HijackedStore,
+    /// a line doesn't fit the meter most of the poem is in. it's only
+    /// checked when asked for, since most poems don't keep to a meter
+    BrokenMeter,
+}
+
+impl Lint {
+    pub const ALL: &'static [Lint] = &[Lint::UnknownWord, Lint::HijackedStore, Lint::BrokenMeter];
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Lint::UnknownWord => "W001",
+            Lint::HijackedStore => "W002",
+            Lint::BrokenMeter => "I001",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Lint::UnknownWord => "unknown-word",
+            Lint::HijackedStore => "hijacked-store",
+            Lint::BrokenMeter => "broken-meter",
+        }
+    }
+
+    /// how serious a lint is unless it's been denied
+    pub fn severity(self) -> Severity {
+        match self {
+            Lint::UnknownWord | Lint::HijackedStore => Severity::Warning,
+            Lint::BrokenMeter => Severity::Information,
+        }
+    }
+
+    /// whether [`lint`] checks for it, rather than only [`lint_with`] when
+    /// it's asked for
+    pub fn is_default(self) -> bool {
+        self != Lint::BrokenMeter
+    }
+}
+
+/// how serious a diagnostic is, in the order editors usually rank them
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+    Information,
+    Hint,
+}
+
+impl Severity {
+    pub fn name(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Information => "information",
+            Severity::Hint => "hint",
+        }
+    }
+}
+
+impl FromStr for Lint {
+    type Err = LintError;
+
+    fn from_str(s: &str) -> LintResult<Self> {
+        Lint::ALL
+            .iter()
+            .copied()
+            .find(|lint| lint.code().eq_ignore_ascii_case(s) || lint.name() == s)
+            .ok_or_else(|| LintError::UnknownLint(s.to_string()))
+    }
+}
+
+/// a single problem found in a poem
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Diagnostic {
+    pub lint: Lint,
+    /// 1-based line number
+    pub line: usize,
+    /// byte offsets of what the diagnostic is about in the source
+    pub span: ops::Range<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: [{}] {}", self.line, self.lint.code(), self.message)
+    }
+}
+
+/// the words of `source` that aren't in the dictionary, lowercased, with
+/// their 0-based line and byte offsets
+pub(crate) fn unknown_words(source: &str) -> Vec<(usize, ops::Range<usize>, String)> {
+    let mut words = Vec::new();
+    for (index, (line_start, line)) in analysis::lines(source).enumerate() {
+        let mut start = line_start;
+        for word in line.split(' ') {
+            // punctuation is part of the word as far as the parser is
+            // concerned, but only the letters matter to whether it's known
+            let trimmed = word.trim_start_matches(|c: char| !c.is_alphanumeric());
+            let offset = start + word.len() - trimmed.len();
+            let trimmed = trimmed.trim_end_matches(|c: char| !c.is_alphanumeric());
+            let lowercase = trimmed.to_lowercase();
+            if lowercase.chars().any(char::is_alphabetic) && !parser::in_dictionary(&lowercase) {
+                words.push((index, offset..offset + trimmed.len(), lowercase));
+            }
+            start += word.len() + 1;
+        }
+    }
+    words
+}
+
+fn unknown_word_diagnostics(source: &str, diagnostics: &mut Vec<Diagnostic>) {
+    for (index, span, word) in unknown_words(source) {
+        diagnostics.push(Diagnostic {
+            lint: Lint::UnknownWord,
+            line: index + 1,
+            span,
+            message: format!(
+                "'{}' isn't in the dictionary, so its syllables are approximated as {}",
+                word,
+                parser::count_syllables(&word)
+            ),
+        });
+    }
+}
+
+fn hijacked_store(source: &str, program: &Program, diagnostics: &mut Vec<Diagnostic>) {
+    let index = match title::title_index(source) {
+        Some(index) => index,
+        None => return,
+    };
+    let instruction = program.ast[index].instruction;
+    if !matches!(instruction, InsType::Store(_)) {
+        let (start, line) = analysis::lines(source)
+            .nth(index)
+            .expect("the title is one of the lines");
+        diagnostics.push(Diagnostic {
+            lint: Lint::HijackedStore,
+            line: index + 1,
+            span: analysis::trimmed(start, line),
+            message: format!(
+                "the title is parsed as {} instead of storing its {} syllables as the input",
+                instruction.mnemonic(),
+                parser::count_syllables(program.ast[index].line())
+            ),
+        });
+    }
+}
+
+/// lines that don't fit the meter of more than half of the poem's lines.
+/// lines whose syllables aren't known, and poems without such a meter,
+/// are left alone
+fn broken_meter(source: &str, diagnostics: &mut Vec<Diagnostic>) {
+    let lines = analysis::meter(source);
+    let measured: Vec<_> = lines
+        .iter()
+        .filter(|line| line.fit(Meter::Iambic).is_some())
+        .collect();
+    let dominant = [Meter::Iambic, Meter::Trochaic]
+        .iter()
+        .copied()
+        .find(|&meter| {
+            measured.iter().filter(|line| line.meter == meter).count() * 2 > measured.len()
+        });
+    let dominant = match dominant {
+        Some(dominant) => dominant,
+        None => return,
+    };
+    for line in measured {
+        if line
+            .fit(dominant)
+            .is_some_and(|fit| fit < analysis::METER_FIT)
+        {
+            diagnostics.push(Diagnostic {
+                lint: Lint::BrokenMeter,
+                line: line.line + 1,
+                span: line.span.clone(),
+                message: format!(
+                    "line {} breaks the poem's dominant {} meter, scanning as {}",
+                    line.line + 1,
+                    dominant.name(),
+                    line.pattern
+                ),
+            });
+        }
+    }
+}
+
+/// check a poem for likely mistakes with the lints that are on by default,
+/// returning diagnostics ordered by line
+pub fn lint(source: &str) -> Vec<Diagnostic> {
+    lint_with(source, &[])
+}
+
+/// [`lint`], also checking the lints in `enabled` that are off by default
+pub fn lint_with(source: &str, enabled: &[Lint]) -> Vec<Diagnostic> {
+    let program = Program::create(source);
+    let mut diagnostics = Vec::new();
+    unknown_word_diagnostics(source, &mut diagnostics);
+    hijacked_store(source, &program, &mut diagnostics);
+    if enabled.contains(&Lint::BrokenMeter) {
+        broken_meter(source, &mut diagnostics);
+    }
+    diagnostics.sort_by_key(|diagnostic| (diagnostic.line, diagnostic.lint));
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[cfg(feature = "bundled-dictionary")]
+    #[test]
+    fn clean_poem() {
+        assert_eq!(lint("lovely poem\n\n  it is a calculator\nprint."), vec![]);
+    }
+
+    #[cfg(feature = "bundled-dictionary")]
+    #[test]
+    fn unknown_word() {
+        assert_eq!(
+            lint("the zorblax sings"),
+            vec![Diagnostic {
+                lint: Lint::UnknownWord,
+                line: 1,
+                span: 4..11,
+                message:
+                    "'zorblax' isn't in the dictionary, so its syllables are approximated as 2"
+                        .to_string()
+            }]
+        );
+    }
+
+    #[cfg(feature = "bundled-dictionary")]
+    #[test]
+    fn hijacked_title() {
+        let diagnostics = lint("\nshe sells sea shells\nprint.");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "2: [W002] the title is parsed as goto instead of storing its 4 syllables as the input"
+        );
+    }
+
+    #[cfg(feature = "bundled-dictionary")]
+    #[test]
+    fn broken_meter() {
+        let poem = "a sonnet
+Shall I compare thee to a summer's day
+Thou art more lovely and more temperate
+
+Happily wandering merrily singing
+";
+        assert_eq!(lint(poem), vec![]);
+        assert_eq!(
+            lint_with(poem, &[Lint::BrokenMeter]),
+            vec![Diagnostic {
+                lint: Lint::BrokenMeter,
+                line: 5,
+                span: 89..122,
+                message:
+                    "line 5 breaks the poem's dominant iambic meter, scanning as 100 100 100 10"
+                        .to_string()
+            }]
+        );
+        // without a meter there's nothing to break
+        assert_eq!(
+            lint_with("happily wandering merrily singing", &[Lint::BrokenMeter]),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn parse_lint() {
+        assert_eq!("W001".parse::<Lint>().unwrap(), Lint::UnknownWord);
+        assert_eq!("w002".parse::<Lint>().unwrap(), Lint::HijackedStore);
+        assert_eq!(
+            "hijacked-store".parse::<Lint>().unwrap(),
+            Lint::HijackedStore
+        );
+        assert_eq!(
+            "W999".parse::<Lint>().unwrap_err(),
+            LintError::UnknownLint("W999".to_string())
+        );
+    }
+}
@@ -0,0 +1,336 @@
+//! taking the blank lines out of a poem without changing what it does
+//!
+//! blank lines are noops, and generated poems can have thousands of them,
+//! but every line taken out moves the ones after it and shortens the poem,
+//! which changes where gotos land. runs of blank lines at the start and end
+//! of a poem are taken out and runs between lines are cut down to one,
+//! which still keeps the lines around it from rhyming, but only while every
+//! goto the poem can reach still lands on the same line. a goto that jumps
+//! by a computed value could land anywhere, so a poem that can reach one
+//! keeps all of its blank lines
+use std::ops;
+
+use super::{
+    edit::constant_jumps,
+    parser::{self, Dialect, InsType, Instruction, ParseOptions, Register},
+    ExecOptions, HaltReason, Program, Result,
+};
+
+pub use super::errors::minify::{MinifyError, MinifyResult};
+
+/// how many instructions the default input runs each poem for
+const DEFAULT_FUEL: u64 = 100_000;
+
+/// how to minify a poem, see [`Program::minify_with`]
+#[derive(Debug, Clone)]
+pub struct MinifyOptions {
+    pub parse: ParseOptions,
+    /// what the minified poem is run with, next to the poem as it was, to
+    /// check they print the same. they should stop poems that may not halt.
+    /// the minified poem runs fewer instructions, so two runs that both use
+    /// up the instruction limit agree whatever they printed. an
+    /// `InputSource` is used up by the first run, so each only gets it once
+    pub inputs: Vec<ExecOptions>,
+}
+
+impl Default for MinifyOptions {
+    fn default() -> Self {
+        MinifyOptions {
+            parse: ParseOptions::default(),
+            inputs: vec![ExecOptions::with_max_instructions(DEFAULT_FUEL)],
+        }
+    }
+}
+
+/// why a run of blank lines was kept
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum KeepReason {
+    /// the poem can reach the goto at this index, which jumps by a computed
+    /// value
+    ComputedGoto { goto: usize },
+    /// taking the lines out would move the goto at `goto` away from the line
+    /// it always jumps to
+    MovesGotoTarget { goto: usize, target: usize },
+}
+
+/// blank lines that were kept, by their indices in the poem as it was
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct KeptBlanks {
+    pub lines: ops::Range<usize>,
+    pub reason: KeepReason,
+}
+
+/// a minified poem, and what couldn't be taken out of it
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Minified {
+    pub source: String,
+    /// how many lines were taken out
+    pub removed: usize,
+    pub kept: Vec<KeptBlanks>,
+}
+
+/// a run of blank lines, and the ones that can go
+struct Run {
+    lines: ops::Range<usize>,
+    removable: ops::Range<usize>,
+}
+
+/// the runs of blank lines in `ast`. a run between two lines keeps its last
+/// line, and in [`Dialect::Extended`] so does a run at the start whose last
+/// line doesn't use r1, since the register of the last line is the
+/// inactive one for the next
+fn blank_runs(ast: &[Instruction], dialect: Dialect) -> Vec<Run> {
+    let blank =
+        |ins: &Instruction| ins.instruction == InsType::Noop && ins.line().trim().is_empty();
+    let mut runs = vec![];
+    let mut index = 0;
+    while index < ast.len() {
+        if !blank(&ast[index]) {
+            index += 1;
+            continue;
+        }
+        let start = index;
+        while index < ast.len() && blank(&ast[index]) {
+            index += 1;
+        }
+        let last_kept = if index == ast.len() {
+            false
+        } else if start == 0 {
+            // before the first instruction, the inactive register is r1
+            dialect == Dialect::Extended && ast[index - 1].register != Register::Register1
+        } else {
+            true
+        };
+        let end = if last_kept { index - 1 } else { index };
+        if start < end {
+            runs.push(Run {
+                lines: start..index,
+                removable: start..end,
+            });
+        }
+    }
+    runs
+}
+
+/// the instructions the poem can reach, or the first computed goto it can
+/// reach, which could jump anywhere
+fn reachable(ast: &[Instruction], jumps: &[(usize, u64)]) -> std::result::Result<Vec<bool>, usize> {
+    let mut reached = vec![false; ast.len()];
+    let mut next = vec![0];
+    while let Some(index) = next.pop() {
+        if index >= ast.len() || reached[index] {
+            continue;
+        }
+        reached[index] = true;
+        let instruction = ast[index].instruction;
+        if matches!(instruction, InsType::Goto | InsType::ConditionalGoto(_)) {
+            match jumps.iter().find(|(goto, _)| *goto == index) {
+                Some((_, syllables)) => next.push((syllables % ast.len() as u64) as usize),
+                None => return Err(index),
+            }
+        }
+        if instruction != InsType::Goto {
+            next.push(index + 1);
+        }
+    }
+    Ok(reached)
+}
+
+/// the first of `jumps` that would land somewhere else with `removed` lines
+/// taken out, as the goto and the line it jumps to
+fn moved_jump(jumps: &[(usize, u64)], removed: &[bool]) -> Option<(usize, usize)> {
+    let len = removed.len() as u64;
+    let new_len = removed.iter().filter(|removed| !**removed).count() as u64;
+    jumps.iter().find_map(|&(goto, syllables)| {
+        let target = (syllables % len) as usize;
+        // a removed blank line lands on the line after it instead
+        let moved_to = target - removed[..target].iter().filter(|removed| **removed).count();
+        (new_len == 0 || (syllables % new_len) as usize != moved_to).then_some((goto, target))
+    })
+}
+
+impl Program {
+    /// `source` without the blank lines it doesn't need, see the
+    /// [module docs](crate::minify), checked to print the same when it's
+    /// run with no input
+    pub fn minify(source: &str) -> Result<String> {
+        Program::minify_with(source, &MinifyOptions::default()).map(|minified| minified.source)
+    }
+
+    /// [`Program::minify`], parsed and checked with `options`, along with
+    /// the blank lines it had to keep
+    pub fn minify_with(source: &str, options: &MinifyOptions) -> Result<Minified> {
+        let program = Program::create_with_options(source, &options.parse);
+        let ast = &program.ast;
+        let runs = blank_runs(ast, options.parse.dialect);
+        let jumps: Vec<_> = constant_jumps(ast, options.parse.dialect).collect();
+
+        let mut removed = vec![false; ast.len()];
+        let mut kept = vec![];
+        match reachable(ast, &jumps) {
+            Err(goto) => kept.extend(runs.iter().map(|run| KeptBlanks {
+                lines: run.lines.clone(),
+                reason: KeepReason::ComputedGoto { goto },
+            })),
+            Ok(reached) => {
+                let jumps: Vec<_> = jumps
+                    .into_iter()
+                    .filter(|(goto, _)| reached[*goto])
+                    .collect();
+                // each run is only taken out if every reachable goto still
+                // lands on the same line with it and the runs after it gone.
+                // from the end, since lines after every goto's target can
+                // go as long as the poem stays longer than the jumps
+                for run in runs.iter().rev() {
+                    removed[run.removable.clone()].fill(true);
+                    if let Some((goto, target)) = moved_jump(&jumps, &removed) {
+                        removed[run.removable.clone()].fill(false);
+                        kept.push(KeptBlanks {
+                            lines: run.lines.clone(),
+                            reason: KeepReason::MovesGotoTarget { goto, target },
+                        });
+                    }
+                }
+            }
+        }
+
+        let lines: Vec<&str> = source.lines().collect();
+        let mut minified: Vec<&str> = lines
+            .iter()
+            .zip(&removed)
+            .filter(|(_, removed)| !**removed)
+            .map(|(line, _)| *line)
+            .collect();
+        if source.ends_with('\n') && !minified.is_empty() {
+            minified.push("");
+        }
+        let minified = minified.join("\n");
+
+        let reparsed = parser::parse_with_options(&minified, &options.parse);
+        let kept_ast = ast.iter().zip(&removed).filter(|(_, removed)| !**removed);
+        for (line, (ins, (original, _))) in reparsed.iter().zip(kept_ast).enumerate() {
+            if (ins.instruction, ins.register) != (original.instruction, original.register) {
+                return Err(MinifyError::Reparsed { line }.into());
+            }
+        }
+        let minified_program = Program::create_with_options(&minified, &options.parse);
+        for (input, exec_options) in options.inputs.iter().enumerate() {
+            let before = program.execute_report(exec_options);
+            let after = minified_program.execute_report(exec_options);
+            let out_of_fuel = |reason| reason == HaltReason::FuelExhausted;
+            if out_of_fuel(before.halted_reason) && out_of_fuel(after.halted_reason) {
+                continue;
+            }
+            if (before.output, before.halted_reason) != (after.output, after.halted_reason) {
+                return Err(MinifyError::Diverged { input }.into());
+            }
+        }
+
+        Ok(Minified {
+            source: minified,
+            removed: removed.iter().filter(|removed| **removed).count(),
+            kept,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::{random_poem, GenConfig};
+    use pretty_assertions::assert_eq;
+
+    /// a generated poem with no gotos, with runs of blank lines around and
+    /// inside it
+    fn padded(seed: u64) -> String {
+        let mut config = GenConfig::default();
+        config.weights.insert("goto", 0);
+        config.weights.insert("cond-goto", 0);
+        let poem = random_poem(&config, seed);
+        let lines: Vec<&str> = poem.lines().collect();
+        let (start, end) = lines.split_at(lines.len() / 2);
+        format!("\n\n\n{}\n\n\n\n{}\n\n\n", start.join("\n"), end.join("\n"))
+    }
+
+    #[test]
+    fn padded_generated_poem() {
+        for seed in 0..8 {
+            let source = padded(seed);
+            let minified = Program::minify_with(&source, &MinifyOptions::default()).unwrap();
+            // the three at the start, two of the three in the middle and
+            // the two after the last line, and any the poem had already
+            assert!(minified.removed >= 7, "seed {}", seed);
+            assert_eq!(minified.kept, vec![]);
+            assert_eq!(
+                Program::create(&minified.source).execute(),
+                Program::create(&source).execute()
+            );
+        }
+    }
+
+    #[test]
+    fn factorial_unchanged() {
+        let source = include_str!("../poems/original-factorial.eso");
+        assert_eq!(Program::minify(source).unwrap(), source);
+    }
+
+    #[test]
+    fn never_halts() {
+        // a goto back to the first line forever
+        let source = "\n\nsells sea shells\n\n\n";
+        let minified = Program::minify_with(source, &MinifyOptions::default()).unwrap();
+        assert_eq!(minified.source, source);
+        assert_eq!(minified.removed, 0);
+        assert_eq!(
+            Program::minify("sells sea shells").unwrap(),
+            "sells sea shells"
+        );
+    }
+
+    #[cfg(feature = "bundled-dictionary")]
+    #[test]
+    fn moved_goto_target() {
+        // the goto jumps by 2 back to the print, which it would land on
+        // itself for if the blank lines at the start went
+        let source = "\n\nfox.\nlovely\nbig bad\n";
+        let options = MinifyOptions {
+            inputs: vec![ExecOptions::with_max_instructions(100)],
+            ..MinifyOptions::default()
+        };
+        let minified = Program::minify_with(source, &options).unwrap();
+        assert_eq!(minified.source, source);
+        assert_eq!(
+            minified.kept,
+            vec![KeptBlanks {
+                lines: 0..2,
+                reason: KeepReason::MovesGotoTarget { goto: 4, target: 2 },
+            }]
+        );
+    }
+
+    #[test]
+    fn computed_goto() {
+        // a goto after anything but a store jumps by whatever is in the
+        // register
+        let source = "fox.\n\n\nbig bad\n\n";
+        let options = MinifyOptions {
+            inputs: vec![ExecOptions::with_max_instructions(100)],
+            ..MinifyOptions::default()
+        };
+        let minified = Program::minify_with(source, &options).unwrap();
+        assert_eq!(minified.removed, 0);
+        assert_eq!(
+            minified.kept,
+            vec![
+                KeptBlanks {
+                    lines: 1..3,
+                    reason: KeepReason::ComputedGoto { goto: 3 },
+                },
+                KeptBlanks {
+                    lines: 4..5,
+                    reason: KeepReason::ComputedGoto { goto: 3 },
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,63 @@
+//! running a poem from async code without blocking the runtime, with the
+//! `async` feature
+//!
+//! the interpreter itself stays synchronous. the poem runs on tokio's
+//! blocking pool, and the future only waits for it, cancelling the run if
+//! it's dropped first
+use std::{future::Future, panic};
+
+use tokio::task;
+
+use super::{
+    CancelToken, Error, ExecError, ExecOptions, ExecutionReport, Program, Result, SourceLocation,
+};
+
+/// cancels a run when the future waiting for it is dropped, unless it's
+/// disarmed because the run finished
+struct CancelOnDrop(Option<CancelToken>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        if let Some(cancel) = self.0.take() {
+            cancel.cancel();
+        }
+    }
+}
+
+impl Program {
+    /// [`Program::execute_report`] on tokio's blocking pool, so it doesn't
+    /// hold up the runtime. the run starts when the future is first polled,
+    /// which has to be within a tokio runtime, and dropping the future
+    /// cancels it before its next instruction, through `options.cancel` if
+    /// it has a token. a poem that fails still resolves to its report, the
+    /// error is only for a run the runtime shut down before starting
+    pub fn execute_async(
+        &self,
+        options: ExecOptions,
+    ) -> impl Future<Output = Result<ExecutionReport>> + Send + 'static {
+        let cancel = options.cancel.clone().unwrap_or_default();
+        let options = ExecOptions {
+            cancel: Some(cancel.clone()),
+            ..options
+        };
+        let program = self.clone();
+        async move {
+            let start = if program.ast.is_empty() {
+                SourceLocation {
+                    line_number: 1,
+                    line_text: String::new(),
+                }
+            } else {
+                program.location(0)
+            };
+            let mut guard = CancelOnDrop(Some(cancel));
+            let joined = task::spawn_blocking(move || program.execute_report(&options)).await;
+            guard.0 = None;
+            match joined {
+                Ok(report) => Ok(report),
+                Err(err) if err.is_panic() => panic::resume_unwind(err.into_panic()),
+                Err(_) => Err(Error::Exec(ExecError::Cancelled { location: start })),
+            }
+        }
+    }
+}
@@ -1,93 +1,839 @@
-use std::{cmp, str::FromStr};
+use std::{
+    borrow::Cow,
+    cmp,
+    collections::HashMap,
+    convert::TryFrom,
+    fmt,
+    hash::{Hash, Hasher},
+    iter, ops,
+    path::PathBuf,
+    sync::Arc,
+};
 
-use cmudict_fast::Cmudict;
-use cmudict_fast::{self as cmudict};
-use lazy_static::lazy_static;
-use regex::Regex;
+use super::dictionary::{self, Dictionary, Pronunciation};
+pub use super::dictionary::{DictionaryError, DictionaryResult};
+use super::Result;
+
+#[cfg(feature = "regex")]
+mod regexes;
+#[cfg(any(not(feature = "regex"), test))]
+mod scan;
+
+#[cfg(feature = "regex")]
+use regexes as patterns;
+#[cfg(not(feature = "regex"))]
+use scan as patterns;
 
 /// represents a single line and its metadata
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "InsTypeRepr", from = "InsTypeRepr")
+)]
 pub enum InsType {
+    /// a line whose last word rhymes with the last word of the line before.
+    /// the spec: "If register 0 < register 1, push the number of syllables
+    /// present in the previous line to the stack. Otherwise, push the number
+    /// of syllables in the current line to the stack." it's r0 and r1 that
+    /// are compared, whichever register the line itself uses, in either
+    /// dialect
     ConditionalPush {
-        prev_syllables: usize,
-        cur_syllables: usize,
+        prev_syllables: u32,
+        cur_syllables: u32,
     },
-    ConditionalGoto(usize),
+    ConditionalGoto(u32),
     Negate,
     Multiply,
     Add,
+    /// only in [`Dialect::Extended`]
+    Subtract,
+    /// only in [`Dialect::Extended`]
+    Divide,
+    /// only in [`Dialect::Extended`]
+    ReadChar,
+    /// only in [`Dialect::Extended`]: write the active register to the tape
+    /// cell numbered by the inactive one
+    TapeStore,
+    /// only in [`Dialect::Extended`]: read the tape cell numbered by the
+    /// inactive register into the active one. cells that were never written
+    /// hold 0
+    TapeLoad,
     PrintChar,
     PrintValue,
     Pop,
     Push,
     Goto,
-    Store(usize),
+    Store(u32),
     Noop,
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+/// which rules a poem is parsed with
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub enum Dialect {
+    /// the rules of the spec
+    #[default]
+    Standard,
+    /// the spec's rules, plus a semicolon for [`InsType::Subtract`], a colon
+    /// for [`InsType::Divide`], an exclamation mark for
+    /// [`InsType::ReadChar`], both square brackets for [`InsType::TapeStore`]
+    /// and just one of them for [`InsType::TapeLoad`]. they're checked right
+    /// after the slash of a cond-goto, so they win over the other rules.
+    /// lines pick one of four registers by how far they're indented, see
+    /// [`Register`], and the inactive register is the one the last
+    /// instruction used
+    Extended,
+}
+
+/// how a poem is parsed
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct ParseOptions {
+    pub dialect: Dialect,
+    /// count the syllables of every word by its vowel clusters, as if the
+    /// dictionary didn't have it. there's no way to tell whether words
+    /// rhyme without the dictionary, so no line rhymes either
+    pub force_heuristics: bool,
+    pub alliteration: AllitOptions,
+    pub unknown_script_syllables: UnknownScriptSyllables,
+    /// rules of your own for what lines compile to, checked among the
+    /// built-in ones. they're only used in [`Dialect::Extended`]
+    pub custom_rules: Vec<CustomRule>,
+    /// a file to keep the syllables of the words counted in, so they aren't
+    /// looked up again the next time the process parses them. it's read the
+    /// first time it's used and written after every poem parsed with it, or
+    /// by [`dictionary::flush_syllable_caches`](crate::dictionary::flush_syllable_caches).
+    /// one made with another dictionary or version of the crate, or that
+    /// can't be read, is started over
+    pub syllable_cache_path: Option<PathBuf>,
+    /// how far a line has to be indented to use r1 rather than r0, 1 by
+    /// default so any leading whitespace does. see [`ParseOptions::indent`]
+    pub register1_min_indent: usize,
+    /// how much indent a tab is worth, or `None` for 1 in
+    /// [`Dialect::Standard`] and 4 in [`Dialect::Extended`]
+    pub indent_tab_width: Option<usize>,
+    /// what counts the syllables of lines instead of the dictionary, e.g.
+    /// for poems in another language. it counts every word itself, so
+    /// `force_heuristics`, `unknown_script_syllables` and
+    /// `syllable_cache_path` don't apply to it. rhymes are still found in
+    /// the dictionary
+    pub syllable_counter: Option<CustomCounter>,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            dialect: Dialect::default(),
+            force_heuristics: false,
+            alliteration: AllitOptions::default(),
+            unknown_script_syllables: UnknownScriptSyllables::default(),
+            custom_rules: Vec::new(),
+            syllable_cache_path: None,
+            register1_min_indent: 1,
+            indent_tab_width: None,
+            syllable_counter: None,
+        }
+    }
+}
+
+impl ParseOptions {
+    /// how much indent a tab is worth with these options
+    pub fn tab_width(&self) -> usize {
+        self.indent_tab_width.unwrap_or(match self.dialect {
+            Dialect::Standard => 1,
+            Dialect::Extended => 4,
+        })
+    }
+
+    /// how far `line` is indented: a column for every leading whitespace
+    /// character but a tab, which is worth [`ParseOptions::tab_width`]
+    pub fn indent(&self, line: &str) -> usize {
+        let tab_width = self.tab_width();
+        line.chars()
+            .take_while(|c| c.is_whitespace())
+            .map(|c| if c == '\t' { tab_width } else { 1 })
+            .sum()
+    }
+}
+
+/// what a [`CustomRule`] can know about a line besides its text
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct LineFeatures {
+    pub syllables: u32,
+    pub register: Register,
+}
+
+/// the instruction a line a [`CustomRule`] matches compiles to
+// built instructions are compared by the function's address, like
+// `UnknownScriptSyllables::Custom`
+#[allow(unpredictable_function_pointer_comparisons)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum CustomInsFactory {
+    /// always the same one
+    Fixed(InsType),
+    /// one made from the line, like a store of its syllables
+    Build(fn(&LineFeatures) -> InsType),
+}
+
+impl CustomInsFactory {
+    fn build(self, features: &LineFeatures) -> InsType {
+        match self {
+            CustomInsFactory::Fixed(ins_type) => ins_type,
+            CustomInsFactory::Build(build) => build(features),
+        }
+    }
+}
+
+/// whether a [`CustomRule`] applies to a line
+pub type LineMatcher = dyn Fn(&LineFeatures, &str) -> bool + Send + Sync;
+
+/// a rule for what lines compile to, e.g. a push for every line with an
+/// em-dash in it. lines that rhyme with the line before them are still
+/// conditional pushes, but otherwise the rule is checked at its `priority`
+/// among the built-in rules, which are checked in this order:
+///
+/// | priority | built-in rule                                 |
+/// |----------|-----------------------------------------------|
+/// | 0        | a blank line is a noop                        |
+/// | 1        | `/` is a cond-goto                            |
+/// | 2 to 6   | the extended dialect's `;`, `:`, `!`, `[` `]` |
+/// | 7        | a capital in a word is a negate               |
+/// | 8        | a capital starting a word is a multiply       |
+/// | 9        | a simile is an add                            |
+/// | 10       | `?` is a print-char                           |
+/// | 11       | `.` is a print-value                          |
+/// | 12       | `,` is a pop                                  |
+/// | 13       | `-` is a push                                 |
+/// | 14       | alliteration is a goto                        |
+///
+/// a rule of priority 0 is checked before any of them, one of 8 after the
+/// negate but before the multiply, and one of 15 or more only before a
+/// line is left as a store of its syllables. rules of the same priority are
+/// checked in the order they're given
+#[derive(Clone)]
+pub struct CustomRule {
+    pub priority: usize,
+    /// called with the line as it's written
+    pub matcher: Arc<LineMatcher>,
+    pub instruction: CustomInsFactory,
+}
+
+impl CustomRule {
+    pub fn new(
+        priority: usize,
+        matcher: impl Fn(&LineFeatures, &str) -> bool + Send + Sync + 'static,
+        instruction: CustomInsFactory,
+    ) -> CustomRule {
+        CustomRule {
+            priority,
+            matcher: Arc::new(matcher),
+            instruction,
+        }
+    }
+}
+
+impl fmt::Debug for CustomRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CustomRule")
+            .field("priority", &self.priority)
+            .field("instruction", &self.instruction)
+            .finish_non_exhaustive()
+    }
+}
+
+/// rules are the same when they share a matcher, since closures can't be
+/// compared
+impl PartialEq for CustomRule {
+    fn eq(&self, other: &CustomRule) -> bool {
+        self.priority == other.priority
+            && Arc::ptr_eq(&self.matcher, &other.matcher)
+            && self.instruction == other.instruction
+    }
+}
+
+impl Eq for CustomRule {}
+
+impl Hash for CustomRule {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.priority.hash(state);
+        (Arc::as_ptr(&self.matcher) as *const u8).hash(state);
+        self.instruction.hash(state);
+    }
+}
+
+/// how many syllables the characters of a word outside the Latin script
+/// have, like the ones of "こんにちは" or "🌸". neither the dictionary nor
+/// the vowel clusters can count them, so they're taken out of the word
+/// before it's counted and counted by this instead. a line of nothing else
+/// is a store of however many this gives it, 0 by default
+// custom counts are compared by address, which is as much as `ParseOptions`
+// needs to tell its options apart
+#[allow(unpredictable_function_pointer_comparisons)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub enum UnknownScriptSyllables {
+    /// none at all
+    #[default]
+    Zero,
+    /// one for each character
+    PerCharacter,
+    /// called with each run of them in a word
+    Custom(fn(&str) -> usize),
+}
+
+impl UnknownScriptSyllables {
+    fn count(self, run: &str) -> usize {
+        match self {
+            UnknownScriptSyllables::Zero => 0,
+            UnknownScriptSyllables::PerCharacter => run.chars().count(),
+            UnknownScriptSyllables::Custom(count) => count(run),
+        }
+    }
+}
+
+/// counts the syllables of the lines a poem is parsed from, which are
+/// what stores, cond-gotos and cond-pushes carry
+pub trait SyllableCounter: Send + Sync {
+    /// the syllables of a lowercase word, with any punctuation next to it
+    /// left on
+    fn count_word(&self, word: &str) -> usize;
+
+    /// the syllables of a line, the words between its spaces counted one at
+    /// a time by default
+    fn count_line(&self, line: &str) -> usize {
+        line.split(' ')
+            .filter(|word| !word.is_empty())
+            .map(|word| self.count_word(&lowercase(word)))
+            .sum()
+    }
+}
+
+/// a function from a lowercase word to its syllables counts them too
+impl<F: Fn(&str) -> usize + Send + Sync> SyllableCounter for F {
+    fn count_word(&self, word: &str) -> usize {
+        self(word)
+    }
+}
+
+/// the words' syllables in the dictionary, or approximated by their vowel
+/// clusters if it doesn't have them, which is how lines are counted unless
+/// [`ParseOptions::syllable_counter`] says otherwise
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub struct CmuCounter;
+
+impl SyllableCounter for CmuCounter {
+    fn count_word(&self, word: &str) -> usize {
+        let (word, unknown) = split_unknown_script(word, UnknownScriptSyllables::default());
+        count_word_syllables(&word) + unknown
+    }
+}
+
+/// a [`SyllableCounter`] of your own, for [`ParseOptions::syllable_counter`]
+#[derive(Clone)]
+pub struct CustomCounter(Arc<dyn SyllableCounter>);
+
+impl CustomCounter {
+    pub fn new(counter: impl SyllableCounter + 'static) -> CustomCounter {
+        CustomCounter(Arc::new(counter))
+    }
+
+    /// where the counter is, which tells counters apart
+    fn address(&self) -> *const u8 {
+        Arc::as_ptr(&self.0) as *const u8
+    }
+}
+
+impl SyllableCounter for CustomCounter {
+    fn count_word(&self, word: &str) -> usize {
+        self.0.count_word(word)
+    }
+
+    fn count_line(&self, line: &str) -> usize {
+        self.0.count_line(line)
+    }
+}
+
+impl fmt::Debug for CustomCounter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CustomCounter").finish_non_exhaustive()
+    }
+}
+
+/// counters are the same when they're the same one, like the matchers of
+/// [`CustomRule`]s
+impl PartialEq for CustomCounter {
+    fn eq(&self, other: &CustomCounter) -> bool {
+        self.address() == other.address()
+    }
+}
+
+impl Eq for CustomCounter {}
+
+impl Hash for CustomCounter {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.address().hash(state);
+    }
+}
+
+/// what makes a line alliterate, and so parse as a goto. the default is
+/// the spec's rule, two words in a row starting with the same letter
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct AllitOptions {
+    /// how many words on from one word of a run the next can be, 1 for the
+    /// very next word, 2 to allow a word between them
+    pub window: usize,
+    /// how many words starting with the same letter make a run. 1 or less
+    /// counts any line with a word in it
+    pub min_run: usize,
+}
+
+impl Default for AllitOptions {
+    fn default() -> Self {
+        AllitOptions {
+            window: 1,
+            min_run: 2,
+        }
+    }
+}
+
+/// how instructions look in json: tagged with their mnemonic, with any
+/// syllables they carry as named fields, e.g.
+/// `{ "instruction": "store", "syllables": 3 }`
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(tag = "instruction", rename_all = "kebab-case")]
+enum InsTypeRepr {
+    CondPush {
+        prev_syllables: u32,
+        cur_syllables: u32,
+    },
+    CondGoto {
+        syllables: u32,
+    },
+    Negate,
+    Multiply,
+    Add,
+    Subtract,
+    Divide,
+    ReadChar,
+    TapeStore,
+    TapeLoad,
+    PrintChar,
+    PrintValue,
+    Pop,
+    Push,
+    Goto,
+    Store {
+        syllables: u32,
+    },
+    Noop,
+}
+
+#[cfg(feature = "serde")]
+impl From<InsType> for InsTypeRepr {
+    fn from(instruction: InsType) -> Self {
+        match instruction {
+            InsType::ConditionalPush {
+                prev_syllables,
+                cur_syllables,
+            } => InsTypeRepr::CondPush {
+                prev_syllables,
+                cur_syllables,
+            },
+            InsType::ConditionalGoto(syllables) => InsTypeRepr::CondGoto { syllables },
+            InsType::Negate => InsTypeRepr::Negate,
+            InsType::Multiply => InsTypeRepr::Multiply,
+            InsType::Add => InsTypeRepr::Add,
+            InsType::Subtract => InsTypeRepr::Subtract,
+            InsType::Divide => InsTypeRepr::Divide,
+            InsType::ReadChar => InsTypeRepr::ReadChar,
+            InsType::TapeStore => InsTypeRepr::TapeStore,
+            InsType::TapeLoad => InsTypeRepr::TapeLoad,
+            InsType::PrintChar => InsTypeRepr::PrintChar,
+            InsType::PrintValue => InsTypeRepr::PrintValue,
+            InsType::Pop => InsTypeRepr::Pop,
+            InsType::Push => InsTypeRepr::Push,
+            InsType::Goto => InsTypeRepr::Goto,
+            InsType::Store(syllables) => InsTypeRepr::Store { syllables },
+            InsType::Noop => InsTypeRepr::Noop,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<InsTypeRepr> for InsType {
+    fn from(repr: InsTypeRepr) -> Self {
+        match repr {
+            InsTypeRepr::CondPush {
+                prev_syllables,
+                cur_syllables,
+            } => InsType::ConditionalPush {
+                prev_syllables,
+                cur_syllables,
+            },
+            InsTypeRepr::CondGoto { syllables } => InsType::ConditionalGoto(syllables),
+            InsTypeRepr::Negate => InsType::Negate,
+            InsTypeRepr::Multiply => InsType::Multiply,
+            InsTypeRepr::Add => InsType::Add,
+            InsTypeRepr::Subtract => InsType::Subtract,
+            InsTypeRepr::Divide => InsType::Divide,
+            InsTypeRepr::ReadChar => InsType::ReadChar,
+            InsTypeRepr::TapeStore => InsType::TapeStore,
+            InsTypeRepr::TapeLoad => InsType::TapeLoad,
+            InsTypeRepr::PrintChar => InsType::PrintChar,
+            InsTypeRepr::PrintValue => InsType::PrintValue,
+            InsTypeRepr::Pop => InsType::Pop,
+            InsTypeRepr::Push => InsType::Push,
+            InsTypeRepr::Goto => InsType::Goto,
+            InsTypeRepr::Store { syllables } => InsType::Store(syllables),
+            InsTypeRepr::Noop => InsType::Noop,
+        }
+    }
+}
+
+/// broad grouping of instructions by what they do
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum InsCategory {
+    ControlFlow,
+    Arithmetic,
+    Io,
+    Stack,
+    Store,
+    Noop,
+}
+
+impl InsType {
+    /// short human readable name for the instruction
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            InsType::ConditionalPush { .. } => "cond-push",
+            InsType::ConditionalGoto(_) => "cond-goto",
+            InsType::Negate => "negate",
+            InsType::Multiply => "multiply",
+            InsType::Add => "add",
+            InsType::Subtract => "subtract",
+            InsType::Divide => "divide",
+            InsType::ReadChar => "read-char",
+            InsType::TapeStore => "tape-store",
+            InsType::TapeLoad => "tape-load",
+            InsType::PrintChar => "print-char",
+            InsType::PrintValue => "print-value",
+            InsType::Pop => "pop",
+            InsType::Push => "push",
+            InsType::Goto => "goto",
+            InsType::Store(_) => "store",
+            InsType::Noop => "noop",
+        }
+    }
+
+    pub fn category(&self) -> InsCategory {
+        match self {
+            InsType::ConditionalGoto(_) | InsType::Goto => InsCategory::ControlFlow,
+            InsType::Negate
+            | InsType::Multiply
+            | InsType::Add
+            | InsType::Subtract
+            | InsType::Divide => InsCategory::Arithmetic,
+            InsType::ReadChar | InsType::PrintChar | InsType::PrintValue => InsCategory::Io,
+            InsType::ConditionalPush { .. }
+            | InsType::Pop
+            | InsType::Push
+            | InsType::TapeStore
+            | InsType::TapeLoad => InsCategory::Stack,
+            InsType::Store(_) => InsCategory::Store,
+            InsType::Noop => InsCategory::Noop,
+        }
+    }
+}
+
+/// serialized as the register's number, 0 to 3
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "u8", try_from = "u8")
+)]
 pub enum Register {
+    /// lines that aren't indented use the first register
+    #[default]
     Register0,
+    /// indented lines, or in [`Dialect::Extended`] lines indented by 1 to 3
+    /// columns
     Register1,
+    /// only in [`Dialect::Extended`], lines indented by 4 to 7 columns
+    Register2,
+    /// only in [`Dialect::Extended`], lines indented further
+    Register3,
 }
 
-#[derive(Debug, PartialEq, Clone)]
+impl Register {
+    /// its number, 0 to 3
+    pub fn index(self) -> usize {
+        u8::from(self) as usize
+    }
+
+    /// the inactive register in [`Dialect::Standard`]: the first and second
+    /// registers go together, and so do the other two
+    pub fn paired(self) -> Register {
+        match self {
+            Register::Register0 => Register::Register1,
+            Register::Register1 => Register::Register0,
+            Register::Register2 => Register::Register3,
+            Register::Register3 => Register::Register2,
+        }
+    }
+}
+
+impl From<Register> for u8 {
+    fn from(register: Register) -> Self {
+        match register {
+            Register::Register0 => 0,
+            Register::Register1 => 1,
+            Register::Register2 => 2,
+            Register::Register3 => 3,
+        }
+    }
+}
+
+impl std::convert::TryFrom<u8> for Register {
+    type Error = String;
+
+    fn try_from(number: u8) -> std::result::Result<Self, Self::Error> {
+        match number {
+            0 => Ok(Register::Register0),
+            1 => Ok(Register::Register1),
+            2 => Ok(Register::Register2),
+            3 => Ok(Register::Register3),
+            _ => Err(format!("there is no register {}, only 0 to 3", number)),
+        }
+    }
+}
+
+/// a line of a poem and what it compiles to. kept to
+/// [`Instruction::MAX_SIZE`] bytes plus the text of the line, since
+/// generated poems can have millions of them. a line that's repeated shares
+/// its text with the other times it appears
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Instruction {
+    #[cfg_attr(feature = "serde", serde(flatten))]
     pub instruction: InsType,
+    #[cfg_attr(feature = "serde", serde(default))]
     pub register: Register,
-    pub line: String,
-}
-
-lazy_static! {
-    // * it is assumed that these Regexes are valid
-    static ref INT_CAP_RE: Regex = Regex::new(r"\b\S+[A-Z]\S+\b").unwrap();
-    static ref CAP_RE: Regex = Regex::new(r"\b[A-Z][^A-Z]+\b").unwrap();
-    static ref SIMILIE_RE: Regex = Regex::new(r"\b(like|as)\b").unwrap();
-    static ref WS_START_RE: Regex = Regex::new(r"^\s").unwrap();
-    static ref VOWEL_CLUSTER_RE: Regex = Regex::new(r"[^aeiouy]+").unwrap();
-    // * no error handling
-    static ref CMUDICT: Cmudict = Cmudict::from_str(include_str!("../res/cmudict.dict")).unwrap();
-}
-
-/// test for alliteration by checking if multiple words in the input
-/// start with the same letter
-fn has_alliteration(input: &str) -> bool {
-    let lower_input = input.to_lowercase();
-    let mut input_iter = lower_input.split(' ').filter(|w| !w.is_empty());
-
-    if let Some(start_word) = input_iter.next() {
-        let mut cur_start_letter = start_word.chars().next().unwrap();
-        for word in input_iter {
-            if word.starts_with(cur_start_letter) {
-                return true;
+    /// the line exactly as written, so the poem can be printed back out.
+    /// optional in json since only the instruction matters when executing
+    #[cfg_attr(feature = "serde", serde(default))]
+    #[deprecated(
+        since = "0.6.0",
+        note = "use `Instruction::new` and `Instruction::line`, the field is going to be private"
+    )]
+    pub line: Arc<str>,
+}
+
+#[allow(deprecated)]
+impl Instruction {
+    /// how big an instruction is, not counting the text of its line
+    pub const MAX_SIZE: usize = 32;
+
+    pub fn new(instruction: InsType, register: Register, line: impl Into<Arc<str>>) -> Instruction {
+        Instruction {
+            instruction,
+            register,
+            line: line.into(),
+        }
+    }
+
+    /// the line exactly as written
+    pub fn line(&self) -> &str {
+        &self.line
+    }
+}
+
+fn dictionary() -> &'static Dictionary {
+    dictionary::load().expect("the bundled dictionary is valid")
+}
+
+/// see [`dictionary::init_from_str`](crate::dictionary::init_from_str)
+pub fn init_dictionary_from_str(source: &str) -> Result<()> {
+    dictionary::init_from_str(source)
+}
+
+/// the words of `input` that aren't empty, with their byte offsets
+fn words(input: &str) -> impl Iterator<Item = (ops::Range<usize>, &str)> + Clone {
+    input
+        .split(' ')
+        .scan(0, |next, word| {
+            let start = *next;
+            *next += word.len() + 1;
+            Some((start..start + word.len(), word))
+        })
+        .filter(|(_, word)| !word.is_empty())
+}
+
+/// `word` in lowercase, only allocating if it isn't already
+pub(crate) fn lowercase(word: &str) -> Cow<'_, str> {
+    if word
+        .bytes()
+        .any(|b| !b.is_ascii() || b.is_ascii_uppercase())
+    {
+        Cow::Owned(word.to_lowercase())
+    } else {
+        Cow::Borrowed(word)
+    }
+}
+
+/// `line` in lowercase, in `buffer` so lines can share one allocation. the
+/// words are the same as if each was lowercased on its own, since the only
+/// letter that lowercases depending on what's around it (a final sigma)
+/// doesn't look past spaces
+fn lowercase_into(buffer: &mut String, line: &str) {
+    buffer.clear();
+    if line.is_ascii() {
+        buffer.push_str(line);
+        buffer.make_ascii_lowercase();
+    } else {
+        buffer.push_str(&line.to_lowercase());
+    }
+}
+
+/// the quotes and apostrophes word processors write in place of `'` and
+/// `"`
+const TYPOGRAPHIC_QUOTES: &[char] = &['‘', '’', '“', '”'];
+
+/// `word` as it's looked up and alliterates: without the typographic quotes
+/// around it, and with the typographic apostrophes in it as ascii ones, so
+/// “shelf” is shelf and don’t is don't. only allocates for the apostrophes
+pub(crate) fn normalize_quotes(word: &str) -> Cow<'_, str> {
+    if word.is_ascii() {
+        return Cow::Borrowed(word);
+    }
+    let word = word.trim_matches(TYPOGRAPHIC_QUOTES);
+    if word.contains(['‘', '’']) {
+        Cow::Owned(word.replace(['‘', '’'], "'"))
+    } else {
+        Cow::Borrowed(word)
+    }
+}
+
+/// the first letter of `word`, lowercased
+fn first_letter(word: &str) -> Option<char> {
+    normalize_quotes(word)
+        .chars()
+        .next()
+        .and_then(|c| c.to_lowercase().next())
+}
+
+/// the index of the first word of the first run of alliterating words in
+/// `input`, and how many words on from it the run ends. the next word of
+/// the run is always the nearest one starting with the letter, which
+/// leaves the most room for the words after it
+fn alliterating_run(input: &str, options: AllitOptions) -> Option<(usize, usize)> {
+    let mut words = words(input).enumerate();
+    while let Some((start, (_, word))) = words.next() {
+        let letter = match first_letter(word) {
+            Some(letter) => letter,
+            None => continue,
+        };
+        let (mut run, mut last) = (1, 0);
+        for (offset, (_, (_, word))) in (1..).zip(words.clone()) {
+            if run >= options.min_run || offset - last > options.window {
+                break;
             }
-            cur_start_letter = word.chars().next().unwrap();
+            if first_letter(word) == Some(letter) {
+                run += 1;
+                last = offset;
+            }
+        }
+        if run >= options.min_run {
+            return Some((start, last));
         }
     }
-    false
+    None
+}
+
+/// the words of the first run of alliterating words in `input`
+fn alliteration(input: &str, options: AllitOptions) -> Option<Vec<ops::Range<usize>>> {
+    let (start, end) = alliterating_run(input, options)?;
+    let mut run = words(input).skip(start).take(end + 1);
+    let (first, word) = run.next()?;
+    let letter = first_letter(word);
+    Some(
+        iter::once(first)
+            .chain(
+                run.filter(|(_, word)| first_letter(word) == letter)
+                    .map(|(span, _)| span),
+            )
+            .collect(),
+    )
+}
+
+/// whether `input` has a run of alliterating words
+pub(crate) fn has_alliteration(input: &str, options: AllitOptions) -> bool {
+    alliterating_run(input, options).is_some()
+}
+
+/// the characters of `line` that made it parse as `instruction` with
+/// `options`, as byte offsets into the line. stores and blank lines aren't
+/// down to particular characters, so they have none
+pub(crate) fn triggers(
+    instruction: InsType,
+    line: &str,
+    options: &ParseOptions,
+) -> Vec<ops::Range<usize>> {
+    let find = |c: char| line.find(c).map(|start| start..start + c.len_utf8());
+    let trigger = match instruction {
+        // the final word rhymes with the line before it
+        InsType::ConditionalPush { .. } => words(line).last().map(|(span, _)| span),
+        InsType::ConditionalGoto(_) => find('/'),
+        InsType::Subtract => find(';'),
+        InsType::Divide => find(':'),
+        InsType::ReadChar => find('!'),
+        // both brackets
+        InsType::TapeStore => return find('[').into_iter().chain(find(']')).collect(),
+        InsType::TapeLoad => find('[').or_else(|| find(']')),
+        InsType::Negate => patterns::internal_capital(line),
+        InsType::Multiply => patterns::leading_capital(line),
+        InsType::Add => patterns::simile(line),
+        InsType::PrintChar => find('?'),
+        InsType::PrintValue => find('.'),
+        InsType::Pop => find(','),
+        InsType::Push => find('-'),
+        InsType::Goto => return alliteration(line, options.alliteration).unwrap_or_default(),
+        InsType::Store(_) | InsType::Noop => None,
+    };
+    trigger.into_iter().collect()
 }
 
+/// the word a line ends in, which is what it rhymes by. punctuation is
+/// left on it, so a word with a comma after it isn't in the dictionary,
+/// though typographic quotes around it are taken off when it's looked up
+pub(crate) fn end_word(line: &str) -> Option<&str> {
+    line.split(' ').rev().find(|s| !s.is_empty())
+}
+
+/// whether two lowercase words rhyme, `None` unless both are in the
+/// dictionary
+pub(crate) fn words_rhyme(one: &str, two: &str) -> Option<bool> {
+    dictionary().rhymes(&normalize_quotes(one), &normalize_quotes(two))
+}
+
+/// whether two lowercase lines end in words that rhyme
 fn check_end_rhyme(last_line_option: Option<&str>, cur_line: &str) -> bool {
     if let Some(last_line) = last_line_option {
         // end-rhyme handling
-        if let (Some(last_line_word), Some(last_word)) = (
-            last_line.split(' ').rev().filter(|s| !s.is_empty()).next(),
-            cur_line.split(' ').rev().filter(|s| !s.is_empty()).next(),
-        ) {
-            if let (Some(last_line_rule), Some(last_rule)) = (
-                CMUDICT.get(&last_line_word.to_lowercase()),
-                CMUDICT.get(&last_word.to_lowercase()),
-            ) {
-                return cmudict::rhymes(last_line_rule, last_rule);
-            }
+        if let (Some(last_line_word), Some(last_word)) = (end_word(last_line), end_word(cur_line)) {
+            return words_rhyme(last_line_word, last_word).unwrap_or(false);
         }
     }
     false
 }
 
 fn approximate_syllables(word: &str) -> usize {
-    let clusters: Vec<_> = VOWEL_CLUSTER_RE.split(word).collect();
-    const DIPHTHONGS: &[&'static str] = &[
+    let clusters = patterns::vowel_clusters(word);
+    const DIPHTHONGS: &[&str] = &[
         "ai", "au", "ay", "ea", "ee", "ei", "ey", "oa", "oe", "oi", "oo", "ou", "oy", "ua", "ue",
         "ui",
     ];
@@ -102,92 +848,604 @@ fn approximate_syllables(word: &str) -> usize {
     count
 }
 
+/// whether the syllables of a (lowercase) word come from the dictionary
+/// rather than being approximated
+pub(crate) fn in_dictionary(word: &str) -> bool {
+    dictionary().contains(&normalize_quotes(word))
+}
+
+/// where the syllables of a word in [`count_syllables_detailed`] came from
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub enum SyllableSource {
+    /// the dictionary, which can list more than one way to say a word. the
+    /// pronunciation with the most syllables is counted, and the first of
+    /// them if there's a tie
+    Dictionary {
+        /// the syllables of every pronunciation, in the order the dictionary
+        /// lists them
+        pronunciations: Vec<usize>,
+        /// the index of the pronunciation that was counted
+        chosen: usize,
+    },
+    /// the word isn't in the dictionary, so its vowel clusters were counted
+    Approximated,
+}
+
+/// how a single word of a line was counted
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct WordSyllables {
+    /// the word lowercased, as it was looked up
+    pub word: String,
+    /// where the word is in the counted text, in bytes
+    pub span: ops::Range<usize>,
+    pub syllables: usize,
+    pub source: SyllableSource,
+}
+
+/// `word` without its characters outside the Latin script, and the
+/// syllables `policy` gives them
+fn split_unknown_script(word: &str, policy: UnknownScriptSyllables) -> (Cow<'_, str>, usize) {
+    if word.is_ascii() {
+        return (Cow::Borrowed(word), 0);
+    }
+    let mut known = String::new();
+    let mut unknown = 0;
+    let mut last = 0;
+    for run in patterns::unknown_script(word) {
+        unknown += policy.count(&word[run.clone()]);
+        known.push_str(&word[last..run.start]);
+        last = run.end;
+    }
+    if last == 0 {
+        return (Cow::Borrowed(word), 0);
+    }
+    known.push_str(&word[last..]);
+    (Cow::Owned(known), unknown)
+}
+
+#[cfg(test)]
+thread_local! {
+    // how many words have been looked up for their syllables, so tests can
+    // check a syllable cache saves them
+    pub(crate) static SYLLABLE_LOOKUPS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+/// the most syllables of any of the word's pronunciations, see
+/// [`word_syllables`] for which one that is, and whether the dictionary had
+/// the word at all
+fn look_up_syllables(word: &str) -> (usize, bool) {
+    #[cfg(test)]
+    SYLLABLE_LOOKUPS.with(|lookups| lookups.set(lookups.get() + 1));
+    match dictionary().syllables(&normalize_quotes(word)) {
+        Some(syllables) => (syllables, true),
+        None => (approximate_syllables(word), false),
+    }
+}
+
+/// [`look_up_syllables`] without saying where they came from
 fn count_word_syllables(word: &str) -> usize {
-    if let Some(rules) = CMUDICT.get(word) {
-        rules
-            .iter()
-            .map(|r| {
-                r.pronunciation()
-                    .iter()
-                    .filter(|po| po.is_syllable())
-                    .count()
-            })
-            .max()
-            .unwrap()
+    look_up_syllables(word).0
+}
+
+/// [`count_word_syllables`] the way a word parsed with `options` is
+/// counted: the approximation even if the dictionary has the word with
+/// [`ParseOptions::force_heuristics`], and the characters outside the Latin
+/// script by [`ParseOptions::unknown_script_syllables`]
+fn count_word_syllables_with(word: &str, options: &ParseOptions) -> usize {
+    let (word, unknown) = split_unknown_script(word, options.unknown_script_syllables);
+    let syllables = if options.force_heuristics {
+        approximate_syllables(&word)
+    } else if let Some(path) = &options.syllable_cache_path {
+        dictionary::cached_syllables(path, &word, || look_up_syllables(&word))
     } else {
-        approximate_syllables(word)
-    }
+        count_word_syllables(&word)
+    };
+    syllables + unknown
 }
 
+/// the pronunciations the dictionary has for a word, and the index of the
+/// one that's counted: the one with the most syllables, or the first of
+/// them if there's a tie
+fn chosen_pronunciation(word: &str) -> Option<(Vec<Pronunciation>, usize)> {
+    let pronunciations = dictionary().pronunciations(&normalize_quotes(word))?;
+    // the first of a tie, where `max_by_key` alone would pick the last
+    let (chosen, _) = pronunciations
+        .iter()
+        .enumerate()
+        .max_by_key(|&(index, pronunciation)| (pronunciation.syllables, cmp::Reverse(index)))?;
+    Some((pronunciations, chosen))
+}
+
+/// [`count_word_syllables`], saying which pronunciation was counted
+fn word_syllables(word: &str) -> (usize, SyllableSource) {
+    let (pronunciations, chosen) = match chosen_pronunciation(word) {
+        Some(chosen) => chosen,
+        None => return (approximate_syllables(word), SyllableSource::Approximated),
+    };
+    let source = SyllableSource::Dictionary {
+        pronunciations: pronunciations
+            .iter()
+            .map(|pronunciation| pronunciation.syllables)
+            .collect(),
+        chosen,
+    };
+    (pronunciations[chosen].syllables, source)
+}
+
+/// the stress of each syllable of a lowercase word as it's counted, `1`
+/// for stressed and `0` for unstressed, if it's in the dictionary
+pub(crate) fn stress_pattern(word: &str) -> Option<String> {
+    chosen_pronunciation(word)
+        .map(|(pronunciations, chosen)| pronunciations[chosen].stress_pattern())
+}
+
+/// the syllables of `input` by [`CmuCounter`]
 pub fn count_syllables(input: &str) -> usize {
+    CmuCounter.count_line(input)
+}
+
+/// [`count_syllables`] the way a line parsed with `options` is counted
+pub fn count_syllables_with_options(input: &str, options: &ParseOptions) -> usize {
+    if let Some(counter) = &options.syllable_counter {
+        return counter.count_line(input);
+    }
     input
         .split(' ')
         .filter(|s| !s.is_empty())
-        .map(|w| count_word_syllables(&w.to_lowercase()))
+        .map(|w| count_word_syllables_with(&lowercase(w), options))
         .sum()
 }
 
-pub fn parse(input: &str) -> Vec<Instruction> {
-    let mut last_line_option: Option<&str> = None;
-    let mut lines = Vec::new();
-    for line in input.lines() {
-        let ins_type = if line.trim().is_empty() {
-            InsType::Noop
-        } else if check_end_rhyme(last_line_option, line) {
-            InsType::ConditionalPush {
-                prev_syllables: count_syllables(last_line_option.unwrap()),
-                cur_syllables: count_syllables(line),
+/// [`count_syllables`] word by word, for finding out why a line has the
+/// syllables it does. the syllables of the words add up to the count
+pub fn count_syllables_detailed(input: &str) -> Vec<WordSyllables> {
+    words(input)
+        .map(|(span, word)| {
+            let word = lowercase(word).into_owned();
+            let (latin, unknown) = split_unknown_script(&word, UnknownScriptSyllables::default());
+            let (syllables, source) = word_syllables(&latin);
+            let syllables = syllables + unknown;
+            WordSyllables {
+                word,
+                span,
+                syllables,
+                source,
             }
-        } else if line.contains('/') {
-            InsType::ConditionalGoto(count_syllables(line))
-        } else if INT_CAP_RE.is_match(line) {
-            InsType::Negate
-        } else if CAP_RE.is_match(line) {
-            InsType::Multiply
-        } else if SIMILIE_RE.is_match(line) {
-            InsType::Add
-        } else if line.contains('?') {
-            InsType::PrintChar
-        } else if line.contains('.') {
-            InsType::PrintValue
-        } else if line.contains(',') {
-            InsType::Pop
-        } else if line.contains('-') {
-            InsType::Push
-        } else if has_alliteration(line) {
-            InsType::Goto
-        } else {
-            InsType::Store(count_syllables(line))
-        };
-        let register = if WS_START_RE.is_match(line) {
-            Register::Register1
-        } else {
-            Register::Register0
+        })
+        .collect()
+}
+
+#[cfg(test)]
+thread_local! {
+    // how many lines have had their syllables counted, so tests can check
+    // that none is counted twice
+    static LINES_COUNTED: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+/// [`count_syllables`] of a line that's already lowercase
+fn count_lowercase_syllables(lower: &str, options: &ParseOptions) -> u32 {
+    #[cfg(test)]
+    LINES_COUNTED.with(|counted| counted.set(counted.get() + 1));
+    let syllables = match &options.syllable_counter {
+        Some(counter) => counter.count_line(lower),
+        None => lower
+            .split(' ')
+            .filter(|s| !s.is_empty())
+            .map(|word| count_word_syllables_with(word, options))
+            .sum(),
+    };
+    // no line is long enough to have more than `u32::MAX`, but if one did it
+    // would saturate
+    u32::try_from(syllables).unwrap_or(u32::MAX)
+}
+
+/// [`parse`], but failing instead of panicking if the dictionary can't be
+/// loaded
+pub fn try_parse(input: &str) -> Result<Vec<Instruction>> {
+    dictionary::load()?;
+    Ok(parse(input))
+}
+
+/// how many words in `input` have their syllables approximated
+#[cfg(feature = "tracing")]
+fn dictionary_misses(input: &str) -> usize {
+    input
+        .lines()
+        .flat_map(|line| line.split(' '))
+        .filter(|word| !word.is_empty() && !in_dictionary(&word.to_lowercase()))
+        .count()
+}
+
+pub fn parse(input: &str) -> Vec<Instruction> {
+    parse_with_dialect(input, Dialect::Standard)
+}
+
+pub fn parse_with_dialect(input: &str, dialect: Dialect) -> Vec<Instruction> {
+    parse_with_options(
+        input,
+        &ParseOptions {
+            dialect,
+            ..ParseOptions::default()
+        },
+    )
+}
+
+pub fn parse_with_options(input: &str, options: &ParseOptions) -> Vec<Instruction> {
+    #[cfg(feature = "tracing")]
+    let span = tracing::info_span!(
+        "parse",
+        lines = input.lines().count(),
+        dictionary_misses = tracing::field::Empty
+    )
+    .entered();
+    #[cfg(feature = "tracing")]
+    if !span.is_disabled() {
+        span.record("dictionary_misses", dictionary_misses(input));
+    }
+
+    #[cfg(feature = "parallel")]
+    let ast = if input.len() >= PARALLEL_THRESHOLD {
+        parse_parallel(input, options)
+    } else {
+        parse_sequential(input, options)
+    };
+    #[cfg(not(feature = "parallel"))]
+    let ast = parse_sequential(input, options);
+    if let Some(path) = &options.syllable_cache_path {
+        // a cache that can't be written only costs the words being counted
+        // again
+        let _ = dictionary::flush_syllable_cache(path);
+    }
+    ast
+}
+
+/// inputs at least this many bytes long are parsed in parallel with the
+/// `parallel` feature
+#[cfg(feature = "parallel")]
+const PARALLEL_THRESHOLD: usize = 256 * 1024;
+
+/// a line in lowercase, with its syllables once they've been counted
+#[derive(Default)]
+struct Lowered {
+    text: String,
+    syllables: Option<u32>,
+}
+
+impl Lowered {
+    /// counted the first time they're needed, which is either when the line
+    /// is parsed or when the next line rhymes with it
+    fn syllables(&mut self, options: &ParseOptions) -> u32 {
+        if let Some(syllables) = self.syllables {
+            return syllables;
+        }
+        let syllables = count_lowercase_syllables(&self.text, options);
+        self.syllables = Some(syllables);
+        syllables
+    }
+}
+
+/// how many distinct lines are kept by [`Lines`] before it starts over
+const SEEN_LINES: usize = 256;
+
+/// what's been worked out about a line that's already been parsed once
+struct Seen {
+    line: Arc<str>,
+    register: Register,
+    /// what it parses to when it doesn't rhyme with the line before it,
+    /// which only depends on the line itself
+    unrhymed: Option<InsType>,
+    syllables: Option<u32>,
+}
+
+/// the line being parsed and the one before it, kept between lines so
+/// lowercasing them doesn't allocate and no line is counted twice. generated
+/// poems often repeat lines, so every line is also kept by its text, and a
+/// repeat reuses its text, instruction and syllables
+struct Lines<'a> {
+    options: &'a ParseOptions,
+    /// the custom rules in the order they're checked, none unless they're
+    /// used
+    custom_rules: Vec<&'a CustomRule>,
+    previous: Option<Lowered>,
+    current: Lowered,
+    seen: HashMap<&'a str, Seen>,
+}
+
+impl<'a> Lines<'a> {
+    fn new(options: &'a ParseOptions) -> Lines<'a> {
+        let mut custom_rules: Vec<_> = match options.dialect {
+            Dialect::Extended => options.custom_rules.iter().collect(),
+            Dialect::Standard => Vec::new(),
         };
-        let ins = Instruction {
-            instruction: ins_type,
-            register,
-            line: line.trim_end().to_string(),
+        custom_rules.sort_by_key(|rule| rule.priority);
+        Lines {
+            options,
+            custom_rules,
+            previous: None,
+            current: Lowered::default(),
+            seen: HashMap::new(),
+        }
+    }
+
+    /// parse `line`, then keep it as the previous line
+    fn parse(&mut self, line: &'a str) -> Instruction {
+        lowercase_into(&mut self.current.text, line);
+        // remembering every line of a poem that doesn't repeat them costs
+        // more than it saves, so only recent ones are kept
+        if self.seen.len() == SEEN_LINES && !self.seen.contains_key(line) {
+            self.seen.clear();
+        }
+        let options = self.options;
+        let seen = self.seen.entry(line).or_insert_with(|| Seen {
+            line: line.into(),
+            register: register(line, options),
+            unrhymed: None,
+            syllables: None,
+        });
+        self.current.syllables = seen.syllables;
+        // whether it rhymes depends on the line before, so that's checked
+        // every time
+        let ins_type = match rhyme(
+            line,
+            &mut self.current,
+            self.previous.as_mut(),
+            self.options,
+        ) {
+            Some(ins_type) => ins_type,
+            None => match seen.unrhymed {
+                Some(ins_type) => ins_type,
+                None => *seen.unrhymed.insert(classify(
+                    line,
+                    &mut self.current,
+                    seen.register,
+                    self.options,
+                    &self.custom_rules,
+                )),
+            },
         };
-        lines.push(ins);
-        last_line_option = Some(line);
+        seen.syllables = self.current.syllables;
+        let ins = Instruction::new(ins_type, seen.register, Arc::clone(&seen.line));
+        let previous = self.previous.get_or_insert_with(Lowered::default);
+        std::mem::swap(previous, &mut self.current);
+        ins
+    }
+}
+
+fn parse_sequential(input: &str, options: &ParseOptions) -> Vec<Instruction> {
+    let mut lines = Lines::new(options);
+    input.lines().map(|line| lines.parse(line)).collect()
+}
+
+/// a line only depends on the line before it, so the lines are parsed in
+/// chunks and the first line of each chunk looks back at the end of the one
+/// before
+#[cfg(feature = "parallel")]
+fn parse_parallel(input: &str, options: &ParseOptions) -> Vec<Instruction> {
+    use rayon::prelude::*;
+
+    const CHUNK_LINES: usize = 4096;
+    // loaded once up front rather than by whichever thread gets there first
+    dictionary();
+    let lines: Vec<&str> = input.lines().collect();
+    let chunks: Vec<Vec<Instruction>> = lines
+        .par_chunks(CHUNK_LINES)
+        .enumerate()
+        .map(|(chunk, chunk_lines)| {
+            let start = chunk * CHUNK_LINES;
+            let mut chunk = Lines::new(options);
+            if let Some(previous) = start.checked_sub(1) {
+                let mut text = String::new();
+                lowercase_into(&mut text, lines[previous]);
+                // counted if the chunk's first line rhymes with it
+                chunk.previous = Some(Lowered {
+                    text,
+                    syllables: None,
+                });
+            }
+            chunk_lines.iter().map(|line| chunk.parse(line)).collect()
+        })
+        .collect();
+    chunks.concat()
+}
+
+/// the conditional push `line` compiles to if it rhymes with `last`, the
+/// line before it. `lower` is the line in lowercase
+fn rhyme(
+    line: &str,
+    lower: &mut Lowered,
+    last: Option<&mut Lowered>,
+    options: &ParseOptions,
+) -> Option<InsType> {
+    let last = last?;
+    if options.force_heuristics
+        || line.trim().is_empty()
+        || !check_end_rhyme(Some(&last.text), &lower.text)
+    {
+        return None;
+    }
+    Some(InsType::ConditionalPush {
+        prev_syllables: last.syllables(options),
+        cur_syllables: lower.syllables(options),
+    })
+}
+
+/// a built-in rule for what a line compiles to, `None` if it doesn't apply
+/// to the line. `lower` is the line in lowercase
+type BuiltinRule = fn(&str, &mut Lowered, &ParseOptions) -> Option<InsType>;
+
+fn extended(options: &ParseOptions) -> bool {
+    options.dialect == Dialect::Extended
+}
+
+/// the built-in rules in the order they're checked, the index of each being
+/// the [`CustomRule::priority`] of a custom rule checked just before it
+const BUILTIN_RULES: [BuiltinRule; 15] = [
+    |line, _, _| line.trim().is_empty().then_some(InsType::Noop),
+    |line, lower, options| {
+        line.contains('/')
+            .then(|| InsType::ConditionalGoto(lower.syllables(options)))
+    },
+    |line, _, options| (extended(options) && line.contains(';')).then_some(InsType::Subtract),
+    |line, _, options| (extended(options) && line.contains(':')).then_some(InsType::Divide),
+    |line, _, options| (extended(options) && line.contains('!')).then_some(InsType::ReadChar),
+    |line, _, options| {
+        (extended(options) && line.contains('[') && line.contains(']'))
+            .then_some(InsType::TapeStore)
+    },
+    |line, _, options| {
+        (extended(options) && (line.contains('[') || line.contains(']')))
+            .then_some(InsType::TapeLoad)
+    },
+    |line, _, _| patterns::internal_capital(line).map(|_| InsType::Negate),
+    |line, _, _| patterns::leading_capital(line).map(|_| InsType::Multiply),
+    |line, _, _| patterns::simile(line).map(|_| InsType::Add),
+    |line, _, _| line.contains('?').then_some(InsType::PrintChar),
+    |line, _, _| line.contains('.').then_some(InsType::PrintValue),
+    |line, _, _| line.contains(',').then_some(InsType::Pop),
+    |line, _, _| line.contains('-').then_some(InsType::Push),
+    |_, lower, options| {
+        has_alliteration(&lower.text, options.alliteration).then_some(InsType::Goto)
+    },
+];
+
+/// the instruction `line` compiles to when it doesn't rhyme with the line
+/// before it: what the first rule that applies to it says, or a store of
+/// its syllables. `lower` is the line in lowercase, and `custom_rules` are
+/// sorted by priority
+fn classify(
+    line: &str,
+    lower: &mut Lowered,
+    register: Register,
+    options: &ParseOptions,
+    custom_rules: &[&CustomRule],
+) -> InsType {
+    let mut custom_rules = custom_rules.iter().peekable();
+    // the rules up to priority `at` that haven't been checked yet
+    let mut custom = |lower: &mut Lowered, at: usize| {
+        while let Some(rule) = custom_rules.next_if(|rule| rule.priority <= at) {
+            let features = LineFeatures {
+                syllables: lower.syllables(options),
+                register,
+            };
+            if (rule.matcher)(&features, line) {
+                return Some(rule.instruction.build(&features));
+            }
+        }
+        None
+    };
+    for (priority, rule) in BUILTIN_RULES.iter().enumerate() {
+        if let Some(ins_type) = custom(lower, priority).or_else(|| rule(line, lower, options)) {
+            return ins_type;
+        }
+    }
+    custom(lower, usize::MAX).unwrap_or_else(|| InsType::Store(lower.syllables(options)))
+}
+
+/// indented lines use the second register. in the extended dialect it's by
+/// how many columns they're indented, counting a tab as 4
+fn register(line: &str, options: &ParseOptions) -> Register {
+    let columns = options.indent(line);
+    if columns == 0 || columns < options.register1_min_indent {
+        return Register::Register0;
+    }
+    if options.dialect == Dialect::Standard {
+        return Register::Register1;
+    }
+    match columns {
+        0..=3 => Register::Register1,
+        4..=7 => Register::Register2,
+        _ => Register::Register3,
     }
-    lines
 }
 
 #[cfg(test)]
+// the tests still build instructions out of their fields, which keeps the
+// deprecated field working until it goes private
+#[allow(deprecated)]
 mod tests {
     use super::*;
     use pretty_assertions::assert_eq;
 
+    #[test]
+    fn instruction_size() {
+        assert_eq!(std::mem::size_of::<InsType>(), 12);
+        assert!(std::mem::size_of::<Instruction>() <= Instruction::MAX_SIZE);
+    }
+
     #[test]
     fn has_alliteration() {
-        assert!(super::has_alliteration("she sells sea shells"));
-        assert!(!super::has_alliteration("no alliteration here"));
-        assert!(!super::has_alliteration("one"));
-        assert!(!super::has_alliteration(""));
+        let has_alliteration = |input| super::has_alliteration(input, AllitOptions::default());
+        assert!(has_alliteration("she sells sea shells"));
+        assert!(!has_alliteration("no alliteration here"));
+        assert!(!has_alliteration("one"));
+        assert!(!has_alliteration(""));
+        assert!(!has_alliteration("she quickly sells"));
+    }
+
+    #[test]
+    fn alliteration_window() {
+        let options = AllitOptions {
+            window: 2,
+            ..AllitOptions::default()
+        };
+        assert!(super::has_alliteration("she quickly sells", options));
+        assert!(super::has_alliteration("she sells", options));
+        assert!(!super::has_alliteration("she quickly now sells", options));
+        assert_eq!(
+            alliteration("She quickly Sells", options),
+            Some(vec![0..3, 12..17])
+        );
+
+        let parse_options = ParseOptions {
+            alliteration: options,
+            ..ParseOptions::default()
+        };
+        let ast = parse_with_options("she quickly sells", &parse_options);
+        assert_eq!(ast[0].instruction, InsType::Goto);
+        assert_eq!(parse("she quickly sells")[0].instruction, InsType::Store(4));
     }
 
+    #[test]
+    fn alliteration_run() {
+        let options = AllitOptions {
+            min_run: 3,
+            ..AllitOptions::default()
+        };
+        assert!(!super::has_alliteration("she sells", options));
+        assert!(!super::has_alliteration("she sells and sees", options));
+        assert!(super::has_alliteration("now she sells sea shells", options));
+        assert_eq!(
+            alliteration("big bad bears", options),
+            Some(vec![0..3, 4..7, 8..13])
+        );
+        // a wider window lets the run skip words
+        let options = AllitOptions {
+            window: 2,
+            ..options
+        };
+        assert!(super::has_alliteration("she sells and sees", options));
+    }
+
+    #[test]
+    fn init_dictionary() {
+        let error = |source| match init_dictionary_from_str(source) {
+            Err(crate::Error::Dictionary(err)) => err,
+            other => panic!("expected a DictionaryError, got {:?}", other),
+        };
+        assert_eq!(
+            error("poem  P OW9 AH0 M"),
+            DictionaryError::Invalid(
+                "rule parse error: Expected stress marker '0', '1', or '2', got 9".to_string()
+            )
+        );
+        count_syllables("lovely");
+        assert_eq!(
+            error("poem  P OW1 AH0 M\n"),
+            DictionaryError::AlreadyInitialized
+        );
+    }
+
+    #[cfg(feature = "bundled-dictionary")]
     #[test]
     fn syllable_counting() {
         let exact = count_syllables("antidisestablishmentarianism");
@@ -198,6 +1456,257 @@ mod tests {
         assert_eq!(misc, 5);
     }
 
+    #[test]
+    fn unknown_scripts() {
+        let per_character = ParseOptions {
+            unknown_script_syllables: UnknownScriptSyllables::PerCharacter,
+            ..ParseOptions::default()
+        };
+        let per_run = ParseOptions {
+            unknown_script_syllables: UnknownScriptSyllables::Custom(|_| 7),
+            ..ParseOptions::default()
+        };
+        let count = |word, options: &ParseOptions| count_syllables_with_options(word, options);
+        for (word, zero, per_character_count, per_run_count) in [
+            ("こんにちは", 0, 5, 7),
+            ("日本語", 0, 3, 7),
+            ("🌸", 0, 1, 7),
+            // the joiners between the emoji aren't counted
+            ("👨\u{200d}👩\u{200d}👧", 0, 3, 21),
+            // counted as if the other characters weren't there
+            ("hello世界", 2, 4, 9),
+            ("x🌸y", 1, 2, 8),
+            ("Ωmega", 2, 3, 9),
+            // accented latin letters aren't another script, though only
+            // their plain vowels are counted
+            ("naïve", 2, 2, 2),
+            ("123", 0, 0, 0),
+        ] {
+            assert_eq!(count(word, &ParseOptions::default()), zero, "{}", word);
+            assert_eq!(count(word, &per_character), per_character_count, "{}", word);
+            assert_eq!(count(word, &per_run), per_run_count, "{}", word);
+        }
+        assert_eq!(count_syllables_detailed("hello世界")[0].syllables, 2);
+
+        // a line of nothing else is a store of however many it has
+        let poem = "こんにちは\n🌸 🍣";
+        let stores = |options| -> Vec<InsType> {
+            parse_with_options(poem, &options)
+                .into_iter()
+                .map(|ins| ins.instruction)
+                .collect()
+        };
+        assert_eq!(
+            stores(ParseOptions::default()),
+            vec![InsType::Store(0), InsType::Store(0)]
+        );
+        assert_eq!(
+            stores(per_character),
+            vec![InsType::Store(5), InsType::Store(2)]
+        );
+    }
+
+    /// the runs of vowels in a word, which is close enough for Spanish
+    #[cfg(feature = "bundled-dictionary")]
+    fn spanish_syllables(word: &str) -> usize {
+        let vowel = |c: char| "aeiouáéíóúü".contains(c);
+        word.chars()
+            .zip(iter::once(' ').chain(word.chars()))
+            .filter(|&(c, before)| vowel(c) && !vowel(before))
+            .count()
+    }
+
+    #[cfg(feature = "bundled-dictionary")]
+    #[test]
+    fn syllable_counter() {
+        let spanish = ParseOptions {
+            syllable_counter: Some(CustomCounter::new(spanish_syllables)),
+            ..ParseOptions::default()
+        };
+        // the dictionary doesn't have canciones, and its vowel clusters make
+        // it 4
+        assert_eq!(count_syllables("canciones"), 4);
+        assert_eq!(count_syllables_with_options("Canciones", &spanish), 3);
+
+        // the cond-push rhymes by the dictionary, but carries the counter's
+        // syllables like the stores and cond-gotos
+        let poem = "una canciones de cat\nla canciones del hat\nsin canciones\nmis canciones / tu";
+        let instructions: Vec<InsType> = parse_with_options(poem, &spanish)
+            .into_iter()
+            .map(|ins| ins.instruction)
+            .collect();
+        assert_eq!(
+            instructions,
+            vec![
+                InsType::Store(7),
+                InsType::ConditionalPush {
+                    prev_syllables: 7,
+                    cur_syllables: 6
+                },
+                InsType::Store(4),
+                InsType::ConditionalGoto(5),
+            ]
+        );
+        assert_eq!(parse(poem)[0].instruction, InsType::Store(8));
+
+        // counting whole lines, like a counter that joins vowels across
+        // words would
+        struct Lines;
+        impl SyllableCounter for Lines {
+            fn count_word(&self, _: &str) -> usize {
+                unreachable!("only lines are counted")
+            }
+
+            fn count_line(&self, line: &str) -> usize {
+                line.len()
+            }
+        }
+        let lines = ParseOptions {
+            syllable_counter: Some(CustomCounter::new(Lines)),
+            ..ParseOptions::default()
+        };
+        assert_eq!(
+            parse_with_options("abc de", &lines)[0].instruction,
+            InsType::Store(6)
+        );
+
+        // options are only the same with the same counter
+        assert_eq!(spanish.clone(), spanish);
+        let other = ParseOptions {
+            syllable_counter: Some(CustomCounter::new(spanish_syllables)),
+            ..ParseOptions::default()
+        };
+        assert_ne!(other, spanish);
+    }
+
+    #[test]
+    fn custom_rules() {
+        let tilde = |priority| {
+            CustomRule::new(
+                priority,
+                |_, line| line.contains('~'),
+                CustomInsFactory::Fixed(InsType::Negate),
+            )
+        };
+        let parse = |poem: &str, dialect, custom_rules| -> Vec<InsType> {
+            let options = ParseOptions {
+                dialect,
+                custom_rules,
+                ..ParseOptions::default()
+            };
+            parse_with_options(poem, &options)
+                .into_iter()
+                .map(|ins| ins.instruction)
+                .collect()
+        };
+        let poem = "a ~ line, with a comma\nthe ~ sea and sky\n";
+
+        // before the pop, and before the store the second line would be
+        assert_eq!(
+            parse(poem, Dialect::Extended, vec![tilde(12)]),
+            vec![InsType::Negate, InsType::Negate]
+        );
+        // after it, so only the line without a comma
+        assert_eq!(
+            parse(poem, Dialect::Extended, vec![tilde(13)]),
+            vec![InsType::Pop, InsType::Negate]
+        );
+        // blank lines are noops unless a rule comes before that
+        let any = |priority| {
+            CustomRule::new(
+                priority,
+                |_, _| true,
+                CustomInsFactory::Fixed(InsType::Negate),
+            )
+        };
+        assert_eq!(
+            parse("  ", Dialect::Extended, vec![any(1)]),
+            vec![InsType::Noop]
+        );
+        assert_eq!(
+            parse("  ", Dialect::Extended, vec![any(0)]),
+            vec![InsType::Negate]
+        );
+        // not in the standard dialect
+        assert_eq!(
+            parse(poem, Dialect::Standard, vec![tilde(0)]),
+            vec![InsType::Pop, InsType::Store(4)]
+        );
+
+        // the first of two rules of the same priority wins, and a rule can
+        // build its instruction from the line
+        let double = CustomRule::new(
+            99,
+            |features, _| features.syllables > 2,
+            CustomInsFactory::Build(|features| InsType::Store(features.syllables * 2)),
+        );
+        assert_eq!(
+            parse(poem, Dialect::Extended, vec![double.clone(), tilde(99)]),
+            vec![InsType::Pop, InsType::Store(8)]
+        );
+        assert_eq!(
+            parse(poem, Dialect::Extended, vec![tilde(99), double]),
+            vec![InsType::Pop, InsType::Negate]
+        );
+    }
+
+    #[cfg(feature = "bundled-dictionary")]
+    #[test]
+    fn detailed_syllables() {
+        let dictionary = |pronunciations: &[usize], chosen| SyllableSource::Dictionary {
+            pronunciations: pronunciations.to_vec(),
+            chosen,
+        };
+        let words = count_syllables_detailed("the  Record of every beloved zorblax");
+        let breakdown: Vec<_> = words
+            .iter()
+            .map(|word| {
+                (
+                    word.word.as_str(),
+                    word.span.clone(),
+                    word.syllables,
+                    &word.source,
+                )
+            })
+            .collect();
+        assert_eq!(
+            breakdown,
+            vec![
+                ("the", 0..3, 1, &dictionary(&[1, 1, 1], 0)),
+                ("record", 5..11, 2, &dictionary(&[2, 2, 2], 0)),
+                ("of", 12..14, 1, &dictionary(&[1], 0)),
+                ("every", 15..20, 3, &dictionary(&[3, 2], 0)),
+                // the second way of saying it has the extra syllable
+                ("beloved", 21..28, 3, &dictionary(&[2, 3], 1)),
+                ("zorblax", 29..36, 2, &SyllableSource::Approximated),
+            ]
+        );
+        let total: usize = words.iter().map(|word| word.syllables).sum();
+        assert_eq!(
+            total,
+            count_syllables("the  Record of every beloved zorblax")
+        );
+        assert_eq!(count_syllables_detailed(" "), vec![]);
+    }
+
+    #[cfg(feature = "bundled-dictionary")]
+    #[test]
+    fn typographic_quotes() {
+        let couplet = parse("he thrust every elf\n    far back on the “shelf”");
+        assert!(matches!(
+            couplet[1].instruction,
+            InsType::ConditionalPush { .. }
+        ));
+        assert_eq!(parse("‘she’ ‘sells’")[0].instruction, InsType::Goto);
+        // the quotes they start with aren't letters
+        assert_eq!(parse("‘a’ ‘big’")[0].instruction, InsType::Store(2));
+        assert_eq!(count_syllables("don’t “everything”"), 4);
+        assert!(in_dictionary("don’t"));
+        assert_eq!(normalize_quotes("“rock’n’roll”"), "rock'n'roll");
+        assert_eq!(normalize_quotes("“”"), "");
+    }
+
+    #[cfg(feature = "bundled-dictionary")]
     #[test]
     fn cond_push() {
         let source = r#"
@@ -212,7 +1721,7 @@ he thrust every elf
             Instruction {
                 instruction: InsType::Goto,
                 register: Register::Register0,
-                line: split.next().unwrap().to_string(),
+                line: split.next().unwrap().into(),
             },
             Instruction {
                 instruction: InsType::ConditionalPush {
@@ -220,7 +1729,7 @@ he thrust every elf
                     cur_syllables: 5,
                 },
                 register: Register::Register1,
-                line: split.next().unwrap().to_string(),
+                line: split.next().unwrap().into(),
             },
         ];
         assert_eq!(tokens, parsed);
@@ -234,7 +1743,7 @@ he thrust every elf
         let target = vec![Instruction {
             instruction: InsType::Negate,
             register: Register::Register0,
-            line: source.to_string(),
+            line: source.into(),
         }];
         assert_eq!(tokens, target);
     }
@@ -246,7 +1755,7 @@ he thrust every elf
         let target = vec![Instruction {
             instruction: InsType::Multiply,
             register: Register::Register1,
-            line: source.to_string(),
+            line: source.into(),
         }];
         assert_eq!(tokens, target);
     }
@@ -265,17 +1774,18 @@ fish are like trout
             Instruction {
                 instruction: InsType::Add,
                 register: Register::Register0,
-                line: lines.next().unwrap().to_string(),
+                line: lines.next().unwrap().into(),
             },
             Instruction {
                 instruction: InsType::Add,
                 register: Register::Register1,
-                line: lines.next().unwrap().to_string(),
+                line: lines.next().unwrap().into(),
             },
         ];
         assert_eq!(tokens, target);
     }
 
+    #[cfg(feature = "bundled-dictionary")]
     #[test]
     fn print_char() {
         let source = r#"
@@ -290,12 +1800,12 @@ printing?
             Instruction {
                 instruction: InsType::Store(21),
                 register: Register::Register0,
-                line: lines.next().unwrap().to_string(),
+                line: lines.next().unwrap().into(),
             },
             Instruction {
                 instruction: InsType::PrintChar,
                 register: Register::Register0,
-                line: lines.next().unwrap().to_string(),
+                line: lines.next().unwrap().into(),
             },
         ];
         assert_eq!(tokens, target)
@@ -315,12 +1825,12 @@ print. it.
             Instruction {
                 instruction: InsType::Store(1),
                 register: Register::Register0,
-                line: lines.next().unwrap().to_string(),
+                line: lines.next().unwrap().into(),
             },
             Instruction {
                 instruction: InsType::PrintValue,
                 register: Register::Register0,
-                line: lines.next().unwrap().to_string(),
+                line: lines.next().unwrap().into(),
             },
         ];
         assert_eq!(tokens, target);
@@ -333,7 +1843,7 @@ print. it.
         let target = vec![Instruction {
             instruction: InsType::Pop,
             register: Register::Register0,
-            line: source.to_string(),
+            line: source.into(),
         }];
         assert_eq!(tokens, target);
     }
@@ -345,11 +1855,12 @@ print. it.
         let target = vec![Instruction {
             instruction: InsType::Push,
             register: Register::Register0,
-            line: source.to_string(),
+            line: source.into(),
         }];
         assert_eq!(tokens, target);
     }
 
+    #[cfg(feature = "bundled-dictionary")]
     #[test]
     fn store() {
         let source = "somebody once";
@@ -357,11 +1868,12 @@ print. it.
         let target = vec![Instruction {
             instruction: InsType::Store(4),
             register: Register::Register0,
-            line: source.to_string(),
+            line: source.into(),
         }];
         assert_eq!(tokens, target);
     }
 
+    #[cfg(feature = "bundled-dictionary")]
     #[test]
     fn conditional_push() {
         let source = r#"
@@ -376,7 +1888,7 @@ the world was gonna roll me
             Instruction {
                 instruction: InsType::Store(6),
                 register: Register::Register0,
-                line: lines.next().unwrap().to_string(),
+                line: lines.next().unwrap().into(),
             },
             Instruction {
                 instruction: InsType::ConditionalPush {
@@ -384,13 +1896,126 @@ the world was gonna roll me
                     cur_syllables: 7,
                 },
                 register: Register::Register0,
-                line: lines.next().unwrap().to_string(),
+                line: lines.next().unwrap().into(),
             },
         ];
 
         assert_eq!(tokens, target);
     }
 
+    #[cfg(feature = "bundled-dictionary")]
+    #[test]
+    fn rhyming_chain() {
+        let words = [
+            "cat", "hat", "bat", "mat", "rat", "sat", "flat", "that", "pat", "vat",
+        ];
+        let source: Vec<String> = (0..20)
+            .map(|i| format!("{} wonderful {}", "and ".repeat(i % 3), words[i % 10]))
+            .collect();
+        let source = source.join("\n");
+
+        LINES_COUNTED.with(|counted| counted.set(0));
+        let tokens = parse(&source);
+        // each line is counted once, and its count reused by the next
+        assert_eq!(LINES_COUNTED.with(|counted| counted.get()), 20);
+        assert_eq!(tokens, reference_parse(&source));
+
+        let syllables: Vec<u32> = source
+            .lines()
+            .map(|line| count_syllables(line) as u32)
+            .collect();
+        assert_eq!(tokens[0].instruction, InsType::Store(syllables[0]));
+        for (i, token) in tokens.iter().enumerate().skip(1) {
+            assert_eq!(
+                token.instruction,
+                InsType::ConditionalPush {
+                    prev_syllables: syllables[i - 1],
+                    cur_syllables: syllables[i],
+                }
+            );
+        }
+    }
+
+    #[cfg(feature = "bundled-dictionary")]
+    #[test]
+    fn extended_dialect() {
+        let cases = [
+            ("fish; whales", InsType::Store(2), InsType::Subtract),
+            ("ratio: sea", InsType::Store(4), InsType::Divide),
+            ("Fish; whales", InsType::Multiply, InsType::Subtract),
+            ("fish: whales; sea", InsType::Store(4), InsType::Subtract),
+            ("[fish]", InsType::Store(1), InsType::TapeStore),
+            ("] fish", InsType::Store(1), InsType::TapeLoad),
+            ("[Fish", InsType::Multiply, InsType::TapeLoad),
+            (
+                "fish / whales; sea",
+                InsType::ConditionalGoto(4),
+                InsType::ConditionalGoto(4),
+            ),
+        ];
+        for &(line, standard, extended) in &cases {
+            assert_eq!(parse(line)[0].instruction, standard, "{}", line);
+            assert_eq!(
+                parse_with_dialect(line, Dialect::Extended)[0].instruction,
+                extended,
+                "{}",
+                line
+            );
+        }
+        // a rhyme still wins
+        let rhymed = parse_with_dialect("the cat\n; a hat", Dialect::Extended);
+        assert_eq!(
+            rhymed[1].instruction,
+            InsType::ConditionalPush {
+                prev_syllables: 2,
+                cur_syllables: 2
+            }
+        );
+    }
+
+    #[test]
+    fn extended_registers() {
+        let source = "fish\n fish\n   fish\n    fish\n       fish\n        fish\n\tfish\n \t fish";
+        let registers = |dialect| -> Vec<u8> {
+            parse_with_dialect(source, dialect)
+                .iter()
+                .map(|ins| ins.register.into())
+                .collect()
+        };
+        assert_eq!(registers(Dialect::Standard), vec![0, 1, 1, 1, 1, 1, 1, 1]);
+        assert_eq!(registers(Dialect::Extended), vec![0, 1, 1, 2, 2, 3, 2, 2]);
+    }
+
+    #[test]
+    fn register1_min_indent() {
+        let source = "fish\n fish\n  fish\n\tfish\n \tfish";
+        let registers = |options: &ParseOptions| -> Vec<u8> {
+            parse_with_options(source, options)
+                .iter()
+                .map(|ins| ins.register.into())
+                .collect()
+        };
+        let mut options = ParseOptions {
+            register1_min_indent: 2,
+            ..ParseOptions::default()
+        };
+        assert_eq!(registers(&options), vec![0, 0, 1, 0, 1]);
+        options.indent_tab_width = Some(2);
+        assert_eq!(registers(&options), vec![0, 0, 1, 1, 1]);
+        options.dialect = Dialect::Extended;
+        options.register1_min_indent = 3;
+        assert_eq!(registers(&options), vec![0, 0, 0, 0, 1]);
+
+        // their indents are all at least 2
+        for poem in bundled_poems() {
+            let options = ParseOptions {
+                register1_min_indent: 2,
+                ..ParseOptions::default()
+            };
+            assert_eq!(parse_with_options(&poem, &options), parse(&poem));
+        }
+    }
+
     #[test]
     fn goto() {
         let source = "sells sea shells";
@@ -398,7 +2023,7 @@ the world was gonna roll me
         let target = vec![Instruction {
             instruction: InsType::Goto,
             register: Register::Register0,
-            line: source.to_string(),
+            line: source.into(),
         }];
 
         assert_eq!(tokens, target);
@@ -412,12 +2037,13 @@ the world was gonna roll me
         let target = vec![Instruction {
             instruction: InsType::Noop,
             register: Register::Register0,
-            line: "".to_string(),
+            line: "".into(),
         }];
 
         assert_eq!(tokens, target);
     }
 
+    #[cfg(feature = "bundled-dictionary")]
     #[test]
     fn registers() {
         let source = r#"
@@ -431,15 +2057,210 @@ register zero
             Instruction {
                 instruction: InsType::Store(5),
                 register: Register::Register0,
-                line: lines.next().unwrap().to_string(),
+                line: lines.next().unwrap().into(),
             },
             Instruction {
                 instruction: InsType::Store(4),
                 register: Register::Register1,
-                line: lines.next().unwrap().to_string(),
+                line: lines.next().unwrap().into(),
             },
         ];
 
         assert_eq!(tokens, target);
     }
+
+    /// the parser before lines were lowercased once into shared buffers,
+    /// lowercasing each word as it's looked at
+    fn reference_parse(input: &str) -> Vec<Instruction> {
+        // before the alliteration rule could be changed
+        fn adjacent_alliteration(line: &str) -> bool {
+            let words: Vec<String> = line
+                .split(' ')
+                .filter(|s| !s.is_empty())
+                .map(str::to_lowercase)
+                .collect();
+            words.windows(2).any(|pair| {
+                pair[0]
+                    .chars()
+                    .next()
+                    .is_some_and(|c| pair[1].starts_with(c))
+            })
+        }
+        fn last_word(line: &str) -> Option<&str> {
+            line.split(' ').rev().find(|s| !s.is_empty())
+        }
+        let rhymes = |last_line: &str, line: &str| match (last_word(last_line), last_word(line)) {
+            (Some(one), Some(two)) => dictionary()
+                .rhymes(&one.to_lowercase(), &two.to_lowercase())
+                .unwrap_or(false),
+            _ => false,
+        };
+        let syllables = |line: &str| -> u32 {
+            line.split(' ')
+                .filter(|s| !s.is_empty())
+                .map(|w| count_word_syllables(&w.to_lowercase()) as u32)
+                .sum()
+        };
+        let mut last_line_option: Option<&str> = None;
+        let mut lines = Vec::new();
+        for line in input.lines() {
+            let ins_type = if line.trim().is_empty() {
+                InsType::Noop
+            } else if last_line_option.is_some_and(|last_line| rhymes(last_line, line)) {
+                InsType::ConditionalPush {
+                    prev_syllables: syllables(last_line_option.unwrap()),
+                    cur_syllables: syllables(line),
+                }
+            } else if line.contains('/') {
+                InsType::ConditionalGoto(syllables(line))
+            } else if patterns::internal_capital(line).is_some() {
+                InsType::Negate
+            } else if patterns::leading_capital(line).is_some() {
+                InsType::Multiply
+            } else if patterns::simile(line).is_some() {
+                InsType::Add
+            } else if line.contains('?') {
+                InsType::PrintChar
+            } else if line.contains('.') {
+                InsType::PrintValue
+            } else if line.contains(',') {
+                InsType::Pop
+            } else if line.contains('-') {
+                InsType::Push
+            } else if adjacent_alliteration(line) {
+                InsType::Goto
+            } else {
+                InsType::Store(syllables(line))
+            };
+            let register = if line.starts_with(char::is_whitespace) {
+                Register::Register1
+            } else {
+                Register::Register0
+            };
+            lines.push(Instruction::new(ins_type, register, line));
+            last_line_option = Some(line);
+        }
+        lines
+    }
+
+    #[cfg(feature = "bundled-dictionary")]
+    #[test]
+    fn repeated_lines() {
+        let lines = [
+            "lovely poem",
+            "  a cat sat",
+            "on the mat",
+            "print it.",
+            "sells sea shells",
+        ];
+        // the lines in a different order each time, so the same line both
+        // rhymes with the one before it and doesn't
+        let source: Vec<&str> = (0..10_000)
+            .flat_map(|i| (0..5).map(move |j| lines[(j * (1 + i % 4) + i) % 5]))
+            .collect();
+        let source = source.join("\n");
+
+        let tokens = parse(&source);
+        assert_eq!(tokens, reference_parse(&source));
+        let mat: Vec<&Instruction> = tokens
+            .iter()
+            .filter(|ins| ins.line() == "on the mat")
+            .collect();
+        assert!(mat
+            .iter()
+            .any(|ins| matches!(ins.instruction, InsType::ConditionalPush { .. })));
+        assert!(mat
+            .iter()
+            .any(|ins| matches!(ins.instruction, InsType::Store(_))));
+        assert!(Arc::ptr_eq(&mat[0].line, &mat[1].line));
+    }
+
+    fn bundled_poems() -> Vec<String> {
+        vec![
+            include_str!("../poems/cond-goto-test.eso").to_string(),
+            include_str!("../poems/goto-test.eso").to_string(),
+            crate::io::latin1_to_string(include_bytes!("../poems/latin1-test.eso")),
+            include_str!("../poems/lovely-poem.eso").to_string(),
+            include_str!("../poems/math-test.eso").to_string(),
+            include_str!("../poems/original-factorial.eso").to_string(),
+            include_str!("../poems/stack-overflow-test.eso").to_string(),
+            include_str!("../poems/stack-test.eso").to_string(),
+        ]
+    }
+
+    #[test]
+    fn same_as_reference() {
+        let mut corpus = bundled_poems();
+        // capitals and letters outside ascii, including ones that lowercase
+        // differently depending on where they are in a word
+        let lines = [
+            "THE CAT",
+            "a HAT",
+            "  Sells Sea",
+            "ΟΔΟΣ ΟΔΟΣ",
+            "σοφός Σοφός",
+            "İstanbul is ıt",
+            "Ärger über ÄRGER",
+            "tab\tseparated\tWords lovely",
+            "  DOVE, ABOVE",
+            "crème brûlée. Crème",
+            "",
+        ];
+        corpus.push(
+            (0..lines.len() * lines.len())
+                .map(|i| format!("{}\n", lines[(i * 7 + i / lines.len()) % lines.len()]))
+                .collect(),
+        );
+        for poem in &corpus {
+            assert_eq!(parse(poem), reference_parse(poem), "{}", poem);
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_matches_sequential() {
+        let endings = ["cat", "hat", "moon", "soon", "tree", "poem"];
+        let lines = [
+            "a lovely line about a",
+            "  Some Capital Letters for a",
+            "like a simile for the",
+            "print it? the",
+            "print it. the",
+            "pop it, the",
+            "push-it to the",
+            "she sells sea shells by the",
+            "jump / over the",
+            "",
+            "  sOme iNterior capitals for the",
+        ];
+        // a cheap deterministic shuffle, so rhymes land on chunk boundaries
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = |n: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % n as u64) as usize
+        };
+        let poem: String = (0..100_000)
+            .map(|_| {
+                let line = lines[next(lines.len())];
+                if line.is_empty() {
+                    "\n".to_string()
+                } else {
+                    format!("{} {}\n", line, endings[next(endings.len())])
+                }
+            })
+            .collect();
+        assert!(poem.len() >= PARALLEL_THRESHOLD);
+
+        let parallel = parse(&poem);
+        let sequential = parse_sequential(&poem, &ParseOptions::default());
+        assert_eq!(parallel.len(), 100_000);
+        assert!(parallel == sequential);
+        let pushes = parallel
+            .iter()
+            .filter(|ins| matches!(ins.instruction, InsType::ConditionalPush { .. }))
+            .count();
+        assert!(pushes > 10_000, "only {} lines rhyme", pushes);
+    }
 }
@@ -0,0 +1,44 @@
+//! the parser's patterns found with regexes, see [`super::scan`] for the
+//! builds without the `regex` feature
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::ops::Range;
+
+lazy_static! {
+    // * it is assumed that these Regexes are valid
+    static ref INT_CAP_RE: Regex = Regex::new(r"\b\S+[A-Z]\S+\b").unwrap();
+    static ref CAP_RE: Regex = Regex::new(r"\b[A-Z][^A-Z]+\b").unwrap();
+    static ref SIMILIE_RE: Regex = Regex::new(r"\b(like|as)\b").unwrap();
+    static ref VOWEL_CLUSTER_RE: Regex = Regex::new(r"[^aeiouy]+").unwrap();
+    // letters of other scripts, and emoji. digits, punctuation and the
+    // joiners between emoji are shared by every script, so they're left
+    static ref UNKNOWN_SCRIPT_RE: Regex =
+        Regex::new(r"(?:[^\p{Latin}\p{Common}\p{Inherited}]|\p{Extended_Pictographic})+").unwrap();
+}
+
+/// the first word with a capital letter inside it
+pub(super) fn internal_capital(line: &str) -> Option<Range<usize>> {
+    INT_CAP_RE.find(line).map(|m| m.range())
+}
+
+/// the first capital letter that starts a word, up to the end of the last
+/// word before the next capital
+pub(super) fn leading_capital(line: &str) -> Option<Range<usize>> {
+    CAP_RE.find(line).map(|m| m.range())
+}
+
+/// the first "like" or "as" on its own
+pub(super) fn simile(line: &str) -> Option<Range<usize>> {
+    SIMILIE_RE.find(line).map(|m| m.range())
+}
+
+/// the runs of vowels in a lowercase word, with an empty one wherever it
+/// starts or ends with something else
+pub(super) fn vowel_clusters(word: &str) -> impl Iterator<Item = &str> {
+    VOWEL_CLUSTER_RE.split(word)
+}
+
+/// the runs of letters of other scripts than Latin, and emoji, in `word`
+pub(super) fn unknown_script(word: &str) -> impl Iterator<Item = Range<usize>> + '_ {
+    UNKNOWN_SCRIPT_RE.find_iter(word).map(|run| run.range())
+}
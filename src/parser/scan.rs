@@ -0,0 +1,231 @@
+//! the parser's patterns found by scanning the characters of a line,
+//! which is what builds without the `regex` feature use. each function
+//! finds exactly what the one of the same name in [`super::regexes`] does
+use std::{cmp::Ordering, iter, ops::Range};
+
+mod tables;
+
+fn in_table(table: &[(char, char)], c: char) -> bool {
+    table
+        .binary_search_by(|&(start, end)| {
+            if end < c {
+                Ordering::Less
+            } else if start > c {
+                Ordering::Greater
+            } else {
+                Ordering::Equal
+            }
+        })
+        .is_ok()
+}
+
+/// whether `c` is a word character, as `\w` matches them
+fn is_word(c: char) -> bool {
+    if c.is_ascii() {
+        c.is_ascii_alphanumeric() || c == '_'
+    } else {
+        in_table(tables::WORD, c)
+    }
+}
+
+/// the characters of `line` with their byte offsets, followed by the end
+/// of the line
+fn positions(line: &str) -> Vec<usize> {
+    line.char_indices()
+        .map(|(at, _)| at)
+        .chain(iter::once(line.len()))
+        .collect()
+}
+
+/// whether a word starts or ends at `at`, as `\b` matches
+fn boundary(line: &str, at: usize) -> bool {
+    let before = line[..at].chars().next_back().is_some_and(is_word);
+    let after = line[at..].chars().next().is_some_and(is_word);
+    before != after
+}
+
+fn is_capital(line: &str, at: usize) -> bool {
+    line.as_bytes().get(at).is_some_and(u8::is_ascii_uppercase)
+}
+
+/// like `\b\S+[A-Z]\S+\b`: the first run of non-whitespace with a capital
+/// after its first word boundary and at least a character before its last,
+/// from the one boundary to the other
+pub(super) fn internal_capital(line: &str) -> Option<Range<usize>> {
+    let positions = positions(line);
+    let len = positions.len() - 1;
+    let whitespace = |k: usize| line[positions[k]..].starts_with(char::is_whitespace);
+    let mut start = 0;
+    while start < len {
+        if whitespace(start) {
+            start += 1;
+            continue;
+        }
+        let end = (start..len).find(|&k| whitespace(k)).unwrap_or(len);
+        let mut boundaries = (start..=end).filter(|&k| boundary(line, positions[k]));
+        if let (Some(first), Some(last)) = (boundaries.next(), boundaries.next_back()) {
+            if (first + 1..last.saturating_sub(1)).any(|k| is_capital(line, positions[k])) {
+                return Some(positions[first]..positions[last]);
+            }
+        }
+        start = end;
+    }
+    None
+}
+
+/// like `\b[A-Z][^A-Z]+\b`: the first capital starting a word with a word
+/// boundary at least a character after it and no later than the next
+/// capital, through the last such boundary
+pub(super) fn leading_capital(line: &str) -> Option<Range<usize>> {
+    let positions = positions(line);
+    let end = positions.len() - 1;
+    for k in 0..end {
+        if !is_capital(line, positions[k]) || !boundary(line, positions[k]) {
+            continue;
+        }
+        let next = (k + 1..end)
+            .find(|&j| is_capital(line, positions[j]))
+            .unwrap_or(end);
+        if let Some(last) = (k + 2..=next).rev().find(|&e| boundary(line, positions[e])) {
+            return Some(positions[k]..positions[last]);
+        }
+    }
+    None
+}
+
+/// like `\b(like|as)\b`
+pub(super) fn simile(line: &str) -> Option<Range<usize>> {
+    line.char_indices().find_map(|(at, _)| {
+        let word = ["like", "as"]
+            .iter()
+            .find(|&&word| line[at..].starts_with(word))?;
+        let end = at + word.len();
+        (boundary(line, at) && boundary(line, end)).then_some(at..end)
+    })
+}
+
+fn is_vowel(c: char) -> bool {
+    matches!(c, 'a' | 'e' | 'i' | 'o' | 'u' | 'y')
+}
+
+/// like splitting on `[^aeiouy]+`
+pub(super) fn vowel_clusters(word: &str) -> impl Iterator<Item = &str> {
+    let mut rest = Some(word);
+    iter::from_fn(move || {
+        let text = rest?;
+        match text.find(|c| !is_vowel(c)) {
+            Some(start) => {
+                let end = text[start..]
+                    .find(is_vowel)
+                    .map_or(text.len(), |end| start + end);
+                rest = Some(&text[end..]);
+                Some(&text[..start])
+            }
+            None => {
+                rest = None;
+                Some(text)
+            }
+        }
+    })
+}
+
+/// like `(?:[^\p{Latin}\p{Common}\p{Inherited}]|\p{Extended_Pictographic})+`
+pub(super) fn unknown_script(word: &str) -> impl Iterator<Item = Range<usize>> + '_ {
+    let unknown = |c: char| !c.is_ascii() && in_table(tables::UNKNOWN_SCRIPT, c);
+    let mut at = 0;
+    iter::from_fn(move || {
+        let start = at + word[at..].find(unknown)?;
+        let end = word[start..]
+            .find(|c| !unknown(c))
+            .map_or(word.len(), |end| start + end);
+        at = end;
+        Some(start..end)
+    })
+}
+
+#[cfg(all(test, feature = "regex"))]
+mod tests {
+    use super::*;
+    use crate::parser::regexes;
+    use pretty_assertions::assert_eq;
+    use proptest::prelude::*;
+    use std::{fs, path::Path};
+
+    /// every function here agrees with its regex on `line`, and on each
+    /// word of it lowercased
+    fn assert_agree(line: &str) {
+        assert_eq!(
+            internal_capital(line),
+            regexes::internal_capital(line),
+            "internal capital in {:?}",
+            line
+        );
+        assert_eq!(
+            leading_capital(line),
+            regexes::leading_capital(line),
+            "leading capital in {:?}",
+            line
+        );
+        assert_eq!(simile(line), regexes::simile(line), "simile in {:?}", line);
+        for word in line.split_whitespace().map(str::to_lowercase) {
+            assert_eq!(
+                vowel_clusters(&word).collect::<Vec<_>>(),
+                regexes::vowel_clusters(&word).collect::<Vec<_>>(),
+                "vowel clusters of {:?}",
+                word
+            );
+            assert_eq!(
+                unknown_script(&word).collect::<Vec<_>>(),
+                regexes::unknown_script(&word).collect::<Vec<_>>(),
+                "unknown script in {:?}",
+                word
+            );
+        }
+    }
+
+    /// the lines of every poem in the repository
+    fn corpus() -> Vec<String> {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let mut lines = vec![];
+        for dir in ["poems", "tests/fixtures", "tests/compliance/fixtures"].iter() {
+            for entry in fs::read_dir(root.join(dir)).unwrap() {
+                let path = entry.unwrap().path();
+                if path.extension().is_some_and(|ext| ext == "eso") {
+                    let bytes = fs::read(&path).unwrap();
+                    let poem = String::from_utf8(bytes.clone())
+                        .unwrap_or_else(|_| crate::io::latin1_to_string(&bytes));
+                    lines.extend(poem.lines().map(str::to_string));
+                }
+            }
+        }
+        lines
+    }
+
+    #[test]
+    fn corpus_agrees() {
+        let corpus = corpus();
+        assert!(corpus.len() > 200);
+        for line in &corpus {
+            assert_agree(line);
+        }
+    }
+
+    #[test]
+    fn nasty_cases_agree() {
+        for line in include_str!("../../tests/fixtures/scanning.txt").lines() {
+            assert_agree(line);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn any_text_agrees(line in any::<String>()) {
+            assert_agree(&line);
+        }
+
+        #[test]
+        fn wordy_text_agrees(line in "[a-zA-Z0-9 _'.,-]{0,24}|[aAsSlLiIkKeE ]{0,24}|[a-zÀ-ÿ\u{300}\u{2009}\u{200d}😀Ωπж२'A-Z ]{0,24}") {
+            assert_agree(&line);
+        }
+    }
+}
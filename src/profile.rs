@@ -0,0 +1,127 @@
+//! per-line execution counts, for finding the hot verses of a poem
+use super::{program::Step, Program};
+
+/// how many times each instruction of a program was executed
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Profile {
+    pub hits: Vec<u64>,
+}
+
+impl Profile {
+    /// an empty profile sized for `program`
+    pub fn new(program: &Program) -> Profile {
+        Profile {
+            hits: vec![0; program.ast.len()],
+        }
+    }
+
+    pub fn record(&mut self, step: &Step) {
+        if let Some(hits) = self.hits.get_mut(step.index) {
+            *hits += 1;
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.hits.iter().sum()
+    }
+
+    /// the poem with a gutter of hit counts (and optionally the share of all
+    /// executed instructions) in front of every line. lines that never ran
+    /// show `-` rather than 0
+    pub fn render(&self, program: &Program, percentages: bool) -> String {
+        let total = self.total();
+        let counts: Vec<String> = self
+            .hits
+            .iter()
+            .map(|&hits| {
+                if hits == 0 {
+                    "-".to_string()
+                } else {
+                    hits.to_string()
+                }
+            })
+            .collect();
+        let width = counts.iter().map(String::len).max().unwrap_or(1);
+
+        let mut rendered = String::new();
+        for ((ins, count), &hits) in program.ast.iter().zip(&counts).zip(&self.hits) {
+            let gutter = if percentages {
+                let share = if hits == 0 {
+                    "-".to_string()
+                } else {
+                    format!("{:.1}%", hits as f64 * 100.0 / total as f64)
+                };
+                format!("{: >width$} {: >6}", count, share, width = width)
+            } else {
+                format!("{: >width$}", count, width = width)
+            };
+            rendered.push_str(format!("{} | {}", gutter, ins.line()).trim_end());
+            rendered.push('\n');
+        }
+        rendered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ExecOptions;
+    use pretty_assertions::assert_eq;
+
+    #[cfg(feature = "bundled-dictionary")]
+    #[test]
+    fn factorial() {
+        let program = Program::create(include_str!("../poems/lovely-poem.eso"));
+        let mut profile = Profile::new(&program);
+        let output = program
+            .execute_profiled(&ExecOptions::default(), &mut profile)
+            .unwrap();
+        assert_eq!(output, "24\n");
+        let expected = "
+1 |
+1 | lovely poem
+2 |
+2 |   it is a calculator, like a
+2 |       poem, is a poem, and finds
+2 |         factori-
+2 |           als
+2 |   The input is the syllAbles
+2 | in the title, count them, as one counts
+2 |   (q) what other poem, programs can be writ
+2 |   (a) anything a Turing
+2 |     machine-machine-machine
+2 |     would do
+2 | re/cur
+1 |     sion works too, in poems, programs, and this
+1 |        a lovely.
+1 | poem or calculator or nothing
+1 | how lovely can it be?
+"
+        .trim_start();
+        assert_eq!(profile.render(&program, false), expected);
+    }
+
+    #[test]
+    fn unexecuted_lines() {
+        let program = Program::create("fish\nsells sea shells\nprint.");
+        let mut profile = Profile::new(&program);
+        let result =
+            program.execute_profiled(&ExecOptions::with_max_instructions(10), &mut profile);
+        assert!(result.is_err());
+        assert_eq!(profile.hits, vec![1, 9, 0]);
+        assert_eq!(
+            profile.render(&program, false),
+            "1 | fish\n9 | sells sea shells\n- | print.\n"
+        );
+
+        let program = Program::create("two\n\nlucky fish\n ");
+        let mut profile = Profile::new(&program);
+        program
+            .execute_profiled(&ExecOptions::default(), &mut profile)
+            .unwrap();
+        assert_eq!(
+            profile.render(&program, true),
+            "1  25.0% | two\n1  25.0% |\n1  25.0% | lucky fish\n1  25.0% |\n"
+        );
+    }
+}
@@ -1,28 +1,110 @@
-use super::parser::{self, InsType, Instruction, Register};
 #[cfg(feature = "jit")]
-use super::{errors::jit::JitResult, jit::JIT};
+pub use super::errors::jit::{JitError, JitResult};
+pub use super::errors::program::{
+    ExecError, ExecResult, RangeError, RewindError, RewindResult, SeedError, SeedResult,
+    SourceLocation, TitleError, TitleResult,
+};
+#[cfg(feature = "jit")]
+pub use super::jit::CompileStats;
+use super::parser::{
+    self, CustomCounter, Dialect, InsType, Instruction, ParseOptions, Register, SyllableCounter,
+};
+use super::{
+    analysis,
+    errors::{Error, Result},
+    host::{self, OutputEvent, PoemIo},
+    io::{self, Encoding},
+    profile::Profile,
+    report::{ExecutionReport, HaltReason},
+    title,
+    trace::TraceRow,
+};
+#[cfg(feature = "jit")]
+use super::{jit::JIT, rt};
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    convert::TryFrom,
+    fmt,
+    hash::{Hash, Hasher},
+    io::{self as std_io, Read},
+    ops::Range,
+    path::Path,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Instant,
+};
 
-#[derive(Debug, Clone)]
-struct Memory {
-    register0: i64,
-    register1: i64,
+mod instructions;
+mod threaded;
+
+use instructions::Operands;
+
+/// the registers, stack and tape a program runs on, which
+/// [`Program::execute_with_memory`] can carry from one program to the next
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Memory {
+    registers: [i64; 4],
     stack: Vec<i64>,
+    /// only written in [`Dialect::Extended`]
+    tape: HashMap<i64, i64>,
+    /// the register of the last instruction, the inactive one in
+    /// [`Dialect::Extended`]
+    previous: Register,
+    dialect: Dialect,
+}
+
+impl Default for Memory {
+    fn default() -> Self {
+        Memory::new()
+    }
 }
 
 impl Memory {
-    fn new() -> Memory {
+    /// zeroed registers and an empty stack and tape
+    pub fn new() -> Memory {
+        Memory::with_dialect(Dialect::Standard)
+    }
+
+    /// `r0`, `r1` and `stack`, the bottom first
+    pub fn with(r0: i64, r1: i64, stack: Vec<i64>) -> Memory {
+        let mut mem = Memory::new();
+        mem.registers[0] = r0;
+        mem.registers[1] = r1;
+        mem.stack = stack;
+        mem
+    }
+
+    fn with_dialect(dialect: Dialect) -> Memory {
         Memory {
-            register0: 0,
-            register1: 0,
+            registers: [0; 4],
             stack: vec![],
+            tape: HashMap::new(),
+            // so the first line in r0 sees r1, like the standard dialect
+            previous: Register::Register1,
+            dialect,
         }
     }
 
+    pub fn registers(&self) -> Registers {
+        let [r0, r1, r2, r3] = self.registers;
+        Registers { r0, r1, r2, r3 }
+    }
+
+    /// the bottom first
+    pub fn stack(&self) -> &[i64] {
+        &self.stack
+    }
+
+    /// the tape cells that have been written
+    pub fn tape(&self) -> &HashMap<i64, i64> {
+        &self.tape
+    }
+
     fn store_syllables(&mut self, register: Register, syllables: i64) {
-        match register {
-            Register::Register0 => self.register0 = syllables,
-            Register::Register1 => self.register1 = syllables,
-        }
+        self.registers[register.index()] = syllables;
     }
 
     fn push_to_stack(&mut self, val: i64) {
@@ -30,156 +112,1541 @@ impl Memory {
     }
 
     fn push(&mut self, register: Register) {
-        match register {
-            Register::Register0 => self.stack.push(self.register0),
-            Register::Register1 => self.stack.push(self.register1),
+        self.stack.push(self.get_active(register));
+    }
+
+    /// `false` if the stack was empty, leaving the register as it was
+    fn pop(&mut self, register: Register) -> bool {
+        match self.stack.pop() {
+            Some(val) => {
+                self.store_syllables(register, val);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// `op` of the active and inactive registers into the active one, `None`
+    /// if it overflows
+    fn combine(&mut self, register: Register, op: fn(i64, i64) -> Option<i64>) -> Option<()> {
+        let result = op(self.get_active(register), self.get_inactive(register))?;
+        self.store_syllables(register, result);
+        Some(())
+    }
+
+    /// `None` if the result overflows
+    fn multiply(&mut self, register: Register) -> Option<()> {
+        self.combine(register, i64::checked_mul)
+    }
+
+    /// `None` if the result overflows
+    fn add(&mut self, register: Register) -> Option<()> {
+        self.combine(register, i64::checked_add)
+    }
+
+    /// `None` if the result overflows
+    fn subtract(&mut self, register: Register) -> Option<()> {
+        self.combine(register, i64::checked_sub)
+    }
+
+    /// rounds towards zero. `None` if the result overflows or the divisor
+    /// is zero
+    fn divide(&mut self, register: Register) -> Option<()> {
+        self.combine(register, i64::checked_div)
+    }
+
+    fn get_active(&self, register: Register) -> i64 {
+        self.registers[register.index()]
+    }
+
+    fn inactive(&self, register: Register) -> Register {
+        match self.dialect {
+            Dialect::Standard => register.paired(),
+            Dialect::Extended => self.previous,
         }
     }
 
-    fn pop(&mut self, register: Register) {
-        if let Some(val) = self.stack.pop() {
-            match register {
-                Register::Register0 => self.register0 = val,
-                Register::Register1 => self.register1 = val,
+    fn get_inactive(&self, register: Register) -> i64 {
+        self.get_active(self.inactive(register))
+    }
+
+    /// `None` if the result overflows
+    fn negate(&mut self, register: Register) -> Option<()> {
+        let negated = self.get_active(register).checked_neg()?;
+        self.store_syllables(register, negated);
+        Some(())
+    }
+}
+
+/// called with the interpreter after every step of `Program::run`
+type StepHook<'h> = dyn FnMut(&Interpreter, &Step) + 'h;
+
+/// stops executions that were given it, e.g. from another thread. clones
+/// share the same flag
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> CancelToken {
+        CancelToken::default()
+    }
+
+    /// executions using this token stop before their next instruction
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// tokens are equal when they're clones of each other
+impl PartialEq for CancelToken {
+    fn eq(&self, other: &CancelToken) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for CancelToken {}
+
+impl Hash for CancelToken {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Arc::as_ptr(&self.0).hash(state);
+    }
+}
+
+/// receives a [`TraceRow`] after every instruction of the executions it's
+/// given to, in place of logging them. clones share the same callback
+#[derive(Clone)]
+pub struct TraceSink(Arc<Mutex<dyn FnMut(TraceRow) + Send>>);
+
+impl TraceSink {
+    pub fn new(sink: impl FnMut(TraceRow) + Send + 'static) -> TraceSink {
+        TraceSink(Arc::new(Mutex::new(sink)))
+    }
+
+    fn send(&self, row: TraceRow) {
+        // a sink that panicked can still be given rows
+        let mut sink = self
+            .0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        (*sink)(row)
+    }
+}
+
+impl fmt::Debug for TraceSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("TraceSink").finish_non_exhaustive()
+    }
+}
+
+/// sinks are equal when they're clones of each other
+impl PartialEq for TraceSink {
+    fn eq(&self, other: &TraceSink) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for TraceSink {}
+
+impl Hash for TraceSink {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Arc::as_ptr(&self.0).cast::<()>().hash(state);
+    }
+}
+
+/// where the `read-char`s of the executions it's given to read from. clones
+/// share the same reader
+#[derive(Clone)]
+pub struct InputSource(Arc<Mutex<dyn Read + Send>>);
+
+impl InputSource {
+    pub fn new(reader: impl Read + Send + 'static) -> InputSource {
+        InputSource(Arc::new(Mutex::new(reader)))
+    }
+
+    /// the next byte, or `None` at the end of the input
+    fn read_byte(&self) -> std_io::Result<Option<u8>> {
+        let mut reader = self
+            .0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut byte = [0];
+        loop {
+            match reader.read(&mut byte) {
+                Ok(0) => return Ok(None),
+                Ok(_) => return Ok(Some(byte[0])),
+                Err(err) if err.kind() == std_io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
             }
         }
     }
+}
+
+impl fmt::Debug for InputSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("InputSource").finish_non_exhaustive()
+    }
+}
+
+/// sources are equal when they're clones of each other
+impl PartialEq for InputSource {
+    fn eq(&self, other: &InputSource) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for InputSource {}
+
+impl Hash for InputSource {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Arc::as_ptr(&self.0).cast::<()>().hash(state);
+    }
+}
+
+/// the log target of the json trace
+#[cfg(not(feature = "tracing"))]
+const TRACE_TARGET: &str = "ashpaper::trace";
+
+/// how the state after every instruction is logged. with the `tracing`
+/// feature steps are events with their own fields instead, so this is
+/// ignored
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub enum TraceFormat {
+    /// aligned columns, readable in a terminal
+    #[default]
+    Table,
+    /// a single-line json object per instruction, logged with the target
+    /// `ashpaper::trace`, with the step, 1-based line, instruction mnemonic,
+    /// registers and stack length. the whole stack is included as well
+    /// while it holds at most `max_stack` values
+    Json { max_stack: usize },
+}
+
+/// how the interpreter runs a program's instructions
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub enum Engine {
+    /// matching on each instruction as it's reached
+    #[default]
+    Classic,
+    /// lowering the program to a function per instruction before it runs,
+    /// then calling them by index. it behaves exactly like the classic
+    /// engine, and runs about as fast
+    Threaded,
+}
+
+/// options controlling a single execution of a program
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Default)]
+pub struct ExecOptions {
+    /// maximum number of instructions to execute before giving up, so poems
+    /// that never halt can be stopped. `None` runs until the poem halts
+    pub max_instructions: Option<u64>,
+    /// maximum number of bytes of output the program may produce. output is
+    /// truncated at the limit as it's produced
+    pub max_output: Option<usize>,
+    /// stop with [`ExecError::Cancelled`] once this is cancelled
+    pub cancel: Option<CancelToken>,
+    pub trace_format: TraceFormat,
+    /// where the state after every instruction goes instead of the logger
+    /// (or tracing events with the `tracing` feature), so a host can
+    /// capture it without installing a global logger
+    pub trace_sink: Option<TraceSink>,
+    /// fail with [`ExecError::StackUnderflow`] on a pop from an empty stack
+    /// instead of ignoring it, since it usually means a push never happened
+    pub strict_pop: bool,
+    /// fail with [`ExecError::DivisionByZero`] on a division by zero instead
+    /// of setting the register to 0. the jit always sets it to 0
+    pub strict_division: bool,
+    /// what `read-char`s read, a byte at a time. `None` is empty input
+    pub input: Option<InputSource>,
+    /// maximum number of tape cells a program may write, failing with
+    /// [`ExecError::TapeLimit`] on the first write to another one. writing a
+    /// cell again doesn't count
+    pub max_tape_cells: Option<usize>,
+    /// maximum number of values the stack may hold, failing with
+    /// [`ExecError::StackLimit`] on a push to a full one
+    pub max_stack: Option<usize>,
+    /// what's on the stack before the first instruction, the bottom first,
+    /// e.g. arguments from [`ExecOptions::stack_seed_from`]
+    pub initial_stack: Vec<i64>,
+    /// how instructions are run, the classic engine unless it's set
+    pub engine: Engine,
+}
+
+impl ExecOptions {
+    pub fn with_max_instructions(max_instructions: u64) -> ExecOptions {
+        ExecOptions {
+            max_instructions: Some(max_instructions),
+            ..ExecOptions::default()
+        }
+    }
 
-    fn multiply(&mut self, register: Register) {
-        match register {
-            Register::Register0 => self.register0 *= self.register1,
-            Register::Register1 => self.register1 *= self.register0,
+    pub fn with_max_output(max_output: usize) -> ExecOptions {
+        ExecOptions {
+            max_output: Some(max_output),
+            ..ExecOptions::default()
         }
     }
 
-    fn add(&mut self, register: Register) {
-        match register {
-            Register::Register0 => self.register0 += self.register1,
-            Register::Register1 => self.register1 += self.register0,
+    pub fn with_cancel_token(cancel: CancelToken) -> ExecOptions {
+        ExecOptions {
+            cancel: Some(cancel),
+            ..ExecOptions::default()
         }
     }
 
-    fn get_active(&self, register: Register) -> i64 {
-        match register {
-            Register::Register0 => self.register0,
-            Register::Register1 => self.register1,
+    /// options that start the stack with the bytes of `args`, for poems
+    /// that act as filters. the first byte of the first argument is on top,
+    /// followed by the rest of its bytes, a space, the next argument and so
+    /// on, with a 0 at the bottom to pop until, since no argument can hold
+    /// one
+    pub fn stack_seed_from(args: &[String]) -> ExecOptions {
+        let mut initial_stack = vec![0];
+        initial_stack.extend(args.join(" ").bytes().rev().map(i64::from));
+        ExecOptions {
+            initial_stack,
+            ..ExecOptions::default()
         }
     }
 
-    fn get_inactive(&self, register: Register) -> i64 {
-        match register {
-            Register::Register0 => self.register1,
-            Register::Register1 => self.register0,
+    /// options that start the stack with `args` parsed as integers, the
+    /// first argument on top. there's nothing below the last one, since 0
+    /// could be an argument too
+    pub fn stack_seed_from_integers(args: &[String]) -> Result<ExecOptions> {
+        let initial_stack = args
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(index, arg)| {
+                arg.trim().parse().map_err(|_| SeedError {
+                    position: index + 1,
+                    argument: arg.clone(),
+                })
+            })
+            .collect::<SeedResult<_>>()?;
+        Ok(ExecOptions {
+            initial_stack,
+            ..ExecOptions::default()
+        })
+    }
+}
+
+/// the values of the registers, e.g. once a program has halted. only
+/// [`Dialect::Extended`] uses `r2` and `r3`
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Registers {
+    pub r0: i64,
+    pub r1: i64,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub r2: i64,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub r3: i64,
+}
+
+/// what happened while executing a single instruction
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Step {
+    /// index of the executed instruction in the program
+    pub index: usize,
+    /// text printed by the instruction, if any
+    pub output: Option<String>,
+}
+
+/// the machine between two instructions, which
+/// [`Program::execute_range`] starts from and hands back
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MachineState {
+    pub registers: Registers,
+    /// the bottom first
+    pub stack: Vec<i64>,
+    pub tape: HashMap<i64, i64>,
+    /// where a goto out of the range would have gone, which stopped the run
+    /// there. `None` when it ran to the end of the range
+    pub escaped_to: Option<usize>,
+}
+
+/// how many steps an [`Interpreter`] can undo unless it's told otherwise
+const HISTORY_LIMIT: usize = 1024;
+
+/// the machine before a step, for [`Interpreter::step_back`]
+#[derive(Debug, Clone)]
+struct Snapshot {
+    mem: Memory,
+    instruction_pointer: usize,
+    instructions_executed: u64,
+    ignored_pops: u64,
+    output_len: usize,
+}
+
+/// executes a program one instruction at a time
+#[derive(Debug, Clone)]
+pub struct Interpreter<'a> {
+    program: &'a Program,
+    mem: Memory,
+    instruction_pointer: usize,
+    instructions_executed: u64,
+    strict_pop: bool,
+    strict_division: bool,
+    input: Option<InputSource>,
+    max_tape_cells: Option<usize>,
+    max_stack: Option<usize>,
+    ignored_pops: u64,
+    /// bytes printed so far
+    output_len: usize,
+    /// the oldest first
+    history: VecDeque<Snapshot>,
+    history_limit: usize,
+    /// the program lowered for [`Engine::Threaded`]
+    ops: Option<Arc<[threaded::Op]>>,
+}
+
+impl<'a> Interpreter<'a> {
+    pub fn new(program: &'a Program) -> Interpreter<'a> {
+        let mut mem = Memory::with_dialect(program.dialect);
+        // enough for every push in the poem to run once, which is all most
+        // poems need
+        let pushes = program
+            .ast
+            .iter()
+            .filter(|ins| {
+                matches!(
+                    ins.instruction,
+                    InsType::Push | InsType::ConditionalPush { .. }
+                )
+            })
+            .count();
+        mem.stack.reserve(pushes);
+        Interpreter {
+            program,
+            mem,
+            instruction_pointer: 0,
+            instructions_executed: 0,
+            strict_pop: false,
+            strict_division: false,
+            input: None,
+            max_tape_cells: None,
+            max_stack: None,
+            ignored_pops: 0,
+            output_len: 0,
+            history: VecDeque::new(),
+            history_limit: HISTORY_LIMIT,
+            ops: None,
+        }
+    }
+
+    /// an interpreter set up with everything in `options` the machine keeps
+    /// itself, leaving the limits and tracing to the caller
+    pub(crate) fn with_options(program: &'a Program, options: &ExecOptions) -> Interpreter<'a> {
+        let mut interpreter = Interpreter::new(program);
+        interpreter.set_strict_pop(options.strict_pop);
+        interpreter.set_strict_division(options.strict_division);
+        interpreter.set_max_tape_cells(options.max_tape_cells);
+        interpreter.set_max_stack(options.max_stack);
+        interpreter.set_stack(&options.initial_stack);
+        interpreter.set_input(options.input.clone());
+        interpreter.set_engine(options.engine);
+        interpreter
+    }
+
+    /// how instructions are run, see [`ExecOptions::engine`]
+    pub fn set_engine(&mut self, engine: Engine) {
+        self.ops = match engine {
+            Engine::Classic => None,
+            Engine::Threaded => Some(threaded::lower(self.program)),
+        };
+    }
+
+    /// whether a pop from an empty stack is an error, see
+    /// [`ExecOptions::strict_pop`]
+    pub fn set_strict_pop(&mut self, strict_pop: bool) {
+        self.strict_pop = strict_pop;
+    }
+
+    /// whether a division by zero is an error, see
+    /// [`ExecOptions::strict_division`]
+    pub fn set_strict_division(&mut self, strict_division: bool) {
+        self.strict_division = strict_division;
+    }
+
+    /// what `read-char`s read, see [`ExecOptions::input`]
+    pub fn set_input(&mut self, input: Option<InputSource>) {
+        self.input = input;
+    }
+
+    /// how many tape cells may be written, see
+    /// [`ExecOptions::max_tape_cells`]
+    pub fn set_max_tape_cells(&mut self, max_tape_cells: Option<usize>) {
+        self.max_tape_cells = max_tape_cells;
+    }
+
+    /// how many values the stack may hold, see [`ExecOptions::max_stack`]
+    pub fn set_max_stack(&mut self, max_stack: Option<usize>) {
+        self.max_stack = max_stack;
+    }
+
+    /// how many steps [`Interpreter::step_back`] can undo, 1024 unless it's
+    /// set. 0 keeps no history
+    pub fn set_history_limit(&mut self, history_limit: usize) {
+        self.history_limit = history_limit;
+        let excess = self.history.len().saturating_sub(history_limit);
+        self.history.drain(..excess);
+    }
+
+    /// replace what's on the stack, the bottom first, see
+    /// [`ExecOptions::initial_stack`]
+    pub fn set_stack(&mut self, stack: &[i64]) {
+        self.mem.stack.clear();
+        self.mem.stack.extend_from_slice(stack);
+    }
+
+    /// how many pops from an empty stack were ignored
+    pub fn ignored_pops(&self) -> u64 {
+        self.ignored_pops
+    }
+
+    pub fn program(&self) -> &'a Program {
+        self.program
+    }
+
+    /// index of the next instruction to be executed
+    pub fn instruction_pointer(&self) -> usize {
+        self.instruction_pointer
+    }
+
+    pub fn instructions_executed(&self) -> u64 {
+        self.instructions_executed
+    }
+
+    /// how many bytes the program has printed, which after
+    /// [`Interpreter::step_back`] is where its output should be cut off
+    pub fn output_len(&self) -> usize {
+        self.output_len
+    }
+
+    /// the program halts when the instruction pointer runs off the end
+    pub fn is_halted(&self) -> bool {
+        self.instruction_pointer >= self.program.ast.len()
+    }
+
+    pub fn register(&self, register: Register) -> i64 {
+        self.mem.get_active(register)
+    }
+
+    pub fn set_register(&mut self, register: Register, value: i64) {
+        self.mem.store_syllables(register, value);
+    }
+
+    pub fn registers(&self) -> Registers {
+        self.mem.registers()
+    }
+
+    pub fn stack(&self) -> &[i64] {
+        &self.mem.stack
+    }
+
+    /// the tape cells that have been written
+    pub fn tape(&self) -> &HashMap<i64, i64> {
+        &self.mem.tape
+    }
+
+    /// the machine state after `step` was executed
+    pub fn trace_row(&self, step: &Step) -> TraceRow {
+        let index = step.index;
+        let ins = &self.program.ast[index];
+        TraceRow {
+            index,
+            // the parser gives every line of the poem an instruction
+            line_number: index + 1,
+            line: ins.line().to_string(),
+            instruction: ins.instruction,
+            register: ins.register,
+            r0: self.mem.registers[0],
+            r1: self.mem.registers[1],
+            stack: self.mem.stack.clone(),
+            output: step.output.clone().unwrap_or_default(),
+        }
+    }
+
+    /// execute the next instruction, returning `None` if the program has
+    /// already halted. an instruction that fails leaves the machine as it
+    /// was before it
+    pub fn step(&mut self) -> Result<Option<Step>> {
+        let snapshot = (self.history_limit > 0 && !self.is_halted()).then(|| Snapshot {
+            mem: self.mem.clone(),
+            instruction_pointer: self.instruction_pointer,
+            instructions_executed: self.instructions_executed,
+            ignored_pops: self.ignored_pops,
+            output_len: self.output_len,
+        });
+        let mut output = String::new();
+        let index = self.step_into(&mut output)?;
+        if let Some(snapshot) = snapshot {
+            if self.history.len() == self.history_limit {
+                self.history.pop_front();
+            }
+            self.history.push_back(snapshot);
+        }
+        Ok(index.map(|index| Step {
+            index,
+            output: (!output.is_empty()).then_some(output),
+        }))
+    }
+
+    /// undo the last [`Interpreter::step`], as long as it's one of the last
+    /// [`Interpreter::set_history_limit`] steps. output can't be taken back,
+    /// so the caller should drop whatever was printed past
+    /// [`Interpreter::output_len`], and bytes a `read-char` read aren't put
+    /// back in the input
+    pub fn step_back(&mut self) -> Result<()> {
+        let snapshot = match self.history.pop_back() {
+            Some(snapshot) => snapshot,
+            None if self.instructions_executed == 0 => return Err(RewindError::AtStart.into()),
+            None => {
+                return Err(RewindError::HistoryExhausted {
+                    limit: self.history_limit,
+                }
+                .into())
+            }
+        };
+        self.mem = snapshot.mem;
+        self.instruction_pointer = snapshot.instruction_pointer;
+        self.instructions_executed = snapshot.instructions_executed;
+        self.ignored_pops = snapshot.ignored_pops;
+        self.output_len = snapshot.output_len;
+        Ok(())
+    }
+
+    /// [`Interpreter::step`], appending what the instruction prints to
+    /// `output` rather than allocating it, and returning the index of the
+    /// instruction
+    fn step_into(&mut self, output: &mut String) -> ExecResult<Option<usize>> {
+        if self.ops.is_some() {
+            return self.step_threaded(output);
         }
+        let index = self.instruction_pointer;
+        let (instruction, reg) = match self.program.ast.get(index) {
+            Some(ins) => (ins.instruction, ins.register),
+            None => return Ok(None),
+        };
+        let operands = Operands::of(instruction, reg);
+        let printed = output.len();
+        let next = match instruction {
+            InsType::ConditionalPush { .. } => {
+                instructions::conditional_push(self, operands, index, output)
+            }
+            InsType::ConditionalGoto(_) => {
+                instructions::conditional_goto(self, operands, index, output)
+            }
+            InsType::Negate => instructions::negate(self, operands, index, output),
+            InsType::Multiply => instructions::multiply(self, operands, index, output),
+            InsType::Add => instructions::add(self, operands, index, output),
+            InsType::Subtract => instructions::subtract(self, operands, index, output),
+            InsType::Divide => instructions::divide(self, operands, index, output),
+            InsType::ReadChar => instructions::read_char(self, operands, index, output),
+            InsType::TapeStore => instructions::tape_store(self, operands, index, output),
+            InsType::TapeLoad => instructions::tape_load(self, operands, index, output),
+            InsType::PrintChar => instructions::print_char(self, operands, index, output),
+            InsType::PrintValue => instructions::print_value(self, operands, index, output),
+            InsType::Pop => instructions::pop(self, operands, index, output),
+            InsType::Push => instructions::push(self, operands, index, output),
+            InsType::Goto => instructions::goto(self, operands, index, output),
+            InsType::Store(_) => instructions::store(self, operands, index, output),
+            InsType::Noop => instructions::noop(self, operands, index, output),
+        }?;
+        Ok(Some(self.finish_step(reg, next, output.len() - printed)))
+    }
+
+    /// [`Interpreter::step_into`] with [`Engine::Threaded`]
+    #[inline]
+    fn step_threaded(&mut self, output: &mut String) -> ExecResult<Option<usize>> {
+        let index = self.instruction_pointer;
+        let op = match self.ops.as_ref().and_then(|ops| ops.get(index)) {
+            Some(&op) => op,
+            None => return Ok(None),
+        };
+        let printed = output.len();
+        let next = (op.run)(self, op.operands, index, output)?;
+        Ok(Some(self.finish_step(
+            op.operands.register,
+            next,
+            output.len() - printed,
+        )))
+    }
+
+    /// moves on to `next` after a step that used `reg` and printed
+    /// `printed` bytes, returning the index of the step
+    #[inline]
+    fn finish_step(&mut self, reg: Register, next: usize, printed: usize) -> usize {
+        let index = self.instruction_pointer;
+        self.mem.previous = reg;
+        self.instruction_pointer = next;
+        self.instructions_executed += 1;
+        self.output_len += printed;
+        index
     }
+}
+
+/// when a run started, or `None` where `Instant` isn't supported
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+fn start_clock() -> Option<Instant> {
+    Some(Instant::now())
+}
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+fn start_clock() -> Option<Instant> {
+    None
+}
 
-    fn negate(&mut self, register: Register) {
-        match register {
-            Register::Register0 => self.register0 = -self.register0,
-            Register::Register1 => self.register1 = -self.register1,
+/// counted during a run for its [`ExecutionReport`]
+struct RunStats {
+    /// executions of each instruction
+    hits: Vec<u64>,
+    max_stack_depth: usize,
+}
+
+/// hands output to the caller, truncating it at the output limit
+struct OutputSink<'a> {
+    emit: &'a mut dyn FnMut(&str),
+    written: usize,
+    limit: Option<usize>,
+    /// only kept for [`Program::execute_report`]
+    events: Option<Vec<OutputEvent>>,
+}
+
+impl OutputSink<'_> {
+    /// `index` is the instruction the output came from, for the error
+    fn write(&mut self, program: &Program, index: usize, s: &str) -> ExecResult<()> {
+        if let Some(limit) = self.limit {
+            let remaining = limit - self.written;
+            if s.len() > remaining {
+                // never split a multi-byte character
+                let mut end = remaining;
+                while !s.is_char_boundary(end) {
+                    end -= 1;
+                }
+                (self.emit)(&s[..end]);
+                self.written += end;
+                return Err(ExecError::OutputLimit {
+                    limit,
+                    location: program.location(index),
+                });
+            }
         }
+        (self.emit)(s);
+        self.written += s.len();
+        Ok(())
     }
 }
 
+/// programs are equal, and hash the same, when they parse to the same
+/// instructions in the same dialect, whether or not they kept their source
+#[derive(Debug, Clone, Default)]
 pub struct Program {
     pub ast: Vec<Instruction>,
+    /// which rules the poem was parsed with, which decide how the registers
+    /// are used when it runs
+    pub dialect: Dialect,
+    /// the rest of what the poem was parsed with, so edited lines are
+    /// parsed again the same way
+    pub(crate) parse_options: ParseOptions,
+    /// the poem exactly as it was parsed, unless it was left out with
+    /// [`Program::create_without_source`]
+    pub(crate) source: Option<Arc<str>>,
+}
+
+impl PartialEq for Program {
+    fn eq(&self, other: &Program) -> bool {
+        self.ast == other.ast && self.dialect == other.dialect
+    }
+}
+
+impl Eq for Program {}
+
+impl Hash for Program {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.ast.hash(state);
+        self.dialect.hash(state);
+    }
 }
 
 impl Program {
+    /// where the instruction at `index` is in the poem
+    pub fn location(&self, index: usize) -> SourceLocation {
+        SourceLocation {
+            line_number: index + 1,
+            line_text: self.ast[index].line().trim().to_string(),
+        }
+    }
+
     pub fn create(source: &str) -> Program {
+        Program::create_with_dialect(source, Dialect::Standard)
+    }
+
+    /// [`Program::create`] with the rules of `dialect`
+    pub fn create_with_dialect(source: &str, dialect: Dialect) -> Program {
+        Program::create_with_options(
+            source,
+            &ParseOptions {
+                dialect,
+                ..ParseOptions::default()
+            },
+        )
+    }
+
+    /// [`Program::create`], parsing as `options` says
+    pub fn create_with_options(source: &str, options: &ParseOptions) -> Program {
         Program {
-            ast: parser::parse(source),
+            ast: parser::parse_with_options(source, options),
+            dialect: options.dialect,
+            parse_options: options.clone(),
+            source: Some(source.into()),
         }
     }
 
-    pub fn execute(&self) -> String {
-        let mut mem = Memory::new();
-        let mut output: String = String::new();
-
-        let mut instruction_pointer: usize = 0;
-
-        log::info!(
-            "{: <51} | {: ^4} | {: ^4} | {: ^7}",
-            "instruction",
-            "r0",
-            "r1",
-            "stack"
-        );
-        log::info!("{:-<51} | {:-^4} | {:-^4} | {:-^7}", "", "", "", "");
-
-        'outer: while let Some(ins) = self.ast.get(instruction_pointer) {
-            let Instruction {
-                instruction,
-                register: reg,
-                ref line,
-            } = *ins;
-
-            match instruction {
-                InsType::ConditionalGoto(syllables) => {
-                    if mem.get_active(reg) > syllables as i64 {
-                        instruction_pointer =
-                            (mem.get_inactive(reg).abs() as usize) % (self.ast.len() as usize);
-                        continue 'outer;
-                    }
-                }
-                InsType::Negate => mem.negate(reg),
-                InsType::Multiply => mem.multiply(reg),
-                InsType::Add => mem.add(reg),
-                InsType::PrintChar => {
-                    let printable = (mem.get_active(reg).abs() % std::u8::MAX as i64) as u8;
-                    output = format!("{}{}", output, printable as char);
-                }
-                InsType::PrintValue => output = format!("{}{}", output, mem.get_active(reg)),
-                InsType::Pop => mem.pop(reg),
-                InsType::Push => mem.push(reg),
-                InsType::Store(syllables) => mem.store_syllables(reg, syllables as i64),
+    /// [`Program::create`] with the syllables of its lines counted by
+    /// `counter` rather than the dictionary, see
+    /// [`ParseOptions::syllable_counter`]
+    pub fn create_with_counter(source: &str, counter: impl SyllableCounter + 'static) -> Program {
+        Program::create_with_options(
+            source,
+            &ParseOptions {
+                syllable_counter: Some(CustomCounter::new(counter)),
+                ..ParseOptions::default()
+            },
+        )
+    }
+
+    /// [`Program::create`] without keeping a copy of `source`, for holding
+    /// a lot of programs at once. [`Program::source`] is empty, and errors
+    /// are rendered from the text of the parsed lines
+    pub fn create_without_source(source: &str) -> Program {
+        Program {
+            source: None,
+            ..Program::create(source)
+        }
+    }
+
+    /// the poem exactly as it was parsed, or nothing if the program was
+    /// made without one, e.g. with [`Program::from_instructions`] or
+    /// [`Program::create_without_source`]
+    pub fn source(&self) -> &str {
+        self.source.as_deref().unwrap_or_default()
+    }
+
+    /// `err` followed by the line it happened on, with what made the line
+    /// the instruction it is underlined, or the whole line for a store:
+    ///
+    /// ```text
+    /// error at line 2 ("Multiply Me."): arithmetic overflow
+    ///   |
+    /// 2 | Multiply Me.
+    ///   | ^^^^^^^^
+    /// ```
+    pub fn render_error(&self, err: &ExecError) -> String {
+        let location = err.location();
+        let index = location.line_number - 1;
+        let line = analysis::lines(self.source())
+            .nth(index)
+            .map(|(_, line)| line)
+            .or_else(|| self.ast.get(index).map(Instruction::line));
+        let line = match line {
+            Some(line) => line,
+            None => return err.to_string(),
+        };
+        let mut spans = match self.ast.get(index) {
+            Some(ins) => parser::triggers(ins.instruction, line, &self.parse_options),
+            None => vec![],
+        };
+        if spans.is_empty() {
+            spans.push(analysis::trimmed(0, line));
+        }
+
+        // the carets line up under the line as long as its characters are
+        // all the same width, keeping tabs so they expand the same way
+        let mut underline = String::new();
+        let mut at = 0;
+        for span in spans {
+            for c in line[at..span.start].chars() {
+                underline.push(if c == '\t' { '\t' } else { ' ' });
+            }
+            underline.extend(line[span.clone()].chars().map(|_| '^'));
+            at = span.end;
+        }
+        let gutter = " ".repeat(location.line_number.to_string().len());
+        format!(
+            "{}\n{} |\n{} | {}\n{} | {}",
+            err, gutter, location.line_number, line, gutter, underline
+        )
+    }
+
+    /// a hash of the instructions that's the same across runs, platforms and
+    /// versions of rust, e.g. for caching results by program. the text of the
+    /// lines isn't hashed, so poems that compile to the same instructions
+    /// share a fingerprint. it's the 64-bit FNV-1a of each instruction's
+    /// mnemonic, the syllables it carries as little-endian u64s and its
+    /// register, followed by `extended` for [`Dialect::Extended`] programs
+    pub fn fingerprint(&self) -> u64 {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        self.write_fingerprint(&mut |byte| {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        });
+        hash
+    }
+
+    /// [`Program::fingerprint`] as 32 hex characters, from the 128-bit
+    /// FNV-1a of the same bytes, for where a collision costs more than a
+    /// few more characters, like a manifest of a whole corpus
+    pub fn fingerprint_hex(&self) -> String {
+        let mut hash: u128 = 0x6c62_272e_07bb_0142_62b8_2175_6295_c58d;
+        self.write_fingerprint(&mut |byte| {
+            hash ^= u128::from(byte);
+            hash = hash.wrapping_mul(0x0000_0000_0100_0000_0000_0000_0000_013b);
+        });
+        format!("{:032x}", hash)
+    }
+
+    /// the bytes the fingerprints hash
+    fn write_fingerprint(&self, write_byte: &mut dyn FnMut(u8)) {
+        let mut write = |bytes: &[u8]| bytes.iter().for_each(|&byte| write_byte(byte));
+        for ins in &self.ast {
+            write(ins.instruction.mnemonic().as_bytes());
+            let syllables: &[u32] = match ins.instruction {
                 InsType::ConditionalPush {
                     prev_syllables,
                     cur_syllables,
-                } => {
-                    if mem.get_active(reg) < mem.get_inactive(reg) {
-                        mem.push_to_stack(prev_syllables as i64);
-                    } else {
-                        mem.push_to_stack(cur_syllables as i64);
-                    }
+                } => &[prev_syllables, cur_syllables],
+                InsType::ConditionalGoto(syllables) | InsType::Store(syllables) => &[syllables],
+                _ => &[],
+            };
+            for &syllables in syllables {
+                write(&(syllables as u64).to_le_bytes());
+            }
+            write(&[u8::from(ins.register)]);
+        }
+        // only extended programs hash it, so standard fingerprints stay the
+        // same as before there were dialects
+        if self.dialect == Dialect::Extended {
+            write(b"extended");
+        }
+    }
+
+    /// a program that runs `instructions` without parsing a poem, e.g. ones
+    /// read from json or generated by tests
+    pub fn from_instructions(instructions: Vec<Instruction>) -> Program {
+        Program {
+            ast: instructions,
+            dialect: Dialect::Standard,
+            parse_options: ParseOptions::default(),
+            source: None,
+        }
+    }
+
+    /// [`Program::create`], but returning an error instead of panicking if
+    /// the dictionary can't be loaded
+    pub fn try_create(source: &str) -> Result<Program> {
+        Ok(Program {
+            ast: parser::try_parse(source)?,
+            dialect: Dialect::Standard,
+            parse_options: ParseOptions::default(),
+            source: Some(source.into()),
+        })
+    }
+
+    /// read and parse a utf-8 poem, see [`io::decode`] for other encodings
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<Program> {
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .map_err(io::DecodeError::Io)?;
+        Program::try_create(&io::decode(&bytes, Encoding::Utf8)?)
+    }
+
+    /// parse `source` with its title, the first line that isn't blank,
+    /// replaced by `title`. the title's syllables are the conventional input
+    /// to a poem. the original indentation is kept so the title is stored in
+    /// the same register
+    pub fn with_title(source: &str, title: &str) -> Result<Program> {
+        Ok(Program::create(&retitle(source, title)?))
+    }
+
+    pub fn execute(&self) -> String {
+        self.execute_with_options(&ExecOptions::default())
+            .expect("execution without limits can't fail")
+    }
+
+    pub fn execute_with_options(&self, options: &ExecOptions) -> Result<String> {
+        self.execute_report(options).into_result()
+    }
+
+    /// execute the program on `mem` rather than an empty machine, leaving it
+    /// as the program did, e.g. to run several poems one after another on
+    /// the same registers and stack. every program starts from its first
+    /// line, whatever ran before it. a program that fails leaves `mem` as it
+    /// was before the instruction that failed
+    pub fn execute_with_memory(&self, mem: &mut Memory) -> Result<String> {
+        let mut interpreter = Interpreter::new(self);
+        interpreter.history_limit = 0;
+        std::mem::swap(&mut interpreter.mem, mem);
+        interpreter.mem.dialect = self.dialect;
+        let mut output = self.output_buffer();
+        let result = loop {
+            match interpreter.step_into(&mut output) {
+                Ok(Some(_)) => (),
+                Ok(None) => break Ok(output),
+                Err(err) => break Err(err.into()),
+            }
+        };
+        std::mem::swap(&mut interpreter.mem, mem);
+        result
+    }
+
+    /// execute the program with the bytes of `args` on the stack, see
+    /// [`ExecOptions::stack_seed_from`]
+    pub fn execute_with_args(&self, args: &[String]) -> Result<String> {
+        self.execute_with_options(&ExecOptions::stack_seed_from(args))
+    }
+
+    /// execute the program, collecting its output along with everything
+    /// else about the run. a run that fails still produces a report, with
+    /// the output up to the failure
+    pub fn execute_report(&self, options: &ExecOptions) -> ExecutionReport {
+        let mut output = self.output_buffer();
+        let mut report = self.run(options, &mut |s| output.push_str(s), None, true);
+        report.output = output;
+        report
+    }
+
+    /// [`Program::execute_report`], but handing output to `emit` as it's
+    /// produced (so the report's output is empty) and the interpreter to
+    /// `on_step` after every step
+    pub fn execute_report_with_hook<E, H>(
+        &self,
+        options: &ExecOptions,
+        mut emit: E,
+        mut on_step: H,
+    ) -> ExecutionReport
+    where
+        E: FnMut(&str),
+        H: FnMut(&Interpreter, &Step),
+    {
+        self.run(options, &mut emit, Some(&mut on_step), false)
+    }
+
+    /// execute the program, handing the machine state after every step to
+    /// `on_step`
+    pub fn execute_with_trace<F>(&self, options: &ExecOptions, mut on_step: F) -> Result<String>
+    where
+        F: FnMut(TraceRow),
+    {
+        let mut output = self.output_buffer();
+        self.run(
+            options,
+            &mut |s| output.push_str(s),
+            Some(&mut |interpreter: &Interpreter, step: &Step| {
+                on_step(interpreter.trace_row(step))
+            }),
+            false,
+        )
+        .error
+        .map_or(Ok(output), |err| Err(err.into()))
+    }
+
+    /// execute the program, handing output to `emit` as soon as it's
+    /// produced rather than collecting it. if the output limit is hit, the
+    /// output up to the limit has already been emitted
+    pub fn execute_streaming<E>(&self, options: &ExecOptions, mut emit: E) -> Result<()>
+    where
+        E: FnMut(&str),
+    {
+        self.run(options, &mut emit, None, false)
+            .error
+            .map_or(Ok(()), |err| Err(err.into()))
+    }
+
+    /// like `execute_streaming`, also handing the machine state after every
+    /// step to `on_step`
+    pub fn execute_streaming_with_trace<E, F>(
+        &self,
+        options: &ExecOptions,
+        mut emit: E,
+        mut on_step: F,
+    ) -> Result<()>
+    where
+        E: FnMut(&str),
+        F: FnMut(TraceRow),
+    {
+        self.run(
+            options,
+            &mut emit,
+            Some(&mut |interpreter: &Interpreter, step: &Step| {
+                on_step(interpreter.trace_row(step))
+            }),
+            false,
+        )
+        .error
+        .map_or(Ok(()), |err| Err(err.into()))
+    }
+
+    /// the most general way to execute a program: output is handed to `emit`
+    /// as it's produced and `on_step` sees the interpreter after every step.
+    /// returns the registers the program halted with
+    pub fn execute_with_hook<E, H>(
+        &self,
+        options: &ExecOptions,
+        mut emit: E,
+        mut on_step: H,
+    ) -> Result<Registers>
+    where
+        E: FnMut(&str),
+        H: FnMut(&Interpreter, &Step),
+    {
+        let report = self.run(options, &mut emit, Some(&mut on_step), false);
+        report
+            .error
+            .map_or(Ok(report.final_registers), |err| Err(err.into()))
+    }
+
+    /// execute the program, handing everything it prints to `io` instead of
+    /// collecting it as text
+    pub fn execute_io(&self, io: &mut impl PoemIo) -> Result<()> {
+        let on_step = |interpreter: &Interpreter, step: &Step| {
+            let ins = &self.ast[step.index];
+            // printing doesn't change the register, so it still holds what
+            // was printed
+            let value = interpreter.register(ins.register);
+            match ins.instruction {
+                InsType::PrintValue => io.put_value(value),
+                InsType::PrintChar => io.put_char(host::char_code(value)),
+                _ => (),
+            }
+        };
+        self.execute_with_hook(&ExecOptions::default(), |_| (), on_step)
+            .map(drop)
+    }
+
+    /// execute only the instructions in `range`, from `start` rather than
+    /// an empty machine, to see what part of a poem does on its own. the run
+    /// stops at the end of the range, or halts at a goto that leaves it,
+    /// which the returned state records. the range is run as if the line
+    /// before it had just run, which decides the inactive register of its
+    /// first line in [`Dialect::Extended`]
+    pub fn execute_range(
+        &self,
+        range: Range<usize>,
+        start: MachineState,
+    ) -> Result<(String, MachineState)> {
+        if range.start > range.end || range.end > self.ast.len() {
+            return Err(RangeError {
+                start: range.start,
+                end: range.end,
+                len: self.ast.len(),
+            }
+            .into());
+        }
+        let mut interpreter = Interpreter::new(self);
+        interpreter.history_limit = 0;
+        let Registers { r0, r1, r2, r3 } = start.registers;
+        interpreter.mem.registers = [r0, r1, r2, r3];
+        interpreter.mem.stack = start.stack;
+        interpreter.mem.tape = start.tape;
+        // the first line's inactive register in the extended dialect is the
+        // register of the line before it, as if that had just run
+        if let Some(before) = range.start.checked_sub(1) {
+            interpreter.mem.previous = self.ast[before].register;
+        }
+        interpreter.instruction_pointer = range.start;
+
+        let mut output = String::new();
+        let mut escaped_to = None;
+        while range.contains(&interpreter.instruction_pointer) {
+            let index = interpreter.instruction_pointer;
+            interpreter.step_into(&mut output)?;
+            let next = interpreter.instruction_pointer;
+            // falling through to the end of the range isn't leaving it
+            if next != index + 1 && !range.contains(&next) {
+                escaped_to = Some(next);
+                break;
+            }
+        }
+        let state = MachineState {
+            registers: interpreter.registers(),
+            stack: interpreter.mem.stack,
+            tape: interpreter.mem.tape,
+            escaped_to,
+        };
+        Ok((output, state))
+    }
+
+    /// execute the program, counting how many times each line runs in
+    /// `profile`. the counts are kept even if execution fails
+    pub fn execute_profiled(&self, options: &ExecOptions, profile: &mut Profile) -> Result<String> {
+        let mut output = self.output_buffer();
+        self.run(
+            options,
+            &mut |s| output.push_str(s),
+            Some(&mut |_: &Interpreter, step: &Step| profile.record(step)),
+            false,
+        )
+        .error
+        .map_or(Ok(output), |err| Err(err.into()))
+    }
+
+    /// room for what most poems print, about a number per line, so
+    /// collecting it rarely has to grow
+    fn output_buffer(&self) -> String {
+        String::with_capacity(self.ast.len() * 4)
+    }
+
+    fn run(
+        &self,
+        options: &ExecOptions,
+        emit: &mut dyn FnMut(&str),
+        on_step: Option<&mut StepHook>,
+        record_events: bool,
+    ) -> ExecutionReport {
+        let start = start_clock();
+        let mut interpreter = Interpreter::with_options(self, options);
+        let mut stats = RunStats {
+            hits: vec![0; self.ast.len()],
+            max_stack_depth: 0,
+        };
+        let mut output = OutputSink {
+            emit,
+            written: 0,
+            limit: options.max_output,
+            events: record_events.then(Vec::new),
+        };
+
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!(
+            "execute",
+            instructions = tracing::field::Empty,
+            output_bytes = tracing::field::Empty
+        )
+        .entered();
+        #[cfg(not(feature = "tracing"))]
+        if options.trace_sink.is_none() && options.trace_format == TraceFormat::Table {
+            log::info!(
+                "{: >4} | {: >5} | {: <51} | {: <11} | {: ^4} | {: ^4} | {: ^7}",
+                "line",
+                "index",
+                "instruction",
+                "op",
+                "r0",
+                "r1",
+                "stack"
+            );
+            log::info!(
+                "{:->4} | {:->5} | {:-<51} | {:-<11} | {:-^4} | {:-^4} | {:-^7}",
+                "",
+                "",
+                "",
+                "",
+                "",
+                "",
+                ""
+            );
+        }
+
+        let result = self.run_steps(&mut interpreter, &mut output, &mut stats, options, on_step);
+        #[cfg(feature = "tracing")]
+        {
+            span.record("instructions", interpreter.instructions_executed());
+            span.record("output_bytes", output.written as u64);
+        }
+
+        let mut per_opcode_counts = BTreeMap::new();
+        for (ins, &hits) in self.ast.iter().zip(&stats.hits) {
+            if hits > 0 {
+                *per_opcode_counts
+                    .entry(ins.instruction.mnemonic())
+                    .or_insert(0) += hits;
+            }
+        }
+        let error = result.err();
+        ExecutionReport {
+            output: String::new(),
+            events: output.events.unwrap_or_default(),
+            final_registers: interpreter.registers(),
+            final_stack: interpreter.mem.stack,
+            instructions_executed: interpreter.instructions_executed,
+            per_opcode_counts,
+            max_stack_depth: stats.max_stack_depth,
+            ignored_pops: interpreter.ignored_pops,
+            elapsed: start.map(|start| start.elapsed()).unwrap_or_default(),
+            halted_reason: HaltReason::of(error.as_ref()),
+            error,
+        }
+    }
+
+    fn run_steps(
+        &self,
+        interpreter: &mut Interpreter,
+        output: &mut OutputSink,
+        stats: &mut RunStats,
+        options: &ExecOptions,
+        mut on_step: Option<&mut StepHook>,
+    ) -> ExecResult<()> {
+        // what each step prints, reused so printing doesn't allocate. the
+        // longest i64 is 20 digits
+        let mut printed = String::with_capacity(20);
+        // only hooks see the text of a step, so it's only copied for them
+        let copy_output = on_step.is_some() || options.trace_sink.is_some();
+        while !interpreter.is_halted() {
+            if options
+                .cancel
+                .as_ref()
+                .is_some_and(CancelToken::is_cancelled)
+            {
+                return Err(ExecError::Cancelled {
+                    location: self.location(interpreter.instruction_pointer()),
+                });
+            }
+            if let Some(limit) = options.max_instructions {
+                if interpreter.instructions_executed() >= limit {
+                    return Err(ExecError::FuelExhausted {
+                        limit,
+                        location: self.location(interpreter.instruction_pointer()),
+                    });
                 }
-                InsType::Goto => {
-                    instruction_pointer =
-                        (mem.get_active(reg).abs() as usize) % (self.ast.len() as usize);
-                    continue 'outer;
+            }
+
+            printed.clear();
+            let step = match interpreter.step_into(&mut printed)? {
+                Some(index) => Step {
+                    index,
+                    output: (copy_output && !printed.is_empty()).then(|| printed.clone()),
+                },
+                None => break,
+            };
+            stats.hits[step.index] += 1;
+            stats.max_stack_depth = stats.max_stack_depth.max(interpreter.mem.stack.len());
+
+            match options.trace_sink.as_ref() {
+                Some(sink) => sink.send(interpreter.trace_row(&step)),
+                #[cfg(feature = "tracing")]
+                None => tracing::info!(
+                    line = step.index + 1,
+                    instruction = %self.ast[step.index].line().trim(),
+                    r0 = interpreter.mem.registers[0],
+                    r1 = interpreter.mem.registers[1],
+                    stack = ?interpreter.mem.stack,
+                    "step"
+                ),
+                #[cfg(not(feature = "tracing"))]
+                None => self.log_step(interpreter, &step, options.trace_format),
+            }
+
+            if let Some(on_step) = on_step.as_mut() {
+                on_step(interpreter, &step);
+            }
+
+            if !printed.is_empty() {
+                output.write(self, step.index, &printed)?;
+                if let Some(events) = output.events.as_mut() {
+                    let ins = &self.ast[step.index];
+                    events.push(OutputEvent::of(
+                        ins.instruction,
+                        interpreter.register(ins.register),
+                    ));
                 }
-                InsType::Noop => (),
             }
+        }
+        Ok(())
+    }
 
-            log::info!(
-                "{: <51} | {: ^4} | {: ^4} | {:^?}",
-                line,
-                mem.register0,
-                mem.register1,
+    #[cfg(not(feature = "tracing"))]
+    fn log_step(&self, interpreter: &Interpreter, step: &Step, format: TraceFormat) {
+        let mem = &interpreter.mem;
+        match format {
+            TraceFormat::Table => log::info!(
+                "{: >4} | {: >5} | {: <51} | {: <11} | {: ^4} | {: ^4} | {:^?}",
+                step.index + 1,
+                step.index,
+                self.ast[step.index].line(),
+                self.ast[step.index].instruction.mnemonic(),
+                mem.registers[0],
+                mem.registers[1],
                 mem.stack
-            );
+            ),
+            TraceFormat::Json { max_stack } => {
+                // don't build the json for nobody
+                if !log::log_enabled!(target: TRACE_TARGET, log::Level::Info) {
+                    return;
+                }
+                let stack = if mem.stack.len() <= max_stack {
+                    format!(",\"stack\":{:?}", mem.stack)
+                } else {
+                    String::new()
+                };
+                log::info!(
+                    target: TRACE_TARGET,
+                    "{{\"step\":{},\"line\":{},\"instruction\":{},\"r0\":{},\"r1\":{},\"stack_len\":{}{}}}",
+                    interpreter.instructions_executed - 1,
+                    step.index + 1,
+                    super::trace::json_string(self.ast[step.index].instruction.mnemonic()),
+                    mem.registers[0],
+                    mem.registers[1],
+                    mem.stack.len(),
+                    stack
+                );
+            }
+        }
+    }
+
+    /// compile the program with the jit without running it, to see what
+    /// compiling it takes
+    #[cfg(feature = "jit")]
+    pub fn compile(&self) -> Result<CompileStats> {
+        let mut jit = JIT::new()?;
+        let (_, stats) = jit.compile(&self.ast, self.dialect)?;
+        Ok(stats)
+    }
+
+    /// jit execute the program, printing what it prints. the jit's stack
+    /// holds 128 values, and a push past that stops the program with
+    /// [`JitError::StackOverflow`]
+    #[cfg(feature = "jit")]
+    pub fn jit_execute(&self) -> Result<()> {
+        let mut jit = JIT::new()?;
+        let (func, _) = jit.compile(&self.ast, self.dialect)?;
+        if !rt::run(func) {
+            return Err(jit_stack_overflow().into());
+        }
+
+        Ok(())
+    }
+
+    /// jit execute the program, collecting its output into a string instead
+    /// of printing it
+    #[cfg(feature = "jit")]
+    pub fn jit_execute_captured(&self) -> Result<String> {
+        self.jit_execute_io(host::StringIo::new())
+            .map(host::StringIo::into_string)
+    }
+
+    /// jit execute the program, handing everything it prints to `io`. the
+    /// host is handed back when the program halts, or dropped if it
+    /// overflows the stack like in [`Program::jit_execute`]
+    #[cfg(feature = "jit")]
+    pub fn jit_execute_io<T: PoemIo + 'static>(&self, io: T) -> Result<T> {
+        let mut jit = JIT::new()?;
+        let (func, _) = jit.compile(&self.ast, self.dialect)?;
+        match rt::with_host(io, func) {
+            (io, true) => Ok(io),
+            (_, false) => Err(jit_stack_overflow().into()),
+        }
+    }
+}
+
+#[cfg(feature = "jit")]
+fn jit_stack_overflow() -> JitError {
+    JitError::StackOverflow {
+        limit: crate::jit::STACK_SIZE as usize,
+    }
+}
+
+/// `source` with its title replaced, see [`Program::with_title`]
+pub(crate) fn retitle(source: &str, title: &str) -> TitleResult<String> {
+    if title.contains('\n') {
+        return Err(TitleError::MultilineTitle);
+    }
+    let mut lines: Vec<&str> = source.lines().collect();
+    let index = title::title_index(source).ok_or(TitleError::EmptyPoem)?;
+    let line = lines[index];
+    let indented = format!("{}{}", &line[..line.len() - line.trim_start().len()], title);
+    lines[index] = &indented;
+    Ok(lines.join("\n"))
+}
+
+/// prints the poem the program was parsed from, which parses back to the
+/// same instructions. instructions that weren't parsed from a poem have no
+/// line to print, so they come out blank
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for ins in &self.ast {
+            writeln!(f, "{}", ins.line())?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Program {
+    type Err = Error;
+
+    fn from_str(source: &str) -> Result<Program> {
+        Program::try_create(source)
+    }
+}
+
+impl TryFrom<&str> for Program {
+    type Error = Error;
+
+    fn try_from(source: &str) -> Result<Program> {
+        Program::try_create(source)
+    }
+}
 
-            instruction_pointer += 1;
-        }
+/// read a poem from `path`, naming the path in the error if it can't be read
+pub(crate) fn read_file(path: &Path, encoding: Encoding) -> Result<String> {
+    io::read_to_string(path, encoding).map_err(|err| match err {
+        Error::Decode(source) => Error::Input {
+            path: path.to_path_buf(),
+            source,
+        },
+        err => err,
+    })
+}
 
-        output
-    }
+/// parse and execute `source`. only a dictionary that can't be loaded is an
+/// error, a run that fails is reported in the [`ExecutionReport`]
+pub fn run_str(source: &str, options: &ExecOptions) -> Result<ExecutionReport> {
+    Ok(Program::try_create(source)?.execute_report(options))
+}
 
-    #[cfg(feature = "jit")]
-    pub fn jit_execute(&self) -> JitResult<()> {
-        let mut jit = JIT::default();
-        let func = jit.compile(&self.ast)?;
-        func();
+/// [`run_str`] on the utf-8 poem at `path`
+pub fn run_file(path: impl AsRef<Path>, options: &ExecOptions) -> Result<ExecutionReport> {
+    run_str(&read_file(path.as_ref(), Encoding::Utf8)?, options)
+}
 
-        Ok(())
+/// [`run_str`] on each of `sources`, in parallel with the `parallel`
+/// feature. the results are in the same order as the sources, and the limits
+/// in `options` apply to each run separately
+pub fn run_many(sources: &[&str], options: &ExecOptions) -> Vec<Result<ExecutionReport>> {
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        sources
+            .par_iter()
+            .map(|source| run_str(source, options))
+            .collect()
     }
+    #[cfg(not(feature = "parallel"))]
+    sources
+        .iter()
+        .map(|source| run_str(source, options))
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::AllitOptions;
     use pretty_assertions::assert_eq;
 
+    /// the error a run or a step failed with
+    fn exec_error<T: fmt::Debug>(result: Result<T>) -> ExecError {
+        match result {
+            Err(Error::Exec(err)) => err,
+            other => panic!("expected an ExecError, got {:?}", other),
+        }
+    }
+
     #[test]
     fn mem_get_inactive() {
         let mut mem = Memory::new();
@@ -205,6 +1672,7 @@ mod tests {
         assert_eq!(mem.stack, vec![1, 2]);
     }
 
+    #[cfg(feature = "bundled-dictionary")]
     #[test]
     fn alliteration() {
         let alliteration_program = r#"
@@ -216,14 +1684,14 @@ word.
 "#
         .trim_start();
 
+        // a misclassified line loops forever, so fail instead of hanging
         let program = Program::create(alliteration_program);
-        let result = program.execute();
-        assert_eq!(result, "");
+        let result = program.execute_with_options(&ExecOptions::with_max_instructions(1000));
+        assert_eq!(result.unwrap(), "");
     }
 
-    #[test]
-    fn rhyming() {
-        let rhyming_program = r#"
+    #[cfg(feature = "bundled-dictionary")]
+    const RHYMING: &str = r#"
 somebody once told me 
     he took a new elf 
 and stabbed it with a shelf
@@ -235,11 +1703,62 @@ pop,
 print.
 "#;
 
-        let program = Program::create(rhyming_program);
+    #[cfg(feature = "bundled-dictionary")]
+    #[test]
+    fn rhyming() {
+        let program = Program::create(RHYMING);
         let result = program.execute();
         assert_eq!(result, "64");
     }
 
+    #[cfg(feature = "bundled-dictionary")]
+    #[test]
+    fn trace_sink() {
+        let rows = Arc::new(Mutex::new(Vec::new()));
+        let collected = Arc::clone(&rows);
+        let options = ExecOptions {
+            trace_sink: Some(TraceSink::new(move |row| {
+                collected.lock().unwrap().push(row)
+            })),
+            ..ExecOptions::default()
+        };
+        let program = Program::create(RHYMING);
+        assert_eq!(program.execute_with_options(&options).unwrap(), "64");
+
+        let rows = rows.lock().unwrap();
+        assert_eq!(rows.len(), 10);
+        assert_eq!(
+            rows[0],
+            TraceRow {
+                index: 0,
+                line_number: 1,
+                line: "".to_string(),
+                instruction: InsType::Noop,
+                register: Register::Register0,
+                r0: 0,
+                r1: 0,
+                stack: vec![],
+                output: String::new(),
+            }
+        );
+        assert_eq!(
+            rows[9],
+            TraceRow {
+                index: 9,
+                line_number: 10,
+                line: "print.".to_string(),
+                instruction: InsType::PrintValue,
+                register: Register::Register0,
+                r0: 4,
+                r1: 5,
+                stack: vec![],
+                output: "4".to_string(),
+            }
+        );
+        assert_eq!(options.clone(), options);
+    }
+
+    #[cfg(feature = "bundled-dictionary")]
     #[test]
     fn factorial() {
         let factorial_program = r#"
@@ -272,6 +1791,1083 @@ how lovely can it be?
         assert_eq!(program.execute(), five_factorial_res);
     }
 
+    #[cfg(feature = "bundled-dictionary")]
+    #[test]
+    fn fuel() {
+        let looping = Program::create("sells sea shells");
+        assert_eq!(
+            exec_error(looping.execute_with_options(&ExecOptions::with_max_instructions(1000))),
+            ExecError::FuelExhausted {
+                limit: 1000,
+                location: looping.location(0)
+            }
+        );
+
+        let program = Program::create(include_str!("../poems/lovely-poem.eso"));
+        assert_eq!(
+            program
+                .execute_with_options(&ExecOptions::with_max_instructions(1000))
+                .unwrap(),
+            "24\n"
+        );
+    }
+
+    #[cfg(feature = "bundled-dictionary")]
+    #[test]
+    fn output_limit() {
+        let program = Program::create("fish\nprint.\nsells sea shells");
+        let mut streamed = String::new();
+        let result = program.execute_streaming(&ExecOptions::with_max_output(1024), |s| {
+            streamed.push_str(s)
+        });
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "error at line 2 (\"print.\"): output limit of 1024 bytes reached"
+        );
+        assert_eq!(streamed, "1".repeat(1024));
+
+        let program = Program::create(include_str!("../poems/lovely-poem.eso"));
+        assert_eq!(
+            program
+                .execute_with_options(&ExecOptions::with_max_output(3))
+                .unwrap(),
+            "24\n"
+        );
+        assert_eq!(
+            exec_error(program.execute_with_options(&ExecOptions::with_max_output(2))),
+            ExecError::OutputLimit {
+                limit: 2,
+                location: program.location(17)
+            }
+        );
+    }
+
+    #[cfg(feature = "bundled-dictionary")]
+    #[test]
+    fn report() {
+        let program = Program::create(include_str!("../poems/lovely-poem.eso"));
+        let report = program.execute_report(&ExecOptions::default());
+        let counts = [
+            ("add", 4),
+            ("cond-goto", 2),
+            ("multiply", 2),
+            ("negate", 2),
+            ("noop", 3),
+            ("pop", 5),
+            ("print-char", 1),
+            ("print-value", 1),
+            ("push", 4),
+            ("store", 6),
+        ];
+        assert_eq!(
+            report,
+            ExecutionReport {
+                output: "24\n".to_string(),
+                events: vec![OutputEvent::Value(24), OutputEvent::Char(b'\n')],
+                final_registers: Registers {
+                    r0: 10,
+                    r1: 24,
+                    ..Registers::default()
+                },
+                final_stack: vec![],
+                instructions_executed: 30,
+                per_opcode_counts: counts.iter().copied().collect(),
+                max_stack_depth: 1,
+                // the first pop comes before anything was pushed
+                ignored_pops: 1,
+                elapsed: report.elapsed,
+                halted_reason: HaltReason::Completed,
+                error: None,
+            }
+        );
+        assert!(report.completed());
+        assert_eq!(
+            report.per_opcode_counts.values().sum::<u64>(),
+            report.instructions_executed
+        );
+    }
+
+    #[test]
+    fn fuel_limited_report() {
+        let looping = Program::create(
+            "fish
+print.
+sells sea shells",
+        );
+        let report = looping.execute_report(&ExecOptions::with_max_instructions(10));
+        assert_eq!(report.output, "11111");
+        assert_eq!(
+            report.final_registers,
+            Registers {
+                r0: 1,
+                r1: 0,
+                ..Registers::default()
+            }
+        );
+        assert_eq!(report.final_stack, Vec::<i64>::new());
+        assert_eq!(report.instructions_executed, 10);
+        assert_eq!(
+            report.per_opcode_counts,
+            [("goto", 4), ("print-value", 5), ("store", 1)]
+                .iter()
+                .copied()
+                .collect()
+        );
+        assert_eq!(report.max_stack_depth, 0);
+        assert_eq!(report.halted_reason, HaltReason::FuelExhausted);
+        assert!(!report.completed());
+        assert_eq!(
+            exec_error(report.into_result()),
+            ExecError::FuelExhausted {
+                limit: 10,
+                location: looping.location(2)
+            }
+        );
+    }
+
+    #[test]
+    fn cancelled() {
+        let looping = Program::create("sells sea shells");
+        let token = CancelToken::new();
+        let options = ExecOptions::with_cancel_token(token.clone());
+        let mut steps = 0;
+        let report = looping.execute_report_with_hook(
+            &options,
+            |_| (),
+            |_, _| {
+                steps += 1;
+                if steps == 100 {
+                    token.cancel();
+                }
+            },
+        );
+        assert_eq!(report.halted_reason, HaltReason::Cancelled);
+        assert_eq!(report.instructions_executed, 100);
+        assert_eq!(
+            report.error.unwrap().to_string(),
+            "error at line 1 (\"sells sea shells\"): cancelled before the program halted"
+        );
+        assert!(token.is_cancelled());
+        assert_eq!(options, ExecOptions::with_cancel_token(token));
+        assert_ne!(options, ExecOptions::with_cancel_token(CancelToken::new()));
+    }
+
+    #[test]
+    fn interpreter_steps() {
+        let program = Program::create("fish\nprint.");
+        let mut interpreter = Interpreter::new(&program);
+        assert_eq!(
+            interpreter.step().unwrap(),
+            Some(Step {
+                index: 0,
+                output: None
+            })
+        );
+        assert_eq!(interpreter.register(Register::Register0), 1);
+        interpreter.set_register(Register::Register0, 42);
+        assert_eq!(
+            interpreter.step().unwrap(),
+            Some(Step {
+                index: 1,
+                output: Some("42".to_string())
+            })
+        );
+        assert!(interpreter.is_halted());
+        assert_eq!(interpreter.step().unwrap(), None);
+        assert_eq!(interpreter.instructions_executed(), 2);
+    }
+
+    #[test]
+    fn execute_with_memory() {
+        let pushes = Program::create("cat\nwe push -\nlovely poem\nwe push -");
+        let prints = Program::create("we pop,\nprint it.\nwe pop,\nprint it.");
+        let mut mem = Memory::new();
+        let mut output = pushes.execute_with_memory(&mut mem).unwrap();
+        assert_eq!(mem.stack(), &[1, 4]);
+        output += &prints.execute_with_memory(&mut mem).unwrap();
+        assert_eq!(output, "41");
+        assert!(mem.stack().is_empty());
+        assert_eq!(mem.registers().r0, 1);
+
+        // the second program starts from its first line, on what was left
+        let mut mem = Memory::with(0, 0, vec![7, 2]);
+        assert_eq!(prints.execute_with_memory(&mut mem).unwrap(), "27");
+        assert_eq!(mem.registers().r0, 7);
+        assert!(mem.stack().is_empty());
+    }
+
+    #[test]
+    fn execute_range() {
+        let program = Program::create(include_str!("../poems/original-factorial.eso"));
+        let registers = |r0, r1| Registers {
+            r0,
+            r1,
+            ..Registers::default()
+        };
+        // the loop body with the machine as it is the first time round for a
+        // title of 5 syllables, multiplying down until r0 is 2
+        let start = MachineState {
+            registers: registers(5, 5),
+            ..MachineState::default()
+        };
+        let (output, state) = program.execute_range(2..12, start.clone()).unwrap();
+        assert_eq!(output, "");
+        assert_eq!(
+            state,
+            MachineState {
+                registers: registers(2, 2),
+                stack: vec![120],
+                ..MachineState::default()
+            }
+        );
+
+        // the loop's goto back to line 3 leaves a range starting after it
+        let (_, state) = program.execute_range(4..12, start).unwrap();
+        assert_eq!(state.escaped_to, Some(2));
+        assert_eq!(state.registers, registers(4, 2));
+
+        // the last stanza prints whatever is left on the stack
+        let start = MachineState {
+            stack: vec![120],
+            ..MachineState::default()
+        };
+        let (output, state) = program.execute_range(12..16, start).unwrap();
+        assert_eq!(output, "120\n");
+        assert!(state.stack.is_empty());
+
+        assert_eq!(
+            program
+                .execute_range(12..17, MachineState::default())
+                .unwrap_err()
+                .to_string(),
+            "instructions 12..17 aren't in a program of 16"
+        );
+    }
+
+    #[cfg(feature = "bundled-dictionary")]
+    #[test]
+    fn execute_range_extended() {
+        let program = Program::create_with_dialect(
+            "lovely poem\n        a cat\n  as one\n    we are done\n",
+            Dialect::Extended,
+        );
+        assert_eq!(program.ast[2].instruction, InsType::Add);
+        assert_eq!(
+            program.ast[3].instruction,
+            InsType::ConditionalPush {
+                prev_syllables: 2,
+                cur_syllables: 3
+            }
+        );
+        let start = MachineState {
+            registers: Registers {
+                r0: 4,
+                r1: 1,
+                r3: 5,
+                ..Registers::default()
+            },
+            ..MachineState::default()
+        };
+
+        // the rhyming line pushes the syllables of the line before it while
+        // r0 < r1, and its own otherwise
+        let (_, state) = program.execute_range(3..4, start.clone()).unwrap();
+        assert_eq!(state.stack, vec![3]);
+
+        // the add after the line in r3 adds r3, which makes r1 bigger than
+        // r0 by the time the rhyming line runs
+        let (_, state) = program.execute_range(2..4, start).unwrap();
+        assert_eq!(state.registers.r1, 6);
+        assert_eq!(state.stack, vec![2]);
+    }
+
+    #[cfg(feature = "bundled-dictionary")]
+    #[test]
+    fn interpreter_steps_back() {
+        let program = Program::create(include_str!("../poems/lovely-poem.eso"));
+        let run = |interpreter: &mut Interpreter, output: &mut String, steps| {
+            for _ in 0..steps {
+                if let Some(printed) = interpreter.step().unwrap().and_then(|step| step.output) {
+                    output.push_str(&printed);
+                }
+            }
+        };
+
+        let mut uninterrupted = Interpreter::new(&program);
+        let mut expected = String::new();
+        run(&mut uninterrupted, &mut expected, 10_000);
+        assert_eq!(expected, "24\n");
+
+        let mut interpreter = Interpreter::new(&program);
+        let mut output = String::new();
+        assert!(matches!(
+            interpreter.step_back(),
+            Err(Error::Rewind(RewindError::AtStart))
+        ));
+        run(&mut interpreter, &mut output, 10);
+        let registers = interpreter.registers();
+        run(&mut interpreter, &mut output, 3);
+        for _ in 0..3 {
+            interpreter.step_back().unwrap();
+        }
+        output.truncate(interpreter.output_len());
+        assert_eq!(interpreter.registers(), registers);
+        assert_eq!(interpreter.instructions_executed(), 10);
+        run(&mut interpreter, &mut output, 10_000);
+        assert_eq!(output, expected);
+        assert_eq!(interpreter.output_len(), 3);
+        assert_eq!(interpreter.registers(), uninterrupted.registers());
+        assert_eq!(interpreter.stack(), uninterrupted.stack());
+        assert_eq!(
+            interpreter.instructions_executed(),
+            uninterrupted.instructions_executed()
+        );
+
+        // back past the print on the third last line, and no further
+        interpreter.set_history_limit(3);
+        for _ in 0..3 {
+            interpreter.step_back().unwrap();
+        }
+        assert_eq!(interpreter.output_len(), 0);
+        assert_eq!(interpreter.instruction_pointer(), 15);
+        assert!(matches!(
+            interpreter.step_back(),
+            Err(Error::Rewind(RewindError::HistoryExhausted { limit: 3 }))
+        ));
+    }
+
+    #[test]
+    fn overflow() {
+        let program = Program::create("fish\n  birds\nsea like rain");
+        let mut interpreter = Interpreter::new(&program);
+        interpreter.step().unwrap();
+        interpreter.step().unwrap();
+        interpreter.set_register(Register::Register0, i64::MAX);
+        let err = interpreter.step().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "error at line 3 (\"sea like rain\"): arithmetic overflow"
+        );
+        assert_eq!(interpreter.register(Register::Register0), i64::MAX);
+        assert_eq!(interpreter.instruction_pointer(), 2);
+    }
+
+    #[cfg(feature = "bundled-dictionary")]
+    #[test]
+    fn extended_registers() {
+        // r2 and r3 hold 3 and 4 while r0 and r1 multiply, then r2 is
+        // multiplied by r3 since that's the register the line before used
+        let source = "    three blind mice\n        an elephant\nlovely poem\n  a bird\nFish\nprint.\n    print.\n        print.\n    Whales\n    print.";
+        let program = Program::create_with_dialect(source, Dialect::Extended);
+        let registers: Vec<u8> = program.ast.iter().map(|ins| ins.register.into()).collect();
+        assert_eq!(registers, vec![2, 3, 0, 1, 0, 0, 2, 3, 2, 2]);
+        assert_eq!(program.execute(), "83412");
+        #[cfg(feature = "jit")]
+        assert_eq!(program.jit_execute_captured().unwrap(), "83412");
+        let report = program.execute_report(&ExecOptions::default());
+        assert_eq!(
+            report.final_registers,
+            Registers {
+                r0: 8,
+                r1: 2,
+                r2: 12,
+                r3: 4
+            }
+        );
+
+        // the standard dialect still has two registers, paired as before
+        let standard = Program::create(source);
+        let registers: Vec<u8> = standard.ast.iter().map(|ins| ins.register.into()).collect();
+        assert_eq!(registers, vec![1, 1, 0, 1, 0, 0, 1, 1, 1, 1]);
+        assert_eq!(standard.execute(), "82216");
+    }
+
+    #[cfg(feature = "bundled-dictionary")]
+    #[test]
+    fn extended_arithmetic() {
+        let source = "lovely poem\n  a bird\nfish; whales\nprint.\nratio: sea\nprint.\n  …\nratio: sea\nprint.";
+        assert_eq!(
+            Program::create(source).ast[2].instruction,
+            InsType::Store(2)
+        );
+        let program = Program::create_with_dialect(source, Dialect::Extended);
+        assert_eq!(program.ast[2].instruction, InsType::Subtract);
+        assert_eq!(program.ast[4].instruction, InsType::Divide);
+        // 4 - 2, then 2 / 2, then 1 / 0
+        assert_eq!(program.execute(), "210");
+        #[cfg(feature = "jit")]
+        assert_eq!(program.jit_execute_captured().unwrap(), "210");
+
+        let strict = ExecOptions {
+            strict_division: true,
+            ..ExecOptions::default()
+        };
+        let report = program.execute_report(&strict);
+        assert_eq!(report.halted_reason, HaltReason::Error);
+        assert_eq!(report.output, "21");
+        assert_eq!(
+            report.final_registers,
+            Registers {
+                r0: 1,
+                r1: 0,
+                ..Registers::default()
+            }
+        );
+        let err = report.error.unwrap();
+        assert_eq!(
+            err,
+            ExecError::DivisionByZero {
+                location: program.location(7)
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "error at line 8 (\"ratio: sea\"): division by zero"
+        );
+
+        let mut interpreter = Interpreter::new(&program);
+        // the last divide, whose divisor is r1 since the line before used it
+        for _ in 0..7 {
+            interpreter.step().unwrap();
+        }
+        interpreter.set_register(Register::Register0, i64::MIN);
+        interpreter.set_register(Register::Register1, -1);
+        assert_eq!(
+            exec_error(interpreter.step()),
+            ExecError::Overflow {
+                location: program.location(7)
+            }
+        );
+    }
+
+    #[test]
+    fn stack_limit() {
+        let program = Program::create("lovely poem\npush-ed\npush-ed\nprint.\npush-ed");
+        let options = ExecOptions {
+            max_stack: Some(2),
+            ..ExecOptions::default()
+        };
+        let report = program.execute_report(&options);
+        assert_eq!(report.output, "4");
+        assert_eq!(report.final_stack, vec![4, 4]);
+        assert_eq!(report.halted_reason, HaltReason::Error);
+        assert_eq!(
+            report.error,
+            Some(ExecError::StackLimit {
+                limit: 2,
+                location: program.location(4)
+            })
+        );
+        assert_eq!(
+            program
+                .execute_with_options(&ExecOptions {
+                    max_stack: Some(3),
+                    ..ExecOptions::default()
+                })
+                .unwrap(),
+            "4"
+        );
+    }
+
+    #[test]
+    fn source() {
+        let source = "lovely poem\r\n\n  it is, a pop  \n\tprint.";
+        let program = Program::create(source);
+        assert_eq!(program.source(), source);
+        assert_eq!(Program::try_create(source).unwrap().source(), source);
+        let without = Program::create_without_source(source);
+        assert_eq!(without.source(), "");
+        assert_eq!(without, program);
+        assert_eq!(without.fingerprint(), program.fingerprint());
+        assert_eq!(Program::from_instructions(program.ast.clone()).source(), "");
+    }
+
+    #[test]
+    fn create_with_counter() {
+        // a syllable a letter, so r0 is 10 times r1's 5
+        let source = "lovely poem\n  six it\nand multiply Them\nprint.";
+        let program = Program::create_with_counter(source, |word: &str| word.len());
+        assert_eq!(program.ast[0].instruction, InsType::Store(10));
+        assert_eq!(program.source(), source);
+        assert_eq!(program.execute(), "50");
+        assert_eq!(Program::create(source).execute(), "8");
+    }
+
+    #[test]
+    fn error_excerpts() {
+        let strict = ExecOptions {
+            strict_pop: true,
+            ..ExecOptions::default()
+        };
+        let program = Program::create("lovely poem\n\t  it is, a pop\nprint.\n");
+        let err = exec_error(program.execute_with_options(&strict));
+        let expected = "error at line 2 (\"it is, a pop\"): pop from an empty stack
+  |
+2 | \t  it is, a pop
+  | \t       ^";
+        assert_eq!(program.render_error(&err), expected);
+        // the parsed lines are enough without the source
+        let without = Program::create_without_source("lovely poem\n\t  it is, a pop\nprint.\n");
+        assert_eq!(without.render_error(&err), expected);
+
+        // a store has nothing that made it one, so all of it is underlined
+        let program = Program::create(&("  a fish\n".repeat(9) + "  lovely poem\n  sea shells"));
+        let err = exec_error(program.execute_with_options(&ExecOptions::with_max_instructions(9)));
+        assert_eq!(
+            program.render_error(&err),
+            "error at line 10 (\"lovely poem\"): instruction limit of 9 reached before the program halted
+   |
+10 |   lovely poem
+   |   ^^^^^^^^^^^"
+        );
+
+        // a goto is underlined by the run of words that alliterate under
+        // the options it was parsed with
+        let options = ParseOptions {
+            alliteration: AllitOptions {
+                window: 2,
+                ..AllitOptions::default()
+            },
+            ..ParseOptions::default()
+        };
+        let program = Program::create_with_options("she quickly sells", &options);
+        let err = exec_error(program.execute_with_options(&ExecOptions::with_max_instructions(3)));
+        assert_eq!(
+            program.render_error(&err),
+            "error at line 1 (\"she quickly sells\"): instruction limit of 3 reached before the program halted
+  |
+1 | she quickly sells
+  | ^^^         ^^^^^"
+        );
+    }
+
+    #[cfg(feature = "bundled-dictionary")]
+    #[test]
+    fn seeded_stack() {
+        let program = Program::create(include_str!("../tests/fixtures/echo-args.eso"));
+        let args = ["hi".to_string(), "there".to_string()];
+        assert_eq!(program.execute_with_args(&args).unwrap(), "hi there");
+        assert_eq!(program.execute_with_args(&[]).unwrap(), "");
+        assert_eq!(
+            ExecOptions::stack_seed_from(&args[..1]).initial_stack,
+            vec![0, 105, 104]
+        );
+
+        let args: Vec<String> = ["1", "-2", "3"].iter().map(|arg| arg.to_string()).collect();
+        let options = ExecOptions::stack_seed_from_integers(&args).unwrap();
+        assert_eq!(options.initial_stack, vec![3, -2, 1]);
+        let report = Program::create("pop,\nprint.").execute_report(&options);
+        assert_eq!(report.output, "1");
+        assert_eq!(report.final_stack, vec![3, -2]);
+        let err = ExecOptions::stack_seed_from_integers(&["4".to_string(), "x".to_string()]);
+        match err {
+            Err(Error::Seed(err)) => assert_eq!(
+                err,
+                SeedError {
+                    position: 2,
+                    argument: "x".to_string()
+                }
+            ),
+            other => panic!("expected a SeedError, got {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "bundled-dictionary")]
+    #[test]
+    fn tape() {
+        let source = "three blind mice\n  a bird\n[fish]\nlovely poem\n  fish\n[whales]\n  a bird\nfish]\nprint.\n  fish\n[sea\nprint.\n  lovely poem\nfish]\nprint.\n  a bird\n[fish]";
+        let program = Program::create_with_dialect(source, Dialect::Extended);
+        assert_eq!(program.ast[2].instruction, InsType::TapeStore);
+        assert_eq!(program.ast[7].instruction, InsType::TapeLoad);
+        assert_eq!(program.ast[10].instruction, InsType::TapeLoad);
+        // cell 4 was never written
+        assert_eq!(program.execute(), "340");
+
+        let mut interpreter = Interpreter::new(&program);
+        while interpreter.step().unwrap().is_some() {}
+        let cells: HashMap<i64, i64> = vec![(2, 0), (1, 4)].into_iter().collect();
+        assert_eq!(interpreter.tape(), &cells);
+
+        // writing cell 2 again doesn't count against the limit
+        let limited = |limit| ExecOptions {
+            max_tape_cells: Some(limit),
+            ..ExecOptions::default()
+        };
+        assert_eq!(program.execute_with_options(&limited(2)).unwrap(), "340");
+        let err = exec_error(program.execute_with_options(&limited(1)));
+        assert_eq!(
+            err,
+            ExecError::TapeLimit {
+                limit: 1,
+                location: program.location(5)
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "error at line 6 (\"[whales]\"): tape limit of 1 cells reached"
+        );
+
+        #[cfg(feature = "jit")]
+        assert!(matches!(
+            program.jit_execute_captured(),
+            Err(Error::Jit(err)) if matches!(
+                *err,
+                JitError::Unsupported {
+                    index: 2,
+                    instruction: "tape-store"
+                }
+            )
+        ));
+    }
+
+    #[test]
+    fn read_char() {
+        let source = "read it!\nprint?\nread it!\nprint?\nread!\nprint.";
+        let program = Program::create_with_dialect(source, Dialect::Extended);
+        assert_eq!(program.ast[0].instruction, InsType::ReadChar);
+        let options = ExecOptions {
+            input: Some(InputSource::new(std_io::Cursor::new(b"hi".to_vec()))),
+            ..ExecOptions::default()
+        };
+        assert_eq!(program.execute_with_options(&options).unwrap(), "hi-1");
+        // the input was used up by the first run
+        assert_eq!(
+            program.execute_with_options(&options).unwrap(),
+            "\u{1}\u{1}-1"
+        );
+        assert_eq!(program.execute(), "\u{1}\u{1}-1");
+
+        struct Broken;
+        impl Read for Broken {
+            fn read(&mut self, _: &mut [u8]) -> std_io::Result<usize> {
+                Err(std_io::Error::other("unplugged"))
+            }
+        }
+        let options = ExecOptions {
+            input: Some(InputSource::new(Broken)),
+            ..ExecOptions::default()
+        };
+        assert_eq!(
+            program
+                .execute_with_options(&options)
+                .unwrap_err()
+                .to_string(),
+            "error at line 1 (\"read it!\"): couldn't read input: unplugged"
+        );
+    }
+
+    #[cfg(feature = "jit")]
+    #[test]
+    fn jit_read_char() {
+        struct Echo {
+            input: std_io::Cursor<Vec<u8>>,
+            output: host::StringIo,
+        }
+
+        impl PoemIo for Echo {
+            fn put_value(&mut self, value: i64) {
+                self.output.put_value(value)
+            }
+
+            fn put_char(&mut self, c: u8) {
+                self.output.put_char(c)
+            }
+
+            fn get_char(&mut self) -> Option<u8> {
+                let mut byte = [0];
+                match self.input.read(&mut byte).unwrap() {
+                    0 => None,
+                    _ => Some(byte[0]),
+                }
+            }
+        }
+
+        let source = "read it!\nprint?\nread it!\nprint?\nread!\nprint.";
+        let program = Program::create_with_dialect(source, Dialect::Extended);
+        let echo = Echo {
+            input: std_io::Cursor::new(b"hi".to_vec()),
+            output: host::StringIo::new(),
+        };
+        let echo = program.jit_execute_io(echo).unwrap();
+        assert_eq!(echo.output.into_string(), "hi-1");
+        // a host that doesn't read has no input
+        assert_eq!(program.jit_execute_captured().unwrap(), "\u{1}\u{1}-1");
+    }
+
+    #[cfg(feature = "bundled-dictionary")]
+    #[test]
+    fn cond_push_counts() {
+        // each case has a different count on the line before and on the
+        // rhyming line, so only the right one prints what's expected
+        let cases = [
+            // an r0 line with r0 < r1 pushes the line before's
+            (include_str!("../tests/fixtures/cond-push-r0-less.eso"), "2"),
+            (
+                include_str!("../tests/fixtures/cond-push-r0-not-less.eso"),
+                "2",
+            ),
+            // an r1 line still compares r0 with r1, not r1 with r0
+            (include_str!("../tests/fixtures/cond-push-r1-less.eso"), "4"),
+            (
+                include_str!("../tests/fixtures/cond-push-r1-not-less.eso"),
+                "4",
+            ),
+        ];
+        for (source, expected) in cases {
+            let program = Program::create(source);
+            assert_eq!(program.execute(), expected, "{}", source);
+            #[cfg(feature = "jit")]
+            assert_eq!(
+                program.jit_execute_captured().unwrap(),
+                expected,
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[cfg(feature = "bundled-dictionary")]
+    #[test]
+    fn with_title() {
+        let source = include_str!("../poems/lovely-poem.eso");
+        let program = Program::with_title(source, "a lovely poem").unwrap();
+        assert_eq!(program.ast[1].line(), "a lovely poem");
+        assert_eq!(program.ast[1].instruction, InsType::Store(5));
+        assert_eq!(program.execute(), "120\n");
+
+        // line two rhymes with the new title, so it becomes a conditional push
+        let source = "old title\nthe cat";
+        assert_eq!(
+            Program::create(source).ast[1].instruction,
+            InsType::Store(2)
+        );
+        let program = Program::with_title(source, "a hat").unwrap();
+        assert_eq!(
+            program.ast[1].instruction,
+            InsType::ConditionalPush {
+                prev_syllables: 2,
+                cur_syllables: 2
+            }
+        );
+
+        // indentation is kept, so the title still goes in r1
+        let program = Program::with_title("  old title\nprint.", "new").unwrap();
+        assert_eq!(program.ast[0].line(), "  new");
+        assert_eq!(program.ast[0].register, Register::Register1);
+
+        assert!(matches!(
+            Program::with_title("\n  \n", "title"),
+            Err(Error::Title(TitleError::EmptyPoem))
+        ));
+        assert!(matches!(
+            Program::with_title(source, "two\nlines"),
+            Err(Error::Title(TitleError::MultilineTitle))
+        ));
+    }
+
+    #[test]
+    fn from_str() {
+        let source = include_str!("../poems/lovely-poem.eso");
+        let program: Program = source.parse().unwrap();
+        assert_eq!(program.ast, Program::create(source).ast);
+        assert_eq!(Program::try_from(source).unwrap().ast, program.ast);
+        assert_eq!(
+            Program::from_reader(source.as_bytes()).unwrap().ast,
+            program.ast
+        );
+
+        let err = Program::from_reader(&b"lovely po\xe9m"[..]).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Decode(io::DecodeError::InvalidUtf8 { offset: 9 })
+        ));
+    }
+
+    #[test]
+    fn display_round_trips() {
+        let poems = concat!(env!("CARGO_MANIFEST_DIR"), "/poems");
+        for entry in std::fs::read_dir(poems).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|ext| ext == "eso") {
+                let source = io::read_to_string(&path, Encoding::Lossy).unwrap();
+                let printed = Program::create(&source).to_string();
+                assert_eq!(
+                    parser::parse(&printed),
+                    parser::parse(&source),
+                    "{}",
+                    path.display()
+                );
+            }
+        }
+        // a trailing tab is part of the last word, so it changes its syllables
+        let source = "lovely\t  \n  \n\tpoem";
+        assert_eq!(
+            Program::create(source).to_string(),
+            "lovely\t  \n  \n\tpoem\n"
+        );
+    }
+
+    #[cfg(feature = "bundled-dictionary")]
+    #[test]
+    fn fingerprint() {
+        let source = include_str!("../poems/lovely-poem.eso");
+        let program = Program::create(source);
+        assert_eq!(program, program.clone());
+        assert_eq!(program.fingerprint(), Program::create(source).fingerprint());
+        // the text doesn't matter, only the instructions
+        assert_eq!(
+            Program::create("lovely poem").fingerprint(),
+            Program::create("pretty sonnet").fingerprint()
+        );
+        assert_ne!(
+            Program::create("lovely poem").fingerprint(),
+            Program::create("lovely poetry").fingerprint()
+        );
+        assert_ne!(
+            Program::create("lovely poem").fingerprint(),
+            Program::create(" lovely poem").fingerprint()
+        );
+        assert_eq!(Program::default().ast, Vec::new());
+        // pinned so an accidental change to the hash shows up
+        assert_eq!(program.fingerprint(), 9542909945707945685);
+    }
+
+    #[test]
+    fn factorial_fingerprint() {
+        let source = include_str!("../poems/original-factorial.eso");
+        let program = Program::create(source);
+        // pinned, since caches and manifests keep them between releases
+        assert_eq!(program.fingerprint(), 6708867368483497235);
+        assert_eq!(
+            program.fingerprint_hex(),
+            "138f5f34490f1cad14477921512f128b"
+        );
+
+        // spaces inside and after lines, and other words with as many
+        // syllables, don't change what the poem does
+        let respaced: String = source
+            .lines()
+            .map(|line| format!("{}  \n", line.replace(' ', "   ")))
+            .collect();
+        let reworded = source.replace("lines of pebbles", "rows of pebbles");
+        for edited in &[respaced, reworded] {
+            let edited = Program::create(edited);
+            assert_eq!(edited.fingerprint(), program.fingerprint());
+            assert_eq!(edited.fingerprint_hex(), program.fingerprint_hex());
+        }
+
+        // more syllables, an indented line and a dialect all do
+        let edits = [
+            Program::create(&source.replace("other woodwork", "other woodworking")),
+            Program::create(&source.replace("as we demand", "  as we demand")),
+            Program::create_with_dialect(source, Dialect::Extended),
+        ];
+        for edited in &edits {
+            assert_ne!(edited.fingerprint(), program.fingerprint());
+            assert_ne!(edited.fingerprint_hex(), program.fingerprint_hex());
+        }
+    }
+
+    #[cfg(feature = "bundled-dictionary")]
+    #[test]
+    fn final_registers() {
+        let program = Program::create("three blind mice\n  fish\nnEgative");
+        let registers = program
+            .execute_with_hook(&ExecOptions::default(), |_| (), |_, _| ())
+            .unwrap();
+        assert_eq!(
+            registers,
+            Registers {
+                r0: -3,
+                r1: 1,
+                ..Registers::default()
+            }
+        );
+    }
+
+    #[cfg(feature = "jit")]
+    #[test]
+    fn jit_captured() {
+        let program = Program::create(include_str!("../poems/lovely-poem.eso"));
+        assert_eq!(program.jit_execute_captured().unwrap(), program.execute());
+    }
+
+    #[test]
+    fn pop_before_push() {
+        let program = Program::create("lovely poem\nprint.\npop,\nprint.");
+        let report = program.execute_report(&ExecOptions::default());
+        assert!(report.completed());
+        assert_eq!(report.output, "44");
+        assert_eq!(report.ignored_pops, 1);
+
+        let strict = ExecOptions {
+            strict_pop: true,
+            ..ExecOptions::default()
+        };
+        let report = program.execute_report(&strict);
+        assert_eq!(report.halted_reason, HaltReason::Error);
+        assert_eq!(report.output, "4");
+        assert_eq!(report.ignored_pops, 0);
+        assert_eq!(
+            report.final_registers,
+            Registers {
+                r0: 4,
+                r1: 0,
+                ..Registers::default()
+            }
+        );
+        let err = report.error.unwrap();
+        assert_eq!(
+            err,
+            ExecError::StackUnderflow {
+                location: program.location(2)
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "error at line 3 (\"pop,\"): pop from an empty stack"
+        );
+
+        // a pop after a push is fine either way
+        let program = Program::create("lovely poem\npush-\npop,\nprint.");
+        let report = program.execute_report(&strict);
+        assert_eq!(report.output, "4");
+        assert_eq!(report.ignored_pops, 0);
+    }
+
+    #[cfg(feature = "bundled-dictionary")]
+    #[test]
+    fn run_files() {
+        let poems = concat!(env!("CARGO_MANIFEST_DIR"), "/poems");
+        let report = run_file(
+            format!("{}/lovely-poem.eso", poems),
+            &ExecOptions::default(),
+        )
+        .unwrap();
+        assert!(report.completed());
+        assert_eq!(report.output, "24\n");
+
+        let missing = format!("{}/missing.eso", poems);
+        let err = run_file(&missing, &ExecOptions::default()).unwrap_err();
+        assert!(matches!(
+            &err,
+            Error::Input { path, source: io::DecodeError::Io(_) } if path.ends_with("missing.eso")
+        ));
+        assert!(
+            err.to_string()
+                .starts_with(&format!("error reading {}: ", missing)),
+            "{}",
+            err
+        );
+
+        let empty = std::env::temp_dir().join(format!("ashpaper-empty-{}.eso", std::process::id()));
+        std::fs::write(&empty, "").unwrap();
+        let report = run_file(&empty, &ExecOptions::default());
+        std::fs::remove_file(&empty).unwrap();
+        let report = report.unwrap();
+        assert_eq!(report.halted_reason, HaltReason::Completed);
+        assert_eq!(report.output, "");
+        assert_eq!(report.instructions_executed, 0);
+        assert_eq!(
+            run_str("", &ExecOptions::default())
+                .unwrap()
+                .final_registers,
+            report.final_registers
+        );
+    }
+
+    #[test]
+    fn run_many_in_order() {
+        let words = [
+            "lovely",
+            "poem",
+            "calculator",
+            "Turing",
+            "like",
+            "as",
+            "-",
+            ",",
+            ".",
+        ];
+        let sources: Vec<String> = (0..200)
+            .map(|seed: usize| {
+                let lines: Vec<String> = (0..1 + seed % 7)
+                    .map(|line| {
+                        let indent = if (seed + line).is_multiple_of(3) {
+                            "  "
+                        } else {
+                            ""
+                        };
+                        let words: Vec<&str> = (0..1 + (seed * 5 + line) % 4)
+                            .map(|word| words[(seed * 7 + line * 3 + word) % words.len()])
+                            .collect();
+                        format!("{}{}", indent, words.join(" "))
+                    })
+                    .collect();
+                // every so often a poem that jumps back to its first line forever
+                if seed.is_multiple_of(10) {
+                    format!("sells sea shells\n{}", lines.join("\n"))
+                } else {
+                    lines.join("\n")
+                }
+            })
+            .collect();
+        let sources: Vec<&str> = sources.iter().map(String::as_str).collect();
+        let options = ExecOptions::with_max_instructions(1000);
+
+        // only the time each run took can differ
+        let without_time = |report: Result<ExecutionReport>| ExecutionReport {
+            elapsed: std::time::Duration::ZERO,
+            ..report.unwrap()
+        };
+        let reports: Vec<_> = run_many(&sources, &options)
+            .into_iter()
+            .map(without_time)
+            .collect();
+        let expected: Vec<_> = sources
+            .iter()
+            .map(|source| without_time(run_str(source, &options)))
+            .collect();
+        assert_eq!(reports, expected);
+        assert_eq!(reports[10].halted_reason, HaltReason::FuelExhausted);
+        assert_eq!(reports[10].instructions_executed, 1000);
+        assert!(reports.iter().any(ExecutionReport::completed));
+    }
+
+    /// values to print, including both extremes and every power of ten
+    /// either side of zero
+    fn print_value_sweep() -> Vec<i64> {
+        let mut values = vec![i64::MIN, i64::MIN + 1, i64::MAX, 0, 1, -1];
+        let mut power = 1i64;
+        while let Some(next) = power.checked_mul(10) {
+            values.extend([power - 1, power, -power, -power + 1]);
+            power = next;
+        }
+        values.extend((-1000..1000).map(|i| i * 7919 * 7919));
+        values
+    }
+
+    #[test]
+    fn print_value() {
+        let program = Program::from_instructions(vec![Instruction::new(
+            InsType::PrintValue,
+            Register::Register1,
+            "print.",
+        )]);
+        let mut io = host::StringIo::new();
+        let mut expected = String::new();
+        for value in print_value_sweep() {
+            let mut interpreter = Interpreter::new(&program);
+            interpreter.set_register(Register::Register1, value);
+            let step = interpreter.step().unwrap().unwrap();
+            assert_eq!(step.output, Some(format!("{}", value)));
+            io.put_value(value);
+            expected += &value.to_string();
+        }
+        assert_eq!(io.output, expected);
+    }
+
+    #[cfg(feature = "bundled-dictionary")]
     #[test]
     fn logging() {
         // everything should work as expected if logging is enabled.
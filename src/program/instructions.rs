@@ -0,0 +1,312 @@
+//! what each instruction does to the interpreter, shared by both engines:
+//! the classic one calls these from its match, and the threaded one from
+//! the table it lowers the program to. each returns the index of the
+//! instruction to run next
+use super::{ExecError, ExecResult, Interpreter};
+use crate::{
+    host,
+    parser::{InsType, Register},
+};
+
+/// runs an instruction at the index it's given
+pub(super) type Run = fn(&mut Interpreter<'_>, Operands, usize, &mut String) -> ExecResult<usize>;
+
+/// what an instruction runs on: its register, and the syllables it
+/// carries, if any
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Operands {
+    pub(super) register: Register,
+    syllables: [u32; 2],
+}
+
+impl Operands {
+    pub(super) fn of(instruction: InsType, register: Register) -> Operands {
+        let syllables = match instruction {
+            InsType::ConditionalPush {
+                prev_syllables,
+                cur_syllables,
+            } => [prev_syllables, cur_syllables],
+            InsType::ConditionalGoto(syllables) | InsType::Store(syllables) => [syllables, 0],
+            _ => [0; 2],
+        };
+        Operands {
+            register,
+            syllables,
+        }
+    }
+}
+
+/// the function that runs `instruction`
+pub(super) fn run(instruction: InsType) -> Run {
+    match instruction {
+        InsType::ConditionalPush { .. } => conditional_push,
+        InsType::ConditionalGoto(_) => conditional_goto,
+        InsType::Negate => negate,
+        InsType::Multiply => multiply,
+        InsType::Add => add,
+        InsType::Subtract => subtract,
+        InsType::Divide => divide,
+        InsType::ReadChar => read_char,
+        InsType::TapeStore => tape_store,
+        InsType::TapeLoad => tape_load,
+        InsType::PrintChar => print_char,
+        InsType::PrintValue => print_value,
+        InsType::Pop => pop,
+        InsType::Push => push,
+        InsType::Goto => goto,
+        InsType::Store(_) => store,
+        InsType::Noop => noop,
+    }
+}
+
+fn overflow(interpreter: &Interpreter, index: usize) -> ExecError {
+    ExecError::Overflow {
+        location: interpreter.program.location(index),
+    }
+}
+
+/// fails if a push would take the stack past its limit
+fn check_stack(interpreter: &Interpreter, index: usize) -> ExecResult<()> {
+    match interpreter.max_stack {
+        Some(limit) if interpreter.mem.stack.len() >= limit => Err(ExecError::StackLimit {
+            limit,
+            location: interpreter.program.location(index),
+        }),
+        _ => Ok(()),
+    }
+}
+
+pub(super) fn conditional_push(
+    interpreter: &mut Interpreter,
+    operands: Operands,
+    index: usize,
+    _: &mut String,
+) -> ExecResult<usize> {
+    check_stack(interpreter, index)?;
+    let mem = &mut interpreter.mem;
+    // r0 and r1 whichever register the line uses, see
+    // `InsType::ConditionalPush`
+    let [prev_syllables, cur_syllables] = operands.syllables;
+    if mem.registers[0] < mem.registers[1] {
+        mem.push_to_stack(prev_syllables as i64);
+    } else {
+        mem.push_to_stack(cur_syllables as i64);
+    }
+    Ok(index + 1)
+}
+
+pub(super) fn conditional_goto(
+    interpreter: &mut Interpreter,
+    operands: Operands,
+    index: usize,
+    _: &mut String,
+) -> ExecResult<usize> {
+    let mem = &interpreter.mem;
+    let reg = operands.register;
+    if mem.get_active(reg) > operands.syllables[0] as i64 {
+        let len = interpreter.program.ast.len();
+        return Ok((mem.get_inactive(reg).unsigned_abs() as usize) % len);
+    }
+    Ok(index + 1)
+}
+
+pub(super) fn negate(
+    interpreter: &mut Interpreter,
+    operands: Operands,
+    index: usize,
+    _: &mut String,
+) -> ExecResult<usize> {
+    match interpreter.mem.negate(operands.register) {
+        Some(()) => Ok(index + 1),
+        None => Err(overflow(interpreter, index)),
+    }
+}
+
+pub(super) fn multiply(
+    interpreter: &mut Interpreter,
+    operands: Operands,
+    index: usize,
+    _: &mut String,
+) -> ExecResult<usize> {
+    match interpreter.mem.multiply(operands.register) {
+        Some(()) => Ok(index + 1),
+        None => Err(overflow(interpreter, index)),
+    }
+}
+
+pub(super) fn add(
+    interpreter: &mut Interpreter,
+    operands: Operands,
+    index: usize,
+    _: &mut String,
+) -> ExecResult<usize> {
+    match interpreter.mem.add(operands.register) {
+        Some(()) => Ok(index + 1),
+        None => Err(overflow(interpreter, index)),
+    }
+}
+
+pub(super) fn subtract(
+    interpreter: &mut Interpreter,
+    operands: Operands,
+    index: usize,
+    _: &mut String,
+) -> ExecResult<usize> {
+    match interpreter.mem.subtract(operands.register) {
+        Some(()) => Ok(index + 1),
+        None => Err(overflow(interpreter, index)),
+    }
+}
+
+pub(super) fn divide(
+    interpreter: &mut Interpreter,
+    operands: Operands,
+    index: usize,
+    _: &mut String,
+) -> ExecResult<usize> {
+    let reg = operands.register;
+    if interpreter.mem.get_inactive(reg) == 0 {
+        if interpreter.strict_division {
+            return Err(ExecError::DivisionByZero {
+                location: interpreter.program.location(index),
+            });
+        }
+        interpreter.mem.store_syllables(reg, 0);
+        return Ok(index + 1);
+    }
+    match interpreter.mem.divide(reg) {
+        Some(()) => Ok(index + 1),
+        None => Err(overflow(interpreter, index)),
+    }
+}
+
+pub(super) fn read_char(
+    interpreter: &mut Interpreter,
+    operands: Operands,
+    index: usize,
+    _: &mut String,
+) -> ExecResult<usize> {
+    let byte = match &interpreter.input {
+        Some(input) => input.read_byte().map_err(|err| ExecError::Input {
+            message: err.to_string(),
+            location: interpreter.program.location(index),
+        })?,
+        None => None,
+    };
+    // -1 at the end of the input, which no byte can be
+    interpreter
+        .mem
+        .store_syllables(operands.register, byte.map_or(-1, i64::from));
+    Ok(index + 1)
+}
+
+pub(super) fn tape_store(
+    interpreter: &mut Interpreter,
+    operands: Operands,
+    index: usize,
+    _: &mut String,
+) -> ExecResult<usize> {
+    let mem = &mut interpreter.mem;
+    let cell = mem.get_inactive(operands.register);
+    if let Some(limit) = interpreter.max_tape_cells {
+        if mem.tape.len() >= limit && !mem.tape.contains_key(&cell) {
+            return Err(ExecError::TapeLimit {
+                limit,
+                location: interpreter.program.location(index),
+            });
+        }
+    }
+    mem.tape.insert(cell, mem.get_active(operands.register));
+    Ok(index + 1)
+}
+
+pub(super) fn tape_load(
+    interpreter: &mut Interpreter,
+    operands: Operands,
+    index: usize,
+    _: &mut String,
+) -> ExecResult<usize> {
+    let mem = &mut interpreter.mem;
+    let value = mem.tape.get(&mem.get_inactive(operands.register)).copied();
+    mem.store_syllables(operands.register, value.unwrap_or(0));
+    Ok(index + 1)
+}
+
+pub(super) fn print_char(
+    interpreter: &mut Interpreter,
+    operands: Operands,
+    index: usize,
+    output: &mut String,
+) -> ExecResult<usize> {
+    output.push(host::char_code(interpreter.mem.get_active(operands.register)) as char);
+    Ok(index + 1)
+}
+
+pub(super) fn print_value(
+    interpreter: &mut Interpreter,
+    operands: Operands,
+    index: usize,
+    output: &mut String,
+) -> ExecResult<usize> {
+    output.push_str(itoa::Buffer::new().format(interpreter.mem.get_active(operands.register)));
+    Ok(index + 1)
+}
+
+pub(super) fn pop(
+    interpreter: &mut Interpreter,
+    operands: Operands,
+    index: usize,
+    _: &mut String,
+) -> ExecResult<usize> {
+    if !interpreter.mem.pop(operands.register) {
+        if interpreter.strict_pop {
+            return Err(ExecError::StackUnderflow {
+                location: interpreter.program.location(index),
+            });
+        }
+        interpreter.ignored_pops += 1;
+    }
+    Ok(index + 1)
+}
+
+pub(super) fn push(
+    interpreter: &mut Interpreter,
+    operands: Operands,
+    index: usize,
+    _: &mut String,
+) -> ExecResult<usize> {
+    check_stack(interpreter, index)?;
+    interpreter.mem.push(operands.register);
+    Ok(index + 1)
+}
+
+pub(super) fn goto(
+    interpreter: &mut Interpreter,
+    operands: Operands,
+    _: usize,
+    _: &mut String,
+) -> ExecResult<usize> {
+    let len = interpreter.program.ast.len();
+    Ok((interpreter.mem.get_active(operands.register).unsigned_abs() as usize) % len)
+}
+
+pub(super) fn store(
+    interpreter: &mut Interpreter,
+    operands: Operands,
+    index: usize,
+    _: &mut String,
+) -> ExecResult<usize> {
+    interpreter
+        .mem
+        .store_syllables(operands.register, operands.syllables[0] as i64);
+    Ok(index + 1)
+}
+
+pub(super) fn noop(
+    _: &mut Interpreter,
+    _: Operands,
+    index: usize,
+    _: &mut String,
+) -> ExecResult<usize> {
+    Ok(index + 1)
+}
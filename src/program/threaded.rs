@@ -0,0 +1,35 @@
+//! the threaded engine, see [`Engine::Threaded`](super::Engine::Threaded)
+//!
+//! each instruction is lowered ahead of the run to the function in
+//! [`super::instructions`] that runs it and the operands it runs on, so a
+//! step calls through a pointer rather than matching on the instruction
+use std::sync::Arc;
+
+use super::instructions::{self, Operands, Run};
+use crate::Program;
+
+/// an instruction lowered for the threaded engine
+#[derive(Clone, Copy)]
+pub(super) struct Op {
+    pub(super) run: Run,
+    pub(super) operands: Operands,
+}
+
+impl std::fmt::Debug for Op {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Op")
+            .field("operands", &self.operands)
+            .finish_non_exhaustive()
+    }
+}
+
+pub(super) fn lower(program: &Program) -> Arc<[Op]> {
+    program
+        .ast
+        .iter()
+        .map(|ins| Op {
+            run: instructions::run(ins.instruction),
+            operands: Operands::of(ins.instruction, ins.register),
+        })
+        .collect()
+}
@@ -0,0 +1,148 @@
+//! python bindings, built into an `ashpaper` module with maturin (see
+//! `pyproject.toml`). a rust panic reaches python as a `PanicException`
+//! rather than aborting the interpreter
+use pyo3::{create_exception, exceptions::PyRuntimeError, prelude::*, types::PyDict};
+
+use super::{ExecOptions, InsType, Program};
+
+create_exception!(
+    ashpaper,
+    ExecutionError,
+    PyRuntimeError,
+    "a poem failed while running, e.g. by exceeding its instruction limit"
+);
+
+#[pyclass(name = "Program", module = "ashpaper", frozen)]
+pub struct PyProgram {
+    program: Program,
+}
+
+impl PyProgram {
+    fn run(&self, py: Python<'_>, options: ExecOptions) -> PyResult<String> {
+        py.allow_threads(|| self.program.execute_with_options(&options))
+            .map_err(|err| ExecutionError::new_err(err.to_string()))
+    }
+}
+
+#[pymethods]
+impl PyProgram {
+    #[new]
+    fn new(source: &str) -> Self {
+        PyProgram {
+            program: Program::create(source),
+        }
+    }
+
+    /// run the poem and return its output
+    fn execute(&self, py: Python<'_>) -> PyResult<String> {
+        self.run(py, ExecOptions::default())
+    }
+
+    /// run the poem, raising `ExecutionError` if it doesn't halt within
+    /// `max_instructions` instructions
+    fn execute_with_limit(&self, py: Python<'_>, max_instructions: u64) -> PyResult<String> {
+        self.run(py, ExecOptions::with_max_instructions(max_instructions))
+    }
+
+    /// a dict per line, with the same keys as `--ast=json`
+    fn ast<'py>(&self, py: Python<'py>) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        self.program
+            .ast
+            .iter()
+            .map(|ins| {
+                let dict = PyDict::new(py);
+                dict.set_item("instruction", ins.instruction.mnemonic())?;
+                match ins.instruction {
+                    InsType::ConditionalPush {
+                        prev_syllables,
+                        cur_syllables,
+                    } => {
+                        dict.set_item("prev_syllables", prev_syllables)?;
+                        dict.set_item("cur_syllables", cur_syllables)?;
+                    }
+                    InsType::ConditionalGoto(syllables) | InsType::Store(syllables) => {
+                        dict.set_item("syllables", syllables)?;
+                    }
+                    _ => (),
+                }
+                dict.set_item("register", u8::from(ins.register))?;
+                dict.set_item("line", ins.line())?;
+                Ok(dict)
+            })
+            .collect()
+    }
+
+    fn __len__(&self) -> usize {
+        self.program.ast.len()
+    }
+}
+
+/// the number of syllables in some text
+#[pyfunction]
+fn count_syllables(text: &str) -> usize {
+    super::count_syllables(text)
+}
+
+#[pymodule]
+fn ashpaper(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyProgram>()?;
+    m.add_function(wrap_pyfunction!(count_syllables, m)?)?;
+    m.add("ExecutionError", m.py().get_type::<ExecutionError>())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pyo3::{ffi::c_str, types::IntoPyDict, wrap_pymodule};
+
+    fn run(code: &std::ffi::CStr) {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let locals = [("ashpaper", wrap_pymodule!(ashpaper)(py))]
+                .into_py_dict(py)
+                .unwrap();
+            locals
+                .set_item("poem", include_str!("../poems/lovely-poem.eso"))
+                .unwrap();
+            py.run(code, None, Some(&locals))
+                .inspect_err(|err| err.display(py))
+                .unwrap();
+        });
+    }
+
+    #[test]
+    fn execute() {
+        run(c_str!(
+            r#"
+program = ashpaper.Program(poem)
+assert program.execute() == "24\n"
+assert program.execute_with_limit(1000) == "24\n"
+assert len(program) == 18
+line = program.ast()[1]
+assert line == {"instruction": "store", "syllables": 4, "register": 0, "line": "lovely poem"}, line
+"#
+        ));
+    }
+
+    #[test]
+    fn fuel_limit() {
+        run(c_str!(
+            r#"
+try:
+    ashpaper.Program("sells sea shells").execute_with_limit(100)
+    raise AssertionError("expected an ExecutionError")
+except ashpaper.ExecutionError as err:
+    assert "instruction limit of 100 reached" in str(err), str(err)
+    assert isinstance(err, RuntimeError)
+"#
+        ));
+    }
+
+    #[test]
+    fn count_syllables() {
+        run(c_str!(
+            r#"assert ashpaper.count_syllables("a lovely poem") == 5"#
+        ));
+    }
+}
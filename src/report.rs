@@ -0,0 +1,94 @@
+//! everything about a single run of a program in one place, see
+//! [`Program::execute_report`](crate::Program::execute_report)
+use std::{collections::BTreeMap, time::Duration};
+
+use super::{
+    host::OutputEvent,
+    program::{ExecError, Registers},
+    Result,
+};
+
+/// why a run stopped
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum HaltReason {
+    /// the instruction pointer ran off the end of the poem
+    Completed,
+    FuelExhausted,
+    OutputLimit,
+    Cancelled,
+    /// an instruction failed, e.g. by overflowing or popping an empty stack
+    /// with strict pops
+    Error,
+}
+
+/// the result of executing a program along with statistics about the run
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ExecutionReport {
+    /// what the program printed. when output is streamed it has already been
+    /// handed out and this is empty
+    pub output: String,
+    /// what the program printed as values and characters, in the order it
+    /// printed them, which `output` is all of written out. empty when
+    /// `output` is, and a print cut off by the output limit isn't included
+    pub events: Vec<OutputEvent>,
+    /// the registers when the run stopped, which is before the failing
+    /// instruction if it didn't complete
+    pub final_registers: Registers,
+    pub final_stack: Vec<i64>,
+    pub instructions_executed: u64,
+    /// how many times each kind of instruction ran, by mnemonic
+    pub per_opcode_counts: BTreeMap<&'static str, u64>,
+    pub max_stack_depth: usize,
+    /// pops of an empty stack, which leave the register as it was. always
+    /// zero with [`ExecOptions::strict_pop`](crate::ExecOptions::strict_pop),
+    /// since the first one is an error
+    pub ignored_pops: u64,
+    /// zero on targets without a clock, like wasm32-unknown-unknown
+    pub elapsed: Duration,
+    pub halted_reason: HaltReason,
+    /// what stopped the run, unless it completed
+    pub error: Option<ExecError>,
+}
+
+impl ExecutionReport {
+    pub fn completed(&self) -> bool {
+        self.halted_reason == HaltReason::Completed
+    }
+
+    /// the output, or the error if the run didn't complete
+    pub fn into_result(self) -> Result<String> {
+        match self.error {
+            Some(err) => Err(err.into()),
+            None => Ok(self.output),
+        }
+    }
+}
+
+impl HaltReason {
+    /// a kebab-case name for the reason, like the instructions' mnemonics,
+    /// e.g. `fuel-exhausted`
+    pub fn name(&self) -> &'static str {
+        match self {
+            HaltReason::Completed => "completed",
+            HaltReason::FuelExhausted => "fuel-exhausted",
+            HaltReason::OutputLimit => "output-limit",
+            HaltReason::Cancelled => "cancelled",
+            HaltReason::Error => "error",
+        }
+    }
+
+    pub(crate) fn of(error: Option<&ExecError>) -> HaltReason {
+        match error {
+            None => HaltReason::Completed,
+            Some(ExecError::FuelExhausted { .. }) => HaltReason::FuelExhausted,
+            Some(ExecError::OutputLimit { .. }) => HaltReason::OutputLimit,
+            Some(ExecError::Cancelled { .. }) => HaltReason::Cancelled,
+            Some(ExecError::Overflow { .. })
+            | Some(ExecError::StackUnderflow { .. })
+            | Some(ExecError::DivisionByZero { .. })
+            | Some(ExecError::Input { .. })
+            | Some(ExecError::TapeLimit { .. })
+            | Some(ExecError::StackLimit { .. }) => HaltReason::Error,
+        }
+    }
+}
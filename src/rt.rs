@@ -1,21 +1,109 @@
+use std::{
+    any::Any,
+    cell::{Cell, RefCell},
+    io::{self, Read, Write},
+};
+
+use super::host::{self, PoemIo};
+
+/// a registered host, which can be taken back as the type it was
+/// registered as once the run is over
+trait Host: PoemIo + Any {}
+
+impl<T: PoemIo + Any> Host for T {}
+
+thread_local! {
+    // where jit functions run through `with_host` send their output
+    static HOST: RefCell<Option<Box<dyn Host>>> = const { RefCell::new(None) };
+    // set when a jit function stopped because its stack was full
+    static STACK_OVERFLOWED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// run a compiled function, `false` if it stopped on a push to a full stack
+pub fn run(func: fn()) -> bool {
+    STACK_OVERFLOWED.with(|overflowed| overflowed.set(false));
+    func();
+    !STACK_OVERFLOWED.with(Cell::take)
+}
+
+/// called by a jit function instead of pushing to a full stack, right
+/// before it returns
+pub fn stack_overflow() {
+    STACK_OVERFLOWED.with(|overflowed| overflowed.set(true));
+}
+
+/// hand output to the registered host, or print it without one
+fn emit<R>(output: impl FnOnce(&mut dyn PoemIo) -> R, fallback: impl FnOnce() -> R) -> R {
+    HOST.with(|host| match host.borrow_mut().as_mut() {
+        Some(io) => output(io.as_mut()),
+        None => fallback(),
+    })
+}
+
+/// run a compiled function, sending everything it prints to `io`, and
+/// whether it finished like [`run`]
+pub fn with_host<T: PoemIo + 'static>(io: T, func: fn()) -> (T, bool) {
+    HOST.with(|host| *host.borrow_mut() = Some(Box::new(io)));
+    let finished = run(func);
+    let io: Box<dyn Any> = HOST
+        .with(|host| host.borrow_mut().take())
+        .expect("the host stays registered for the whole run");
+    let io = *io
+        .downcast()
+        .expect("the host is the type it was registered as");
+    (io, finished)
+}
+
+/// `print!` without going through the formatting machinery
+fn print_value(val: i64) {
+    io::stdout()
+        .write_all(itoa::Buffer::new().format(val).as_bytes())
+        .expect("failed printing to stdout");
+}
+
 #[cfg(all(target_pointer_width = "64", feature = "jit"))]
 pub fn put_value(val: i64) {
-    print!("{}", val);
+    emit(|io| io.put_value(val), || print_value(val));
 }
 
 #[cfg(all(target_pointer_width = "32", feature = "jit"))]
 pub fn put_value(val: i32) {
-    print!("{}", val);
+    emit(|io| io.put_value(val.into()), || print_value(val.into()));
 }
 
 #[cfg(all(target_pointer_width = "64", feature = "jit"))]
 pub fn put_char(c: i64) {
-    let c = (c.abs() % std::u8::MAX as i64) as u8;
-    print!("{}", c as char);
+    let c = host::char_code(c);
+    emit(|io| io.put_char(c), || print!("{}", c as char));
 }
 
 #[cfg(all(target_pointer_width = "32", feature = "jit"))]
 pub fn put_char(c: i32) {
-    let c = (c.abs() % std::u8::MAX as i32) as u8;
-    print!("{}", c as char);
+    let c = host::char_code(c.into());
+    emit(|io| io.put_char(c), || print!("{}", c as char));
+}
+
+/// the next byte of stdin, or `None` at its end or if it can't be read
+fn read_stdin() -> Option<u8> {
+    let mut byte = [0];
+    loop {
+        match io::stdin().read(&mut byte) {
+            Ok(0) => return None,
+            Ok(_) => return Some(byte[0]),
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(_) => return None,
+        }
+    }
+}
+
+/// the next byte of input from the host, or stdin without one. -1 at the
+/// end of the input, which no byte can be
+#[cfg(all(target_pointer_width = "64", feature = "jit"))]
+pub fn get_char() -> i64 {
+    emit(|io| io.get_char(), read_stdin).map_or(-1, i64::from)
+}
+
+#[cfg(all(target_pointer_width = "32", feature = "jit"))]
+pub fn get_char() -> i32 {
+    emit(|io| io.get_char(), read_stdin).map_or(-1, i32::from)
 }
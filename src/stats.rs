@@ -0,0 +1,123 @@
+//! statistics about a poem that don't need it to run, e.g. for comparing
+//! the poems of a corpus
+use std::collections::BTreeMap;
+
+use super::{
+    parser::{self, AllitOptions, InsType},
+    Program,
+};
+
+/// what a poem is made of, from [`Program::poem_stats`]
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PoemStats {
+    pub lines: usize,
+    /// lines with something besides whitespace on them
+    pub non_empty_lines: usize,
+    /// how many lines parsed to each instruction, by its mnemonic
+    pub instructions: BTreeMap<String, usize>,
+    /// how many lines use each register, by its index
+    pub registers: [usize; 4],
+    pub syllables: usize,
+    /// of the lines that aren't empty, 0 if they all are
+    pub mean_syllables_per_line: f64,
+    /// lines that rhyme with the line before them, so parsed to conditional
+    /// pushes
+    pub rhymed_couplets: usize,
+    /// lines with a run of alliterating words, whether or not a rule before
+    /// alliteration made them something other than a goto
+    pub alliterative_lines: usize,
+}
+
+impl Program {
+    /// statistics about the program's poem, counting the syllables of its
+    /// lines like the default [`ParseOptions`](crate::ParseOptions) do
+    pub fn poem_stats(&self) -> PoemStats {
+        let mut stats = PoemStats {
+            lines: self.ast.len(),
+            ..PoemStats::default()
+        };
+        for ins in &self.ast {
+            let line = ins.line();
+            *stats
+                .instructions
+                .entry(ins.instruction.mnemonic().to_string())
+                .or_insert(0) += 1;
+            stats.registers[ins.register.index()] += 1;
+            if matches!(ins.instruction, InsType::ConditionalPush { .. }) {
+                stats.rhymed_couplets += 1;
+            }
+            if line.trim().is_empty() {
+                continue;
+            }
+            stats.non_empty_lines += 1;
+            stats.syllables += parser::count_syllables(line);
+            if parser::has_alliteration(&line.to_lowercase(), AllitOptions::default()) {
+                stats.alliterative_lines += 1;
+            }
+        }
+        if stats.non_empty_lines > 0 {
+            stats.mean_syllables_per_line = stats.syllables as f64 / stats.non_empty_lines as f64;
+        }
+        stats
+    }
+}
+
+#[cfg(all(test, feature = "bundled-dictionary"))]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn factorial() {
+        let program = Program::create(include_str!("../poems/original-factorial.eso"));
+        let instructions = [
+            ("add", 2),
+            ("cond-goto", 1),
+            ("multiply", 1),
+            ("negate", 1),
+            ("pop", 3),
+            ("print-char", 1),
+            ("print-value", 1),
+            ("push", 2),
+            ("store", 4),
+        ];
+        assert_eq!(
+            program.poem_stats(),
+            PoemStats {
+                lines: 16,
+                non_empty_lines: 16,
+                instructions: instructions
+                    .iter()
+                    .map(|&(mnemonic, count)| (mnemonic.to_string(), count))
+                    .collect(),
+                registers: [5, 11, 0, 0],
+                syllables: 75,
+                mean_syllables_per_line: 4.6875,
+                rhymed_couplets: 0,
+                alliterative_lines: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn rhymes_and_alliteration() {
+        // the rhyme on the last line wins over its alliteration
+        let program = Program::create("sells sea shells\n\nthe cat\n  on the mat\nbig bad bat");
+        let stats = program.poem_stats();
+        assert_eq!(stats.non_empty_lines, 4);
+        assert_eq!(stats.rhymed_couplets, 2);
+        assert_eq!(stats.alliterative_lines, 2);
+        assert_eq!(stats.instructions["goto"], 1);
+        assert_eq!(stats.registers, [4, 1, 0, 0]);
+        assert_eq!(stats.mean_syllables_per_line, 2.75);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn round_trips() {
+        let stats = Program::create(include_str!("../poems/lovely-poem.eso")).poem_stats();
+        let json = serde_json::to_string(&stats).unwrap();
+        assert_eq!(serde_json::from_str::<PoemStats>(&json).unwrap(), stats);
+    }
+}
@@ -0,0 +1,312 @@
+//! helpers for testing poems and the interpreter
+//!
+//! for poem authors, [`assert_poem!`](crate::assert_poem),
+//! [`assert_poem_output!`](crate::assert_poem_output) and
+//! [`assert_poem_ast!`](crate::assert_poem_ast) check a single poem, and
+//! [`run_cases`] checks a whole collection of [`PoemCase`]s at once, showing
+//! a line diff of every poem that doesn't print what it should:
+//!
+//! ```
+//! use ashpaper_plus::{assert_poem_ast, assert_poem_output, testing::{run_cases, PoemCase}};
+//!
+//! assert_poem_output!("lovely poem\nprint.", "4");
+//! assert_poem_ast!("lovely poem\nprint.", ["store", "print-value"]);
+//! run_cases(&[PoemCase {
+//!     source: "lovely poem\nprint.",
+//!     expected_output: "6",
+//!     title_override: Some("lovely poem again"),
+//! }]);
+//! ```
+//!
+//! for the fuzz targets and property tests, the `testing` feature adds
+//! `ComposableProgram`, which only generates instruction lists that
+//! [`compose`](crate::compose::compose) can write a poem for: syllables
+//! stay small, and a cond-push always follows a line that ends in a word it
+//! can rhyme with and has the syllables it expects
+use super::{ExecOptions, Program};
+
+#[cfg(feature = "testing")]
+mod generators;
+#[cfg(feature = "testing")]
+pub use generators::ComposableProgram;
+
+/// how many instructions a case can run before it's assumed to loop forever
+pub const MAX_INSTRUCTIONS: u64 = 10_000_000;
+
+/// a poem and what it should print
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PoemCase<'a> {
+    pub source: &'a str,
+    pub expected_output: &'a str,
+    /// replaces the poem's title, see [`Program::with_title`]
+    pub title_override: Option<&'a str>,
+}
+
+impl PoemCase<'_> {
+    /// the first line of the poem that isn't blank, to tell cases apart
+    fn name(&self) -> &str {
+        self.source
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty())
+            .unwrap_or("<empty poem>")
+    }
+
+    /// `Err` with a description of what went wrong if the poem doesn't print
+    /// what it should
+    pub fn check(&self) -> Result<(), String> {
+        let program = match self.title_override {
+            Some(title) => Program::with_title(self.source, title)
+                .map_err(|err| format!("can't use title {:?}: {}", title, err))?,
+            None => Program::create(self.source),
+        };
+        let output = program
+            .execute_with_options(&ExecOptions::with_max_instructions(MAX_INSTRUCTIONS))
+            .map_err(|err| err.to_string())?;
+        if output == self.expected_output {
+            return Ok(());
+        }
+        let mut message = format!(
+            "output differs (- expected, + actual):\n{}",
+            diff(self.expected_output, &output)
+        );
+        if let Some(region) = diverging_region(&program, self.expected_output) {
+            message.push_str(&format!("\nwhere it starts to differ:\n{}", region));
+        }
+        Err(message)
+    }
+}
+
+/// how many instructions [`diverging_region`] shows either side of the one
+/// that printed the first wrong byte
+const REGION_CONTEXT: usize = 2;
+
+/// the instructions around the print that output the first byte that isn't
+/// in `expected`, or the last print if the output stops short. `None` if
+/// nothing was printed
+fn diverging_region(program: &Program, expected: &str) -> Option<String> {
+    // where each print's output ends, and the instruction that printed it
+    let mut prints: Vec<(usize, usize)> = Vec::new();
+    let mut output = String::new();
+    let options = ExecOptions::with_max_instructions(MAX_INSTRUCTIONS);
+    let _ = program.execute_with_hook(
+        &options,
+        |_| (),
+        |_, step| {
+            if let Some(printed) = &step.output {
+                output.push_str(printed);
+                prints.push((output.len(), step.index));
+            }
+        },
+    );
+    let same = output
+        .bytes()
+        .zip(expected.bytes())
+        .take_while(|(actual, expected)| actual == expected)
+        .count();
+    let (_, culprit) = prints
+        .iter()
+        .find(|(end, _)| *end > same)
+        .or_else(|| prints.last())?;
+
+    let start = culprit.saturating_sub(REGION_CONTEXT);
+    let end = (culprit + REGION_CONTEXT + 1).min(program.ast.len());
+    let mut region = String::new();
+    for (index, ins) in program.ast[start..end].iter().enumerate() {
+        let index = start + index;
+        let marker = if index == *culprit { '>' } else { ' ' };
+        let instruction = format!("{} r{}", ins.instruction.mnemonic(), ins.register.index());
+        region.push_str(&format!(
+            "{} {:>3} | {:<16} | {}\n",
+            marker,
+            index + 1,
+            instruction,
+            ins.line().trim()
+        ));
+    }
+    Some(region)
+}
+
+/// the lines of `expected` and `actual`, marking the ones only in one of
+/// them with `-` or `+`. a missing final newline is shown as `⏎` missing
+/// from the end of the last line
+fn diff(expected: &str, actual: &str) -> String {
+    fn lines(text: &str) -> Vec<String> {
+        text.split_inclusive('\n')
+            .map(|line| match line.strip_suffix('\n') {
+                Some(line) => format!("{}⏎", line),
+                None => line.to_string(),
+            })
+            .collect()
+    }
+    let (expected, actual) = (lines(expected), lines(actual));
+    // longest common subsequence, from the ends of both
+    let mut common = vec![vec![0usize; actual.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            common[i][j] = if expected[i] == actual[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+    let mut out = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < expected.len() || j < actual.len() {
+        if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+            out.push_str(&format!("  {}\n", expected[i]));
+            i += 1;
+            j += 1;
+        } else if j == actual.len() || (i < expected.len() && common[i + 1][j] >= common[i][j + 1])
+        {
+            out.push_str(&format!("- {}\n", expected[i]));
+            i += 1;
+        } else {
+            out.push_str(&format!("+ {}\n", actual[j]));
+            j += 1;
+        }
+    }
+    out
+}
+
+/// check every case, panicking with all of the failures if any fail
+#[track_caller]
+pub fn run_cases(cases: &[PoemCase<'_>]) {
+    let failures: Vec<String> = cases
+        .iter()
+        .enumerate()
+        .filter_map(|(index, case)| {
+            case.check()
+                .err()
+                .map(|err| format!("case {} ({:?}): {}", index, case.name(), err))
+        })
+        .collect();
+    if !failures.is_empty() {
+        panic!(
+            "{} of {} poems failed\n\n{}",
+            failures.len(),
+            cases.len(),
+            failures.join("\n")
+        );
+    }
+}
+
+/// panic with a diff unless `source` parses to instructions with the
+/// `expected` mnemonics, one per line
+#[track_caller]
+pub fn assert_ast(source: &str, expected: &[&str]) {
+    let actual: Vec<&str> = Program::create(source)
+        .ast
+        .iter()
+        .map(|ins| ins.instruction.mnemonic())
+        .collect();
+    if actual != expected {
+        panic!(
+            "instructions differ (- expected, + actual):\n{}",
+            diff(&expected.join("\n"), &actual.join("\n"))
+        );
+    }
+}
+
+/// assert that a poem prints `expected`, showing a diff if it doesn't
+#[macro_export]
+macro_rules! assert_poem_output {
+    ($source:expr, $expected:expr $(,)?) => {
+        $crate::testing::run_cases(&[$crate::testing::PoemCase {
+            source: $source,
+            expected_output: $expected,
+            title_override: None,
+        }])
+    };
+}
+
+/// assert that a poem prints what it should, showing a diff and the lines
+/// around where the output starts to differ if it doesn't. the poem can be
+/// given as its source, or as a file included relative to the file the macro
+/// is in, like `assert_poem!(file = "../poems/lovely-poem.eso", "24\n")`,
+/// and its title can be replaced. it runs for at most
+/// [`MAX_INSTRUCTIONS`](crate::testing::MAX_INSTRUCTIONS) instructions
+///
+/// ```
+/// use ashpaper_plus::assert_poem;
+///
+/// assert_poem!("lovely poem\nprint.", "4");
+/// // the title is the first line, so the poem prints its syllables
+/// assert_poem!("lovely poem\nprint.", title = "lovely poem again", "6");
+/// ```
+#[macro_export]
+macro_rules! assert_poem {
+    (file = $path:expr, $expected:expr $(,)?) => {
+        $crate::assert_poem!(include_str!($path), $expected)
+    };
+    ($source:expr, title = $title:expr, $expected:expr $(,)?) => {
+        $crate::testing::run_cases(&[$crate::testing::PoemCase {
+            source: $source,
+            expected_output: $expected,
+            title_override: Some($title),
+        }])
+    };
+    ($source:expr, $expected:expr $(,)?) => {
+        $crate::testing::run_cases(&[$crate::testing::PoemCase {
+            source: $source,
+            expected_output: $expected,
+            title_override: None,
+        }])
+    };
+}
+
+/// assert that a poem parses to instructions with the given mnemonics, like
+/// `["store", "print-value"]`
+#[macro_export]
+macro_rules! assert_poem_ast {
+    ($source:expr, $expected:expr $(,)?) => {
+        $crate::testing::assert_ast($source, &$expected)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn diffs() {
+        assert_eq!(diff("a\nb\nc\n", "a\nb\nc\n"), "  a⏎\n  b⏎\n  c⏎\n");
+        assert_eq!(diff("24\n", "120\n"), "- 24⏎\n+ 120⏎\n");
+        assert_eq!(
+            diff("-5\n42\n10", "-5\n10\n"),
+            "  -5⏎\n- 42⏎\n- 10\n+ 10⏎\n"
+        );
+    }
+
+    #[test]
+    fn failures() {
+        let case = PoemCase {
+            source: "lovely poem\nprint.",
+            expected_output: "5",
+            ..PoemCase::default()
+        };
+        assert_eq!(
+            case.check(),
+            Err("output differs (- expected, + actual):\n- 5\n+ 4\n\n\
+                 where it starts to differ:\n    \
+                 1 | store r0         | lovely poem\n\
+                 >   2 | print-value r0   | print.\n"
+                .to_string())
+        );
+        let looping = PoemCase {
+            source: "sells sea shells",
+            ..PoemCase::default()
+        };
+        assert!(looping.check().unwrap_err().contains("instruction limit"));
+        let err = std::panic::catch_unwind(|| run_cases(&[case, looping])).unwrap_err();
+        let message = err.downcast_ref::<String>().unwrap();
+        assert!(message.starts_with("2 of 2 poems failed"), "{}", message);
+        assert!(
+            message.contains("case 1 (\"sells sea shells\")"),
+            "{}",
+            message
+        );
+    }
+}
@@ -0,0 +1,89 @@
+//! [`Arbitrary`] for instructions, and for lists of them that can be
+//! written as a poem
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+use crate::{InsType, Instruction, Register};
+
+/// the most syllables a generated line has, more only makes poems longer
+const MAX_SYLLABLES: usize = 12;
+
+fn syllables(u: &mut Unstructured<'_>) -> Result<u32> {
+    u.int_in_range(0..=MAX_SYLLABLES as u32)
+}
+
+impl<'a> Arbitrary<'a> for Register {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(if u.arbitrary()? {
+            Register::Register1
+        } else {
+            Register::Register0
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for InsType {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(match u.int_in_range(0..=11)? {
+            0 => InsType::ConditionalPush {
+                prev_syllables: syllables(u)?,
+                cur_syllables: syllables(u)?,
+            },
+            1 => InsType::ConditionalGoto(syllables(u)?),
+            2 => InsType::Negate,
+            3 => InsType::Multiply,
+            4 => InsType::Add,
+            5 => InsType::PrintChar,
+            6 => InsType::PrintValue,
+            7 => InsType::Pop,
+            8 => InsType::Push,
+            9 => InsType::Goto,
+            10 => InsType::Store(syllables(u)?),
+            _ => InsType::Noop,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Instruction {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Instruction::new(u.arbitrary()?, u.arbitrary()?, ""))
+    }
+}
+
+/// a list of instructions that can be written as a poem
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComposableProgram(pub Vec<Instruction>);
+
+/// the syllables a line for `instruction` is sure to have, if it ends in a
+/// word that can be rhymed with. a cond-goto can end in its slash
+fn rhymable_syllables(instruction: InsType) -> Option<u32> {
+    match instruction {
+        InsType::Store(syllables) if syllables > 0 => Some(syllables),
+        InsType::ConditionalPush { cur_syllables, .. } => Some(cur_syllables),
+        _ => None,
+    }
+}
+
+impl<'a> Arbitrary<'a> for ComposableProgram {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut instructions: Vec<Instruction> = Vec::new();
+        for _ in 0..u.arbitrary_len::<Instruction>()? {
+            let mut ins: Instruction = u.arbitrary()?;
+            if let InsType::ConditionalPush { .. } = ins.instruction {
+                let previous = instructions
+                    .last()
+                    .and_then(|previous| rhymable_syllables(previous.instruction));
+                ins.instruction = match previous {
+                    // the rhyming word can have as many syllables as the
+                    // whole line before it
+                    Some(prev_syllables) => InsType::ConditionalPush {
+                        prev_syllables,
+                        cur_syllables: prev_syllables + u.int_in_range(0..=3)?,
+                    },
+                    None => InsType::Store(syllables(u)?),
+                };
+            }
+            instructions.push(ins);
+        }
+        Ok(ComposableProgram(instructions))
+    }
+}
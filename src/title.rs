@@ -0,0 +1,203 @@
+//! generating titles with an exact number of syllables
+//!
+//! the syllables of a poem's title are conventionally its input, so being
+//! able to conjure up a title of any length saves a lot of counting on
+//! fingers. titles are built from a small word bank of known syllable counts
+//! and checked by parsing the poem with them, since a title that alliterates
+//! or rhymes with the line after it would change the program
+use super::{errors::program::TitleError, parser::InsType, Program, Result};
+
+/// how many different titles to try before giving up
+const ATTEMPTS: usize = 64;
+
+pub(crate) const ADJECTIVES: &[(&str, usize)] = &[
+    ("red", 1),
+    ("cold", 1),
+    ("dark", 1),
+    ("soft", 1),
+    ("wild", 1),
+    ("blue", 1),
+    ("gentle", 2),
+    ("golden", 2),
+    ("silver", 2),
+    ("hollow", 2),
+    ("quiet", 2),
+    ("distant", 2),
+    ("beautiful", 3),
+    ("forgotten", 3),
+    ("radiant", 3),
+    ("wandering", 3),
+];
+
+pub(crate) const NOUNS: &[(&str, usize)] = &[
+    ("moon", 1),
+    ("sea", 1),
+    ("rain", 1),
+    ("song", 1),
+    ("light", 1),
+    ("stone", 1),
+    ("bird", 1),
+    ("wind", 1),
+    ("river", 2),
+    ("garden", 2),
+    ("window", 2),
+    ("shadow", 2),
+    ("candle", 2),
+    ("ocean", 2),
+    ("memory", 3),
+    ("harmony", 3),
+    ("melody", 3),
+    ("galaxy", 3),
+    ("canopy", 3),
+];
+
+const CONNECTIVES: &[(&str, usize)] = &[
+    ("of", 1),
+    ("and", 1),
+    ("in", 1),
+    ("for", 1),
+    ("with", 1),
+    ("from", 1),
+    ("under", 2),
+    ("over", 2),
+];
+
+#[derive(Clone, Copy)]
+enum Slot {
+    Adjective,
+    Noun,
+    Connective,
+}
+
+/// pick a word for `slot` with at most `remaining` syllables that leaves
+/// enough syllables for the rest of the title to end on a noun. `seed`
+/// rotates through the bank so different attempts give different titles
+fn pick(
+    slot: Slot,
+    remaining: usize,
+    previous: Option<char>,
+    seed: usize,
+) -> Option<(&'static str, usize)> {
+    let bank = match slot {
+        Slot::Adjective => ADJECTIVES,
+        Slot::Noun => NOUNS,
+        Slot::Connective => CONNECTIVES,
+    };
+    (0..bank.len())
+        .map(|i| bank[(seed + i) % bank.len()])
+        .filter(|(word, _)| previous != word.chars().next())
+        .find(|&(_, syllables)| match slot {
+            // a connective needs a noun after it
+            Slot::Adjective | Slot::Connective => syllables < remaining,
+            // either finish the title or leave room for a connective and noun
+            Slot::Noun => syllables == remaining || syllables + 2 <= remaining,
+        })
+}
+
+pub(crate) fn build(syllables: usize, seed: usize) -> Option<String> {
+    let mut words = Vec::new();
+    let mut remaining = syllables;
+    let mut previous = None;
+    let mut slot = Slot::Adjective;
+    let mut position = 0;
+    while remaining > 0 {
+        let seed = seed + position * 7;
+        position += 1;
+        let picked = pick(slot, remaining, previous, seed);
+        slot = match (slot, picked) {
+            // titles don't need adjectives, so skip one that doesn't fit
+            (Slot::Adjective, None) => Slot::Noun,
+            (_, None) => return None,
+            (Slot::Adjective, Some(_)) => Slot::Noun,
+            (Slot::Noun, Some(_)) => Slot::Connective,
+            (Slot::Connective, Some(_)) => Slot::Adjective,
+        };
+        if let Some((word, count)) = picked {
+            words.push(word);
+            remaining -= count;
+            previous = word.chars().next();
+        }
+    }
+    Some(words.join(" "))
+}
+
+/// the index of a poem's title, the first line that isn't blank
+pub(crate) fn title_index(source: &str) -> Option<usize> {
+    source.lines().position(|line| !line.trim().is_empty())
+}
+
+/// generate a title with exactly `syllables` syllables that can replace the
+/// title of `source` without turning it into a different instruction or
+/// making the next line rhyme with it
+pub fn generate(syllables: usize, source: &str) -> Result<String> {
+    if syllables == 0 {
+        return Err(TitleError::NoSyllables.into());
+    }
+    let index = title_index(source).ok_or(TitleError::EmptyPoem)?;
+    let original = Program::create(source);
+    let keeps_next_line = |program: &Program| match program.ast.get(index + 1) {
+        Some(next) => {
+            !matches!(next.instruction, InsType::ConditionalPush { .. })
+                || next.instruction == original.ast[index + 1].instruction
+        }
+        None => true,
+    };
+
+    (0..ATTEMPTS)
+        .filter_map(|seed| build(syllables, seed))
+        .find(|title| {
+            let program = Program::with_title(source, title)
+                .expect("the poem has a title and generated titles are one line");
+            program.ast[index].instruction == InsType::Store(syllables as u32)
+                && keeps_next_line(&program)
+        })
+        .ok_or_else(|| TitleError::GenerationFailed { syllables }.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::count_syllables;
+    use pretty_assertions::assert_eq;
+
+    #[cfg(feature = "bundled-dictionary")]
+    #[test]
+    fn word_bank_syllables() {
+        for &(word, syllables) in ADJECTIVES.iter().chain(NOUNS).chain(CONNECTIVES) {
+            assert_eq!(count_syllables(word), syllables, "{}", word);
+        }
+    }
+
+    #[test]
+    fn exact_syllables() {
+        let source = include_str!("../poems/lovely-poem.eso");
+        for syllables in 1..=30 {
+            let title = generate(syllables, source).unwrap();
+            assert_eq!(count_syllables(&title), syllables, "{}", title);
+            let program = Program::with_title(source, &title).unwrap();
+            assert_eq!(program.ast[1].instruction, InsType::Store(syllables as u32));
+        }
+    }
+
+    #[cfg(feature = "bundled-dictionary")]
+    #[test]
+    fn avoids_rhyming_with_next_line() {
+        // the first candidate rhymes with the next line, so it has to be skipped
+        assert_eq!(build(1, 0).unwrap(), "wind");
+        let source = "title\nin the wind";
+        let title = generate(1, source).unwrap();
+        assert_ne!(title, "wind");
+        let program = Program::with_title(source, &title).unwrap();
+        assert_eq!(program.ast[1].instruction, InsType::Store(3));
+    }
+
+    #[test]
+    fn errors() {
+        let error = |syllables, source| match generate(syllables, source) {
+            Err(crate::Error::Title(err)) => err,
+            other => panic!("expected a TitleError, got {:?}", other),
+        };
+        assert_eq!(error(0, "title"), TitleError::NoSyllables);
+        assert_eq!(error(3, "\n\n"), TitleError::EmptyPoem);
+    }
+}
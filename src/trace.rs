@@ -0,0 +1,586 @@
+//! structured execution traces, a table renderer for them, and exports to
+//! json lines and csv for loading into other tools
+//!
+//! a [`RecordedTrace`] keeps every step of a run so it can be replayed
+//! later, to catch a run that doesn't go the same way twice, or a poem that
+//! runs differently under another version of the crate
+use std::io::{self, Write};
+
+use super::{
+    parser::{InsCategory, InsType, Register},
+    program::{ExecOptions, Interpreter, Program},
+    HaltReason, Result,
+};
+
+pub use super::errors::trace::Divergence;
+
+/// the state of the machine right after a single instruction was executed
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TraceRow {
+    /// index of the executed instruction in the program
+    pub index: usize,
+    /// 1-based line of the poem the instruction was parsed from
+    pub line_number: usize,
+    pub line: String,
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub instruction: InsType,
+    pub register: Register,
+    pub r0: i64,
+    pub r1: i64,
+    pub stack: Vec<i64>,
+    /// what the instruction printed, if anything
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub output: String,
+}
+
+/// every step of a single execution, in order
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Trace {
+    pub rows: Vec<TraceRow>,
+}
+
+/// a run recorded by [`Trace::record`], to be checked against another with
+/// [`RecordedTrace::verify`]
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RecordedTrace {
+    /// the version of the crate that recorded it
+    pub version: String,
+    pub rows: Vec<TraceRow>,
+    /// whether the poem halted after the last row, rather than being
+    /// stopped by a limit or an error
+    pub completed: bool,
+}
+
+const RESET: &str = "\x1b[0m";
+const HIGHLIGHT: &str = "\x1b[1;4m";
+
+fn category_color(category: InsCategory) -> &'static str {
+    match category {
+        InsCategory::ControlFlow => "\x1b[35m",
+        InsCategory::Arithmetic => "\x1b[33m",
+        InsCategory::Io => "\x1b[32m",
+        InsCategory::Stack => "\x1b[36m",
+        InsCategory::Store => "\x1b[34m",
+        InsCategory::Noop => "\x1b[2m",
+    }
+}
+
+struct Cell {
+    text: String,
+    style: Option<&'static str>,
+}
+
+impl Cell {
+    fn plain(text: String) -> Cell {
+        Cell { text, style: None }
+    }
+
+    fn styled(text: String, style: &'static str, color: bool) -> Cell {
+        Cell {
+            text,
+            style: if color { Some(style) } else { None },
+        }
+    }
+
+    // pad before styling so escape codes don't throw off the alignment
+    fn render(&self, width: usize) -> String {
+        let padded = format!("{: <width$}", self.text, width = width);
+        match self.style {
+            Some(style) => format!("{}{}{}", style, padded, RESET),
+            None => padded,
+        }
+    }
+}
+
+fn format_stack(stack: &[i64]) -> String {
+    format!("{:?}", stack)
+}
+
+/// the columns of a csv export, which are also the keys of a json lines one
+const COLUMNS: [&str; 8] = [
+    "step",
+    "line",
+    "mnemonic",
+    "r0",
+    "r1",
+    "stack_depth",
+    "output",
+    "text",
+];
+
+/// a value in an exported record
+enum Field {
+    Number(String),
+    Text(String),
+}
+
+pub(crate) fn json_string(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len() + 2);
+    escaped.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+/// quote a csv field if it needs it, per RFC 4180
+pub(crate) fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+impl TraceRow {
+    /// the values of the export columns. `step` is the row's position in
+    /// the trace, and `line` is 1-based
+    fn record(&self, step: usize) -> [Field; 8] {
+        [
+            Field::Number(step.to_string()),
+            Field::Number(self.line_number.to_string()),
+            Field::Text(self.instruction.mnemonic().to_string()),
+            Field::Number(self.r0.to_string()),
+            Field::Number(self.r1.to_string()),
+            Field::Number(self.stack.len().to_string()),
+            Field::Text(self.output.clone()),
+            Field::Text(self.line.clone()),
+        ]
+    }
+}
+
+impl Trace {
+    pub fn push(&mut self, row: TraceRow) {
+        self.rows.push(row);
+    }
+
+    /// run `program` with `options`, keeping the state after every step
+    pub fn record(program: &Program, options: &ExecOptions) -> RecordedTrace {
+        let mut rows = vec![];
+        let report = program.execute_report_with_hook(
+            options,
+            |_| (),
+            |interpreter: &Interpreter, step| rows.push(interpreter.trace_row(step)),
+        );
+        RecordedTrace {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            rows,
+            completed: report.halted_reason == HaltReason::Completed,
+        }
+    }
+
+    /// write a json object per executed instruction, one per line, with the
+    /// step, line number, mnemonic, registers, stack depth, what was printed
+    /// and the text of the line
+    pub fn write_jsonl<W: Write>(&self, mut w: W) -> io::Result<()> {
+        for (step, row) in self.rows.iter().enumerate() {
+            let fields: Vec<String> = COLUMNS
+                .iter()
+                .zip(row.record(step))
+                .map(|(key, value)| match value {
+                    Field::Number(number) => format!("\"{}\":{}", key, number),
+                    Field::Text(text) => format!("\"{}\":{}", key, json_string(&text)),
+                })
+                .collect();
+            writeln!(w, "{{{}}}", fields.join(","))?;
+        }
+        w.flush()
+    }
+
+    /// write the same records as [`Trace::write_jsonl`] as csv with a
+    /// header, quoting fields that need it
+    pub fn write_csv<W: Write>(&self, mut w: W) -> io::Result<()> {
+        writeln!(w, "{}", COLUMNS.join(","))?;
+        for (step, row) in self.rows.iter().enumerate() {
+            let record: Vec<String> = row
+                .record(step)
+                .iter()
+                .map(|value| match value {
+                    Field::Number(number) => number.clone(),
+                    Field::Text(text) => csv_field(text),
+                })
+                .collect();
+            writeln!(w, "{}", record.join(","))?;
+        }
+        w.flush()
+    }
+
+    /// render the trace as a table with columns sized to fit their contents,
+    /// giving the line number and index of each instruction so repeated
+    /// lines can be told apart. with `color` enabled instructions are
+    /// colored by category and any register changed by a step is
+    /// highlighted
+    pub fn render_table(&self, color: bool) -> String {
+        let headers = ["line", "index", "instruction", "op", "r0", "r1", "stack"];
+
+        let mut prev_regs = (0, 0);
+        let rows: Vec<Vec<Cell>> = self
+            .rows
+            .iter()
+            .map(|row| {
+                let r0_changed = row.r0 != prev_regs.0;
+                let r1_changed = row.r1 != prev_regs.1;
+                prev_regs = (row.r0, row.r1);
+                let reg_cell = |value: i64, changed: bool| {
+                    if changed {
+                        Cell::styled(value.to_string(), HIGHLIGHT, color)
+                    } else {
+                        Cell::plain(value.to_string())
+                    }
+                };
+                vec![
+                    Cell::plain(row.line_number.to_string()),
+                    Cell::plain(row.index.to_string()),
+                    Cell::plain(row.line.clone()),
+                    Cell::styled(
+                        row.instruction.mnemonic().to_string(),
+                        category_color(row.instruction.category()),
+                        color,
+                    ),
+                    reg_cell(row.r0, r0_changed),
+                    reg_cell(row.r1, r1_changed),
+                    Cell::plain(format_stack(&row.stack)),
+                ]
+            })
+            .collect();
+
+        let mut widths: Vec<usize> = headers.iter().map(|h| h.len()).collect();
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.text.chars().count());
+            }
+        }
+
+        let join = |cells: Vec<String>| cells.join(" | ").trim_end().to_string();
+        let mut table = Vec::with_capacity(rows.len() + 2);
+        table.push(join(
+            headers
+                .iter()
+                .zip(&widths)
+                .map(|(h, &w)| format!("{: <w$}", h, w = w))
+                .collect(),
+        ));
+        table.push(join(widths.iter().map(|&w| "-".repeat(w)).collect()));
+        for row in rows {
+            table.push(join(
+                row.iter()
+                    .zip(&widths)
+                    .map(|(cell, &w)| cell.render(w))
+                    .collect(),
+            ));
+        }
+        let mut rendered = table.join("\n");
+        rendered.push('\n');
+        rendered
+    }
+}
+
+impl RecordedTrace {
+    /// run `program` again a step at a time, failing at the first step that
+    /// doesn't leave the machine as it was recorded
+    pub fn verify(&self, program: &Program) -> Result<()> {
+        self.verify_with(program, &ExecOptions::default())
+    }
+
+    /// [`RecordedTrace::verify`] with the options the run was recorded
+    /// with, like its input or initial stack. its limits are left out,
+    /// since the run stops where the recording does
+    pub fn verify_with(&self, program: &Program, options: &ExecOptions) -> Result<()> {
+        let mut interpreter = Interpreter::with_options(program, options);
+        interpreter.set_history_limit(0);
+        let replay = |interpreter: &mut Interpreter| match interpreter.step() {
+            Ok(Some(step)) => Some(Box::new(interpreter.trace_row(&step))),
+            _ => None,
+        };
+        for (step, recorded) in self.rows.iter().enumerate() {
+            let replayed = replay(&mut interpreter);
+            if replayed.as_deref() != Some(recorded) {
+                return Err(Divergence {
+                    step,
+                    recorded: Some(Box::new(recorded.clone())),
+                    replayed,
+                }
+                .into());
+            }
+        }
+        if self.completed && !interpreter.is_halted() {
+            return Err(Divergence {
+                step: self.rows.len(),
+                recorded: None,
+                replayed: replay(&mut interpreter),
+            }
+            .into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Error, ExecOptions, Program};
+    use pretty_assertions::assert_eq;
+
+    /// where a replay diverged from the recording
+    fn diverged(result: Result<()>) -> Divergence {
+        match result {
+            Err(Error::Divergence(divergence)) => divergence,
+            other => panic!("expected a Divergence, got {:?}", other),
+        }
+    }
+
+    fn trace_of(source: &str) -> Trace {
+        let mut trace = Trace::default();
+        Program::create(source)
+            .execute_with_trace(&ExecOptions::default(), |row| trace.push(row))
+            .unwrap();
+        trace
+    }
+
+    #[cfg(feature = "bundled-dictionary")]
+    #[test]
+    fn large_values() {
+        let source = "
+antidisestablishmentarianism supercalifragilisticexpialidocious
+    antidisestablishmentarianism supercalifragilisticexpialidocious
+Multiply
+Grow
+Bigger
+Again"
+            .trim_start();
+        let expected = "
+line | index | instruction                                                         | op       | r0       | r1 | stack
+---- | ----- | ------------------------------------------------------------------- | -------- | -------- | -- | -----
+1    | 0     | antidisestablishmentarianism supercalifragilisticexpialidocious     | store    | 27       | 0  | []
+2    | 1     |     antidisestablishmentarianism supercalifragilisticexpialidocious | store    | 27       | 27 | []
+3    | 2     | Multiply                                                            | multiply | 729      | 27 | []
+4    | 3     | Grow                                                                | multiply | 19683    | 27 | []
+5    | 4     | Bigger                                                              | multiply | 531441   | 27 | []
+6    | 5     | Again                                                               | multiply | 14348907 | 27 | []
+"
+        .trim_start();
+        assert_eq!(trace_of(source).render_table(false), expected);
+    }
+
+    #[cfg(feature = "bundled-dictionary")]
+    #[test]
+    fn deep_stack() {
+        let source = "
+stacked
+push-it
+push-it
+push-it
+push-it
+push-it
+push-it"
+            .trim_start();
+        let expected = "
+line | index | instruction | op    | r0 | r1 | stack
+---- | ----- | ----------- | ----- | -- | -- | ------------------
+1    | 0     | stacked     | store | 1  | 0  | []
+2    | 1     | push-it     | push  | 1  | 0  | [1]
+3    | 2     | push-it     | push  | 1  | 0  | [1, 1]
+4    | 3     | push-it     | push  | 1  | 0  | [1, 1, 1]
+5    | 4     | push-it     | push  | 1  | 0  | [1, 1, 1, 1]
+6    | 5     | push-it     | push  | 1  | 0  | [1, 1, 1, 1, 1]
+7    | 6     | push-it     | push  | 1  | 0  | [1, 1, 1, 1, 1, 1]
+"
+        .trim_start();
+        assert_eq!(trace_of(source).render_table(false), expected);
+    }
+
+    #[test]
+    fn repeated_lines() {
+        let source = "fish\npush-it\nfish\npush-it";
+        let expected = "
+line | index | instruction | op    | r0 | r1 | stack
+---- | ----- | ----------- | ----- | -- | -- | ------
+1    | 0     | fish        | store | 1  | 0  | []
+2    | 1     | push-it     | push  | 1  | 0  | [1]
+3    | 2     | fish        | store | 1  | 0  | [1]
+4    | 3     | push-it     | push  | 1  | 0  | [1, 1]
+"
+        .trim_start();
+        assert_eq!(trace_of(source).render_table(false), expected);
+    }
+
+    #[test]
+    fn color() {
+        let rendered = trace_of("fish\nprint.").render_table(true);
+        let lines: Vec<&str> = rendered.lines().collect();
+        // r0 changed from 0 to 1, so it's highlighted
+        assert_eq!(
+            lines[2],
+            format!(
+                "1    | 0     | fish        | {}store      {} | {}1 {} | 0  | []",
+                category_color(InsCategory::Store),
+                RESET,
+                HIGHLIGHT,
+                RESET
+            )
+        );
+        assert_eq!(
+            lines[3],
+            format!(
+                "2    | 1     | print.      | {}print-value{} | 1  | 0  | []",
+                category_color(InsCategory::Io),
+                RESET
+            )
+        );
+    }
+
+    fn awkward() -> Trace {
+        let mut trace = trace_of("fish\nsay \"hi, there\".");
+        trace.push(TraceRow {
+            index: 2,
+            line_number: 3,
+            line: "tab\tand \\ back\u{1}".to_string(),
+            instruction: InsType::PrintChar,
+            register: Register::Register1,
+            r0: -3,
+            r1: 10,
+            stack: vec![1, 2],
+            output: "\n".to_string(),
+        });
+        trace
+    }
+
+    #[test]
+    fn jsonl() {
+        let trace = awkward();
+        let mut out = Vec::new();
+        trace.write_jsonl(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let records: Vec<serde_json::Value> = out
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(
+            records[1],
+            serde_json::json!({
+                "step": 1,
+                "line": 2,
+                "mnemonic": "print-value",
+                "r0": 1,
+                "r1": 0,
+                "stack_depth": 0,
+                "output": "1",
+                "text": "say \"hi, there\".",
+            })
+        );
+        assert_eq!(records[2]["text"], "tab\tand \\ back\u{1}");
+        assert_eq!(records[2]["output"], "\n");
+        assert_eq!(records[2]["r0"], -3);
+        assert_eq!(records[2]["stack_depth"], 2);
+        assert_eq!(records.len(), 3);
+    }
+
+    #[test]
+    fn csv() {
+        let trace = awkward();
+        let mut out = Vec::new();
+        trace.write_csv(&mut out).unwrap();
+        let mut reader = csv::Reader::from_reader(out.as_slice());
+        assert_eq!(
+            reader.headers().unwrap(),
+            vec![
+                "step",
+                "line",
+                "mnemonic",
+                "r0",
+                "r1",
+                "stack_depth",
+                "output",
+                "text"
+            ]
+        );
+        let records: Vec<csv::StringRecord> =
+            reader.records().map(|record| record.unwrap()).collect();
+        assert_eq!(
+            records[1],
+            vec![
+                "1",
+                "2",
+                "print-value",
+                "1",
+                "0",
+                "0",
+                "1",
+                "say \"hi, there\"."
+            ]
+        );
+        assert_eq!(
+            records[2],
+            vec![
+                "2",
+                "3",
+                "print-char",
+                "-3",
+                "10",
+                "2",
+                "\n",
+                "tab\tand \\ back\u{1}"
+            ]
+        );
+        assert_eq!(records.len(), 3);
+    }
+
+    #[test]
+    fn record_and_verify() {
+        let program = Program::create(include_str!("../poems/original-factorial.eso"));
+        let mut recorded = Trace::record(&program, &ExecOptions::default());
+        assert!(recorded.completed);
+        recorded.verify(&program).unwrap();
+
+        recorded.rows[20].r0 += 1;
+        let divergence = diverged(recorded.verify(&program));
+        assert_eq!(divergence.step, 20);
+        assert_eq!(
+            divergence.recorded.unwrap().r0,
+            divergence.replayed.unwrap().r0 + 1
+        );
+    }
+
+    #[test]
+    fn verify_ends() {
+        let program = Program::create("lovely poem\nprint.");
+        let mut recorded = Trace::record(&program, &ExecOptions::default());
+        // a recording that stopped early doesn't say where the run should
+        // stop, but one that halted does
+        let halted = recorded.clone();
+        recorded.rows.pop();
+        recorded.completed = false;
+        recorded.verify(&program).unwrap();
+        recorded.completed = true;
+        let divergence = diverged(recorded.verify(&program));
+        assert_eq!((divergence.step, divergence.recorded), (1, None));
+        assert_eq!(divergence.replayed, Some(Box::new(halted.rows[1].clone())));
+
+        let other = Program::create("lovely poem");
+        let divergence = diverged(halted.verify(&other));
+        assert_eq!((divergence.step, divergence.replayed), (1, None));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn recording_round_trips() {
+        let program = Program::create(include_str!("../poems/lovely-poem.eso"));
+        let recorded = Trace::record(&program, &ExecOptions::default());
+        let json = serde_json::to_string(&recorded).unwrap();
+        let read: RecordedTrace = serde_json::from_str(&json).unwrap();
+        assert_eq!(read, recorded);
+        read.verify(&program).unwrap();
+    }
+}
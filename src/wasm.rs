@@ -0,0 +1,33 @@
+//! bindings for running poems in the browser with wasm-bindgen
+//!
+//! the dictionary is big, so web builds will usually want to leave out the
+//! `bundled-dictionary` feature and either pass one to [`init_dictionary`]
+//! once it's been fetched or make do with approximated syllables
+use std::convert::TryInto;
+use wasm_bindgen::prelude::*;
+
+use super::{ExecOptions, Program};
+
+/// execute a poem and return its output, failing if it runs for more than
+/// `max_instructions` instructions (0 means no limit)
+#[wasm_bindgen]
+pub fn execute(source: &str, max_instructions: u32) -> Result<String, JsValue> {
+    let options = ExecOptions {
+        max_instructions: Some(u64::from(max_instructions)).filter(|&limit| limit > 0),
+        ..ExecOptions::default()
+    };
+    Program::create(source)
+        .execute_with_options(&options)
+        .map_err(|err| JsValue::from_str(&err.to_string()))
+}
+
+#[wasm_bindgen]
+pub fn count_syllables(text: &str) -> u32 {
+    super::count_syllables(text).try_into().unwrap_or(u32::MAX)
+}
+
+/// see [`crate::dictionary::init_from_str`]
+#[wasm_bindgen]
+pub fn init_dictionary(source: &str) -> Result<(), JsValue> {
+    super::dictionary::init_from_str(source).map_err(|err| JsValue::from_str(&err.to_string()))
+}
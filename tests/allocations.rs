@@ -0,0 +1,67 @@
+//! how often the parser allocates, counted by a global allocator. there's
+//! only one test here, so nothing else allocates while it counts
+
+use ashpaper_plus::Program;
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+/// a line of every kind, with words in and out of the dictionary. the poem
+/// is kept small enough that the `parallel` feature doesn't split it up
+const LINES: &[&str] = &[
+    "lovely poem",
+    "  it is a calculator, like a",
+    "      poem, is a poem, and finds",
+    "        factori-",
+    "          als",
+    "  The input is the syllAbles",
+    "in the title, count them, as one counts",
+    "  (or should) in the sound of a gentle rain. /",
+    "she sells sea shells",
+    "print it? print it. xyzzy plugh",
+    "",
+];
+
+#[test]
+fn parsing_allocations_per_line() {
+    let poem: String = LINES
+        .iter()
+        .cycle()
+        .take(5_500)
+        .map(|line| format!("{}\n", line))
+        .collect();
+    // the dictionary and the regexes are only set up once
+    Program::create(&poem[..1000]);
+
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let program = Program::create(&poem);
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    assert_eq!(program.ast.len(), 5_500);
+    // just the text of the 5,000 lines that aren't blank, and growing the
+    // list of instructions
+    assert!(allocations <= 5_000 + 64, "{} allocations", allocations);
+}
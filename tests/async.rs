@@ -0,0 +1,59 @@
+#![cfg(feature = "async")]
+
+use ashpaper_plus::{ExecOptions, HaltReason, Program, TraceSink};
+use pretty_assertions::assert_eq;
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+use tokio::runtime::{Builder, Runtime};
+
+fn runtime() -> Runtime {
+    Builder::new_multi_thread()
+        .worker_threads(2)
+        .enable_time()
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn resolves() {
+    let program = Program::create(include_str!("../poems/lovely-poem.eso"));
+    let report = runtime()
+        .block_on(program.execute_async(ExecOptions::default()))
+        .unwrap();
+    assert_eq!(report.output, "24\n");
+    assert_eq!(report.halted_reason, HaltReason::Completed);
+}
+
+#[test]
+fn dropping_cancels() {
+    let program = Program::create(include_str!("fixtures/infinite-print.eso"));
+    let executed = Arc::new(AtomicU64::new(0));
+    let counter = Arc::clone(&executed);
+    let options = ExecOptions {
+        trace_sink: Some(TraceSink::new(move |_| {
+            counter.fetch_add(1, Ordering::Relaxed);
+        })),
+        max_output: Some(1 << 30),
+        ..ExecOptions::default()
+    };
+    // kept until the end, since dropping it waits for a run that's still going
+    let runtime = runtime();
+    let timed_out = runtime.block_on(async {
+        tokio::time::timeout(Duration::from_millis(100), program.execute_async(options)).await
+    });
+    assert!(timed_out.is_err());
+
+    // the run stops before its next instruction, so the count settles
+    thread::sleep(Duration::from_millis(50));
+    let stopped_at = executed.load(Ordering::Relaxed);
+    assert!(stopped_at > 0);
+    thread::sleep(Duration::from_millis(200));
+    assert_eq!(executed.load(Ordering::Relaxed), stopped_at);
+    runtime.shutdown_background();
+}
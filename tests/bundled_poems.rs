@@ -0,0 +1,42 @@
+//! a test for every poem in poems/, generated by the build script. a new
+//! poem needs a .expected file next to it with what it prints, which
+//! `ashpaper-plus test poems --bless` can write. the poems are written against
+//! the bundled dictionary, so they're only run with it
+#![cfg(feature = "bundled-dictionary")]
+
+use ashpaper_plus::{
+    io::{self, Encoding},
+    testing::{run_cases, PoemCase, MAX_INSTRUCTIONS},
+    Engine, ExecOptions, Program,
+};
+use pretty_assertions::assert_eq;
+
+/// run a poem saved as utf-8 or latin-1, showing a diff if it prints
+/// something else, then check the threaded engine and the jit, when it's
+/// built, print the same
+fn check_poem(source: &[u8], expected: &str) {
+    let source =
+        io::decode(source, Encoding::Utf8).unwrap_or_else(|_| io::latin1_to_string(source));
+    run_cases(&[PoemCase {
+        source: &source,
+        expected_output: expected,
+        title_override: None,
+    }]);
+    let program = Program::create(&source);
+    let options = ExecOptions {
+        engine: Engine::Threaded,
+        ..ExecOptions::with_max_instructions(MAX_INSTRUCTIONS)
+    };
+    assert_eq!(program.execute_with_options(&options).unwrap(), expected);
+    #[cfg(feature = "jit")]
+    assert_eq!(program.jit_execute_captured().unwrap(), expected);
+}
+
+fn missing_expected(poem: &str) {
+    panic!(
+        "poems/{} has no expected output, add a .expected file next to it or run `ashpaper-plus test poems --bless`",
+        poem
+    );
+}
+
+include!(concat!(env!("OUT_DIR"), "/bundled_poems.rs"));
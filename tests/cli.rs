@@ -0,0 +1,818 @@
+#![cfg(feature = "cli")]
+
+use std::process::{Command, Output};
+
+fn ashpaper(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_ashpaper-plus"))
+        .args(args)
+        .output()
+        .expect("failed to run ashpaper-plus")
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn latin1_encoding() {
+    let output = ashpaper(&["--encoding", "latin1", "poems/latin1-test.eso"]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "executing\n31");
+}
+
+#[test]
+fn strict_utf8_reports_offset() {
+    let output = ashpaper(&["poems/latin1-test.eso"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("invalid utf-8 sequence at byte offset 3"));
+}
+
+#[test]
+fn missing_file() {
+    let output = ashpaper(&["poems/missing.eso"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).starts_with("error reading poems/missing.eso: failed to read input"));
+}
+
+fn bench_row<'a>(out: &'a str, poem: &str) -> Vec<&'a str> {
+    out.lines()
+        .find(|line| line.starts_with(poem))
+        .unwrap_or_else(|| panic!("no row for {} in:\n{}", poem, out))
+        .split(" | ")
+        .map(str::trim)
+        .collect()
+}
+
+#[test]
+fn bench_poems() {
+    let output = ashpaper(&["bench", "poems", "--iterations", "2"]);
+    assert!(output.status.success());
+    let out = stdout(&output);
+    assert!(out.starts_with("poem"));
+
+    let lovely = bench_row(&out, "lovely-poem.eso");
+    assert_eq!(lovely.len(), 5);
+    assert_eq!(lovely[4], "3");
+
+    let overflow = bench_row(&out, "stack-overflow-test.eso");
+    assert_eq!(overflow[2], "timed out");
+}
+
+#[cfg(feature = "jit")]
+#[test]
+fn jit_compile_stats() {
+    let output = ashpaper(&["--jit", "--stats", "--time", "poems/original-factorial.eso"]);
+    assert!(output.status.success());
+    let err = stderr(&output);
+    assert!(err.contains("blocks: 16\n"), "{}", err);
+    assert!(err.contains("jump table entries: 16\n"), "{}", err);
+    assert!(err.contains("compile time: "), "{}", err);
+    assert!(!err.contains("not supported"), "{}", err);
+}
+
+#[cfg(feature = "jit")]
+#[test]
+fn bench_poems_jit() {
+    let output = ashpaper(&["bench", "poems", "--iterations", "1", "--jit"]);
+    assert!(output.status.success());
+    let out = stdout(&output);
+
+    assert_eq!(bench_row(&out, "lovely-poem.eso").len(), 7);
+    let overflow = bench_row(&out, "stack-overflow-test.eso");
+    assert_eq!(overflow[2], "timed out");
+    assert_eq!(overflow[5], "-");
+}
+
+#[test]
+fn trace_table() {
+    let output = ashpaper(&["--trace", "--color", "never", "poems/lovely-poem.eso"]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "executing\n24\n");
+    let err = stderr(&output);
+    let mut lines = err.lines();
+    assert!(lines
+        .next()
+        .unwrap()
+        .starts_with("line | index | instruction"));
+    assert!(lines.next().unwrap().starts_with("---- | -----"));
+    assert!(!err.contains('\x1b'));
+    assert!(err.contains("how lovely can it be?"));
+}
+
+#[test]
+fn trace_color() {
+    let output = ashpaper(&["--trace", "--color", "always", "poems/lovely-poem.eso"]);
+    assert!(stderr(&output).contains('\x1b'));
+
+    // stderr isn't a terminal here, so auto never colors
+    let output = ashpaper(&["--trace", "poems/lovely-poem.eso"]);
+    assert!(!stderr(&output).contains('\x1b'));
+}
+
+#[test]
+fn trace_to_file() {
+    let dir = std::env::temp_dir();
+    let id = std::process::id();
+    for (extension, first) in &[
+        ("jsonl", r#"{"step":0,"line":1,"mnemonic":"noop","#),
+        ("csv", "step,line,mnemonic,r0,r1,stack_depth,output,text\n"),
+        ("txt", "line | index | instruction "),
+    ] {
+        let path = dir.join(format!("ashpaper-trace-{}.{}", id, extension));
+        let arg = format!("--trace={}", path.display());
+        let output = ashpaper(&[&arg, "poems/lovely-poem.eso"]);
+        assert!(output.status.success());
+        assert_eq!(stdout(&output), "executing\n24\n");
+        assert_eq!(stderr(&output), "");
+        let trace = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(trace.starts_with(first), "{}", trace);
+    }
+
+    let output = ashpaper(&["-t", "poems/lovely-poem.eso"]);
+    assert!(stderr(&output).starts_with("line | index | instruction"));
+}
+
+#[test]
+fn limit_output() {
+    let start = std::time::Instant::now();
+    let output = ashpaper(&["--limit-output", "1k", "tests/fixtures/infinite-print.eso"]);
+    assert!(start.elapsed() < std::time::Duration::from_secs(30));
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(stdout(&output), format!("executing\n{}", "1".repeat(1024)));
+    assert!(stderr(&output).contains("output truncated at the 1024 byte limit"));
+}
+
+#[test]
+fn limit_output_not_reached() {
+    let output = ashpaper(&["--limit-output", "64", "poems/lovely-poem.eso"]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "executing\n24\n");
+}
+
+#[test]
+fn limit_output_invalid() {
+    let output = ashpaper(&["--limit-output", "lots", "poems/lovely-poem.eso"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("invalid value for --limit-output"));
+}
+
+fn json(output: &Output) -> serde_json::Value {
+    serde_json::from_str(&stdout(output)).unwrap()
+}
+
+#[test]
+fn json_run() {
+    let output = ashpaper(&["--json", "poems/lovely-poem.eso"]);
+    assert!(output.status.success());
+    let run = json(&output);
+    assert_eq!(run["output"], "24\n");
+    assert_eq!(run["exit_reason"], "completed");
+    assert_eq!(run["instructions_executed"], 30);
+    assert_eq!(run["registers"], serde_json::json!({ "r0": 10, "r1": 24 }));
+    assert_eq!(run["stack"], serde_json::json!([]));
+    assert!(run["duration_ms"].is_f64());
+    assert_eq!(
+        run["warnings"],
+        serde_json::json!(["ignored 1 pop from an empty stack"])
+    );
+    assert_eq!(run["error"], serde_json::Value::Null);
+}
+
+#[test]
+fn json_fuel_exhausted() {
+    let output = ashpaper(&[
+        "--json",
+        "--limit-fuel",
+        "30",
+        "tests/fixtures/infinite-print.eso",
+    ]);
+    assert_eq!(output.status.code(), Some(1));
+    let run = json(&output);
+    assert_eq!(run["exit_reason"], "fuel-exhausted");
+    assert_eq!(run["instructions_executed"], 30);
+    assert_eq!(run["output"], "1".repeat(15));
+    assert!(run["error"]
+        .as_str()
+        .unwrap()
+        .contains("instruction limit of 30 reached"));
+
+    let output = ashpaper(&["--json", "tests/fixtures/missing.eso"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(json(&output)["error"]
+        .as_str()
+        .unwrap()
+        .starts_with("error reading tests/fixtures/missing.eso"));
+}
+
+#[test]
+fn profile_to_stderr() {
+    let output = ashpaper(&["--profile", "poems/lovely-poem.eso"]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "executing\n24\n");
+    let err = stderr(&output);
+    assert!(err.starts_with("1 |\n1 | lovely poem\n2 |\n"));
+    assert!(err.ends_with("1 | how lovely can it be?\n"));
+}
+
+#[test]
+fn profile_to_file() {
+    let path = std::env::temp_dir().join(format!("ashpaper-profile-{}.txt", std::process::id()));
+    let arg = format!("--profile={}", path.display());
+    let output = ashpaper(&[&arg, "--profile-percentages", "poems/lovely-poem.eso"]);
+    assert!(output.status.success());
+    assert_eq!(stderr(&output), "");
+    let profile = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(profile.contains("1   3.3% | lovely poem\n"));
+}
+
+#[test]
+fn stats_and_time() {
+    let output = ashpaper(&["--stats", "--time", "poems/lovely-poem.eso"]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "executing\n24\n");
+    let err = stderr(&output);
+    assert!(
+        err.starts_with("instructions executed: 30\nmax stack depth: 1\n  add          4\n"),
+        "{}",
+        err
+    );
+    assert!(err.contains("  store        6\ntime: "), "{}", err);
+}
+
+#[test]
+fn exit_register() {
+    for (poem, code, printed) in &[
+        ("tests/fixtures/exit-zero.eso", 0, "0"),
+        ("tests/fixtures/exit-seven.eso", 7, "7"),
+        ("tests/fixtures/exit-negative.eso", 255, "-3"),
+    ] {
+        let output = ashpaper(&["--exit-register", poem]);
+        assert_eq!(output.status.code(), Some(*code), "{}", poem);
+        assert_eq!(stdout(&output), format!("executing\n{}", printed));
+    }
+
+    let output = ashpaper(&["tests/fixtures/exit-seven.eso"]);
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn title() {
+    let output = ashpaper(&["--title", "the quiet moon", "poems/lovely-poem.eso"]);
+    assert_eq!(stdout(&output), "executing\n24\n");
+    let output = ashpaper(&["--title", "a lovely poem", "poems/lovely-poem.eso"]);
+    assert_eq!(stdout(&output), "executing\n120\n");
+}
+
+#[test]
+fn title_of_empty_poem() {
+    let path = std::env::temp_dir().join(format!("ashpaper-empty-{}.eso", std::process::id()));
+    std::fs::write(&path, "").unwrap();
+    let output = ashpaper(&["--title", "a lovely poem", path.to_str().unwrap()]);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("the poem is empty"));
+}
+
+#[test]
+fn gen_title() {
+    for (syllables, factorial) in &[("4", "24"), ("7", "5040"), ("13", "6227020800")] {
+        let output = ashpaper(&["--gen-title", syllables, "poems/lovely-poem.eso"]);
+        assert!(output.status.success());
+        assert_eq!(stdout(&output), format!("executing\n{}\n", factorial));
+        let err = stderr(&output);
+        let title = err.lines().next().unwrap().strip_prefix("title: ").unwrap();
+        assert_eq!(
+            ashpaper_plus::count_syllables(title).to_string(),
+            *syllables
+        );
+    }
+}
+
+/// a fresh directory for a test to write a poem corpus into
+fn corpus_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("ashpaper-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn test_bundled_poems() {
+    let output = ashpaper(&["test", "poems"]);
+    assert!(output.status.success(), "{}", stdout(&output));
+    let out = stdout(&output);
+    assert!(out.contains("test lovely-poem.eso ... ok"));
+    assert!(out.contains("test stack-overflow-test.eso ... skipped"));
+    assert!(out.ends_with("test result: ok. 7 passed; 0 failed; 1 skipped\n"));
+}
+
+#[cfg(feature = "jit")]
+#[test]
+fn test_bundled_poems_jit() {
+    let output = ashpaper(&["test", "poems", "--jit"]);
+    assert!(output.status.success(), "{}", stdout(&output));
+}
+
+#[test]
+fn test_failures_and_bless() {
+    let dir = corpus_dir("corpus");
+    std::fs::copy("poems/math-test.eso", dir.join("math.eso")).unwrap();
+    std::fs::write(dir.join("math.expected"), "-5\n41\n10\n").unwrap();
+    std::fs::copy("poems/lovely-poem.eso", dir.join("lovely.eso")).unwrap();
+    std::fs::copy("poems/stack-test.eso", dir.join("unknown.eso")).unwrap();
+    std::fs::write(
+        dir.join("manifest.toml"),
+        "[expected]\n\"lovely.eso\" = \"24\\n\"\n",
+    )
+    .unwrap();
+    let dir_arg = dir.to_str().unwrap();
+
+    let output = ashpaper(&["test", dir_arg]);
+    assert_eq!(output.status.code(), Some(1));
+    let out = stdout(&output);
+    assert!(out.contains("test lovely.eso ... ok"));
+    assert!(out.contains("test math.eso ... FAILED"));
+    assert!(out.contains("---- math.eso ----\n-\"41\"\n+\"42\"\n"));
+    assert!(out.contains("---- unknown.eso ----\nno expected output"));
+    assert!(out.ends_with("test result: FAILED. 1 passed; 2 failed; 0 skipped\n"));
+
+    let output = ashpaper(&["test", "--bless", dir_arg]);
+    assert!(output.status.success());
+    assert_eq!(
+        std::fs::read_to_string(dir.join("math.expected")).unwrap(),
+        "-5\n42\n10\n"
+    );
+    let output = ashpaper(&["test", dir_arg]);
+    assert!(output.status.success());
+    assert!(stdout(&output).ends_with("3 passed; 0 failed; 0 skipped\n"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn lint_text() {
+    let output = ashpaper(&["--lint", "tests/fixtures/lint.eso"]);
+    assert!(output.status.success());
+    assert_eq!(
+        stdout(&output),
+        "tests/fixtures/lint.eso:1: [W002] the title is parsed as goto instead of storing its 4 syllables as the input
+tests/fixtures/lint.eso:2: [W001] 'zorblax' isn't in the dictionary, so its syllables are approximated as 2
+"
+    );
+
+    let output = ashpaper(&[
+        "--lint",
+        "--allow",
+        "W002",
+        "--deny",
+        "unknown-word",
+        "tests/fixtures/lint.eso",
+    ]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(!stdout(&output).contains("W002"));
+    assert!(stdout(&output).contains("W001"));
+}
+
+#[test]
+fn lint_json() {
+    let output = ashpaper(&["--lint=json", "tests/fixtures/lint.eso"]);
+    assert!(output.status.success());
+    let diagnostics: serde_json::Value = serde_json::from_str(&stdout(&output)).unwrap();
+    let diagnostics = diagnostics.as_array().unwrap();
+    assert_eq!(diagnostics.len(), 2);
+    assert_eq!(diagnostics[0]["code"], "W002");
+    assert_eq!(diagnostics[0]["line"], 1);
+    assert_eq!(diagnostics[1]["code"], "W001");
+    assert_eq!(diagnostics[1]["name"], "unknown-word");
+    assert_eq!(diagnostics[1]["severity"], "warning");
+}
+
+#[test]
+fn lint_meter() {
+    let output = ashpaper(&["--lint", "tests/fixtures/meter.eso"]);
+    assert_eq!(stdout(&output), "");
+
+    let output = ashpaper(&[
+        "--lint=json",
+        "--enable",
+        "I001",
+        "tests/fixtures/meter.eso",
+    ]);
+    assert!(output.status.success());
+    let diagnostics: serde_json::Value = serde_json::from_str(&stdout(&output)).unwrap();
+    assert_eq!(diagnostics[0]["line"], 4);
+    assert_eq!(diagnostics[0]["name"], "broken-meter");
+    assert_eq!(diagnostics[0]["severity"], "information");
+
+    let output = ashpaper(&[
+        "--lint",
+        "--deny",
+        "broken-meter",
+        "tests/fixtures/meter.eso",
+    ]);
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn lint_and_run() {
+    let output = ashpaper(&["--lint", "--run", "poems/lovely-poem.eso"]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "executing\n24\n");
+    assert!(stderr(&output).contains("[W001] 'factori'"));
+}
+
+#[test]
+fn dot() {
+    let expected = r#"digraph poem {
+    node [shape=box, fontname="monospace"];
+    start [shape=circle];
+    halt [shape=doublecircle];
+    jump [shape=diamond, label="computed jump"];
+    l0 [label="1: store\nfive is enough"];
+    l1 [label="2: store\nthree"];
+    l2 [label="3: print-value\nprint."];
+    l3 [label="4: cond-goto\nre/turn"];
+    start -> l0;
+    l0 -> l1;
+    l1 -> l2;
+    l2 -> l3;
+    l3 -> jump [style=dashed, label="taken"];
+    l3 -> halt;
+}
+"#;
+    let output = ashpaper(&["--dot", "tests/fixtures/dot.eso"]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), expected);
+
+    let path = std::env::temp_dir().join(format!("ashpaper-{}.dot", std::process::id()));
+    let arg = format!("--dot={}", path.display());
+    let output = ashpaper(&[&arg, "tests/fixtures/dot.eso"]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "");
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), expected);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn explain() {
+    let output = ashpaper(&["--explain", "poems/original-factorial.eso"]);
+    assert!(output.status.success());
+    assert_eq!(
+        stdout(&output),
+        "r1 from an indent of 1, a tab counting 1\n12 (cond-goto) -> 3\n3 <- 12\n"
+    );
+
+    let output = ashpaper(&[
+        "--explain",
+        "--min-indent",
+        "2",
+        "--extended",
+        "poems/original-factorial.eso",
+    ]);
+    assert!(output.status.success());
+    assert!(stdout(&output).starts_with("r1 from an indent of 2, a tab counting 4\n"));
+}
+
+#[test]
+fn diff_text_only() {
+    let output = ashpaper(&[
+        "diff",
+        "tests/fixtures/diff-old.eso",
+        "tests/fixtures/diff-reworded.eso",
+    ]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        stdout(&output),
+        "no semantic changes\n1 -> 1: text only\n2 -> 2: text only\n3 -> 3: text only\n"
+    );
+}
+
+#[test]
+fn diff_semantic() {
+    let args = [
+        "diff",
+        "--color=always",
+        "tests/fixtures/diff-old.eso",
+        "tests/fixtures/diff-changed.eso",
+    ];
+    let output = ashpaper(&args);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stdout(&output),
+        "\x1b[33m2 -> 2: r1 became r0\x1b[0m\n\x1b[32m- -> 4: added print-char\x1b[0m\n"
+    );
+
+    let output = ashpaper(&["diff", "--json", args[2], args[3]]);
+    assert_eq!(output.status.code(), Some(1));
+    let diff = json(&output);
+    assert_eq!(diff["semantically_equal"], false);
+    assert_eq!(diff["lines"][1]["old_line"], serde_json::Value::Null);
+    assert_eq!(diff["lines"][1]["new_line"], 4);
+    assert_eq!(diff["lines"][1]["changes"][0]["kind"], "added");
+
+    let output = ashpaper(&["diff", "missing.eso", "tests/fixtures/diff-old.eso"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn count_poems() {
+    let output = ashpaper(&["count", "--format", "json", "poems"]);
+    assert!(output.status.success());
+    let report = json(&output);
+    let file = |name: &str| {
+        report["files"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|file| file["file"].as_str().unwrap().ends_with(name))
+            .unwrap()
+            .clone()
+    };
+    assert_eq!(file("lovely-poem.eso")["syllables"], 99);
+    assert_eq!(file("lovely-poem.eso")["lines"], 18);
+    assert_eq!(file("original-factorial.eso")["syllables"], 75);
+    assert!(report["total"]["syllables"].as_u64().unwrap() > 99 + 75);
+
+    let output = ashpaper(&[
+        "count",
+        "--per-line",
+        "--format",
+        "csv",
+        "poems/lovely-poem.eso",
+    ]);
+    assert!(output.status.success());
+    let stdout = stdout(&output);
+    let mut lines = stdout.lines();
+    assert_eq!(lines.next(), Some("file,line,syllables,approximated,text"));
+    assert_eq!(
+        lines.nth(1),
+        Some("poems/lovely-poem.eso,2,4,0,lovely poem")
+    );
+}
+
+#[test]
+fn dot_conflicts() {
+    let output = ashpaper(&["--dot", "--trace", "tests/fixtures/dot.eso"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("cannot be used with"));
+
+    let output = ashpaper(&["--dot-open", "tests/fixtures/dot.eso"]);
+    assert!(!output.status.success());
+}
+
+fn ashpaper_with_stdin(args: &[&str], input: &str) -> Output {
+    use std::{io::Write, process::Stdio};
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_ashpaper-plus"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to run ashpaper-plus");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn debug_session() {
+    let commands = "list\nbreak 4\nstep\nregs\nset r0 5\ncontinue\nstack\nlist\nc\nback\nregs\nq\n";
+    let output = ashpaper_with_stdin(&["debug", "tests/fixtures/debug.eso"], commands);
+    assert!(output.status.success());
+    let expected = "  1: fish (store)
+(ashpaper) ->   1 | fish
+     2 | print.
+     3 |     birds
+     4 |     print.
+(ashpaper) breakpoint set on line 4
+(ashpaper)   2: print. (print-value)
+(ashpaper) r0 = 1
+r1 = 0
+(ashpaper) r0 = 5
+(ashpaper) [output] \"5\"
+breakpoint hit
+  4:     print. (print-value)
+(ashpaper) []
+(ashpaper)      1 | fish
+     2 | print.
+     3 |     birds
+->*  4 |     print.
+(ashpaper) [output] \"1\"
+program halted after 4 instructions
+(ashpaper)   4:     print. (print-value)
+(ashpaper) r0 = 5
+r1 = 1
+(ashpaper) ";
+    assert_eq!(stdout(&output), expected);
+}
+
+#[test]
+fn fmt_rewrites_files() {
+    let dir = corpus_dir("fmt");
+    let messy = dir.join("messy.eso");
+    let tidy = dir.join("tidy.eso");
+    std::fs::write(&messy, "lovely   poem  \r\n\n  it is a calculator\nprint.").unwrap();
+    std::fs::write(&tidy, "lovely poem\nprint.\n").unwrap();
+    let (messy_arg, tidy_arg) = (messy.to_str().unwrap(), tidy.to_str().unwrap());
+
+    let output = ashpaper(&["fmt", "--check", messy_arg, tidy_arg]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), format!("would reformat {}\n", messy_arg));
+    assert!(std::fs::read_to_string(&messy)
+        .unwrap()
+        .contains("lovely   poem"));
+
+    let output = ashpaper(&["fmt", messy_arg, tidy_arg]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), format!("formatted {}\n", messy_arg));
+    assert_eq!(
+        std::fs::read_to_string(&messy).unwrap(),
+        "lovely poem\n\n  it is a calculator\nprint.\n"
+    );
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+
+    let output = ashpaper(&["fmt", "--check", messy_arg, tidy_arg]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "");
+}
+
+#[test]
+fn generate_from_ast() {
+    let dir = corpus_dir("generate");
+    let json = dir.join("lovely.json");
+    let poem = dir.join("lovely.eso");
+    let ast = ashpaper(&["--ast=json", "poems/lovely-poem.eso"]);
+    assert!(ast.status.success());
+    std::fs::write(&json, &ast.stdout).unwrap();
+
+    let args = ["generate", json.to_str().unwrap(), "--seed", "7", "-o"];
+    let output = ashpaper(&[&args[..], &[poem.to_str().unwrap()]].concat());
+    assert!(output.status.success(), "{}", stderr(&output));
+    let generated = std::fs::read_to_string(&poem).unwrap();
+    assert_ne!(generated, include_str!("../poems/lovely-poem.eso"));
+
+    let expected = ashpaper(&["poems/lovely-poem.eso"]);
+    let actual = ashpaper(&[poem.to_str().unwrap()]);
+    assert_eq!(stdout(&actual), stdout(&expected));
+
+    // the same seed always writes the same poem
+    let output = ashpaper(&["generate", json.to_str().unwrap(), "--seed", "7"]);
+    assert_eq!(stdout(&output), generated);
+}
+
+#[test]
+fn generate_names_instruction() {
+    let dir = corpus_dir("generate-error");
+    let json = dir.join("program.json");
+    std::fs::write(
+        &json,
+        r#"[{"instruction": "store", "syllables": 2}, {"instruction": "cond-push", "prev_syllables": 5, "cur_syllables": 1}]"#,
+    )
+    .unwrap();
+    let output = ashpaper(&["generate", json.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("instruction 1 is a cond-push"));
+}
+
+#[test]
+fn generate_rejects_newer_schema() {
+    let dir = corpus_dir("generate-schema");
+    let json = dir.join("program.json");
+    std::fs::write(&json, r#"{"schema_version": "2.0", "instructions": []}"#).unwrap();
+    let output = ashpaper(&["generate", json.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("schema version 2.0 is newer than the newest"));
+}
+
+fn ashpaper_without_rust_log(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_ashpaper-plus"))
+        .args(args)
+        .env_remove("RUST_LOG")
+        .output()
+        .expect("failed to run ashpaper-plus")
+}
+
+#[test]
+fn silent_by_default() {
+    let output = ashpaper_without_rust_log(&["poems/lovely-poem.eso"]);
+    assert!(output.status.success());
+    assert_eq!(stderr(&output), "");
+}
+
+#[test]
+fn verbose_logs_trace() {
+    let output = ashpaper_without_rust_log(&["-v", "poems/lovely-poem.eso"]);
+    assert!(output.status.success());
+    let err = stderr(&output);
+    assert!(err.contains("instruction"), "{}", err);
+    assert!(err.contains("lovely poem"), "{}", err);
+    assert_eq!(stdout(&output), "executing\n24\n");
+
+    let output = ashpaper_without_rust_log(&["-vv", "poems/lovely-poem.eso"]);
+    assert!(stderr(&output).contains("lovely poem"));
+}
+
+#[test]
+fn rust_log_overrides_verbosity() {
+    let output = Command::new(env!("CARGO_BIN_EXE_ashpaper-plus"))
+        .args(["-v", "poems/lovely-poem.eso"])
+        .env("RUST_LOG", "off")
+        .output()
+        .unwrap();
+    assert_eq!(stderr(&output), "");
+}
+
+#[test]
+fn eval() {
+    let output = ashpaper(&["--eval", "lovely poem\nprint."]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "executing\n4");
+
+    // each -e is a line, so the second rhymes with the first
+    let couplet = ["-e", "the lovely sea", "-e", "a flea"];
+    let output = ashpaper(&[&couplet[..], &["--ast=json"]].concat());
+    let ast: serde_json::Value = serde_json::from_str(&stdout(&output)).unwrap();
+    assert_eq!(ast["instructions"][0]["instruction"], "store");
+    assert_eq!(ast["instructions"][1]["instruction"], "cond-push");
+    let output = ashpaper(&[&couplet[..], &["--limit-output", "1", "--stats"]].concat());
+    assert!(output.status.success());
+    assert!(stderr(&output).contains("cond-push"));
+
+    let output = ashpaper(&["-e", "lovely poem", "poems/lovely-poem.eso"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn trailing_args() {
+    let output = ashpaper(&["tests/fixtures/echo-args.eso", "--", "hi", "there"]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "executing\nhi there");
+
+    let output = ashpaper(&["tests/fixtures/echo-args.eso", "--int-args", "--", "1", "x"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("argument 2 (\"x\") isn't an integer"));
+}
+
+#[test]
+fn extended_reads_stdin() {
+    let output = ashpaper_with_stdin(&["--extended", "tests/fixtures/echo.eso"], "hi");
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "executing\nhi-1");
+
+    // without --extended the exclamation marks are just punctuation
+    let output = ashpaper_with_stdin(&["tests/fixtures/echo.eso"], "hi");
+    assert_eq!(stdout(&output), "executing\n\u{2}\u{2}1");
+}
+
+#[test]
+fn no_dict() {
+    let ast = |args: &[&str]| {
+        let output = ashpaper(&[args, &["--ast=json"]].concat());
+        assert!(output.status.success(), "{}", stderr(&output));
+        serde_json::from_str::<serde_json::Value>(&stdout(&output)).unwrap()
+    };
+    // the factorial poem's stores are approximated the same as they're
+    // looked up
+    let factorial = ["poems/original-factorial.eso"];
+    assert_eq!(
+        ast(&factorial),
+        ast(&[&["--no-dict"], &factorial[..]].concat())
+    );
+
+    // "poem" is approximated as 1 syllable rather than 2
+    let lovely = ast(&["poems/lovely-poem.eso"]);
+    let approximated = ast(&["--no-dict", "poems/lovely-poem.eso"]);
+    assert_eq!(lovely["instructions"][16]["syllables"], 10);
+    assert_eq!(approximated["instructions"][16]["syllables"], 9);
+
+    // nothing rhymes without the dictionary
+    let couplet = ["-e", "the lovely sea", "-e", "a flea"];
+    let approximated = ast(&[&["--no-dict"], &couplet[..]].concat());
+    assert_eq!(approximated["instructions"][1]["instruction"], "store");
+
+    assert_eq!(stdout(&ashpaper(&["-s", "hour"])), "2\n");
+    assert_eq!(stdout(&ashpaper(&["--no-dict", "-s", "hour"])), "1\n");
+}
+
+#[test]
+fn dict() {
+    // tests/fixtures/tiny.dict says "poem" in one syllable
+    let output = ashpaper(&["--dict", "tests/fixtures/tiny.dict", "-s", "poem"]);
+    assert_eq!(stdout(&output), "1\n");
+    assert_eq!(stdout(&ashpaper(&["-s", "poem"])), "2\n");
+
+    let output = ashpaper(&["--dict", "tests/fixtures/missing.dict", "-s", "poem"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).starts_with("error: can't use --dict: "));
+}
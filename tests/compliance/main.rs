@@ -0,0 +1,213 @@
+//! checks this crate against William Hicks' reference implementation
+//!
+//! every `fixtures/<case>.eso` is run through both of the interpreter's
+//! engines, and the jit with the `jit` feature, and has to print exactly what the reference
+//! implementation prints for it, kept next to it in `<case>.expected`. to add
+//! a case, save the reference's stdout for the poem as its `.expected`, no
+//! code changes needed.
+//!
+//! anything this crate knowingly does differently is listed in
+//! [`DEVIATIONS`] with the reason, so it can't drift in silently: a listed
+//! case has to print the deviating output instead, and fails once it matches
+//! the reference again so the entry gets removed
+#![cfg(any(feature = "bundled-dictionary", feature = "compact-dictionary"))]
+
+use ashpaper_plus::{Engine, ExecOptions, Program};
+use std::{fs, path::Path};
+
+/// how many instructions a case can run before it's assumed to loop forever
+const MAX_INSTRUCTIONS: u64 = 1_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Backend {
+    Interpreter,
+    /// the interpreter with [`Engine::Threaded`]
+    Threaded,
+    Jit,
+}
+
+/// a case one of the backends doesn't print the reference's output for, on
+/// purpose
+struct Deviation {
+    case: &'static str,
+    backends: &'static [Backend],
+    /// what the backends print instead. `None` if they can't run the case at
+    /// all, which skips it
+    output: Option<&'static str>,
+    reason: &'static str,
+}
+
+const DEVIATIONS: &[Deviation] = &[
+    Deviation {
+        case: "negative-goto",
+        backends: &[Backend::Interpreter, Backend::Threaded, Backend::Jit],
+        output: Some("-32"),
+        reason: "the spec keeps a negative goto target as it is, which the \
+                 reference's list indexing counts from the last line. this \
+                 crate goes to the line numbered by its absolute value",
+    },
+    Deviation {
+        case: "deep-stack",
+        backends: &[Backend::Jit],
+        output: None,
+        reason: "the jit's stack only holds 128 values, so it stops with \
+                 JitError::StackOverflow where the reference keeps pushing",
+    },
+];
+
+fn deviation(case: &str, backend: Backend) -> Option<&'static Deviation> {
+    DEVIATIONS
+        .iter()
+        .find(|deviation| deviation.case == case && deviation.backends.contains(&backend))
+}
+
+/// the name, source and reference output of every fixture, by name
+fn cases() -> Vec<(String, String, String)> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/compliance/fixtures");
+    let mut cases: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "eso"))
+        .map(|path| {
+            let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+            let expected = fs::read_to_string(path.with_extension("expected"))
+                .unwrap_or_else(|err| panic!("{} has no expected output: {}", name, err));
+            (name, fs::read_to_string(&path).unwrap(), expected)
+        })
+        .collect();
+    cases.sort();
+    cases
+}
+
+fn run(source: &str, backend: Backend) -> Result<String, String> {
+    let program = Program::create(source);
+    let interpret = |engine| {
+        let options = ExecOptions {
+            engine,
+            ..ExecOptions::with_max_instructions(MAX_INSTRUCTIONS)
+        };
+        program
+            .execute_with_options(&options)
+            .map_err(|err| err.to_string())
+    };
+    match backend {
+        Backend::Interpreter => interpret(Engine::Classic),
+        Backend::Threaded => interpret(Engine::Threaded),
+        #[cfg(feature = "jit")]
+        Backend::Jit => program
+            .jit_execute_captured()
+            .map_err(|err| err.to_string()),
+        #[cfg(not(feature = "jit"))]
+        Backend::Jit => unreachable!("the jit isn't compiled in"),
+    }
+}
+
+/// `Err` with what went wrong if `backend` doesn't print the reference's
+/// output, or the deviating one for a case in [`DEVIATIONS`]
+fn check(name: &str, source: &str, expected: &str, backend: Backend) -> Result<(), String> {
+    match deviation(name, backend) {
+        None => {
+            let output = run(source, backend)?;
+            if output != expected {
+                return Err(format!("printed {:?}, expected {:?}", output, expected));
+            }
+        }
+        Some(Deviation { output: None, .. }) => {}
+        Some(Deviation {
+            output: Some(deviating),
+            reason,
+            ..
+        }) => {
+            let output = run(source, backend)?;
+            if output == expected {
+                return Err(format!(
+                    "matches the reference now, remove its deviation ({})",
+                    reason
+                ));
+            }
+            if output != *deviating {
+                return Err(format!(
+                    "printed {:?}, expected the deviating {:?}",
+                    output, deviating
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn check_all(backend: Backend) {
+    let failures: Vec<String> = cases()
+        .iter()
+        .filter_map(|(name, source, expected)| {
+            check(name, source, expected, backend)
+                .err()
+                .map(|err| format!("{} ({:?}): {}", name, backend, err))
+        })
+        .collect();
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+#[test]
+fn interpreter() {
+    check_all(Backend::Interpreter);
+}
+
+#[test]
+fn threaded() {
+    check_all(Backend::Threaded);
+}
+
+#[cfg(feature = "jit")]
+#[test]
+fn jit() {
+    check_all(Backend::Jit);
+}
+
+#[test]
+fn deviations_name_fixtures() {
+    let cases = cases();
+    for deviation in DEVIATIONS {
+        assert!(
+            cases.iter().any(|(name, ..)| name == deviation.case),
+            "deviation for missing case {}",
+            deviation.case
+        );
+    }
+}
+
+#[test]
+fn enough_fixtures() {
+    let sources: Vec<String> = cases().into_iter().map(|(_, source, _)| source).collect();
+    assert!(sources.len() >= 12);
+    // every instruction the spec has shows up somewhere
+    let mut mnemonics: Vec<&str> = sources
+        .iter()
+        .flat_map(|source| {
+            Program::create(source)
+                .ast
+                .iter()
+                .map(|ins| ins.instruction.mnemonic())
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    mnemonics.sort_unstable();
+    mnemonics.dedup();
+    assert_eq!(
+        mnemonics,
+        [
+            "add",
+            "cond-goto",
+            "cond-push",
+            "goto",
+            "multiply",
+            "negate",
+            "noop",
+            "pop",
+            "print-char",
+            "print-value",
+            "push",
+            "store",
+        ]
+    );
+}
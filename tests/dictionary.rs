@@ -0,0 +1,119 @@
+//! setting up the process-wide dictionary. each test runs in its own
+//! process, since the dictionary can only be set up once
+
+use ashpaper_plus::{count_syllables, dictionary, DictionaryError, Error};
+use pretty_assertions::assert_eq;
+use std::{env, fs, process::Command};
+
+const CHILD: &str = "ASHPAPER_DICTIONARY_TEST";
+
+/// run `test` in a fresh copy of this test binary, returning true in the
+/// copy so the test can go ahead
+fn in_own_process(test: &str) -> bool {
+    if env::var_os(CHILD).is_some() {
+        return true;
+    }
+    let output = Command::new(env::current_exe().unwrap())
+        .args([test, "--exact", "--nocapture", "--test-threads=1"])
+        .env(CHILD, test)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}\n{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    false
+}
+
+#[cfg(all(feature = "bundled-dictionary", not(ashpaper_embedded_dict)))]
+#[test]
+fn lazy_init() {
+    if !in_own_process("lazy_init") {
+        return;
+    }
+    assert!(!dictionary::is_initialized());
+    // a word that's in the bundled dictionary, which the approximation gets
+    // wrong
+    assert_eq!(count_syllables("antidisestablishmentarianism"), 12);
+    assert!(dictionary::is_initialized());
+    assert!(matches!(
+        dictionary::init_default(),
+        Err(Error::Dictionary(DictionaryError::AlreadyInitialized))
+    ));
+}
+
+#[test]
+fn pre_init() {
+    if !in_own_process("pre_init") {
+        return;
+    }
+    let path = env::temp_dir().join(format!("ashpaper-dictionary-{}.dict", std::process::id()));
+    fs::write(&path, "poem  P OW1 AH0 M\nrhythm  R IH1 DH AH0 M\n").unwrap();
+    let missing = path.with_extension("missing");
+    assert!(matches!(
+        dictionary::init_from_path(&missing),
+        Err(Error::Input { path, .. }) if path == missing
+    ));
+    assert!(!dictionary::is_initialized());
+
+    dictionary::init_from_path(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert!(dictionary::is_initialized());
+    // looked up, so not approximated as one syllable
+    assert_eq!(count_syllables("rhythm"), 2);
+    assert_eq!(count_syllables("antidisestablishmentarianism"), 11);
+}
+
+#[test]
+fn double_init() {
+    if !in_own_process("double_init") {
+        return;
+    }
+    // an invalid dictionary doesn't use up the one chance to set it up
+    assert!(dictionary::init_from_str("poem  P OW9 AH0 M").is_err());
+    dictionary::init_default().unwrap();
+    assert!(matches!(
+        dictionary::init_default(),
+        Err(Error::Dictionary(DictionaryError::AlreadyInitialized))
+    ));
+    assert!(matches!(
+        dictionary::init_from_str("poem  P OW1 AH0 M\n"),
+        Err(Error::Dictionary(DictionaryError::AlreadyInitialized))
+    ));
+    assert_eq!(
+        dictionary::init_from_str("poem  P OW1 AH0 M\n")
+            .unwrap_err()
+            .to_string(),
+        "the dictionary was already initialized"
+    );
+}
+
+/// a dictionary compiled in with `ASHPAPER_DICT` replaces the bundled one,
+/// and one given at runtime still replaces that. to run it:
+///
+/// ```sh
+/// ASHPAPER_DICT=tests/fixtures/tiny.dict cargo test --test dictionary
+/// ```
+#[cfg(all(feature = "bundled-dictionary", ashpaper_embedded_dict))]
+#[test]
+fn embedded() {
+    if !in_own_process("embedded") {
+        return;
+    }
+    // tests/fixtures/tiny.dict says "poem" in one syllable, and has no
+    // "antidisestablishmentarianism" to look up
+    assert_eq!(count_syllables("poem"), 1);
+    assert_eq!(count_syllables("antidisestablishmentarianism"), 11);
+}
+
+#[cfg(all(feature = "bundled-dictionary", ashpaper_embedded_dict))]
+#[test]
+fn embedded_overridden() {
+    if !in_own_process("embedded_overridden") {
+        return;
+    }
+    dictionary::init_from_str("poem  P OW1 AH0 M\n").unwrap();
+    assert_eq!(count_syllables("poem"), 2);
+}
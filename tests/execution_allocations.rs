@@ -0,0 +1,78 @@
+//! how often the interpreter allocates while it runs, counted by a global
+//! allocator. kept apart from the parser's count so the two tests can't
+//! count each other
+
+use ashpaper_plus::{ExecOptions, InsType, Instruction, Program, Register};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+/// pushes, pops, prints a character and a number, and counts r0 down to 0
+/// from `passes`
+fn countdown(passes: u32) -> Program {
+    let ins = |instruction, register| Instruction::new(instruction, register, "");
+    Program::from_instructions(vec![
+        ins(InsType::Store(passes), Register::Register0),
+        ins(InsType::Push, Register::Register0),
+        ins(InsType::PrintValue, Register::Register0),
+        ins(InsType::Store(32), Register::Register1),
+        ins(InsType::PrintChar, Register::Register1),
+        ins(InsType::Pop, Register::Register1),
+        ins(InsType::Store(1), Register::Register1),
+        ins(InsType::Negate, Register::Register1),
+        ins(InsType::Add, Register::Register0),
+        // back to the push while r0 > 0, by the -1 in r1
+        ins(InsType::ConditionalGoto(0), Register::Register0),
+    ])
+}
+
+/// allocations made running `program`, with its output counted rather than
+/// kept so only the interpreter's own allocations are left
+fn allocations(program: &Program) -> (usize, usize) {
+    let options = ExecOptions::default();
+    let mut printed = 0;
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    program
+        .execute_streaming(&options, |s| printed += s.len())
+        .unwrap();
+    (ALLOCATIONS.load(Ordering::Relaxed) - before, printed)
+}
+
+#[test]
+fn steady_state_allocations() {
+    let short = countdown(10);
+    let long = countdown(10_000);
+
+    let (short_allocations, short_printed) = allocations(&short);
+    let (long_allocations, long_printed) = allocations(&long);
+    assert_eq!(short_printed, 21);
+    // a space after every number
+    assert_eq!(long_printed, 9 + 90 * 2 + 900 * 3 + 9_000 * 4 + 5 + 10_000);
+    // the reserves up front are all there is, so 10,000 passes allocate no
+    // more than 10
+    assert_eq!(long_allocations, short_allocations);
+}
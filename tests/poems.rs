@@ -0,0 +1,41 @@
+//! the bundled poems, checked the way a poem repository would check its own
+#![cfg(feature = "bundled-dictionary")]
+
+use ashpaper_plus::{assert_poem, assert_poem_ast, assert_poem_output};
+
+const FACTORIAL: &str = include_str!("../poems/lovely-poem.eso");
+
+#[test]
+fn factorial_of_the_title() {
+    assert_poem!(file = "../poems/lovely-poem.eso", "24\n");
+    assert_poem!(FACTORIAL, title = "lovely poem and", "120\n");
+    assert_poem!(FACTORIAL, title = "sweet poem", "6\n");
+    assert_poem_output!(FACTORIAL, "24\n");
+}
+
+#[test]
+fn factorial_instructions() {
+    assert_poem_ast!(
+        FACTORIAL,
+        [
+            "noop",
+            "store",
+            "noop",
+            "add",
+            "pop",
+            "push",
+            "store",
+            "negate",
+            "add",
+            "pop",
+            "multiply",
+            "push",
+            "store",
+            "cond-goto",
+            "pop",
+            "print-value",
+            "store",
+            "print-char",
+        ]
+    );
+}
@@ -0,0 +1,280 @@
+//! invariants of the interpreter over generated programs
+
+use ashpaper_plus::{
+    count_syllables_with_options, Engine, Error, ExecError, ExecOptions, InputSource, InsType,
+    Instruction, Interpreter, ParseOptions, Program, Register, UnknownScriptSyllables,
+};
+use proptest::prelude::*;
+use std::io::Cursor;
+
+/// generators for programs, biased towards the shapes that exercise control
+/// flow: jumps near the end that can loop back, and cond-pushes
+mod strategies {
+    use super::*;
+
+    fn syllables() -> impl Strategy<Value = u32> {
+        // mostly small so jumps land inside the program
+        prop_oneof![4 => 0u32..8, 1 => 0u32..64]
+    }
+
+    fn register() -> impl Strategy<Value = Register> {
+        prop_oneof![Just(Register::Register0), Just(Register::Register1)]
+    }
+
+    pub fn instruction_type() -> impl Strategy<Value = InsType> {
+        prop_oneof![
+            3 => (syllables(), syllables()).prop_map(|(prev_syllables, cur_syllables)| {
+                InsType::ConditionalPush {
+                    prev_syllables,
+                    cur_syllables,
+                }
+            }),
+            1 => syllables().prop_map(InsType::ConditionalGoto),
+            1 => Just(InsType::Negate),
+            1 => Just(InsType::Multiply),
+            1 => Just(InsType::Add),
+            1 => Just(InsType::PrintChar),
+            2 => Just(InsType::PrintValue),
+            2 => Just(InsType::Pop),
+            2 => Just(InsType::Push),
+            1 => Just(InsType::Goto),
+            3 => syllables().prop_map(InsType::Store),
+            1 => Just(InsType::Noop),
+        ]
+    }
+
+    fn jump() -> impl Strategy<Value = InsType> {
+        prop_oneof![
+            Just(InsType::Goto),
+            syllables().prop_map(InsType::ConditionalGoto),
+        ]
+    }
+
+    fn instruction(
+        instruction: impl Strategy<Value = InsType>,
+    ) -> impl Strategy<Value = Instruction> {
+        (instruction, register())
+            .prop_map(|(instruction, register)| Instruction::new(instruction, register, ""))
+    }
+
+    /// [`instruction_type`] and the instructions that can fail or read,
+    /// which need options to get anywhere
+    fn any_instruction_type() -> impl Strategy<Value = InsType> {
+        prop_oneof![
+            8 => instruction_type(),
+            1 => Just(InsType::Subtract),
+            1 => Just(InsType::Divide),
+            1 => Just(InsType::ReadChar),
+            1 => Just(InsType::TapeStore),
+            1 => Just(InsType::TapeLoad),
+        ]
+    }
+
+    /// options with every limit and strictness either on or off, and what
+    /// `read-char`s read
+    pub fn options() -> impl Strategy<Value = (ExecOptions, Vec<u8>)> {
+        (
+            any::<bool>(),
+            any::<bool>(),
+            prop::option::of(0usize..8),
+            prop::option::of(0usize..8),
+            prop::option::of(0usize..64),
+            prop::collection::vec(any::<u8>(), 0..4),
+        )
+            .prop_map(
+                |(strict_pop, strict_division, max_stack, max_tape_cells, max_output, input)| {
+                    let options = ExecOptions {
+                        max_instructions: Some(super::FUEL),
+                        max_output,
+                        strict_pop,
+                        strict_division,
+                        max_stack,
+                        max_tape_cells,
+                        ..ExecOptions::default()
+                    };
+                    (options, input)
+                },
+            )
+    }
+
+    /// a body of any instructions followed by a few jumps
+    pub fn program() -> impl Strategy<Value = Program> {
+        body(instruction_type())
+    }
+
+    /// [`program`] with every instruction, see [`any_instruction_type`]
+    pub fn any_program() -> impl Strategy<Value = Program> {
+        body(any_instruction_type())
+    }
+
+    fn body(instruction_type: impl Strategy<Value = InsType>) -> impl Strategy<Value = Program> {
+        (
+            prop::collection::vec(instruction(instruction_type), 1..24),
+            prop::collection::vec(instruction(jump()), 0..3),
+        )
+            .prop_map(|(mut body, tail)| {
+                body.extend(tail);
+                Program::from_instructions(body)
+            })
+    }
+}
+
+const FUEL: u64 = 2_000;
+
+/// `result` with its error debug-printed, since errors can't be compared
+fn comparable<T>(result: ashpaper_plus::Result<T>) -> Result<T, String> {
+    result.map_err(|err| format!("{:?}", err))
+}
+
+fn is_push(instruction: InsType) -> bool {
+    matches!(instruction, InsType::Push | InsType::ConditionalPush { .. })
+}
+
+proptest! {
+    #[test]
+    fn completes_or_runs_out_of_fuel(program in strategies::program()) {
+        match program.execute_with_options(&ExecOptions::with_max_instructions(FUEL)) {
+            Ok(_) | Err(Error::Exec(ExecError::Overflow { .. })) => (),
+            Err(Error::Exec(ExecError::FuelExhausted { limit, .. })) => prop_assert_eq!(limit, FUEL),
+            Err(err) => prop_assert!(false, "unexpected error: {}", err),
+        }
+    }
+
+    #[test]
+    fn stack_only_grows_by_pushes(program in strategies::program()) {
+        let mut interpreter = Interpreter::new(&program);
+        let mut pushes = 0;
+        while interpreter.instructions_executed() < FUEL {
+            let index = interpreter.instruction_pointer();
+            match interpreter.step() {
+                Ok(Some(_)) => (),
+                Ok(None) | Err(_) => break,
+            }
+            if is_push(program.ast[index].instruction) {
+                pushes += 1;
+            }
+            prop_assert!(interpreter.stack().len() <= pushes);
+        }
+    }
+
+    #[test]
+    fn deterministic(program in strategies::program()) {
+        let options = ExecOptions::with_max_instructions(FUEL);
+        prop_assert_eq!(
+            comparable(program.execute_with_options(&options)),
+            comparable(program.execute_with_options(&options))
+        );
+    }
+
+    #[test]
+    fn threaded_engine_agrees(
+        program in strategies::any_program(),
+        (options, input) in strategies::options(),
+    ) {
+        // each run reads the input afresh
+        let with_engine = |engine| ExecOptions {
+            engine,
+            input: Some(InputSource::new(Cursor::new(input.clone()))),
+            ..options.clone()
+        };
+        prop_assert_eq!(
+            comparable(program.execute_with_options(&with_engine(Engine::Threaded))),
+            comparable(program.execute_with_options(&with_engine(Engine::Classic)))
+        );
+
+        // step by step too, for the state the output doesn't show
+        let interpreter = |engine| {
+            let mut interpreter = Interpreter::new(&program);
+            interpreter.set_strict_pop(options.strict_pop);
+            interpreter.set_strict_division(options.strict_division);
+            interpreter.set_max_stack(options.max_stack);
+            interpreter.set_max_tape_cells(options.max_tape_cells);
+            interpreter.set_input(with_engine(engine).input);
+            interpreter.set_engine(engine);
+            interpreter
+        };
+        let mut classic = interpreter(Engine::Classic);
+        let mut threaded = interpreter(Engine::Threaded);
+        while classic.instructions_executed() < FUEL {
+            let step = comparable(classic.step());
+            prop_assert_eq!(comparable(threaded.step()), step.clone());
+            prop_assert_eq!(threaded.registers(), classic.registers());
+            prop_assert_eq!(threaded.stack(), classic.stack());
+            prop_assert_eq!(threaded.tape(), classic.tape());
+            prop_assert_eq!(threaded.instruction_pointer(), classic.instruction_pointer());
+            if !matches!(step, Ok(Some(_))) {
+                break;
+            }
+        }
+    }
+}
+
+/// the output of a program if it halts within the fuel without overflowing,
+/// along with the deepest the stack got
+#[cfg(feature = "jit")]
+fn halts(program: &Program) -> Option<(String, usize)> {
+    let mut interpreter = Interpreter::new(program);
+    let mut output = String::new();
+    let mut depth = 0;
+    while interpreter.instructions_executed() < FUEL {
+        match interpreter.step().ok()? {
+            Some(step) => output.extend(step.output),
+            None => return Some((output, depth)),
+        }
+        depth = depth.max(interpreter.stack().len());
+    }
+    None
+}
+
+/// how many values the jit's stack holds before it stops with an error
+#[cfg(feature = "jit")]
+const JIT_STACK: usize = 128;
+
+#[cfg(feature = "jit")]
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn jit_agrees(program in strategies::program()) {
+        // the jit has no fuel and a fixed stack, so only programs that halt
+        // cleanly within both can be compared
+        if let Some((output, depth)) = halts(&program) {
+            if depth <= JIT_STACK {
+                prop_assert_eq!(program.jit_execute_captured().unwrap(), output);
+            } else {
+                let result = program.jit_execute_captured();
+                prop_assert!(
+                    matches!(
+                        result,
+                        Err(Error::Jit(ref err))
+                            if matches!(**err, ashpaper_plus::JitError::StackOverflow { limit } if limit == JIT_STACK)
+                    ),
+                    "{:?}",
+                    result
+                );
+            }
+        }
+    }
+}
+
+proptest! {
+    /// syllables of any text at all, in whatever script, are counted
+    /// without panicking, and the characters outside the Latin script only
+    /// add to the count by however many the policy gives them
+    #[test]
+    fn counts_syllables_of_any_text(text in any::<String>()) {
+        let count = |unknown_script_syllables| {
+            count_syllables_with_options(
+                &text,
+                &ParseOptions {
+                    unknown_script_syllables,
+                    ..ParseOptions::default()
+                },
+            )
+        };
+        let zero = count(UnknownScriptSyllables::Zero);
+        prop_assert!(count(UnknownScriptSyllables::PerCharacter) >= zero);
+        prop_assert_eq!(count(UnknownScriptSyllables::Custom(|_| 0)), zero);
+        Program::create(&text);
+    }
+}
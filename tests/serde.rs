@@ -0,0 +1,115 @@
+#![cfg(feature = "serde")]
+
+use ashpaper_plus::{
+    trace::{Trace, TraceRow},
+    ExecOptions, InsCategory, InsType, Instruction, Program, Register, Registers, Step,
+};
+use pretty_assertions::assert_eq;
+use serde::{de::DeserializeOwned, Serialize};
+use std::fmt::Debug;
+
+fn round_trip<T: Serialize + DeserializeOwned + PartialEq + Debug>(value: &T) {
+    let json = serde_json::to_string(value).unwrap();
+    assert_eq!(
+        &serde_json::from_str::<T>(&json).unwrap(),
+        value,
+        "{}",
+        json
+    );
+}
+
+const INSTRUCTIONS: &[InsType] = &[
+    InsType::ConditionalPush {
+        prev_syllables: 3,
+        cur_syllables: 5,
+    },
+    InsType::ConditionalGoto(4),
+    InsType::Negate,
+    InsType::Multiply,
+    InsType::Add,
+    InsType::PrintChar,
+    InsType::PrintValue,
+    InsType::Pop,
+    InsType::Push,
+    InsType::Goto,
+    InsType::Store(7),
+    InsType::Noop,
+];
+
+#[test]
+fn instructions() {
+    for &instruction in INSTRUCTIONS {
+        round_trip(&instruction);
+        round_trip(&instruction.category());
+        for &register in &[Register::Register0, Register::Register1] {
+            round_trip(&Instruction::new(instruction, register, "a lovely poem"));
+        }
+    }
+    round_trip(&InsCategory::ControlFlow);
+}
+
+#[test]
+fn stable_names() {
+    let instruction = Instruction::new(
+        InsType::ConditionalPush {
+            prev_syllables: 3,
+            cur_syllables: 5,
+        },
+        Register::Register1,
+        "  in the rain",
+    );
+    assert_eq!(
+        serde_json::to_string(&instruction).unwrap(),
+        r#"{"instruction":"cond-push","prev_syllables":3,"cur_syllables":5,"register":1,"line":"  in the rain"}"#
+    );
+    assert_eq!(
+        serde_json::to_string(&InsType::PrintValue).unwrap(),
+        r#"{"instruction":"print-value"}"#
+    );
+    assert_eq!(
+        serde_json::to_string(&InsCategory::ControlFlow).unwrap(),
+        r#""control-flow""#
+    );
+    assert!(serde_json::from_str::<Register>("4").is_err());
+}
+
+#[test]
+fn execution() {
+    let program = Program::create(include_str!("../poems/lovely-poem.eso"));
+    let mut trace = Trace::default();
+    let mut steps = Vec::new();
+    let registers = program
+        .execute_with_hook(
+            &ExecOptions::default(),
+            |_| (),
+            |interpreter, step: &Step| {
+                trace.push(interpreter.trace_row(step));
+                steps.push(step.clone());
+            },
+        )
+        .unwrap();
+
+    round_trip(&registers);
+    round_trip(&Registers {
+        r0: -4,
+        r1: 9,
+        r2: 0,
+        r3: 7,
+    });
+    // from before there were four registers
+    assert_eq!(
+        serde_json::from_str::<Registers>(r#"{"r0":-4,"r1":9}"#).unwrap(),
+        Registers {
+            r0: -4,
+            r1: 9,
+            ..Registers::default()
+        }
+    );
+    for step in &steps {
+        round_trip(step);
+    }
+    for row in &trace.rows {
+        round_trip::<TraceRow>(row);
+    }
+    round_trip(&trace);
+}
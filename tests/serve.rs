@@ -0,0 +1,144 @@
+#![cfg(feature = "serve")]
+
+use serde_json::Value;
+use std::{
+    io::{BufRead, BufReader},
+    process::{Child, Command, Stdio},
+    thread,
+};
+
+/// the server on a free port, killed when dropped
+struct Server {
+    child: Child,
+    url: String,
+}
+
+impl Server {
+    fn start(args: &[&str]) -> Server {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_ashpaper-plus"))
+            .args(["serve", "--port", "0"])
+            .args(args)
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("failed to run ashpaper-plus");
+        let mut line = String::new();
+        BufReader::new(child.stderr.take().unwrap())
+            .read_line(&mut line)
+            .unwrap();
+        let url = line
+            .trim()
+            .strip_prefix("listening on ")
+            .unwrap_or_else(|| panic!("unexpected first line {:?}", line))
+            .to_string();
+        Server { child, url }
+    }
+
+    fn limited() -> Server {
+        Server::start(&[
+            "--limit-fuel",
+            "1000",
+            "--limit-output",
+            "4",
+            "--limit-stack",
+            "16",
+            "--limit-body",
+            "1k",
+        ])
+    }
+
+    /// the status and json body of posting `body` to `path`
+    fn post(&self, path: &str, body: &str) -> (u16, Value) {
+        let response = match ureq::post(&format!("{}{}", self.url, path)).send_string(body) {
+            Ok(response) => response,
+            Err(ureq::Error::Status(_, response)) => response,
+            Err(err) => panic!("request failed: {}", err),
+        };
+        let status = response.status();
+        (
+            status,
+            serde_json::from_str(&response.into_string().unwrap()).unwrap(),
+        )
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[test]
+fn run() {
+    let server = Server::start(&[]);
+    let poem = std::fs::read_to_string("poems/original-factorial.eso").unwrap();
+    let (status, body) = server.post("/run", &poem);
+    assert_eq!(status, 200);
+    assert_eq!(body["output"], "24\n");
+    assert_eq!(body["halted_reason"], "completed");
+    assert_eq!(body["instructions_executed"], 26);
+    assert_eq!(body["error"], Value::Null);
+}
+
+#[test]
+fn limits() {
+    let server = Server::limited();
+
+    // loops forever
+    let (status, body) = server.post("/run", "  sea\nsoft sand");
+    assert_eq!(status, 200);
+    assert_eq!(body["halted_reason"], "fuel-exhausted");
+    assert_eq!(body["instructions_executed"], 1000);
+
+    // prints 4 forever
+    let (_, body) = server.post("/run", "lovely poem\n  sea\nprint.\nsoft sand");
+    assert_eq!(body["halted_reason"], "output-limit");
+    assert_eq!(body["output"], "4444");
+
+    // pushes forever
+    let (_, body) = server.post("/run", "lovely poem\npush-ed\n  sea\nsoft sand");
+    assert_eq!(body["halted_reason"], "error");
+    assert!(body["error"]
+        .as_str()
+        .unwrap()
+        .contains("stack limit of 16 values reached"));
+
+    let (status, body) = server.post("/run", &"lovely poem\n".repeat(100));
+    assert_eq!(status, 413);
+    assert!(body["error"].as_str().unwrap().contains("over 1024 bytes"));
+}
+
+#[test]
+fn syllables_and_ast() {
+    let server = Server::start(&[]);
+    let (status, body) = server.post("/syllables", "lovely poem");
+    assert_eq!(status, 200);
+    assert_eq!(body["syllables"], 4);
+
+    let (status, body) = server.post("/ast", "lovely poem\nprint.");
+    assert_eq!(status, 200);
+    assert_eq!(body["instructions"][0]["instruction"], "store");
+    assert_eq!(body["instructions"].as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn unknown_requests() {
+    let server = Server::start(&[]);
+    assert_eq!(server.post("/nowhere", "").0, 404);
+    let response = ureq::get(&format!("{}/run", server.url)).call();
+    assert!(matches!(response, Err(ureq::Error::Status(405, _))));
+}
+
+#[test]
+fn concurrent_requests() {
+    let server = Server::start(&["--workers", "4"]);
+    let poem = std::fs::read_to_string("poems/original-factorial.eso").unwrap();
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..16)
+            .map(|_| scope.spawn(|| server.post("/run", &poem).1))
+            .collect();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap()["output"], "24\n");
+        }
+    });
+}
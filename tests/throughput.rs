@@ -0,0 +1,52 @@
+//! a smoke test for how fast `count_syllables` is, so a change that makes it
+//! drastically slower fails here and not just in `benches/count_syllables`.
+//! the bound is generous enough for unoptimized builds on slow machines, so
+//! it only catches regressions by orders of magnitude
+
+use ashpaper_plus::count_syllables;
+use std::time::{Duration, Instant};
+
+const WORDS: usize = 10_000;
+const ROUNDS: u32 = 10;
+const BOUND: Duration = Duration::from_secs(30);
+
+fn text(words: &[&str]) -> String {
+    (0..WORDS)
+        .map(|i| words[(i * 11) % words.len()])
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// count the syllables of `text` a few times, printing the rate
+fn throughput(name: &str, text: &str) -> usize {
+    // the dictionary is loaded by the first count, which isn't timed
+    let syllables = count_syllables(text);
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        assert_eq!(count_syllables(text), syllables);
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "{}: {:.0} words/s",
+        name,
+        (WORDS as u32 * ROUNDS) as f64 / elapsed.as_secs_f64()
+    );
+    assert!(
+        elapsed < BOUND,
+        "counting {} words {} times took {:?}",
+        WORDS,
+        ROUNDS,
+        elapsed
+    );
+    syllables
+}
+
+#[test]
+fn count_syllables_throughput() {
+    // 1 + 2 + 2 + 1 syllables, looked up
+    let known = text(&["the", "lovely", "poem", "sound"]);
+    assert_eq!(throughput("dictionary", &known), WORDS / 4 * 6);
+    // made up words, so every one is approximated
+    let unknown = text(&["zorblax", "quifth", "plonyx", "mrraeu"]);
+    assert!(throughput("approximated", &unknown) > WORDS);
+}
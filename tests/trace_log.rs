@@ -0,0 +1,173 @@
+// with the tracing feature steps are tracing events rather than log rows, and
+// the traced poems need the bundled dictionary to parse as they're meant to
+#![cfg(all(not(feature = "tracing"), feature = "bundled-dictionary"))]
+
+use ashpaper_plus::{ExecOptions, Program, TraceFormat, TraceSink};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use pretty_assertions::assert_eq;
+use serde_json::Value;
+use std::{
+    cell::RefCell,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Once,
+    },
+};
+
+thread_local! {
+    // tests run on their own threads, so each only sees its own records
+    static CAPTURED: RefCell<Vec<(String, String)>> = const { RefCell::new(Vec::new()) };
+}
+
+struct Capture;
+
+impl Log for Capture {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Info
+    }
+
+    fn log(&self, record: &Record) {
+        let entry = (record.target().to_string(), record.args().to_string());
+        CAPTURED.with(|captured| captured.borrow_mut().push(entry));
+    }
+
+    fn flush(&self) {}
+}
+
+/// the messages logged with `target` while running `f`
+fn capture(target: &str, f: impl FnOnce()) -> Vec<String> {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| {
+        log::set_logger(&Capture).unwrap();
+        log::set_max_level(LevelFilter::Info);
+    });
+    CAPTURED.with(|captured| captured.borrow_mut().clear());
+    f();
+    CAPTURED.with(|captured| {
+        captured
+            .borrow()
+            .iter()
+            .filter(|(logged, _)| logged == target)
+            .map(|(_, message)| message.clone())
+            .collect()
+    })
+}
+
+fn json_trace(max_stack: usize) -> Vec<Value> {
+    let program = Program::create(include_str!("../poems/lovely-poem.eso"));
+    let options = ExecOptions {
+        trace_format: TraceFormat::Json { max_stack },
+        ..ExecOptions::default()
+    };
+    capture("ashpaper::trace", || {
+        assert_eq!(program.execute_with_options(&options).unwrap(), "24\n");
+    })
+    .iter()
+    .map(|line| {
+        assert!(!line.contains('\n'), "{}", line);
+        serde_json::from_str(line).unwrap()
+    })
+    .collect()
+}
+
+#[test]
+fn json_lines() {
+    let records = json_trace(8);
+    assert_eq!(records.len(), 30);
+    assert_eq!(
+        records[1],
+        serde_json::json!({
+            "step": 1,
+            "line": 2,
+            "instruction": "store",
+            "r0": 4,
+            "r1": 0,
+            "stack_len": 0,
+            "stack": [],
+        })
+    );
+    for (step, record) in records.iter().enumerate() {
+        let mut fields: Vec<&str> = record
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        fields.sort_unstable();
+        assert_eq!(
+            fields,
+            vec![
+                "instruction",
+                "line",
+                "r0",
+                "r1",
+                "stack",
+                "stack_len",
+                "step"
+            ]
+        );
+        assert_eq!(record["step"], step);
+    }
+}
+
+#[test]
+fn stack_cap() {
+    let records = json_trace(0);
+    let pushed = records
+        .iter()
+        .find(|record| record["stack_len"] == 1)
+        .unwrap();
+    assert_eq!(pushed["instruction"], "push");
+    assert!(pushed.get("stack").is_none());
+    // an empty stack is still within the cap
+    assert_eq!(records[0]["stack"], serde_json::json!([]));
+}
+
+#[test]
+fn table_by_default() {
+    let program = Program::create(include_str!("../poems/lovely-poem.eso"));
+    assert_eq!(
+        capture("ashpaper::trace", || drop(program.execute())),
+        Vec::<String>::new()
+    );
+    let rows = capture("ashpaper_plus::program", || drop(program.execute()));
+    assert!(
+        rows[0].starts_with("line | index | instruction"),
+        "{}",
+        rows[0]
+    );
+    assert!(
+        rows[3].starts_with("   2 |     1 | lovely poem      "),
+        "{}",
+        rows[3]
+    );
+    assert!(rows[3].contains(" | store       |  4   |"), "{}", rows[3]);
+    assert_eq!(rows.len(), 32);
+}
+
+#[test]
+fn sink_replaces_logging() {
+    let program = Program::create(include_str!("../poems/lovely-poem.eso"));
+    let rows = Arc::new(AtomicUsize::new(0));
+    let counted = Arc::clone(&rows);
+    let options = ExecOptions {
+        trace_sink: Some(TraceSink::new(move |_| {
+            counted.fetch_add(1, Ordering::Relaxed);
+        })),
+        trace_format: TraceFormat::Json { max_stack: 8 },
+        ..ExecOptions::default()
+    };
+    let logged = capture("ashpaper::trace", || {
+        assert_eq!(program.execute_with_options(&options).unwrap(), "24\n");
+    });
+    assert_eq!(logged, Vec::<String>::new());
+    let options = ExecOptions {
+        trace_format: TraceFormat::Table,
+        ..options
+    };
+    let logged = capture("ashpaper_plus::program", || {
+        program.execute_with_options(&options).unwrap();
+    });
+    assert_eq!(logged, Vec::<String>::new());
+    assert_eq!(rows.load(Ordering::Relaxed), 60);
+}
@@ -0,0 +1,124 @@
+#![cfg(feature = "tracing")]
+
+use ashpaper_plus::Program;
+use pretty_assertions::assert_eq;
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    sync::{Arc, Mutex},
+};
+use tracing::{
+    field::{Field, Visit},
+    span, Event, Subscriber,
+};
+use tracing_subscriber::{layer::Context, prelude::*, registry::LookupSpan, Layer};
+
+type Fields = BTreeMap<String, String>;
+
+struct RecordedSpan {
+    id: span::Id,
+    name: &'static str,
+    fields: Fields,
+    events: usize,
+}
+
+/// remembers every span with its fields, and how many events it contained
+#[derive(Clone, Default)]
+struct Recorder {
+    spans: Arc<Mutex<Vec<RecordedSpan>>>,
+}
+
+struct FieldVisitor<'a>(&'a mut Fields);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{:?}", value));
+    }
+}
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for Recorder {
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, _: Context<'_, S>) {
+        let mut fields = Fields::new();
+        attrs.record(&mut FieldVisitor(&mut fields));
+        self.spans.lock().unwrap().push(RecordedSpan {
+            id: id.clone(),
+            name: attrs.metadata().name(),
+            fields,
+            events: 0,
+        });
+    }
+
+    fn on_record(&self, id: &span::Id, values: &span::Record<'_>, _: Context<'_, S>) {
+        let mut spans = self.spans.lock().unwrap();
+        if let Some(span) = spans.iter_mut().rev().find(|span| &span.id == id) {
+            values.record(&mut FieldVisitor(&mut span.fields));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        if let Some(parent) = ctx.event_span(event) {
+            let mut spans = self.spans.lock().unwrap();
+            if let Some(span) = spans.iter_mut().rev().find(|span| span.id == parent.id()) {
+                span.events += 1;
+            }
+        }
+    }
+}
+
+impl Recorder {
+    fn record(&self, f: impl FnOnce()) {
+        tracing::subscriber::with_default(tracing_subscriber::registry().with(self.clone()), f);
+    }
+
+    fn span(&self, name: &str) -> (Fields, usize) {
+        let spans = self.spans.lock().unwrap();
+        let span = spans
+            .iter()
+            .find(|span| span.name == name)
+            .unwrap_or_else(|| panic!("no {} span", name));
+        (span.fields.clone(), span.events)
+    }
+}
+
+#[test]
+fn parse() {
+    let recorder = Recorder::default();
+    recorder.record(|| {
+        Program::create("lovely poem\n\n  it is a calculator like a\nflibbertigibbetzz");
+    });
+    let (fields, _) = recorder.span("parse");
+    assert_eq!(fields["lines"], "4");
+    assert_eq!(fields["dictionary_misses"], "1");
+}
+
+#[test]
+fn execute() {
+    let program = Program::create(include_str!("../poems/lovely-poem.eso"));
+    let recorder = Recorder::default();
+    recorder.record(|| {
+        assert_eq!(program.execute(), "24\n");
+    });
+    let (fields, events) = recorder.span("execute");
+    assert_eq!(fields["output_bytes"], "3");
+    let instructions: usize = fields["instructions"].parse().unwrap();
+    assert!(instructions >= program.ast.len(), "{}", instructions);
+    // an event for every step
+    assert_eq!(events, instructions);
+}
+
+#[cfg(feature = "jit")]
+#[test]
+fn jit_compile() {
+    let program = Program::create(include_str!("../poems/lovely-poem.eso"));
+    let recorder = Recorder::default();
+    recorder.record(|| {
+        program.jit_execute_captured().unwrap();
+    });
+    let (fields, _) = recorder.span("jit_compile");
+    // its two noops share the blocks of the lines after them
+    let blocks = program.compile().unwrap().blocks;
+    assert_eq!(blocks, program.ast.len() - 2);
+    assert_eq!(fields["blocks"], blocks.to_string());
+    assert!(fields.contains_key("duration_us"), "{:?}", fields);
+}
@@ -0,0 +1,48 @@
+//! the cli built for wasm32-wasip1, run under wasmtime. build it first with
+//! `cargo build --release --target wasm32-wasip1 --features wasi`, the test
+//! is skipped if it or wasmtime isn't there
+#![cfg(feature = "wasi")]
+
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// the wasi build of the cli, release before debug
+fn binary() -> Option<PathBuf> {
+    let target = Path::new(env!("CARGO_MANIFEST_DIR")).join("target/wasm32-wasip1");
+    ["release", "debug"]
+        .iter()
+        .map(|profile| target.join(profile).join("ashpaper-plus.wasm"))
+        .find(|path| path.exists())
+}
+
+#[test]
+fn factorial_under_wasmtime() {
+    let binary = match binary() {
+        Some(binary) => binary,
+        None => {
+            eprintln!("skipped, the wasm32-wasip1 build of the cli isn't there");
+            return;
+        }
+    };
+    let output = match Command::new("wasmtime")
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .args(["run", "--dir=poems"])
+        .arg(&binary)
+        .arg("poems/original-factorial.eso")
+        .output()
+    {
+        Ok(output) => output,
+        Err(err) => {
+            eprintln!("skipped, can't run wasmtime: {}", err);
+            return;
+        }
+    };
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "executing\n24\n");
+}
@@ -0,0 +1,26 @@
+#![cfg(all(feature = "wasm", target_arch = "wasm32"))]
+
+use ashpaper_plus::wasm;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+#[wasm_bindgen_test]
+fn execute() {
+    assert_eq!(
+        wasm::execute(include_str!("../poems/lovely-poem.eso"), 0).unwrap(),
+        "24\n"
+    );
+}
+
+#[wasm_bindgen_test]
+fn fuel_limit() {
+    let err = wasm::execute("sells sea shells", 100).unwrap_err();
+    assert!(err
+        .as_string()
+        .unwrap()
+        .contains("instruction limit of 100 reached"));
+}
+
+#[wasm_bindgen_test]
+fn count_syllables() {
+    assert_eq!(wasm::count_syllables("a lovely poem"), 5);
+}